@@ -0,0 +1,13 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+use edge_tts::{build_ssml, request_audio, text_chunks, FlushPolicy};
+
+/// `echo "hello" | cargo run --example stdin`
+fn main() {
+    for (i, chunk) in text_chunks(std::io::stdin(), FlushPolicy::Idle(Duration::from_millis(500))).enumerate() {
+        let chunk = chunk.unwrap();
+        OpenOptions::new().create(true).truncate(true).write(true).open(format!("test_{}.mp3", i)).unwrap()
+            .write_all(&request_audio(&build_ssml(&chunk, "zh-CN-XiaoxiaoNeural", "medium", "medium", "medium"), "audio-24khz-48kbitrate-mono-mp3").unwrap()).unwrap();
+    }
+}
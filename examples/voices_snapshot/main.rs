@@ -0,0 +1,10 @@
+//! Regenerate `data/voices.json`, the voices of `edge_tts::bundled_voices`.
+use std::fs;
+
+use edge_tts::get_voice_list;
+
+fn main() {
+    let voices = get_voice_list().unwrap();
+    fs::write(concat!(env!("CARGO_MANIFEST_DIR"), "/data/voices.json"), serde_json::to_string_pretty(&voices).unwrap() + "\n").unwrap();
+    println!("{} voices", voices.len());
+}
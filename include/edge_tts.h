@@ -0,0 +1,37 @@
+/* C API of the edge-tts crate, built with: cargo rustc --release --features ffi --crate-type cdylib */
+#ifndef EDGE_TTS_H
+#define EDGE_TTS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Options of edge_tts_synthesize. NULL fields are left unset. */
+typedef struct EdgeTtsOptions {
+    const char *pitch;         /* eg: "+10Hz" */
+    const char *rate;          /* eg: "+20%" */
+    const char *volume;        /* eg: "-10%" */
+    const char *output_format; /* eg: "audio-24khz-48kbitrate-mono-mp3" */
+    const char *proxy;         /* socks5 proxy, eg: "127.0.0.1:1080" */
+} EdgeTtsOptions;
+
+/* Synthesize text with voice (NULL for "en-US-AriaNeural") and options, which may be NULL. Returns 0 and the
+ * audio in out_buf and out_len, to be freed with edge_tts_free_buffer, or -1, see edge_tts_last_error. */
+int edge_tts_synthesize(const char *text, const char *voice, const EdgeTtsOptions *options, uint8_t **out_buf, size_t *out_len);
+void edge_tts_free_buffer(uint8_t *buf, size_t len);
+
+/* The voice list as a JSON array, to be freed with edge_tts_free_string, or NULL on error. */
+char *edge_tts_list_voices(void);
+void edge_tts_free_string(char *s);
+
+/* The message of the last error on this thread, or NULL. Valid until the next error on this thread. */
+const char *edge_tts_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+
+use anyhow::{anyhow, Result};
+
+use crate::Client;
+
+/// External audio player fed through stdin, eg: `ffplay -nodisp -autoexit -loglevel quiet -`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new("ffplay", ["-nodisp", "-autoexit", "-loglevel", "quiet", "-"])
+    }
+}
+
+impl Player {
+    pub fn new<I, S>(program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whitespace separated command line, eg: "mpv --no-terminal -". Returns `None` for an empty command.
+    pub fn parse(command_line: &str) -> Option<Self> {
+        let mut parts = command_line.split_whitespace();
+        let program = parts.next()?;
+        Some(Self::new(program, parts))
+    }
+
+    /// Play `audio` and wait for the player to exit.
+    pub fn play(&self, audio: &[u8]) -> Result<()> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow!("failed to start player {}: {}", self.program, e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // The player may exit before reading everything, that's not an error.
+            let _ = stdin.write_all(audio);
+        }
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("player {} exited with {}", self.program, status))
+        }
+    }
+}
+
+/// Background queue that synthesizes and plays announcements one after another.
+pub struct Announcer {
+    tx: Option<SyncSender<String>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Announcer {
+    /// Start the worker thread.
+    ///
+    /// `to_ssml` turns announced text into SSML, eg: `|text| build_ssml(text, "en-US-AriaNeural", "default", "default", "default")`.
+    /// At most `capacity` announcements wait in the queue. Failures are passed to `on_error` and don't stop the worker.
+    pub fn spawn<F, E>(client: Client, output_format: &str, player: Player, capacity: usize, to_ssml: F, on_error: E) -> Self
+    where
+        F: Fn(&str) -> String + Send + 'static,
+        E: Fn(anyhow::Error) + Send + 'static,
+    {
+        let (tx, rx) = sync_channel::<String>(capacity);
+        let output_format = output_format.to_owned();
+        let worker = thread::spawn(move || {
+            for text in rx {
+                let result = client
+                    .synthesize(&to_ssml(&text), &output_format)
+                    .and_then(|output| player.play(&output.audio));
+                if let Err(e) = result {
+                    on_error(e);
+                }
+            }
+        });
+        Self {
+            tx: Some(tx),
+            worker: Some(worker),
+        }
+    }
+
+    /// Queue `text`. Returns `false` if the queue is full and the announcement was dropped.
+    pub fn announce(&self, text: impl Into<String>) -> bool {
+        match &self.tx {
+            Some(tx) => match tx.try_send(text.into()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Wait until every queued announcement has been played.
+    pub fn finish(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    /// A player appending each audio and a newline to a file.
+    fn recorder() -> (Player, PathBuf) {
+        let path = std::env::temp_dir().join(format!("edge-tts-announcer-{}", uuid::Uuid::new_v4().simple()));
+        (Player::new("sh", ["-c".to_owned(), format!("cat >> {}; echo >> {}", path.display(), path.display())]), path)
+    }
+
+    #[test]
+    fn plays_in_order_and_drops_when_full() {
+        let slow = vec![
+            MockReply::Text { path: "turn.start".to_owned(), body: "{}".to_owned() },
+            MockReply::Wait(Duration::from_millis(300)),
+            MockReply::Audio(b"one".to_vec()),
+            MockReply::TurnEnd,
+        ];
+        let server = MockServer::start(vec![slow, MockReply::turn(b"two"), vec![MockReply::Close { code: 1011, reason: "busy".to_owned() }]]).unwrap();
+        let (player, path) = recorder();
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let on_error = {
+            let errors = errors.clone();
+            move |e: anyhow::Error| errors.lock().unwrap().push(format!("{:#}", e))
+        };
+        let announcer = Announcer::spawn(server.client(), "audio-24khz-48kbitrate-mono-mp3", player, 2, |text| format!("<{}>", text), on_error);
+        assert!(announcer.announce("one"));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.requests().is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        // The worker is busy with "one": two wait in the queue, the rest is dropped.
+        assert!(announcer.announce("two"));
+        assert!(announcer.announce("three"));
+        assert!(!announcer.announce("four"));
+        announcer.finish();
+
+        let ssml: Vec<String> = server.requests().into_iter().map(|r| r.ssml).collect();
+        assert_eq!(ssml, ["<one>", "<two>", "<three>"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+        assert_eq!(errors.lock().unwrap().len(), 1);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,62 @@
+use std::ops::ControlFlow;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::synthesize::SynthesisEvent;
+use crate::{Client, SynthesisRequest};
+
+impl Client {
+    /// Pass the audio of `request` to `on_chunk` as it arrives, eg: to forward it into hyper bodies or a broadcast
+    /// channel. Chunks share the buffer of the message they came in instead of being copied, except from another
+    /// [`crate::TtsBackend`]; `Bytes::from(output.audio)` takes a whole [`crate::SynthesisOutput`] without a copy too.
+    ///
+    /// Boundaries are dropped and the disk cache isn't used. `on_chunk` returning [`ControlFlow::Break`] stops the
+    /// synthesis.
+    pub fn synthesize_bytes(&self, request: &SynthesisRequest, mut on_chunk: impl FnMut(Bytes) -> ControlFlow<()>) -> Result<()> {
+        let request = self.prepare(request)?;
+        let (ssml, output_format) = (self.request_ssml(&request), request.output_format.as_str());
+        if self.backend.is_some() {
+            self.connect_and_stream(&ssml, output_format, &mut |event| match event {
+                SynthesisEvent::Audio(audio) => on_chunk(Bytes::copy_from_slice(audio)),
+                SynthesisEvent::Boundaries(_) => ControlFlow::Continue(()),
+            })?;
+            return Ok(());
+        }
+        let mut turn = self.start_turn(&ssml, output_format)?;
+        while let Some(event) = turn.next_event()? {
+            if matches!(event, SynthesisEvent::Audio(_)) && on_chunk(turn.take_audio()?).is_break() {
+                turn.stop();
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn passes_shared_chunks() {
+        let mut turn = MockReply::turn(b"one");
+        turn.insert(2, MockReply::Audio(b"two".to_vec()));
+        let server = MockServer::start(vec![turn.clone(), turn]).unwrap();
+        let request = SynthesisRequest::new("Hi", "en-US-AriaNeural");
+        let mut chunks = Vec::new();
+        server.client().synthesize_bytes(&request, |chunk| {
+            chunks.push(chunk);
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(chunks, [&b"one"[..], b"two"]);
+
+        let mut chunks = 0;
+        server.client().synthesize_bytes(&request, |_| {
+            chunks += 1;
+            ControlFlow::Break(())
+        }).unwrap();
+        assert_eq!(chunks, 1);
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use anyhow::{Context as _, Result};
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{AsyncWrite, AsyncWriteExt, StreamExt};
+
+use crate::{Client, SynthesisRequest};
+
+/// Audio chunks buffered before the synthesis thread waits for the consumer.
+const DEFAULT_CAPACITY: usize = 16;
+
+#[derive(Default)]
+struct State {
+    chunks: VecDeque<Result<Bytes>>,
+    finished: bool,
+    /// The stream was dropped, so the synthesis should stop.
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled when the consumer takes a chunk or goes away.
+    space: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `item`, waiting while `capacity` chunks are queued. `Break` once the stream is dropped.
+    fn push(&self, item: Result<Bytes>, capacity: usize) -> ControlFlow<()> {
+        let mut state = self.lock();
+        while state.chunks.len() >= capacity && !state.cancelled {
+            state = self.space.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if state.cancelled {
+            return ControlFlow::Break(());
+        }
+        state.chunks.push_back(item);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn finish(&self) {
+        let mut state = self.lock();
+        state.finished = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Audio of one synthesis as a [`Stream`] of chunks, as they arrive from the service, see
+/// [`Client::synthesize_stream`].
+///
+/// The synthesis runs on its own thread, which waits while the consumer is `capacity` chunks behind, and stops
+/// when the stream is dropped. A failed synthesis ends the stream with its error.
+pub struct AudioStream {
+    shared: Arc<Shared>,
+}
+
+impl AudioStream {
+    /// Run `produce` on a new thread, streaming the chunks it emits.
+    fn spawn<F>(capacity: usize, produce: F) -> Self
+    where
+        F: FnOnce(&mut dyn FnMut(Bytes) -> ControlFlow<()>) -> Result<()> + Send + 'static,
+    {
+        let shared = Arc::new(Shared::default());
+        let producer = shared.clone();
+        let capacity = capacity.max(1);
+        std::thread::spawn(move || {
+            let result = produce(&mut |chunk| producer.push(Ok(chunk), capacity));
+            if let Err(e) = result {
+                let _ = producer.push(Err(e), usize::MAX);
+            }
+            producer.finish();
+        });
+        Self { shared }
+    }
+}
+
+impl Stream for AudioStream {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.shared.lock();
+        match state.chunks.pop_front() {
+            Some(item) => {
+                self.shared.space.notify_all();
+                Poll::Ready(Some(item))
+            }
+            None if state.finished => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for AudioStream {
+    fn drop(&mut self) {
+        self.shared.lock().cancelled = true;
+        self.shared.space.notify_all();
+    }
+}
+
+impl Client {
+    /// Stream the audio of `request` chunk by chunk, eg: into a hyper or axum response body. Boundaries are dropped
+    /// and the disk cache isn't used.
+    pub fn synthesize_stream(&self, request: &SynthesisRequest) -> AudioStream {
+        self.synthesize_stream_with_capacity(request, DEFAULT_CAPACITY)
+    }
+
+    /// [`Client::synthesize_stream`] buffering up to `capacity` chunks ahead of the consumer.
+    pub fn synthesize_stream_with_capacity(&self, request: &SynthesisRequest, capacity: usize) -> AudioStream {
+        let client = self.clone();
+        let request = request.clone();
+        AudioStream::spawn(capacity, move |emit| client.synthesize_bytes(&request, emit))
+    }
+
+    /// Write the audio of `request` into `writer` as it arrives, returning the number of bytes written. Dropping
+    /// the future, or a failing `writer`, stops the synthesis and closes the connection.
+    ///
+    /// `writer` is a `futures` [`AsyncWrite`]. Tokio writers implement `tokio::io::AsyncWrite` instead; wrap them
+    /// with tokio-util's compat shim, eg: `client.synthesize_to_async_writer(&request, file.compat_write())` with
+    /// `tokio_util::compat::TokioAsyncWriteCompatExt` in scope.
+    pub async fn synthesize_to_async_writer<W: AsyncWrite + Unpin>(&self, request: &SynthesisRequest, mut writer: W) -> Result<u64> {
+        let mut stream = self.synthesize_stream(request);
+        let mut bytes = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            writer.write_all(&chunk).await.context("writing audio")?;
+            bytes += chunk.len() as u64;
+        }
+        writer.flush().await.context("writing audio")?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    /// Wakes no one, so polled again until ready.
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+                return output;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Fails every write.
+    struct Broken;
+
+    impl AsyncWrite for Broken {
+        fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, _: &[u8]) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone")))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll(stream: &mut AudioStream) -> Poll<Option<Result<Bytes>>> {
+        Pin::new(stream).poll_next(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn next(stream: &mut AudioStream) -> Option<Result<Bytes>> {
+        loop {
+            if let Poll::Ready(item) = poll(stream) {
+                return item;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn streams_with_backpressure_and_errors() {
+        let produced = Arc::new(AtomicUsize::new(0));
+        let counter = produced.clone();
+        let mut stream = AudioStream::spawn(2, move |emit| {
+            for i in 0..5u8 {
+                if emit(Bytes::from(vec![i])).is_break() {
+                    return Ok(());
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            anyhow::bail!("closed")
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        // Two queued, the third waiting for space.
+        assert_eq!(produced.load(Ordering::SeqCst), 2);
+        let chunks: Vec<_> = std::iter::from_fn(|| next(&mut stream)).collect();
+        assert_eq!(chunks.iter().filter_map(|c| c.as_ref().ok()).map(|b| b[0]).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+        assert_eq!(chunks.last().unwrap().as_ref().unwrap_err().to_string(), "closed");
+    }
+
+    #[test]
+    fn writes_into_async_writers() {
+        let request = SynthesisRequest::new("Hello", "en-US-AriaNeural");
+        let server = MockServer::start(vec![vec![MockReply::Audio(b"abc".to_vec()), MockReply::Audio(b"de".to_vec()), MockReply::TurnEnd]]).unwrap();
+        let mut audio = futures_util::io::Cursor::new(Vec::new());
+        assert_eq!(block_on(server.client().synthesize_to_async_writer(&request, &mut audio)).unwrap(), 5);
+        assert_eq!(audio.into_inner(), b"abcde");
+
+        let server = MockServer::start(vec![vec![MockReply::Audio(b"abc".to_vec()), MockReply::Wait(Duration::from_millis(300)), MockReply::Audio(b"de".to_vec()), MockReply::TurnEnd]]).unwrap();
+        let error = block_on(server.client().synthesize_to_async_writer(&request, Broken)).unwrap_err();
+        assert_eq!(format!("{:#}", error), "writing audio: gone");
+        // The synthesis thread closes the connection once it notices the stream is gone.
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while server.closes() == 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.closes(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::concat::{concat_audio, ConcatOptions};
+use crate::save::write_atomic;
+use crate::{Client, Error, OutputFormat, SynthesisOutput, SynthesisRequest};
+
+/// Chapters to synthesize into one file each, see [`Client::synthesize_audiobook`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audiobook {
+    /// (title, text)
+    pub chapters: Vec<(String, String)>,
+    /// eg: "en-US-AriaNeural"
+    pub voice: String,
+    pub output_format: OutputFormat,
+    /// Longer chapters are split at sentence ends into chunks of at most this many characters. Default 3000.
+    pub max_chunk_chars: usize,
+    /// Attempts of a chunk after the first one fails. Default 3.
+    pub retries: u32,
+    /// Before the first retry, doubled for each next one. Default 1s.
+    pub retry_delay: Duration,
+}
+
+impl Audiobook {
+    pub fn new(voice: impl Into<String>) -> Self {
+        Self {
+            chapters: Vec::new(),
+            voice: voice.into(),
+            output_format: OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3,
+            max_chunk_chars: 3000,
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_chapter(mut self, title: impl Into<String>, text: impl Into<String>) -> Self {
+        self.chapters.push((title.into(), text.into()));
+        self
+    }
+
+    /// MP3 or 16-bit PCM, which chunks can be joined in.
+    pub fn with_output_format(mut self, output_format: impl Into<OutputFormat>) -> Self {
+        self.output_format = output_format.into();
+        self
+    }
+
+    pub fn with_max_chunk_chars(mut self, max_chunk_chars: usize) -> Self {
+        self.max_chunk_chars = max_chunk_chars;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Hex SHA-256 of what determines the audio of a chapter, to tell whether a written one is still valid.
+    fn digest(&self, title: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [title, text, self.voice.as_str(), self.output_format.as_str()] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// What [`Client::synthesize_audiobook`] wrote, saved as `manifest.json` next to the chapter files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudiobookManifest {
+    pub chapters: Vec<ManifestChapter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestChapter {
+    pub title: String,
+    /// In the audiobook directory, eg: "01-introduction.mp3"
+    pub file: String,
+    pub bytes: u64,
+    pub duration: Option<Duration>,
+    /// Of the title, text, voice and output format.
+    pub digest: String,
+}
+
+impl AudiobookManifest {
+    pub const FILE_NAME: &'static str = "manifest.json";
+
+    /// Sum of the chapter durations, `None` if one is unknown.
+    pub fn duration(&self) -> Option<Duration> {
+        self.chapters.iter().map(|c| c.duration).sum()
+    }
+
+    /// eg: `{"chapters": [{"title": "Introduction", "file": "01-introduction.mp3", "bytes": 1200, "duration": 0.2, "digest": "<hex>"}]}`
+    pub fn to_json(&self) -> Value {
+        let chapters: Vec<Value> = self
+            .chapters
+            .iter()
+            .map(|c| json!({ "title": c.title, "file": c.file, "bytes": c.bytes, "duration": c.duration.map(|d| d.as_secs_f64()), "digest": c.digest }))
+            .collect();
+        json!({ "chapters": chapters })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let chapters = value.get("chapters").and_then(Value::as_array).ok_or_else(|| anyhow!("manifest without chapters"))?;
+        let chapters = chapters
+            .iter()
+            .map(|c| {
+                let text = |key: &str| c.get(key).and_then(Value::as_str).map(str::to_owned).ok_or_else(|| anyhow!("manifest chapter without {}", key));
+                Ok(ManifestChapter {
+                    title: text("title")?,
+                    file: text("file")?,
+                    bytes: c.get("bytes").and_then(Value::as_u64).unwrap_or_default(),
+                    duration: c.get("duration").and_then(Value::as_f64).and_then(|d| Duration::try_from_secs_f64(d).ok()),
+                    digest: text("digest")?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { chapters })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| path.display().to_string())?;
+        Self::from_json(&serde_json::from_str(&text)?).with_context(|| path.display().to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        write_atomic(path, serde_json::to_string_pretty(&self.to_json())?.as_bytes()).with_context(|| path.display().to_string())
+    }
+}
+
+impl Client {
+    /// Synthesize each chapter of `book` into a file in `dir`, and list them in its `manifest.json`, which is
+    /// updated after every chapter.
+    ///
+    /// Run again after an interruption to resume: chapters of the manifest whose file is still there and whose
+    /// title, text, voice and format didn't change are kept.
+    pub fn synthesize_audiobook(&self, book: &Audiobook, dir: impl AsRef<Path>) -> Result<AudiobookManifest> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).with_context(|| dir.display().to_string())?;
+        let manifest_path = dir.join(AudiobookManifest::FILE_NAME);
+        let previous = match manifest_path.exists() {
+            true => AudiobookManifest::load(&manifest_path)?,
+            false => AudiobookManifest::default(),
+        };
+        let mut manifest = AudiobookManifest::default();
+        for (i, (title, text)) in book.chapters.iter().enumerate() {
+            let digest = book.digest(title, text);
+            let done = previous.chapters.iter().find(|c| {
+                c.digest == digest && fs::metadata(dir.join(&c.file)).is_ok_and(|m| m.len() == c.bytes)
+            });
+            if let Some(chapter) = done {
+                manifest.chapters.push(chapter.clone());
+                continue;
+            }
+            let output = self.synthesize_chapter(book, text).with_context(|| format!("chapter {}: {}", i + 1, title))?;
+            let file = format!("{:02}-{}.{}", i + 1, slug(title), book.output_format.extension());
+            write_atomic(&dir.join(&file), &output.audio)?;
+            manifest.chapters.push(ManifestChapter { title: title.clone(), file, bytes: output.audio.len() as u64, duration: output.duration, digest });
+            manifest.save(&manifest_path)?;
+        }
+        manifest.save(&manifest_path)?;
+        Ok(manifest)
+    }
+
+    fn synthesize_chapter(&self, book: &Audiobook, text: &str) -> Result<SynthesisOutput> {
+        let mut parts = Vec::new();
+        for chunk in chunk_text(text, book.max_chunk_chars) {
+            let request = SynthesisRequest::new(chunk, &book.voice).with_output_format(book.output_format.clone());
+            let mut delay = book.retry_delay;
+            let mut attempt = 0;
+            let output = loop {
+                match self.synthesize_request(&request) {
+                    Err(e) if attempt < book.retries && !matches!(e.downcast_ref::<Error>(), Some(Error::InputTooLong { .. } | Error::QuotaExceeded { .. })) => {
+                        thread::sleep(delay);
+                        delay *= 2;
+                        attempt += 1;
+                    }
+                    result => break result?,
+                }
+            };
+            parts.push(output);
+        }
+        match parts.len() {
+            1 => Ok(parts.remove(0)),
+            _ => concat_audio(parts, &book.output_format, &ConcatOptions::default()),
+        }
+    }
+}
+
+/// `text` in chunks of at most `max_chars` characters, split after sentence ends, else at spaces, else anywhere.
+pub(crate) fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks: Vec<String> = Vec::new();
+    let mut chunk = String::new();
+    let mut sentence = String::new();
+    let mut push = |chunk: &mut String, piece: &str| {
+        if chunk.chars().count() + piece.chars().count() > max_chars && !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_owned());
+            chunk.clear();
+        }
+        let piece = if chunk.trim().is_empty() { piece.trim_start() } else { piece };
+        if chunk.chars().count() + piece.chars().count() <= max_chars {
+            chunk.push_str(piece);
+            return;
+        }
+        // A sentence too long for a chunk of its own.
+        for word in piece.split_inclusive(char::is_whitespace) {
+            let mut word = word;
+            while !word.is_empty() {
+                if chunk.chars().count() >= max_chars {
+                    chunks.push(chunk.trim().to_owned());
+                    chunk.clear();
+                }
+                let room = max_chars - chunk.chars().count();
+                let split = word.char_indices().nth(room).map_or(word.len(), |(i, _)| i);
+                if split < word.len() && !chunk.trim().is_empty() {
+                    chunks.push(chunk.trim().to_owned());
+                    chunk.clear();
+                    continue;
+                }
+                chunk.push_str(word.get(..split).unwrap_or_default());
+                word = word.get(split..).unwrap_or_default();
+            }
+        }
+    };
+    for c in text.chars() {
+        sentence.push(c);
+        if matches!(c, '.' | '!' | '?' | '\n' | '。' | '！' | '？' | '；') {
+            push(&mut chunk, &sentence);
+            sentence.clear();
+        }
+    }
+    push(&mut chunk, &sentence);
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk.trim().to_owned());
+    }
+    chunks.retain(|c| !c.is_empty());
+    chunks
+}
+
+/// eg: "intro-to-rust" of "Intro to Rust!", "chapter" if nothing is left.
+fn slug(title: &str) -> String {
+    let words: Vec<String> = title.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect();
+    match words.is_empty() {
+        true => "chapter".to_owned(),
+        false => words.join("-").chars().take(60).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::VoicePresets;
+
+    #[test]
+    fn writes_and_resumes_audiobook() {
+        assert_eq!(chunk_text("One. Two three. Four", 10), ["One.", "Two three.", "Four"]);
+        assert_eq!(chunk_text("abcdefghij klm", 4), ["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(slug("1. Intro to Rust!"), "1-intro-to-rust");
+        let dir = std::env::temp_dir().join(format!("edge-tts-audiobook-{}", uuid::Uuid::new_v4()));
+        let book = Audiobook::new("en-US-AriaNeural")
+            .with_output_format("raw-24khz-16bit-mono-pcm")
+            .with_max_chunk_chars(12)
+            .with_retries(1, Duration::ZERO)
+            .with_chapter("Intro", "Hello there.")
+            .with_chapter("Part two", "First part. Second part.");
+        let loud = [0, 64].repeat(2400);
+        let closed = vec![MockReply::Close { code: 1011, reason: String::new() }];
+        let server = MockServer::start(vec![MockReply::turn(&loud), closed, MockReply::turn(&loud), MockReply::turn(&loud)]).unwrap();
+        let client = server.client().with_voice_presets(VoicePresets::empty());
+        let manifest = client.synthesize_audiobook(&book, &dir).unwrap();
+        assert_eq!(server.requests().len(), 4);
+        assert_eq!(manifest.chapters.iter().map(|c| c.file.as_str()).collect::<Vec<_>>(), ["01-intro.pcm", "02-part-two.pcm"]);
+        assert_eq!(manifest.chapters[0].duration, Some(Duration::from_millis(100)));
+        assert_eq!(fs::metadata(dir.join("01-intro.pcm")).unwrap().len(), manifest.chapters[0].bytes);
+        assert_eq!(AudiobookManifest::load(dir.join(AudiobookManifest::FILE_NAME)).unwrap(), manifest);
+        // Only the new chapter is synthesized.
+        let server = MockServer::start(vec![MockReply::turn(&loud)]).unwrap();
+        let book = book.with_chapter("Epilogue", "Bye.");
+        let resumed = server.client().synthesize_audiobook(&book, &dir).unwrap();
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(resumed.chapters[..2], manifest.chapters[..]);
+        assert_eq!(resumed.chapters[2].file, "03-epilogue.pcm");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
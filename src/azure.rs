@@ -0,0 +1,110 @@
+use std::io::Read;
+use std::ops::ControlFlow;
+
+use anyhow::{anyhow, Result};
+
+use crate::backend::TtsBackend;
+use crate::browser::{edge_version, user_agent};
+use crate::synthesize::SynthesisEvent;
+use crate::SynthesisOutput;
+
+/// The official Azure Speech text to speech REST API, with a subscription key. Takes the same SSML, voices and
+/// output formats as the Edge endpoint, but returns no boundaries. See [`crate::Client::with_fallback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureBackend {
+    endpoint: String,
+    subscription_key: String,
+}
+
+impl AzureBackend {
+    /// `region`: eg: "westeurope"
+    pub fn new(region: &str, subscription_key: impl Into<String>) -> Self {
+        Self::with_endpoint(format!("https://{}.tts.speech.microsoft.com/cognitiveservices/v1", region), subscription_key)
+    }
+
+    /// eg: a custom domain, "https://my-speech.cognitiveservices.azure.com/tts/cognitiveservices/v1"
+    pub fn with_endpoint(endpoint: impl Into<String>, subscription_key: impl Into<String>) -> Self {
+        Self { endpoint: endpoint.into(), subscription_key: subscription_key.into() }
+    }
+
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn synthesize(&self, ssml: &str, output_format: &str) -> Result<SynthesisOutput> {
+        let mut output = SynthesisOutput::default();
+        self.request(ssml, output_format)?.read_to_end(&mut output.audio)?;
+        Ok(output)
+    }
+
+    /// Reader of the response body.
+    fn request(&self, ssml: &str, output_format: &str) -> Result<Box<dyn Read + Send + Sync>> {
+        let response = ureq::post(&self.endpoint)
+            .set("Ocp-Apim-Subscription-Key", &self.subscription_key)
+            .set("Content-Type", "application/ssml+xml")
+            .set("X-Microsoft-OutputFormat", output_format)
+            .set("User-Agent", &user_agent(&edge_version()))
+            .send_string(ssml);
+        match response {
+            Ok(response) => Ok(response.into_reader()),
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                Err(anyhow!("Azure TTS failed with {}: {}", code, body))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl TtsBackend for AzureBackend {
+    fn synthesize(&self, ssml: &str, output_format: &str, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<()> {
+        let mut body = self.request(ssml, output_format)?;
+        let mut chunk = vec![0; 8192];
+        loop {
+            match body.read(&mut chunk)? {
+                0 => return Ok(()),
+                len => {
+                    if on_event(SynthesisEvent::Audio(chunk.get(..len).unwrap_or_default())).is_break() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::build_ssml;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn falls_back_when_edge_rejects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/cognitiveservices/v1", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let (mut headers, mut line) = (Vec::new(), String::new());
+            while reader.read_line(&mut line).unwrap() > 2 {
+                headers.push(line.trim_end().to_lowercase());
+                line.clear();
+            }
+            let len = headers.iter().find_map(|h| h.strip_prefix("content-length: ")).unwrap().parse().unwrap();
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nazure").unwrap();
+            (headers, String::from_utf8(body).unwrap())
+        });
+        let edge = MockServer::start(vec![vec![MockReply::Close { code: 1013, reason: "try again later".to_owned() }]]).unwrap();
+        let client = edge.client().with_fallback(Arc::new(AzureBackend::with_endpoint(endpoint, "secret")));
+        let ssml = build_ssml("Hello", "en-US-AriaNeural", "default", "default", "default");
+        assert_eq!(client.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"azure");
+        let (headers, body) = server.join().unwrap();
+        assert!(headers.contains(&"ocp-apim-subscription-key: secret".to_owned()), "{:?}", headers);
+        assert!(headers.contains(&"x-microsoft-outputformat: audio-24khz-48kbitrate-mono-mp3".to_owned()), "{:?}", headers);
+        assert_eq!(body, ssml);
+    }
+}
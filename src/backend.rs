@@ -0,0 +1,75 @@
+use std::fmt::Debug;
+use std::ops::ControlFlow;
+
+use anyhow::Result;
+
+use crate::synthesize::SynthesisEvent;
+use crate::{Client, Error};
+
+/// A text to speech service, eg: [`Client`] for the Edge endpoint or `AzureBackend` (feature `azure`).
+///
+/// Implement it to synthesize with another service through [`Client::with_backend`] or [`Client::with_fallback`].
+pub trait TtsBackend: Debug + Send + Sync {
+    /// Synthesize `ssml` in `output_format`, eg: "audio-24khz-48kbitrate-mono-mp3", passing audio and boundaries to
+    /// `on_event` as they arrive. Stop early, without error, when it returns [`ControlFlow::Break`].
+    fn synthesize(&self, ssml: &str, output_format: &str, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<()>;
+}
+
+impl TtsBackend for Client {
+    fn synthesize(&self, ssml: &str, output_format: &str, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<()> {
+        self.connect_and_stream(ssml, output_format, on_event)?;
+        Ok(())
+    }
+}
+
+/// The Edge endpoint refused the synthesis: the handshake was rejected, eg: 403 or 429, or the service closed the
+/// connection.
+pub(crate) fn is_rejection(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| {
+        matches!(e.downcast_ref::<tungstenite::Error>(), Some(tungstenite::Error::Http(_)))
+            || matches!(e.downcast_ref::<Error>(), Some(Error::ConnectionClosedByServer { .. }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::metadata::{Boundary, BoundaryKind};
+    use crate::{build_ssml, SynthesisRequest};
+
+    /// Says every word as one byte.
+    #[derive(Debug)]
+    struct WordBytes;
+
+    impl TtsBackend for WordBytes {
+        fn synthesize(&self, ssml: &str, _: &str, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<()> {
+            let text = ssml.split("</prosody>").next().and_then(|s| s.rsplit('>').next()).unwrap_or_default();
+            for (i, word) in text.split_whitespace().enumerate() {
+                let offset = Duration::from_millis(100 * i as u64);
+                let boundary = Boundary { kind: BoundaryKind::Word, offset, duration: Duration::from_millis(100), text: word.to_owned() };
+                if on_event(SynthesisEvent::Boundaries(vec![boundary])).is_break() || on_event(SynthesisEvent::Audio(&[i as u8])).is_break() {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn synthesizes_with_custom_backend() {
+        let client = Client::new().with_backend(Arc::new(WordBytes));
+        let output = client.synthesize_request(&SynthesisRequest::new("one two three", "en-US-AriaNeural").with_output_format("raw-24khz-16bit-mono-pcm")).unwrap();
+        assert_eq!(output.audio, [0, 1, 2]);
+        assert_eq!(output.boundaries.iter().map(|b| b.text.as_str()).collect::<Vec<_>>(), ["one", "two", "three"]);
+        let mut written = Vec::new();
+        client.synthesize_to_writer(&SynthesisRequest::new("four five", "en-US-AriaNeural"), &mut written).unwrap();
+        assert_eq!(written, [0, 1]);
+        let rejected = crate::testing::MockServer::start(vec![vec![crate::testing::MockReply::Close { code: 1013, reason: String::new() }]]).unwrap();
+        let ssml = build_ssml("six", "en-US-AriaNeural", "default", "default", "default");
+        let fallback = rejected.client().with_fallback(Arc::new(WordBytes));
+        assert_eq!(fallback.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, [0]);
+    }
+}
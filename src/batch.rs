@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use anyhow::Result;
+
+use crate::{AggregateError, Client, SynthesisOutput, SynthesisRequest};
+
+impl Client {
+    /// Synthesize `requests` on up to `concurrency` threads, each with its own connection.
+    ///
+    /// Results are in the order of `requests`. A failed item doesn't stop the others.
+    pub fn synthesize_batch(&self, requests: Vec<SynthesisRequest>, concurrency: usize) -> Vec<Result<SynthesisOutput>> {
+        let next = AtomicUsize::new(0);
+        let workers = concurrency.clamp(1, requests.len().max(1));
+        let mut results: Vec<(usize, Result<SynthesisOutput>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            match requests.get(i) {
+                                Some(request) => done.push((i, self.synthesize_request(request))),
+                                None => return done,
+                            }
+                        }
+                    })
+                })
+                .collect();
+            // A worker only panics if synthesis itself panicked, so propagate it.
+            handles.into_iter().flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+        });
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Like [`Client::synthesize_batch`], returning either every output or every failure with the start of its text.
+    pub fn synthesize_batch_all(&self, requests: Vec<SynthesisRequest>, concurrency: usize) -> Result<Vec<SynthesisOutput>, AggregateError> {
+        let snippets: Vec<String> = requests.iter().map(|r| r.text.chars().take(40).collect()).collect();
+        AggregateError::collect(self.synthesize_batch(requests, concurrency)).map_err(|mut e| {
+            for item in &mut e.errors {
+                item.context = snippets.get(item.index).cloned();
+            }
+            e
+        })
+    }
+}
+
+/// [`Client::synthesize_batch`] with [`Client::from_env`]. Every item fails if the environment is invalid.
+pub fn synthesize_batch(requests: Vec<SynthesisRequest>, concurrency: usize) -> Vec<Result<SynthesisOutput>> {
+    match Client::from_env() {
+        Ok(client) => client.synthesize_batch(requests, concurrency),
+        Err(e) => requests.iter().map(|_| Err(anyhow::anyhow!("{:#}", e))).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::DiskCache;
+
+    #[test]
+    fn keeps_order_and_goes_on_after_failures() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-batch-{}", uuid::Uuid::new_v4().simple()));
+        // Nothing listens on the proxy, so only the cached items succeed: all but the middle one.
+        let proxy = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let client = Client::new().with_socks5_proxy(proxy.to_string()).with_disk_cache(DiskCache::new(&dir));
+        let requests: Vec<SynthesisRequest> = (0..5).map(|i| SynthesisRequest::new(format!("Item {}.", i), "en-US-AriaNeural")).collect();
+        let cache = DiskCache::new(&dir);
+        for (i, request) in requests.iter().enumerate().filter(|(i, _)| *i != 2) {
+            let output = SynthesisOutput { audio: format!("audio {}", i).into_bytes(), ..Default::default() };
+            cache.put(&client.synth_key(&request.to_ssml(), request.output_format.as_str()), &output).unwrap();
+        }
+
+        let results = client.synthesize_batch(requests.clone(), 3);
+        assert_eq!(results.len(), 5);
+        for (i, result) in results.iter().enumerate() {
+            match (i, result) {
+                (2, result) => assert!(result.is_err()),
+                (_, Ok(output)) => assert_eq!(output.audio, format!("audio {}", i).as_bytes()),
+                (_, Err(e)) => panic!("item {}: {:#}", i, e),
+            }
+        }
+        // No concurrency asked for still runs every item, on one thread.
+        let results = client.synthesize_batch(requests[..2].to_vec(), 0);
+        assert_eq!(results.into_iter().map(|r| r.unwrap().audio).collect::<Vec<_>>(), [b"audio 0", b"audio 1"]);
+        assert!(client.synthesize_batch(Vec::new(), 4).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{anyhow, bail, Result};
+use edge_tts::{Client, DiskCache, Lexicon, ProtocolDump, SynthesisRequest, TextNormalizer, VoicePresets};
+
+use crate::config;
+
+/// Command line arguments: positionals, `--name value` / `--name=value` options and `--flag`s, with the speech
+/// options of the environment, then of the config file, as defaults.
+pub struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+    env: Vec<(String, String)>,
+    config: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Everything starting with `--` is an option taking a value, except the names in `flags`.
+    pub fn parse(args: impl IntoIterator<Item = String>, flags: &[&str]) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                positional.extend(iter.by_ref());
+            } else if let Some(name) = arg.strip_prefix("--") {
+                if let Some((name, value)) = name.split_once('=') {
+                    options.push((name.to_owned(), Some(value.to_owned())));
+                } else if flags.contains(&name) {
+                    options.push((name.to_owned(), None));
+                } else {
+                    let value = iter.next().ok_or_else(|| anyhow!("--{} requires a value", name))?;
+                    options.push((name.to_owned(), Some(value)));
+                }
+            } else {
+                positional.push(arg);
+            }
+        }
+        let env = SPEECH_ENV
+            .iter()
+            .filter_map(|(name, var)| std::env::var(var).ok().filter(|v| !v.is_empty()).map(|v| (name.to_string(), v)))
+            .collect();
+        let mut args = Self { positional, options, env, config: Vec::new() };
+        args.config = match args.value("config") {
+            Some("none") => Vec::new(),
+            Some(path) => config::load(path.as_ref())?,
+            None => match config::default_path().filter(|path| path.exists()) {
+                Some(path) => config::load(&path)?,
+                None => Vec::new(),
+            },
+        };
+        Ok(args)
+    }
+
+    /// Fail on options not listed in `known`.
+    pub fn check(&self, known: &[&str]) -> Result<()> {
+        for (name, _) in &self.options {
+            if !known.contains(&name.as_str()) && !SPEECH_OPTIONS.contains(&name.as_str()) {
+                bail!("unknown option --{}", name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// Last value of option `name`, else its value in the environment or the config file.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        match self.options.iter().rev().find(|(k, _)| k == name) {
+            Some((_, value)) => value.as_deref(),
+            None => self
+                .env
+                .iter()
+                .chain(&self.config)
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str()),
+        }
+    }
+
+    pub fn parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.value(name)
+            .map(|v| v.parse().map_err(|_| anyhow!("invalid value for --{}: {}", name, v)))
+            .transpose()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(k, _)| k == name)
+    }
+}
+
+/// Options shared by every subcommand.
+/// Environment variables of speech options.
+const SPEECH_ENV: &[(&str, &str)] = &[
+    ("voice", edge_tts::ENV_VOICE),
+    ("pitch", edge_tts::ENV_PITCH),
+    ("rate", edge_tts::ENV_RATE),
+    ("volume", edge_tts::ENV_VOLUME),
+    ("format", edge_tts::ENV_OUTPUT_FORMAT),
+    ("proxy", edge_tts::ENV_PROXY),
+    ("cache", edge_tts::ENV_CACHE_DIR),
+    ("player", "EDGE_TTS_PLAYER"),
+    ("config", "EDGE_TTS_CONFIG"),
+];
+
+pub const SPEECH_OPTIONS: &[&str] = &["voice", "pitch", "rate", "volume", "style", "style-degree", "format", "proxy", "player", "presets", "dump", "cache", "lexicon", "normalize", "config"];
+
+pub const SPEECH_USAGE: &str = "\
+    --voice NAME      eg: zh-CN-XiaoxiaoNeural (default: en-US-AriaNeural)
+    --pitch VALUE     eg: x-low, high, +10Hz (default: default)
+    --rate VALUE      eg: slow, fast, +20% (default: default)
+    --volume VALUE    eg: soft, loud, -10% (default: default)
+    --style NAME      speaking style of voices that have some, eg: cheerful, narration-professional
+    --style-degree N  intensity of the style from 0.01 to 2, eg: 1.5 (default: 1)
+    --presets off     don't apply the voice's recommended prosody when no pitch, rate, volume or style is given
+    --format FORMAT   eg: audio-24khz-48kbitrate-mono-mp3
+    --proxy ADDR      socks5 proxy, eg: 127.0.0.1:1080
+    --dump FILE       append every protocol message sent and received to FILE, for debugging
+    --cache DIR       keep synthesized audio in DIR and reuse it for the same text and options
+    --lexicon FILE    say the words of FILE its way, one per line, eg: nginx = /ˈɛndʒɪnˈɛks/ or SQL = sequel
+    --normalize LANG  expand the abbreviations, units and currencies of LANG and collapse whitespace, eg: en-US
+    --player CMD      audio player reading stdin (default: ffplay -nodisp -autoexit -loglevel quiet -)
+    --config FILE     read defaults of these options from FILE, or none (default: ~/.config/edge-tts/config.toml),
+                      eg: voice = \"en-GB-SoniaNeural\"
+
+Without an option, EDGE_TTS_VOICE, _PITCH, _RATE, _VOLUME, _OUTPUT_FORMAT, _PROXY, _CACHE_DIR, _PLAYER or _CONFIG
+is used before the config file. EDGE_TTS_TOKEN, _ENDPOINT, _EDGE_VERSION and _KEEP_ALIVE (seconds) configure the
+connection.";
+
+pub struct SpeechArgs {
+    pub voice: String,
+    pub pitch: String,
+    pub rate: String,
+    pub volume: String,
+    pub style: Option<String>,
+    pub style_degree: Option<String>,
+    pub format: String,
+    pub proxy: Option<String>,
+    pub player: Option<String>,
+    pub dump: Option<String>,
+    pub cache: Option<String>,
+    pub lexicon: Lexicon,
+    pub normalizer: Option<TextNormalizer>,
+}
+
+impl SpeechArgs {
+    pub fn from_args(args: &Args) -> Result<Self> {
+        let voice = args.value("voice").unwrap_or("en-US-AriaNeural");
+        let no_prosody = ["pitch", "rate", "volume", "style"].iter().all(|name| args.value(name).is_none());
+        let preset = match args.value("presets") {
+            Some("off") => None,
+            _ => VoicePresets::builtin().get(voice).cloned().filter(|_| no_prosody),
+        }
+        .unwrap_or_default();
+        let get = |name: &str, preset: &Option<String>| args.value(name).or(preset.as_deref()).unwrap_or("default").to_owned();
+        Ok(Self {
+            voice: voice.to_owned(),
+            pitch: get("pitch", &preset.pitch),
+            rate: get("rate", &preset.rate),
+            volume: get("volume", &preset.volume),
+            style: args.value("style").map(str::to_owned).or(preset.style),
+            style_degree: args.value("style-degree").map(str::to_owned).or(preset.style_degree),
+            format: args.value("format").unwrap_or("audio-24khz-48kbitrate-mono-mp3").to_owned(),
+            proxy: args.value("proxy").map(str::to_owned),
+            player: args.value("player").map(str::to_owned),
+            dump: args.value("dump").map(str::to_owned),
+            cache: args.value("cache").map(str::to_owned),
+            lexicon: args.value("lexicon").map(Lexicon::load).transpose()?.unwrap_or_default(),
+            normalizer: args.value("normalize").map(TextNormalizer::for_locale),
+        })
+    }
+
+    pub fn client(&self) -> Result<Client> {
+        let mut client = Client::from_env()?;
+        if let Some(proxy) = &self.proxy {
+            client = client.with_socks5_proxy(proxy);
+        }
+        if let Some(path) = &self.dump {
+            client = client.with_protocol_dump(ProtocolDump::to_file(path)?);
+        }
+        if let Some(dir) = &self.cache {
+            client = client.with_disk_cache(DiskCache::new(dir));
+        }
+        if let Some(normalizer) = &self.normalizer {
+            client = client.with_normalizer(normalizer.clone());
+        }
+        Ok(client.with_lexicon(self.lexicon.clone()))
+    }
+
+    /// SSML of `text`, normalized like [`Client::synthesize_request`] would.
+    pub fn ssml(&self, text: &str) -> String {
+        let text = match &self.normalizer {
+            Some(normalizer) => normalizer.normalize(text),
+            None => text.to_owned(),
+        };
+        self.request(&text).to_ssml_with_lexicon(&self.lexicon)
+    }
+
+    pub fn request(&self, text: &str) -> SynthesisRequest {
+        SynthesisRequest {
+            style: self.style.clone(),
+            style_degree: self.style_degree.clone(),
+            ..SynthesisRequest::new(text, &self.voice)
+                .with_pitch(&self.pitch)
+                .with_rate(&self.rate)
+                .with_volume(&self.volume)
+                .with_output_format(self.format.as_str())
+        }
+    }
+
+    pub fn player(&self) -> Result<edge_tts::Player> {
+        match &self.player {
+            Some(command) => edge_tts::Player::parse(command).ok_or_else(|| anyhow!("empty --player command")),
+            None => Ok(edge_tts::Player::default()),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::args::SPEECH_OPTIONS;
+
+/// `$XDG_CONFIG_HOME/edge-tts/config.toml`, or `~/.config/edge-tts/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("edge-tts").join("config.toml"))
+}
+
+/// Defaults of the speech options, named like them, eg:
+/// ```toml
+/// voice = "en-GB-SoniaNeural"
+/// rate = "+10%"
+/// format = "audio-24khz-96kbitrate-mono-mp3"
+/// cache = "~/.cache/edge-tts"
+/// ```
+pub fn load(path: &Path) -> Result<Vec<(String, String)>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+    parse(&text).with_context(|| format!("{}", path.display()))
+}
+
+/// Top level `key = value` pairs of TOML, values as text. A leading `~/` is the home directory.
+fn parse(text: &str) -> Result<Vec<(String, String)>> {
+    let mut values = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| anyhow!("line {}: {}", i + 1, message);
+        if line.starts_with('[') {
+            return Err(error("tables aren't supported"));
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| error("expected key = value"))?;
+        let key = key.trim().trim_matches('"');
+        if key == "config" || !SPEECH_OPTIONS.contains(&key) {
+            return Err(error(&format!("unknown key {}", key)));
+        }
+        let value = parse_value(value.trim()).map_err(|e| error(&e.to_string()))?;
+        let value = match (value.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest).to_string_lossy().into_owned(),
+            _ => value,
+        };
+        values.push((key.to_owned(), value));
+    }
+    Ok(values)
+}
+
+/// A basic or literal string, or a bare value such as a number, up to a comment.
+fn parse_value(s: &str) -> Result<String> {
+    let mut chars = s.chars();
+    let (value, rest) = match chars.next() {
+        Some('"') => {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => value.push(match chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some(c @ ('"' | '\\')) => c,
+                        _ => bail!("unsupported escape"),
+                    }),
+                    Some(c) => value.push(c),
+                    None => bail!("unterminated string"),
+                }
+            }
+            (value, chars.as_str())
+        }
+        Some('\'') => {
+            let (value, rest) = chars.as_str().split_once('\'').ok_or_else(|| anyhow!("unterminated string"))?;
+            (value.to_owned(), rest)
+        }
+        Some(_) => (s.split_once('#').map_or(s, |(value, _)| value).trim().to_owned(), ""),
+        None => bail!("missing value"),
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        bail!("unexpected {}", rest);
+    }
+    Ok(value)
+}
@@ -0,0 +1,32 @@
+use anyhow::{anyhow, Result};
+use edge_tts::{dub_subtitles, parse_srt, DubOptions};
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts dub SUBTITLES.srt --output FILE.wav [OPTIONS]
+
+Speak each subtitle at its time, writing a replacement audio track.
+
+    --output FILE       WAV file to write
+    --max-speedup X     speak cues that don't fit their time up to X times faster (default: 1.5)
+    --concurrency N     cues synthesized at the same time (default: 4)
+{}", SPEECH_USAGE)
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["output", "max-speedup", "concurrency"])?;
+    let path = args.positional().first().ok_or_else(|| anyhow!("missing SUBTITLES\n\n{}", usage()))?;
+    let output = args.value("output").ok_or_else(|| anyhow!("missing --output\n\n{}", usage()))?;
+    let defaults = DubOptions::default();
+    let options = DubOptions {
+        max_speedup: args.parsed("max-speedup")?.unwrap_or(defaults.max_speedup),
+        concurrency: args.parsed("concurrency")?.unwrap_or(defaults.concurrency),
+    };
+    let cues = parse_srt(&std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {}", path, e))?)?;
+    let speech = SpeechArgs::from_args(&args)?;
+    let wav = dub_subtitles(&speech.client()?, &cues, &speech.request(""), &options)?;
+    std::fs::write(output, wav).map_err(|e| anyhow!("{}: {}", output, e))?;
+    Ok(())
+}
@@ -0,0 +1,188 @@
+mod args;
+mod config;
+mod dub;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+mod notifications;
+#[cfg(feature = "server")]
+mod serve;
+mod tail;
+#[cfg(feature = "tui")]
+mod tune;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use edge_tts::{text_chunks, validate_ssml, FlushPolicy};
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+fn usage() -> String {
+    format!("\
+Usage: edge-tts [OPTIONS] [TEXT]...
+       edge-tts tail FILE [OPTIONS]
+       edge-tts dub SUBTITLES.srt --output FILE.wav [OPTIONS]
+       edge-tts tune [TEXT]... [OPTIONS]
+       edge-tts notifications [OPTIONS]
+       edge-tts mqtt --broker ADDR [OPTIONS]
+       edge-tts serve [OPTIONS]
+
+Speak TEXT, or stdin if no TEXT is given.
+
+    --output FILE     write audio to FILE instead of playing it (- for stdout)
+    --flush POLICY    when stdin is synthesized: line, paragraph, idle:MILLIS or eof (default: eof)
+    --dry-run         check and print the SSML of each chunk instead of synthesizing it
+{}{}", CAPTIONS_USAGE, SPEECH_USAGE)
+}
+
+#[cfg(feature = "captions")]
+const CAPTIONS_USAGE: &str = "\
+    --caption-file FILE   keep FILE updated with the words being spoken
+    --obs URL             push captions to obs-websocket, eg: ws://127.0.0.1:4455
+    --obs-password PASS
+    --obs-source NAME     OBS text source to update instead of sending stream captions
+";
+#[cfg(not(feature = "captions"))]
+const CAPTIONS_USAGE: &str = "";
+
+#[cfg(feature = "captions")]
+fn caption_sink(args: &Args) -> Result<Option<Box<dyn edge_tts::CaptionSink>>> {
+    use edge_tts::{CaptionFile, ObsCaptionTarget, ObsCaptions};
+    if let Some(url) = args.value("obs") {
+        let target = match args.value("obs-source") {
+            Some(name) => ObsCaptionTarget::TextSource(name.to_owned()),
+            None => ObsCaptionTarget::StreamCaption,
+        };
+        return Ok(Some(Box::new(ObsCaptions::connect(url, args.value("obs-password"), target)?)));
+    }
+    Ok(args.value("caption-file").map(|path| Box::new(CaptionFile::new(path)) as Box<dyn edge_tts::CaptionSink>))
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let mut argv = std::env::args().skip(1).peekable();
+    match argv.peek().map(String::as_str) {
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", usage());
+            Ok(())
+        }
+        Some("tail") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", tail::usage());
+                return Ok(());
+            }
+            tail::run(Args::parse(argv, tail::FLAGS)?)
+        }
+        Some("dub") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", dub::usage());
+                return Ok(());
+            }
+            dub::run(Args::parse(argv, &[])?)
+        }
+        #[cfg(feature = "tui")]
+        Some("tune") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", tune::usage());
+                return Ok(());
+            }
+            tune::run(Args::parse(argv, &[])?)
+        }
+        #[cfg(all(feature = "notifications", target_os = "linux"))]
+        Some("notifications") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", notifications::usage());
+                return Ok(());
+            }
+            notifications::run(Args::parse(argv, notifications::FLAGS)?)
+        }
+        #[cfg(feature = "mqtt")]
+        Some("mqtt") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", mqtt::usage());
+                return Ok(());
+            }
+            mqtt::run(Args::parse(argv, mqtt::FLAGS)?)
+        }
+        #[cfg(feature = "server")]
+        Some("serve") => {
+            argv.next();
+            if argv.peek().is_some_and(|a| a == "--help") {
+                println!("{}", serve::usage());
+                return Ok(());
+            }
+            serve::run(Args::parse(argv, &[])?)
+        }
+        _ => speak(Args::parse(argv, &["dry-run"])?),
+    }
+}
+
+fn parse_flush_policy(s: &str) -> Result<FlushPolicy> {
+    Ok(match s {
+        "line" => FlushPolicy::Line,
+        "paragraph" => FlushPolicy::Paragraph,
+        "eof" => FlushPolicy::Eof,
+        _ => match s.strip_prefix("idle:").map(str::parse) {
+            Some(Ok(millis)) => FlushPolicy::Idle(Duration::from_millis(millis)),
+            _ => bail!("invalid flush policy: {}", s),
+        },
+    })
+}
+
+fn speak(args: Args) -> Result<()> {
+    args.check(&["output", "flush", "dry-run", "caption-file", "obs", "obs-password", "obs-source"])?;
+    let speech = SpeechArgs::from_args(&args)?;
+    let chunks: Box<dyn Iterator<Item = std::io::Result<String>>> = if args.positional().is_empty() {
+        let policy = parse_flush_policy(args.value("flush").unwrap_or("eof"))?;
+        Box::new(text_chunks(std::io::stdin(), policy))
+    } else {
+        Box::new(std::iter::once(Ok(args.positional().join(" "))))
+    };
+    if args.flag("dry-run") {
+        for chunk in chunks {
+            let ssml = speech.ssml(&chunk?);
+            println!("{}", ssml);
+            validate_ssml(&ssml, &[])?;
+        }
+        return Ok(());
+    }
+    let client = speech.client()?;
+    let mut output: Option<Box<dyn Write>> = match args.value("output") {
+        None => None,
+        Some("-") => Some(Box::new(std::io::stdout())),
+        Some(path) => Some(Box::new(OpenOptions::new().create(true).truncate(true).write(true).open(path).map_err(|e| anyhow!("{}: {}", path, e))?)),
+    };
+    let player = speech.player()?;
+    #[cfg(feature = "captions")]
+    let mut captions = caption_sink(&args)?;
+    for chunk in chunks {
+        let synthesis = client.synthesize(&speech.ssml(&chunk?), &speech.format)?;
+        match &mut output {
+            Some(output) => {
+                output.write_all(&synthesis.audio)?;
+                output.flush()?;
+            }
+            #[cfg(feature = "captions")]
+            None if captions.is_some() => {
+                let sink = captions.as_deref_mut().ok_or_else(|| anyhow!("no caption sink"))?;
+                edge_tts::play_with_captions(&synthesis.audio, &synthesis.boundaries, &player, sink, &Default::default())?;
+            }
+            None => player.play(&synthesis.audio)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use edge_tts::{build_ssml, MqttClient, MqttOptions, OutputFormat};
+use serde_json::{json, Value};
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+pub const FLAGS: &[&str] = &["play"];
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts mqtt --broker ADDR [OPTIONS]
+
+Speak text published to an MQTT topic. Payloads are plain text or JSON:
+{{\"id\": \"door\", \"text\": \"Someone is at the door\", \"voice\": \"en-US-AriaNeural\", \"rate\": \"+10%\"}}
+Ids are letters, digits, '-' and '_'; others get an error status.
+
+    --broker ADDR         eg: 127.0.0.1:1883
+    --topic TOPIC         command topic (default: edge-tts/say)
+    --status-topic TOPIC  where status messages are published (default: edge-tts/status)
+    --output-dir DIR      save audio as DIR/ID.EXT and publish its path
+    --play                play the audio
+    --client-id ID        (default: edge-tts)
+    --username USER
+    --password PASS
+{}", SPEECH_USAGE)
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["broker", "topic", "status-topic", "output-dir", "play", "client-id", "username", "password"])?;
+    let broker = args.value("broker").ok_or_else(|| anyhow!("missing --broker\n\n{}", usage()))?;
+    let topic = args.value("topic").unwrap_or("edge-tts/say");
+    let status_topic = args.value("status-topic").unwrap_or("edge-tts/status");
+    let output_dir = args.value("output-dir").map(PathBuf::from);
+    let play = args.flag("play");
+    let speech = SpeechArgs::from_args(&args)?;
+    let client = speech.client()?;
+    let player = speech.player()?;
+    let options = MqttOptions {
+        client_id: args.value("client-id").unwrap_or("edge-tts").to_owned(),
+        username: args.value("username").map(str::to_owned),
+        password: args.value("password").map(str::to_owned),
+        ..Default::default()
+    };
+    if let Some(dir) = &output_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut mqtt = MqttClient::connect(broker, &options)?;
+    mqtt.subscribe(topic)?;
+    loop {
+        let (_, payload) = mqtt.next_message()?;
+        let payload = String::from_utf8_lossy(&payload);
+        let command: Value = match serde_json::from_str(&payload) {
+            Ok(value @ Value::Object(_)) => value,
+            _ => json!({ "text": payload.trim() }),
+        };
+        let get = |name: &str, default: &str| command.get(name).and_then(Value::as_str).unwrap_or(default).to_owned();
+        let id = get("id", &SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis().to_string());
+        // The id names the output file, so it mustn't reach outside the output directory.
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            let error = format!("invalid id {:?}: only letters, digits, '-' and '_' are allowed", id);
+            mqtt.publish(status_topic, json!({ "id": id, "status": "error", "error": error }).to_string().as_bytes(), false)?;
+            continue;
+        }
+        let format = get("format", &speech.format);
+        let ssml = build_ssml(&get("text", ""), &get("voice", &speech.voice), &get("pitch", &speech.pitch), &get("rate", &speech.rate), &get("volume", &speech.volume));
+
+        mqtt.publish(status_topic, json!({ "id": id, "status": "started" }).to_string().as_bytes(), false)?;
+        let result = client.synthesize(&ssml, &format).and_then(|output| {
+            let path = match &output_dir {
+                Some(dir) => {
+                    let path = dir.join(format!("{}.{}", id, OutputFormat::new(format.as_str()).extension()));
+                    std::fs::write(&path, &output.audio)?;
+                    Some(path)
+                }
+                None => None,
+            };
+            if play {
+                player.play(&output.audio)?;
+            }
+            Ok((path, output.audio.len()))
+        });
+        let status = match result {
+            Ok((path, bytes)) => json!({ "id": id, "status": "done", "path": path, "bytes": bytes }),
+            Err(e) => json!({ "id": id, "status": "error", "error": format!("{:#}", e) }),
+        };
+        mqtt.publish(status_topic, status.to_string().as_bytes(), false)?;
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use edge_tts::{listen_notifications, Announcer, NotificationFilter};
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+pub const FLAGS: &[&str] = &["body"];
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts notifications [OPTIONS]
+
+Speak desktop notifications (requires dbus-monitor).
+
+    --app NAMES          comma separated apps to speak, all if not given
+    --ignore-app NAMES   comma separated apps never to speak
+    --body               speak the body after the summary
+    --queue N            notifications waiting to be played before new ones are dropped (default: 4)
+{}", SPEECH_USAGE)
+}
+
+fn names(value: Option<&str>) -> Vec<String> {
+    value.map(|v| v.split(',').map(|s| s.trim().to_owned()).filter(|s| !s.is_empty()).collect()).unwrap_or_default()
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["app", "ignore-app", "body", "queue"])?;
+    let filter = NotificationFilter {
+        allow_apps: names(args.value("app")),
+        deny_apps: names(args.value("ignore-app")),
+    };
+    let include_body = args.flag("body");
+    let queue = args.parsed("queue")?.unwrap_or(4);
+    let speech = SpeechArgs::from_args(&args)?;
+    let (client, format, player) = (speech.client()?, speech.format.clone(), speech.player()?);
+    let announcer = Announcer::spawn(client, &format, player, queue, move |text| speech.ssml(text), |e| eprintln!("error: {:#}", e));
+    for notification in listen_notifications()? {
+        let notification = notification?;
+        if filter.matches(&notification) {
+            announcer.announce(notification.to_speech(include_body));
+        }
+    }
+    announcer.finish();
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::net::TcpListener;
+
+use anyhow::{anyhow, Result};
+use edge_tts::{HttpServer, SynthesisRequest, VoiceListCache};
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts serve [OPTIONS]
+
+Serve syntheses over HTTP, eg: as a sidecar of programs in other languages.
+
+    POST /synthesize  JSON body, eg: {{\"text\": \"Hello\", \"voice\": \"en-US-AriaNeural\", \"rate\": \"+10%\",
+                      \"output_format\": \"audio-24khz-48kbitrate-mono-mp3\"}}, replies with the audio,
+                      in chunks as it arrives with \"stream\": true
+    GET /events       eg: /events?text=Hello&voice=en-US-AriaNeural, or POST the same JSON: server-sent events
+                      for live captions: audio (base64), boundary ({{\"kind\": \"word\", \"offset_ms\": 100, ...}}), end
+    POST /v1/audio/speech  OpenAI's speech API, eg: {{\"model\": \"tts-1\", \"input\": \"Hello\", \"voice\": \"alloy\"}}
+    GET /voices       the voice list as JSON
+
+    --listen ADDR         (default: 127.0.0.1:5500)
+    --voices-cache DIR    keep the voice list in DIR instead of fetching it for each request
+    --openai-voices MAP   Edge voices of OpenAI voices, eg: alloy=en-GB-SoniaNeural,echo=en-GB-RyanNeural
+Speech options are the defaults of requests that don't give theirs.
+{}", SPEECH_USAGE)
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["listen", "voices-cache", "openai-voices"])?;
+    let listener = TcpListener::bind(args.value("listen").unwrap_or("127.0.0.1:5500"))?;
+    let speech = SpeechArgs::from_args(&args)?;
+    // Not the prosody preset of the default voice, which requests of other voices would get too.
+    let defaults = SynthesisRequest {
+        pitch: args.value("pitch").map(str::to_owned),
+        rate: args.value("rate").map(str::to_owned),
+        volume: args.value("volume").map(str::to_owned),
+        ..SynthesisRequest::new("", &speech.voice).with_output_format(speech.format.as_str())
+    };
+    let mut server = HttpServer::new(speech.client()?).with_defaults(defaults);
+    if let Some(dir) = args.value("voices-cache") {
+        server = server.with_voice_list(VoiceListCache::new(dir));
+    }
+    for pair in args.value("openai-voices").unwrap_or_default().split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (name, voice) = pair.split_once('=').ok_or_else(|| anyhow!("invalid --openai-voices entry: {}", pair))?;
+        server = server.with_openai_voice(name.trim(), voice.trim());
+    }
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    server.serve(listener)
+}
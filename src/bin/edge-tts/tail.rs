@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use edge_tts::{follow_file, text_chunks, Announcer, FlushPolicy, TailFilter};
+use regex::Regex;
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+pub const FLAGS: &[&str] = &["from-start"];
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts tail FILE [OPTIONS]
+
+Follow FILE and speak new lines.
+
+    --filter REGEX    only speak matching lines
+    --exclude REGEX   never speak matching lines
+    --interval SECS   minimum seconds between announcements (default: 5)
+    --queue N         announcements waiting to be played before new ones are dropped (default: 4)
+    --from-start      speak the existing content too
+{}", SPEECH_USAGE)
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["filter", "exclude", "interval", "queue", "from-start"])?;
+    let path = args.positional().first().ok_or_else(|| anyhow!("missing FILE\n\n{}", usage()))?;
+    let mut filter = TailFilter::new(
+        args.value("filter").map(Regex::new).transpose()?,
+        args.value("exclude").map(Regex::new).transpose()?,
+        Duration::from_secs_f64(args.parsed("interval")?.unwrap_or(5.0)),
+    );
+    let queue = args.parsed("queue")?.unwrap_or(4);
+    let speech = SpeechArgs::from_args(&args)?;
+    let (client, format, player) = (speech.client()?, speech.format.clone(), speech.player()?);
+    let announcer = Announcer::spawn(client, &format, player, queue, move |text| speech.ssml(text), |e| eprintln!("error: {:#}", e));
+    for line in text_chunks(follow_file(path, args.flag("from-start"))?, FlushPolicy::Line) {
+        filter.offer(&announcer, line?);
+    }
+    announcer.finish();
+    Ok(())
+}
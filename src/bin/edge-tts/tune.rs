@@ -0,0 +1,261 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use edge_tts::{Client, Player, Session, SynthesisOutput, SynthesisRequest};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::json;
+
+use crate::args::{Args, SpeechArgs, SPEECH_USAGE};
+
+/// Styles to choose from, after none. Voices ignore the ones they don't have.
+const STYLES: &[&str] = &[
+    "cheerful",
+    "sad",
+    "angry",
+    "excited",
+    "friendly",
+    "hopeful",
+    "whispering",
+    "chat",
+    "customerservice",
+    "narration-professional",
+    "newscast",
+];
+
+pub fn usage() -> String {
+    format!("\
+Usage: edge-tts tune [TEXT]... [OPTIONS]
+
+Adjust voice, rate, pitch, volume and style with the keyboard, hearing TEXT after each change.
+
+    Up/Down select   Left/Right adjust   Enter preview   q quit, printing the settings
+
+    --voices LIST     comma separated voices to choose from, eg: en-US-AriaNeural,en-US-GuyNeural
+    --export FILE     also write the settings as a JSON speaker profile to FILE on quit
+{}", SPEECH_USAGE)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Voice,
+    Rate,
+    Pitch,
+    Volume,
+    Style,
+}
+
+const FIELDS: [Field; 5] = [Field::Voice, Field::Rate, Field::Pitch, Field::Volume, Field::Style];
+
+struct Tuner {
+    voices: Vec<String>,
+    voice: usize,
+    /// Percent.
+    rate: i32,
+    /// Hz.
+    pitch: i32,
+    /// Percent.
+    volume: i32,
+    styles: Vec<String>,
+    /// Index into `styles` plus one, 0 for none.
+    style: usize,
+    selected: usize,
+    status: String,
+}
+
+/// eg: "+5%", "-10Hz"
+fn signed(value: i32, unit: &str) -> String {
+    format!("{:+}{}", value, unit)
+}
+
+/// eg: "+5%" → 5. "default" and named values count as 0.
+fn parse_signed(value: &str, unit: &str) -> i32 {
+    value.strip_suffix(unit).and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+impl Tuner {
+    fn voice(&self) -> &str {
+        self.voices.get(self.voice).map(String::as_str).unwrap_or("en-US-AriaNeural")
+    }
+
+    fn style(&self) -> Option<&str> {
+        self.style.checked_sub(1).and_then(|i| self.styles.get(i)).map(String::as_str)
+    }
+
+    fn adjust(&mut self, step: i32) {
+        match FIELDS.get(self.selected) {
+            Some(Field::Voice) => self.voice = (self.voice as i32 + step).rem_euclid(self.voices.len().max(1) as i32) as usize,
+            Some(Field::Rate) => self.rate = (self.rate + 5 * step).clamp(-50, 100),
+            Some(Field::Pitch) => self.pitch = (self.pitch + 2 * step).clamp(-50, 50),
+            Some(Field::Volume) => self.volume = (self.volume + 5 * step).clamp(-50, 50),
+            Some(Field::Style) => self.style = (self.style as i32 + step).rem_euclid(self.styles.len() as i32 + 1) as usize,
+            None => {}
+        }
+    }
+
+    fn request(&self, text: &str, format: &str) -> SynthesisRequest {
+        SynthesisRequest {
+            style: self.style().map(str::to_owned),
+            ..SynthesisRequest::new(text, self.voice())
+                .with_rate(signed(self.rate, "%"))
+                .with_pitch(signed(self.pitch, "Hz"))
+                .with_volume(signed(self.volume, "%"))
+                .with_output_format(format)
+        }
+    }
+
+    fn command_line(&self) -> String {
+        let mut line = format!("--voice {} --rate={} --pitch={} --volume={}", self.voice(), signed(self.rate, "%"), signed(self.pitch, "Hz"), signed(self.volume, "%"));
+        if let Some(style) = self.style() {
+            line += &format!(" --style {}", style);
+        }
+        line
+    }
+
+    fn profile(&self) -> serde_json::Value {
+        json!({
+            "voice": self.voice(),
+            "rate": signed(self.rate, "%"),
+            "pitch": signed(self.pitch, "Hz"),
+            "volume": signed(self.volume, "%"),
+            "style": self.style(),
+        })
+    }
+
+    fn draw(&self, frame: &mut Frame, text: &str) {
+        let [settings, sample, status] = Layout::vertical([Constraint::Length(FIELDS.len() as u16 + 2), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let items: Vec<ListItem> = FIELDS
+            .iter()
+            .map(|field| {
+                ListItem::new(match field {
+                    Field::Voice => format!("Voice   {}", self.voice()),
+                    Field::Rate => format!("Rate    {}", signed(self.rate, "%")),
+                    Field::Pitch => format!("Pitch   {}", signed(self.pitch, "Hz")),
+                    Field::Volume => format!("Volume  {}", signed(self.volume, "%")),
+                    Field::Style => format!("Style   {}", self.style().unwrap_or("none")),
+                })
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" edge-tts tune "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, settings, &mut ListState::default().with_selected(Some(self.selected)));
+        let sample_text = Paragraph::new(text).wrap(Wrap { trim: true }).block(Block::default().borders(Borders::ALL).title(" Text "));
+        frame.render_widget(sample_text, sample);
+        frame.render_widget(Line::from(self.status.as_str()), status);
+    }
+}
+
+/// `ssml` synthesized on `session`, opened on first use and again after the service closed an idle one.
+fn synthesize_on(client: &Client, session: &mut Option<Session>, ssml: &str, format: &str) -> Result<SynthesisOutput> {
+    if let Some(open) = session {
+        if let Ok(output) = open.synthesize(ssml) {
+            return Ok(output);
+        }
+    }
+    let mut fresh = client.session(format)?;
+    let output = fresh.synthesize(ssml);
+    *session = output.is_ok().then_some(fresh);
+    output
+}
+
+/// Synthesize and play previews one at a time on another thread, on one connection, so keys keep working while
+/// audio plays and changes are heard without a handshake each.
+fn spawn_previewer(client: Client, format: String, player: Player, status: Sender<String>) -> Sender<String> {
+    let (requests, receiver): (Sender<String>, Receiver<String>) = channel();
+    std::thread::spawn(move || {
+        let mut session = None;
+        while let Ok(mut ssml) = receiver.recv() {
+            // Only the latest change is worth hearing.
+            while let Ok(newer) = receiver.try_recv() {
+                ssml = newer;
+            }
+            let _ = status.send("Synthesizing...".to_owned());
+            let result = synthesize_on(&client, &mut session, &ssml, &format).and_then(|output| {
+                let _ = status.send("Playing".to_owned());
+                player.play(&output.audio)
+            });
+            let _ = status.send(match result {
+                Ok(()) => "Enter to hear again".to_owned(),
+                Err(e) => format!("error: {:#}", e),
+            });
+        }
+    });
+    requests
+}
+
+pub fn run(args: Args) -> Result<()> {
+    args.check(&["voices", "export"])?;
+    let speech = SpeechArgs::from_args(&args)?;
+    let text = match args.positional() {
+        [] => "The quick brown fox jumps over the lazy dog.".to_owned(),
+        words => words.join(" "),
+    };
+    let mut voices: Vec<String> = args.value("voices").unwrap_or_default().split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_owned).collect();
+    if !voices.contains(&speech.voice) {
+        voices.insert(0, speech.voice.clone());
+    }
+    let styles: Vec<String> = STYLES.iter().map(|s| s.to_string()).chain(speech.style.clone().filter(|s| !STYLES.contains(&s.as_str()))).collect();
+    let mut tuner = Tuner {
+        voice: voices.iter().position(|v| *v == speech.voice).unwrap_or(0),
+        voices,
+        rate: parse_signed(&speech.rate, "%"),
+        pitch: parse_signed(&speech.pitch, "Hz"),
+        volume: parse_signed(&speech.volume, "%"),
+        style: speech.style.as_ref().and_then(|style| styles.iter().position(|s| s == style)).map_or(0, |i| i + 1),
+        styles,
+        selected: 0,
+        status: "Enter to preview".to_owned(),
+    };
+    let (status_sender, status) = channel();
+    let previews = spawn_previewer(speech.client()?, speech.format.clone(), speech.player()?, status_sender);
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut tuner, &text, &speech, &previews, &status);
+    ratatui::restore();
+    result?;
+    println!("{}", tuner.command_line());
+    if let Some(path) = args.value("export") {
+        std::fs::write(path, serde_json::to_string_pretty(&tuner.profile())?).map_err(|e| anyhow!("{}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, tuner: &mut Tuner, text: &str, speech: &SpeechArgs, previews: &Sender<String>, status: &Receiver<String>) -> Result<()> {
+    // Normalized like `Client::synthesize_request` would.
+    let spoken = match &speech.normalizer {
+        Some(normalizer) => normalizer.normalize(text),
+        None => text.to_owned(),
+    };
+    let preview = |tuner: &Tuner| tuner.request(&spoken, &speech.format).to_ssml_with_lexicon(&speech.lexicon);
+    loop {
+        while let Ok(message) = status.try_recv() {
+            tuner.status = message;
+        }
+        terminal.draw(|frame| tuner.draw(frame, text))?;
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up => tuner.selected = tuner.selected.saturating_sub(1),
+            KeyCode::Down => tuner.selected = (tuner.selected + 1).min(FIELDS.len() - 1),
+            KeyCode::Left | KeyCode::Right => {
+                tuner.adjust(if key.code == KeyCode::Left { -1 } else { 1 });
+                let _ = previews.send(preview(tuner));
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let _ = previews.send(preview(tuner));
+            }
+            _ => {}
+        }
+    }
+}
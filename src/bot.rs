@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+
+use crate::ogg;
+use crate::Client;
+
+/// Telegram voice notes and Discord voice messages both want Ogg Opus.
+pub const VOICE_MESSAGE_FORMAT: &str = "ogg-48khz-16bit-mono-opus";
+
+/// An Ogg Opus voice message ready to be uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceMessage {
+    pub audio: Vec<u8>,
+    pub duration: Duration,
+    /// Up to 256 amplitude samples (0-255) for Discord's waveform preview.
+    pub waveform: Vec<u8>,
+}
+
+impl VoiceMessage {
+    /// Wrap Ogg Opus `audio`, reading its duration from the granule positions.
+    ///
+    /// The waveform is approximated by Opus packet sizes, which grow with signal energy, to avoid decoding.
+    pub fn from_ogg(audio: Vec<u8>) -> Result<Self> {
+        let duration = Duration::from_secs_f64(ogg::opus_duration_samples(&audio)? as f64 / 48000.0);
+        // The first two packets are OpusHead and OpusTags.
+        let sizes: Vec<usize> = ogg::packets(&audio)?.iter().skip(2).map(Vec::len).collect();
+        let waveform = match sizes.iter().max() {
+            Some(&max) if max > 0 => {
+                let n = sizes.len().min(256);
+                (0..n)
+                    .map(|i| {
+                        let bucket = &sizes[i * sizes.len() / n..((i + 1) * sizes.len() / n).max(i * sizes.len() / n + 1)];
+                        let avg = bucket.iter().sum::<usize>() / bucket.len();
+                        (avg * 255 / max) as u8
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        Ok(Self { audio, duration, waveform })
+    }
+}
+
+/// Synthesize `ssml` as a voice message.
+pub fn synthesize_voice_message(client: &Client, ssml: &str) -> Result<VoiceMessage> {
+    VoiceMessage::from_ogg(client.synthesize(ssml, VOICE_MESSAGE_FORMAT)?.audio)
+}
+
+struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Multipart {
+    fn new() -> Self {
+        Self {
+            boundary: format!("edge-tts-{}", uuid::Uuid::new_v4().simple()),
+            body: Vec::new(),
+        }
+    }
+
+    fn text(mut self, name: &str, value: &str) -> Self {
+        self.body.extend(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", self.boundary, name, value).as_bytes());
+        self
+    }
+
+    fn file(mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) -> Self {
+        self.body.extend(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n", self.boundary, name, filename, content_type).as_bytes());
+        self.body.extend(data);
+        self.body.extend(b"\r\n");
+        self
+    }
+
+    fn send(mut self, request: ureq::Request) -> Result<Value> {
+        self.body.extend(format!("--{}--\r\n", self.boundary).as_bytes());
+        let response = request
+            .set("Content-Type", &format!("multipart/form-data; boundary={}", self.boundary))
+            .send_bytes(&self.body);
+        match response {
+            Ok(response) => Ok(response.into_json()?),
+            Err(ureq::Error::Status(code, response)) => Err(anyhow!("http status {}: {}", code, response.into_string().unwrap_or_default())),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Telegram Bot API `sendVoice`. Returns the sent `Message` object.
+///
+/// `chat_id`: eg: "123456789" or "@channelusername"
+pub fn telegram_send_voice(bot_token: &str, chat_id: &str, message: &VoiceMessage, reply_to_message_id: Option<i64>) -> Result<Value> {
+    let mut form = Multipart::new()
+        .text("chat_id", chat_id)
+        .text("duration", &message.duration.as_secs().max(1).to_string());
+    if let Some(id) = reply_to_message_id {
+        form = form.text("reply_parameters", &json!({ "message_id": id }).to_string());
+    }
+    let response = form
+        .file("voice", "voice.ogg", "audio/ogg", &message.audio)
+        .send(ureq::post(&format!("https://api.telegram.org/bot{}/sendVoice", bot_token)))?;
+    response.get("result").cloned().ok_or_else(|| anyhow!("telegram error: {}", response))
+}
+
+/// Discord API: post a voice message to `channel_id`. Returns the created message object.
+pub fn discord_send_voice_message(bot_token: &str, channel_id: &str, message: &VoiceMessage, reply_to_message_id: Option<&str>) -> Result<Value> {
+    let mut payload = json!({
+        // IS_VOICE_MESSAGE
+        "flags": 1 << 13,
+        "attachments": [{
+            "id": "0",
+            "filename": "voice-message.ogg",
+            "duration_secs": message.duration.as_secs_f64(),
+            "waveform": base64::engine::general_purpose::STANDARD.encode(&message.waveform),
+        }],
+    });
+    if let Some(id) = reply_to_message_id {
+        payload["message_reference"] = json!({ "message_id": id });
+    }
+    Multipart::new()
+        .text("payload_json", &payload.to_string())
+        .file("files[0]", "voice-message.ogg", "audio/ogg", &message.audio)
+        .send(ureq::post(&format!("https://discord.com/api/v10/channels/{}/messages", channel_id)).set("Authorization", &format!("Bot {}", bot_token)))
+}
@@ -0,0 +1,80 @@
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+
+/// Edge build the requests look like they come from, unless updated, see [`set_edge_version`].
+pub const DEFAULT_EDGE_VERSION: &str = "143.0.3650.139";
+
+static EDGE_VERSION: RwLock<Option<String>> = RwLock::new(None);
+
+/// Edge build of clients without [`crate::Client::with_edge_version`], eg: "143.0.3650.139".
+pub fn edge_version() -> String {
+    let version = EDGE_VERSION.read().unwrap_or_else(|e| e.into_inner());
+    version.clone().unwrap_or_else(|| DEFAULT_EDGE_VERSION.to_owned())
+}
+
+/// Make `version` the Edge build of clients without [`crate::Client::with_edge_version`]. Fails unless it looks like
+/// "143.0.3650.139".
+pub fn set_edge_version(version: &str) -> Result<()> {
+    check_version(version)?;
+    *EDGE_VERSION.write().unwrap_or_else(|e| e.into_inner()) = Some(version.to_owned());
+    Ok(())
+}
+
+/// Fetch the current Edge stable build from Microsoft's update service and [`set_edge_version`] it. On failure the
+/// version stays as it was, so callers can ignore the error to keep the built-in one offline.
+#[cfg(feature = "edge_update")]
+pub fn update_edge_version() -> Result<String> {
+    let products = ureq::get("https://edgeupdates.microsoft.com/api/products").call()?.into_string()?;
+    let version = stable_version(&serde_json::from_str(&products)?).ok_or_else(|| anyhow!("no Edge stable release for Windows x64"))?;
+    set_edge_version(&version)?;
+    Ok(version)
+}
+
+/// ProductVersion of the Windows x64 release of the Stable product.
+#[cfg(feature = "edge_update")]
+fn stable_version(products: &serde_json::Value) -> Option<String> {
+    let stable = products.as_array()?.iter().find(|product| product.get("Product").and_then(|p| p.as_str()) == Some("Stable"))?;
+    let release = stable.get("Releases")?.as_array()?.iter().find(|release| {
+        release.get("Platform").and_then(|p| p.as_str()) == Some("Windows") && release.get("Architecture").and_then(|a| a.as_str()) == Some("x64")
+    })?;
+    Some(release.get("ProductVersion")?.as_str()?.to_owned())
+}
+
+pub(crate) fn check_version(version: &str) -> Result<()> {
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() != 4 || parts.iter().any(|part| part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit())) {
+        return Err(anyhow!("bad Edge version: {:?}, eg: {}", version, DEFAULT_EDGE_VERSION));
+    }
+    Ok(())
+}
+
+/// eg: "Mozilla/5.0 (...) Chrome/143.0.0.0 Safari/537.36 Edg/143.0.0.0"
+pub(crate) fn user_agent(version: &str) -> String {
+    let major = version.split('.').next().unwrap_or_default();
+    format!("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{0}.0.0.0 Safari/537.36 Edg/{0}.0.0.0", major)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_and_checks_versions() {
+        assert!(user_agent("143.0.3650.139").ends_with("Chrome/143.0.0.0 Safari/537.36 Edg/143.0.0.0"));
+        assert!(check_version(DEFAULT_EDGE_VERSION).is_ok());
+        assert!(check_version("143.0.3650").is_err());
+        assert!(check_version("143.0.3650.1\r\nX: y").is_err());
+        #[cfg(feature = "edge_update")]
+        {
+            let products = serde_json::json!([
+                { "Product": "Beta", "Releases": [{ "Platform": "Windows", "Architecture": "x64", "ProductVersion": "144.0.1.2" }] },
+                { "Product": "Stable", "Releases": [
+                    { "Platform": "MacOS", "Architecture": "universal", "ProductVersion": "143.0.3650.140" },
+                    { "Platform": "Windows", "Architecture": "x64", "ProductVersion": "143.0.3650.139" },
+                ] },
+            ]);
+            assert_eq!(stable_version(&products).as_deref(), Some("143.0.3650.139"));
+        }
+    }
+}
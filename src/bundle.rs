@@ -0,0 +1,86 @@
+use std::io::{Cursor, Write};
+
+use anyhow::Result;
+use serde_json::json;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+use crate::save::audio_duration;
+use crate::subtitle::{cues_from_boundaries, to_srt, to_webvtt};
+use crate::{Container, OutputFormat, SynthesisOutput};
+
+/// Characters per subtitle cue when grouping word boundaries.
+const CUE_CHARS: usize = 42;
+
+/// Package `output` in `format` as a ZIP holding, in this order:
+///
+/// - `manifest.json`: format, duration and the paths below, with `text` when given
+/// - `audio.<ext>`
+/// - `subtitles.srt` and `subtitles.vtt`, built from the boundaries
+/// - `marks.json`: the boundaries, offsets in 100ns ticks
+///
+/// Entries carry a fixed timestamp and permissions, so the same output always gives the same bytes.
+pub fn write_bundle(output: &SynthesisOutput, format: &OutputFormat, text: Option<&str>) -> Result<Vec<u8>> {
+    let audio = format!("audio.{}", format.extension());
+    let manifest = json!({
+        "format": format.as_str(),
+        "audio": audio,
+        "duration_ms": output.duration.or_else(|| audio_duration(format, &output.audio)).map(|d| d.as_millis() as u64),
+        "subtitles": { "srt": "subtitles.srt", "vtt": "subtitles.vtt" },
+        "marks": "marks.json",
+        "text": text,
+    });
+    let cues = cues_from_boundaries(&output.boundaries, CUE_CHARS);
+    let marks: Vec<_> = output.boundaries.iter().map(|b| b.to_json()).collect();
+    let entries = [
+        ("manifest.json", serde_json::to_vec_pretty(&manifest)?),
+        (audio.as_str(), output.audio.clone()),
+        ("subtitles.srt", to_srt(&cues).into_bytes()),
+        ("subtitles.vtt", to_webvtt(&cues).into_bytes()),
+        ("marks.json", serde_json::to_vec_pretty(&marks)?),
+    ];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        // Compressed audio doesn't shrink any further.
+        let compressed = name != audio || matches!(format.container(), Container::Raw | Container::Riff);
+        let options = FileOptions::default()
+            .compression_method(if compressed { CompressionMethod::Deflated } else { CompressionMethod::Stored })
+            .last_modified_time(DateTime::default())
+            .unix_permissions(0o644);
+        zip.start_file(name, options)?;
+        zip.write_all(&data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::{Boundary, BoundaryKind};
+
+    #[test]
+    fn writes_deterministic_bundle() {
+        let output = SynthesisOutput {
+            audio: vec![0; 6000],
+            boundaries: vec![Boundary {
+                kind: BoundaryKind::Word,
+                offset: Duration::from_millis(100),
+                duration: Duration::from_millis(400),
+                text: "Hello".to_owned(),
+            }],
+            ..Default::default()
+        };
+        let bundle = write_bundle(&output, &OutputFormat::default(), Some("Hello")).unwrap();
+        assert_eq!(bundle, write_bundle(&output, &OutputFormat::default(), Some("Hello")).unwrap());
+        let mut archive = ZipArchive::new(Cursor::new(bundle)).unwrap();
+        assert_eq!(archive.len(), 5);
+        assert_eq!(archive.by_index(1).unwrap().name(), "audio.mp3");
+        let manifest: serde_json::Value = serde_json::from_reader(archive.by_name("manifest.json").unwrap()).unwrap();
+        assert_eq!(manifest["duration_ms"], 1000);
+        assert_eq!(manifest["audio"], "audio.mp3");
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::metadata::Boundary;
+use crate::storage::{FsStorage, Storage};
+use crate::{Client, SynthesisOutput};
+
+/// Everything that determines the synthesized audio. The SSML carries text, voice and prosody.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SynthKey {
+    pub ssml: String,
+    /// eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub output_format: String,
+    /// The speech.config message sent, with the metadata options or the one of
+    /// [`crate::Client::with_speech_config`].
+    pub speech_config: String,
+}
+
+impl SynthKey {
+    /// Hex encoded SHA-256 of the key, used as file name.
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [self.ssml.as_str(), self.output_format.as_str(), self.speech_config.as_str()] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Synthesis results in a [`Storage`]: `<digest>.audio` and `<digest>.json` with the boundaries.
+///
+/// Entries older than the TTL are ignored and removed. When the total size exceeds the limit, the least recently
+/// written entries are removed first.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    storage: Arc<dyn Storage>,
+    namespace: String,
+    max_bytes: Option<u64>,
+    ttl: Option<Duration>,
+}
+
+impl DiskCache {
+    /// Files directly in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_storage(Arc::new(FsStorage::new(dir)), "")
+    }
+
+    /// eg: `namespace`: "cache"
+    pub fn with_storage(storage: Arc<dyn Storage>, namespace: impl Into<String>) -> Self {
+        Self {
+            storage,
+            namespace: namespace.into(),
+            max_bytes: None,
+            ttl: None,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        match self.ttl {
+            Some(ttl) => modified.elapsed().map(|age| age > ttl).unwrap_or(false),
+            None => false,
+        }
+    }
+
+    pub fn get(&self, key: &SynthKey) -> Option<SynthesisOutput> {
+        let digest = key.digest();
+        let audio_key = format!("{}.audio", digest);
+        let modified = self.storage.stat(&self.namespace, &audio_key).ok()??.modified;
+        if self.is_expired(modified) {
+            self.remove_entry(&digest);
+            return None;
+        }
+        let audio = self.storage.get(&self.namespace, &audio_key).ok()??;
+        let boundaries: Value = serde_json::from_slice(&self.storage.get(&self.namespace, &format!("{}.json", digest)).ok()??).ok()?;
+        let boundaries = boundaries.as_array()?.iter().map(Boundary::from_json).collect::<Option<Vec<_>>>()?;
+        Some(SynthesisOutput { audio, boundaries, ..Default::default() })
+    }
+
+    pub fn put(&self, key: &SynthKey, output: &SynthesisOutput) -> Result<()> {
+        let digest = key.digest();
+        let boundaries = Value::Array(output.boundaries.iter().map(Boundary::to_json).collect());
+        // The audio marks a complete entry, so it is stored last.
+        self.storage.put(&self.namespace, &format!("{}.json", digest), boundaries.to_string().as_bytes())?;
+        self.storage.put(&self.namespace, &format!("{}.audio", digest), &output.audio)?;
+        self.evict()
+    }
+
+    /// Remove expired entries, then the oldest ones until the cache fits in its size limit.
+    pub fn evict(&self) -> Result<()> {
+        let stored = self.storage.list(&self.namespace)?;
+        let json_lens: HashMap<&str, u64> = stored.iter().filter_map(|e| Some((e.key.strip_suffix(".json")?, e.len))).collect();
+        let mut entries = Vec::new();
+        for entry in &stored {
+            if let Some(digest) = entry.key.strip_suffix(".audio") {
+                if self.is_expired(entry.modified) {
+                    self.remove_entry(digest);
+                } else {
+                    entries.push((entry.modified, entry.len + json_lens.get(digest).copied().unwrap_or(0), digest));
+                }
+            }
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            entries.sort();
+            let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+            for (_, len, digest) in entries {
+                if total <= max_bytes {
+                    break;
+                }
+                self.remove_entry(digest);
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_entry(&self, digest: &str) {
+        let _ = self.storage.delete(&self.namespace, &format!("{}.audio", digest));
+        let _ = self.storage.delete(&self.namespace, &format!("{}.json", digest));
+    }
+}
+
+/// Hit and miss counts of an [`LruCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct LruInner<K, V> {
+    entries: HashMap<K, (u64, V)>,
+    /// Last use tick to key, oldest first.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+/// Thread safe in-memory cache keeping the `capacity` most recently used entries.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    inner: Mutex<LruInner<K, V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(LruInner {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruInner<K, V>> {
+        // The cache stays consistent even if a panic poisoned the lock.
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.lock();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let LruInner { entries, order, .. } = &mut *inner;
+        match entries.get_mut(key) {
+            Some((last_used, value)) => {
+                order.remove(last_used);
+                order.insert(tick, key.clone());
+                *last_used = tick;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value.clone())
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.lock();
+        inner.tick += 1;
+        let tick = inner.tick;
+        if let Some((last_used, _)) = inner.entries.insert(key.clone(), (tick, value)) {
+            inner.order.remove(&last_used);
+        }
+        inner.order.insert(tick, key);
+        while inner.entries.len() > self.capacity {
+            match inner.order.pop_first() {
+                Some((_, oldest)) => {
+                    inner.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// [`Client`] wrapper answering repeated requests from an in-memory [`LruCache`].
+///
+/// ```no_run
+/// use edge_tts::{build_ssml, CachedClient, Client};
+///
+/// let client = CachedClient::new(Client::new(), 64);
+/// let ssml = build_ssml("3", "en-US-AriaNeural", "default", "default", "default");
+/// let audio = client.synthesize_audio(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap();
+/// let again = client.synthesize_audio(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap();
+/// assert_eq!(client.cache().stats().hits, 1);
+/// ```
+pub struct CachedClient {
+    client: Client,
+    cache: LruCache<SynthKey, Arc<Vec<u8>>>,
+}
+
+impl CachedClient {
+    pub fn new(client: Client, capacity: usize) -> Self {
+        Self {
+            client,
+            cache: LruCache::new(capacity),
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &LruCache<SynthKey, Arc<Vec<u8>>> {
+        &self.cache
+    }
+
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn synthesize_audio(&self, ssml: &str, output_format: &str) -> Result<Arc<Vec<u8>>> {
+        let key = self.client.synth_key(ssml, output_format);
+        if let Some(audio) = self.cache.get(&key) {
+            return Ok(audio);
+        }
+        let audio = Arc::new(self.client.synthesize(ssml, output_format)?.audio);
+        self.cache.put(key, audio.clone());
+        Ok(audio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::{build_ssml, BoundaryKind, MetadataOptions};
+
+    fn key(ssml: &str) -> SynthKey {
+        SynthKey {
+            ssml: ssml.to_owned(),
+            output_format: "audio-24khz-48kbitrate-mono-mp3".to_owned(),
+            speech_config: String::new(),
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("edge-tts-cache-{}", uuid::Uuid::new_v4().simple()))
+    }
+
+    #[test]
+    fn digest_depends_on_every_field() {
+        let a = key("<speak>a</speak>");
+        assert_eq!(a.digest(), key("<speak>a</speak>").digest());
+        assert_ne!(a.digest(), key("<speak>b</speak>").digest());
+        assert_ne!(a.digest(), SynthKey { output_format: "riff-24khz-16bit-mono-pcm".to_owned(), ..a.clone() }.digest());
+        assert_ne!(a.digest(), SynthKey { speech_config: "{}".to_owned(), ..a.clone() }.digest());
+    }
+
+    #[test]
+    fn round_trips_and_evicts_oldest() {
+        let dir = temp_dir();
+        let cache = DiskCache::new(&dir).with_max_bytes(150);
+        let output = SynthesisOutput {
+            audio: vec![1; 50],
+            boundaries: vec![Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(100), duration: Duration::from_millis(300), text: "a".to_owned() }],
+            ..Default::default()
+        };
+        cache.put(&key("1"), &output).unwrap();
+        assert_eq!(cache.get(&key("1")), Some(output.clone()));
+        std::thread::sleep(Duration::from_millis(20));
+        cache.put(&key("2"), &SynthesisOutput { audio: vec![2; 100], ..Default::default() }).unwrap();
+        assert_eq!(cache.get(&key("1")), None);
+        assert_eq!(cache.get(&key("2")).map(|o| o.audio), Some(vec![2; 100]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keys_on_metadata_options() {
+        let dir = temp_dir();
+        let server = MockServer::start(vec![MockReply::turn(b"plain"), MockReply::turn(b"visemes")]).unwrap();
+        let plain = server.client().with_disk_cache(DiskCache::new(&dir));
+        let visemes = plain.clone().with_metadata_options(MetadataOptions { viseme_enabled: true, ..Default::default() });
+        let ssml = build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default");
+        assert_eq!(plain.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"plain");
+        assert_eq!(visemes.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"visemes");
+        assert_eq!(plain.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"plain");
+        assert_eq!(server.requests().len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ignores_expired_entries() {
+        let dir = temp_dir();
+        let cache = DiskCache::new(&dir).with_ttl(Duration::from_millis(10));
+        cache.put(&key("1"), &SynthesisOutput::default()).unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&key("1")), None);
+        assert!(!dir.join(format!("{}.audio", key("1").digest())).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = LruCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.put("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+        cache.put("c", 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"c"), Some(4));
+        assert_eq!(cache.stats(), CacheStats { hits: 4, misses: 1 });
+    }
+
+    #[test]
+    fn zero_capacity_stores_nothing() {
+        let cache = LruCache::new(0);
+        cache.put(1, 1);
+        assert!(cache.is_empty());
+    }
+}
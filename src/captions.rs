@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::{Message, WebSocket};
+
+use crate::metadata::{Boundary, BoundaryKind};
+use crate::stream::{connect_stream, websocket_handshake, Stream};
+use crate::Player;
+
+/// Where live captions go.
+pub trait CaptionSink {
+    /// Replace the displayed caption with `text`. An empty `text` clears it.
+    fn show(&mut self, text: &str) -> Result<()>;
+}
+
+/// Caption written to a text file, eg: for an OBS "Text (GDI+)" source reading from file.
+#[derive(Debug, Clone)]
+pub struct CaptionFile {
+    path: PathBuf,
+}
+
+impl CaptionFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CaptionSink for CaptionFile {
+    fn show(&mut self, text: &str) -> Result<()> {
+        // Readers polling the file never see it half written.
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, text)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// What [`ObsCaptions`] updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObsCaptionTarget {
+    /// `SendStreamCaption`: CEA-608 captions of the active stream.
+    StreamCaption,
+    /// `SetInputSettings` on the text source with this name.
+    TextSource(String),
+}
+
+/// Caption pushed to OBS through obs-websocket 5.
+pub struct ObsCaptions {
+    socket: WebSocket<Box<dyn Stream>>,
+    target: ObsCaptionTarget,
+    next_request_id: u64,
+}
+
+impl ObsCaptions {
+    /// `url`: eg: "ws://127.0.0.1:4455"
+    pub fn connect(url: &str, password: Option<&str>, target: ObsCaptionTarget) -> Result<Self> {
+        let url = url::Url::parse(url)?;
+        let stream = connect_stream(&url, None)?;
+        let mut socket = websocket_handshake(url.into_client_request()?, stream, None)?;
+        let hello = read_op(&mut socket, 0)?;
+        let mut identify = json!({ "rpcVersion": 1, "eventSubscriptions": 0 });
+        if let Some(auth) = hello.get("authentication") {
+            let password = password.ok_or_else(|| anyhow!("obs-websocket requires a password"))?;
+            let challenge = auth.get("challenge").and_then(Value::as_str).unwrap_or("");
+            let salt = auth.get("salt").and_then(Value::as_str).unwrap_or("");
+            identify["authentication"] = json!(obs_auth(password, salt, challenge));
+        }
+        socket.send(Message::Text(json!({ "op": 1, "d": identify }).to_string()))?;
+        read_op(&mut socket, 2)?;
+        Ok(Self {
+            socket,
+            target,
+            next_request_id: 1,
+        })
+    }
+
+    fn request(&mut self, request_type: &str, request_data: Value) -> Result<()> {
+        let request_id = self.next_request_id.to_string();
+        self.next_request_id += 1;
+        self.socket.send(Message::Text(json!({
+            "op": 6,
+            "d": { "requestType": request_type, "requestId": request_id, "requestData": request_data },
+        }).to_string()))?;
+        loop {
+            let response = read_op(&mut self.socket, 7)?;
+            if response.get("requestId").and_then(Value::as_str) == Some(request_id.as_str()) {
+                let status = response.get("requestStatus");
+                if status.and_then(|s| s.get("result")).and_then(Value::as_bool) == Some(true) {
+                    return Ok(());
+                }
+                bail!("obs {} failed: {}", request_type, status.and_then(|s| s.get("comment")).and_then(Value::as_str).unwrap_or("unknown error"));
+            }
+        }
+    }
+}
+
+impl CaptionSink for ObsCaptions {
+    fn show(&mut self, text: &str) -> Result<()> {
+        match self.target.clone() {
+            ObsCaptionTarget::StreamCaption => self.request("SendStreamCaption", json!({ "captionText": text })),
+            ObsCaptionTarget::TextSource(name) => self.request("SetInputSettings", json!({ "inputName": name, "inputSettings": { "text": text } })),
+        }
+    }
+}
+
+fn obs_auth(password: &str, salt: &str, challenge: &str) -> String {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let secret = base64.encode(Sha256::digest(format!("{}{}", password, salt)));
+    base64.encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+/// Read messages until one with opcode `op`, returning its `d` field.
+fn read_op(socket: &mut WebSocket<Box<dyn Stream>>, op: u64) -> Result<Value> {
+    loop {
+        match socket.read()? {
+            Message::Text(s) => {
+                let message: Value = serde_json::from_str(&s)?;
+                if message.get("op").and_then(Value::as_u64) == Some(op) {
+                    return Ok(message.get("d").cloned().unwrap_or(Value::Null));
+                }
+            }
+            Message::Close(frame) => bail!("obs-websocket closed the connection: {}", frame.map(|f| f.reason.into_owned()).unwrap_or_default()),
+            _ => {}
+        }
+    }
+}
+
+/// How words are grouped into caption lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptionOptions {
+    /// A new line starts after this many words.
+    pub max_words: usize,
+    /// How long the last line stays after the speech ends.
+    pub linger: Duration,
+}
+
+impl Default for CaptionOptions {
+    fn default() -> Self {
+        Self {
+            max_words: 12,
+            linger: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Play `audio` and show word boundaries on `sink` in time with it. Returns when both are done.
+pub fn play_with_captions(audio: &[u8], boundaries: &[Boundary], player: &Player, sink: &mut dyn CaptionSink, options: &CaptionOptions) -> Result<()> {
+    thread::scope(|scope| {
+        let start = Instant::now();
+        let playing = scope.spawn(|| player.play(audio));
+        let mut line: Vec<&str> = Vec::new();
+        let mut end = Duration::ZERO;
+        // Sentences would repeat the words, and visemes and bookmarks aren't speech.
+        for boundary in boundaries.iter().filter(|b| b.kind == BoundaryKind::Word) {
+            if let Some(wait) = boundary.offset.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+            if line.len() >= options.max_words.max(1) {
+                line.clear();
+            }
+            line.push(&boundary.text);
+            sink.show(&line.join(" "))?;
+            end = end.max(boundary.offset + boundary.duration);
+        }
+        if let Some(wait) = (end + options.linger).checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+        sink.show("")?;
+        playing.join().map_err(|_| anyhow!("player thread panicked"))?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Shown(Vec<String>);
+
+    impl CaptionSink for Shown {
+        fn show(&mut self, text: &str) -> Result<()> {
+            self.0.push(text.to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shows_only_words() {
+        let boundary = |kind: BoundaryKind, ms: u64, text: &str| Boundary { kind, offset: Duration::from_millis(ms), duration: Duration::from_millis(10), text: text.to_owned() };
+        let boundaries = [
+            boundary(BoundaryKind::Sentence, 0, "Hi there."),
+            boundary(BoundaryKind::Word, 0, "Hi"),
+            boundary(BoundaryKind::Viseme(21), 5, ""),
+            boundary(BoundaryKind::Bookmark, 10, "m1"),
+            boundary(BoundaryKind::Word, 20, "there."),
+        ];
+        let mut shown = Shown::default();
+        let options = CaptionOptions { linger: Duration::ZERO, ..Default::default() };
+        play_with_captions(b"audio", &boundaries, &Player::new("cat", [] as [&str; 0]), &mut shown, &options).unwrap();
+        assert_eq!(shown.0, ["Hi", "Hi there.", ""]);
+    }
+
+    #[test]
+    fn computes_obs_auth() {
+        // Example from the obs-websocket 5 protocol documentation.
+        assert_eq!(obs_auth("supersecretpassword", "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=", "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY="), "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4=");
+    }
+}
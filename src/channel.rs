@@ -0,0 +1,63 @@
+use std::ops::ControlFlow;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+use anyhow::Result;
+
+use crate::synthesize::SynthesisEvent;
+use crate::{Client, SynthesisRequest};
+
+impl Client {
+    /// Synthesize `request` on its own thread, sending its audio message by message into a channel of `capacity`
+    /// chunks, eg: for a slow network client. While the consumer is `capacity` chunks behind, the thread stops
+    /// reading the socket, so the service is held back by TCP instead of the audio piling up in memory; a pause of
+    /// minutes may get the connection closed by the service.
+    ///
+    /// A failed synthesis ends the channel with its error. Dropping the receiver stops the synthesis. Boundaries are
+    /// dropped and the disk cache isn't used.
+    pub fn synthesize_channel(&self, request: &SynthesisRequest, capacity: usize) -> Receiver<Result<Vec<u8>>> {
+        let (sender, receiver) = sync_channel(capacity.max(1));
+        let (client, request) = (self.clone(), request.clone());
+        thread::spawn(move || {
+            let result = client.prepare(&request).and_then(|request| {
+                client.connect_and_stream(&client.request_ssml(&request), request.output_format.as_str(), &mut |event| match event {
+                    SynthesisEvent::Audio(audio) => match sender.send(Ok(audio.to_vec())) {
+                        Ok(()) => ControlFlow::Continue(()),
+                        Err(_) => ControlFlow::Break(()),
+                    },
+                    SynthesisEvent::Boundaries(_) => ControlFlow::Continue(()),
+                })
+            });
+            if let Err(e) = result {
+                let _ = sender.send(Err(e));
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn delivers_through_a_bounded_channel() {
+        let mut turn = MockReply::turn(b"one");
+        turn.insert(2, MockReply::Audio(b"two".to_vec()));
+        turn.insert(3, MockReply::Audio(b"three".to_vec()));
+        let closed = vec![MockReply::Close { code: 1011, reason: String::new() }];
+        let server = MockServer::start(vec![turn, closed]).unwrap();
+        let request = SynthesisRequest::new("Hi", "en-US-AriaNeural");
+        let chunks = server.client().synthesize_channel(&request, 1);
+        std::thread::sleep(Duration::from_millis(50));
+        let chunks: Vec<Vec<u8>> = chunks.into_iter().map(Result::unwrap).collect();
+        assert_eq!(chunks, [&b"one"[..], b"two", b"three"]);
+
+        let mut failed = server.client().synthesize_channel(&request, 1).into_iter();
+        assert!(failed.next().unwrap().is_err());
+        assert!(failed.next().is_none());
+    }
+}
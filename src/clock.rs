@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds the service's clock is ahead of the local one, learned from rejected handshakes.
+static SKEW: AtomicI64 = AtomicI64::new(0);
+
+fn local_now() -> i64 {
+    // A clock before 1970 is wrong anyway, the service rejects the token and reports its time.
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+/// Unix time of the service, as far as known.
+pub(crate) fn now() -> u64 {
+    (local_now() + SKEW.load(Ordering::Relaxed)).max(0) as u64
+}
+
+/// Learn the clock skew from a handshake rejected with 403, which is how the service rejects a Sec-MS-GEC token
+/// generated with a wrong clock, from its `Date` header. `true` if the skew changed, so a retry can succeed.
+pub(crate) fn correct_skew(error: &anyhow::Error) -> bool {
+    let Some(tungstenite::Error::Http(response)) = error.downcast_ref::<tungstenite::Error>() else { return false };
+    if response.status() != 403 {
+        return false;
+    }
+    let Some(server) = response.headers().get("date").and_then(|value| value.to_str().ok()).and_then(parse_http_date) else { return false };
+    let skew = server - local_now();
+    SKEW.swap(skew, Ordering::Relaxed) != skew
+}
+
+/// Unix time of an IMF-fixdate, eg: "Fri, 24 May 2013 00:00:00 GMT".
+fn parse_http_date(date: &str) -> Option<i64> {
+    let mut fields = date.split_whitespace().skip(1);
+    let day: i64 = fields.next()?.parse().ok()?;
+    let month = fields.next()?;
+    let month = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"].iter().position(|m| *m == month)? as i64 + 1;
+    let year: i64 = fields.next()?.parse().ok()?;
+    let mut time = fields.next()?.split(':').map(|field| field.parse::<i64>().ok());
+    let (h, m, s) = (time.next()??, time.next()??, time.next()??);
+    // Days since 1970-01-01, after Howard Hinnant's algorithm.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Some(days * 86400 + h * 3600 + m * 60 + s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learns_skew_from_rejected_handshake() {
+        assert_eq!(parse_http_date("Fri, 24 May 2013 00:00:00 GMT"), Some(1369353600));
+        assert_eq!(parse_http_date("Sun, 29 Feb 2032 23:59:59 GMT"), Some(1961711999));
+        assert_eq!(parse_http_date("yesterday"), None);
+        let rejected = |status: u16| {
+            let date = "Fri, 24 May 2013 00:00:00 GMT";
+            let response = tungstenite::http::Response::builder().status(status).header("Date", date).body(None).unwrap();
+            anyhow::Error::from(tungstenite::Error::Http(response))
+        };
+        assert!(!correct_skew(&rejected(500)));
+        assert!(correct_skew(&rejected(403)));
+        assert!(now().abs_diff(1369353600) < 5);
+        SKEW.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::metadata::Boundary;
+use crate::mp3::{frames, id3v2_len, main_data_begin, silent_frame};
+use crate::pcm::{loud_range, to_bytes, to_samples};
+use crate::wav::{wav_data, wav_header};
+use crate::{Codec, Container, OutputFormat, SynthesisOutput};
+
+/// Options of [`concat_audio`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConcatOptions {
+    /// Remove the silence around the speech of each part.
+    pub trim: bool,
+    /// Silence kept before and after the speech when trimming.
+    pub margin: Duration,
+    /// Silence inserted between parts.
+    pub pause: Duration,
+    /// PCM samples quieter than this many dBFS count as silence.
+    pub silence_threshold_db: f32,
+    /// PCM parts overlap by this much, one fading out as the next fades in, eg: 20 to 50 ms against clicks at the
+    /// joins. With a pause, each fades into the silence instead. MP3 joins are not faded.
+    pub crossfade: Duration,
+}
+
+impl Default for ConcatOptions {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            margin: Duration::from_millis(60),
+            pause: Duration::ZERO,
+            silence_threshold_db: -50.0,
+            crossfade: Duration::ZERO,
+        }
+    }
+}
+
+/// Join chunks synthesized separately in `format` into one continuous stream, with boundaries moved to their new
+/// offsets.
+///
+/// MP3 is spliced on frame boundaries: silence is trimmed by whole frames around the first and last word boundary,
+/// pauses are silent frames, and frames that would decode from another chunk's bit reservoir are replaced with
+/// silence. Raw and RIFF 16-bit PCM are trimmed by level. Other formats fail.
+pub fn concat_audio(parts: Vec<SynthesisOutput>, format: &OutputFormat, options: &ConcatOptions) -> Result<SynthesisOutput> {
+    match (format.container(), format.codec(), format.bits_per_sample()) {
+        (Container::Mp3, _, _) => Ok(concat_mp3(parts, options).with_duration(format)),
+        (Container::Raw | Container::Riff, Codec::Pcm, Some(16)) => {
+            let riff = format.container() == Container::Riff;
+            let (Some(sample_rate), channels) = (format.sample_rate(), format.channels()) else { bail!("{} has no sample rate", format) };
+            let data = concat_pcm(parts, sample_rate, channels, riff, options);
+            Ok(match riff {
+                true => SynthesisOutput {
+                    audio: [wav_header(sample_rate, 16, channels, data.audio.len() as u32), data.audio].concat(),
+                    ..data
+                },
+                false => data,
+            }
+            .with_duration(format))
+        }
+        _ => bail!("can't concatenate {}", format),
+    }
+}
+
+/// Whether [`concat_audio`] can join audio in `format`.
+pub(crate) fn can_concat(format: &OutputFormat) -> bool {
+    matches!((format.container(), format.codec(), format.bits_per_sample()), (Container::Mp3, _, _) | (Container::Raw | Container::Riff, Codec::Pcm, Some(16)))
+}
+
+/// `boundaries` kept after trimming from `trimmed_start`, moved to start at `elapsed`.
+fn shift(boundaries: Vec<Boundary>, trimmed_start: Duration, elapsed: Duration) -> impl Iterator<Item = Boundary> {
+    boundaries.into_iter().map(move |b| Boundary {
+        offset: elapsed + b.offset.saturating_sub(trimmed_start),
+        ..b
+    })
+}
+
+/// First and last audio offsets of the speech, from the boundaries.
+fn speech_span(boundaries: &[Boundary]) -> Option<(Duration, Duration)> {
+    let start = boundaries.iter().map(|b| b.offset).min()?;
+    let end = boundaries.iter().map(|b| b.offset + b.duration).max()?;
+    Some((start, end))
+}
+
+fn concat_mp3(parts: Vec<SynthesisOutput>, options: &ConcatOptions) -> SynthesisOutput {
+    let mut output = SynthesisOutput { first_audio: parts.first().and_then(|part| part.first_audio), ..SynthesisOutput::default() };
+    let mut elapsed = Duration::ZERO;
+    for part in parts {
+        let audio = id3v2_len(&part.audio).and_then(|len| part.audio.get(len..)).unwrap_or(&part.audio);
+        let frames = frames(audio);
+        let Some((first, _)) = frames.first() else { continue };
+        let frame_duration = first.duration().as_secs_f64();
+        let (from, to) = match speech_span(&part.boundaries).filter(|_| options.trim) {
+            Some((start, end)) => (
+                (start.saturating_sub(options.margin).as_secs_f64() / frame_duration).floor() as usize,
+                ((end + options.margin).as_secs_f64() / frame_duration).ceil() as usize,
+            ),
+            None => (0, frames.len()),
+        };
+        let kept = frames.get(from..to.min(frames.len())).unwrap_or_default();
+        if !output.audio.is_empty() && !options.pause.is_zero() {
+            let count = (options.pause.as_secs_f64() / frame_duration).round() as usize;
+            if let Some(silence) = silent_frame(frames.first().map(|(_, frame)| *frame).unwrap_or_default()) {
+                for _ in 0..count {
+                    output.audio.extend_from_slice(&silence);
+                }
+                elapsed += Duration::from_secs_f64(count as f64 * frame_duration);
+            }
+        }
+        // Main data bytes of the kept frames that later frames can borrow.
+        let mut reservoir = 0;
+        for (header, frame) in kept {
+            match main_data_begin(frame) {
+                Some(begin) if begin <= reservoir => {
+                    output.audio.extend_from_slice(frame);
+                    reservoir += header.len - header.main_data_offset;
+                }
+                _ => {
+                    output.audio.extend(silent_frame(frame).unwrap_or_default());
+                    reservoir = 0;
+                }
+            }
+        }
+        let trimmed_start = Duration::from_secs_f64(from as f64 * frame_duration);
+        output.boundaries.extend(shift(part.boundaries, trimmed_start, elapsed));
+        elapsed += Duration::from_secs_f64(kept.len() as f64 * frame_duration);
+    }
+    output
+}
+
+fn concat_pcm(parts: Vec<SynthesisOutput>, sample_rate: u32, channels: u16, riff: bool, options: &ConcatOptions) -> SynthesisOutput {
+    // Samples per second over all channels.
+    let (rate, channels) = (sample_rate * channels as u32, channels.max(1) as usize);
+    let crossfade = (options.crossfade.as_secs_f64() * sample_rate as f64) as usize * channels;
+    let to_duration = |samples: usize| Duration::from_secs_f64(samples as f64 / rate as f64);
+    let mut samples = Vec::new();
+    let mut boundaries = Vec::new();
+    let first_audio = parts.first().and_then(|part| part.first_audio);
+    for part in parts {
+        let data = if riff { wav_data(&part.audio).unwrap_or_default() } else { &part.audio };
+        let pcm = to_samples(data);
+        let margin = (options.margin.as_secs_f64() * rate as f64) as usize;
+        let range = match loud_range(&pcm, options.silence_threshold_db).filter(|_| options.trim) {
+            Some(range) => range.start.saturating_sub(margin)..(range.end + margin).min(pcm.len()),
+            None if options.trim => continue,
+            None => 0..pcm.len(),
+        };
+        let trimmed_start = to_duration(range.start);
+        let pcm = pcm.get(range).unwrap_or_default();
+        let (mut start, mut fade) = (samples.len(), 0);
+        if !samples.is_empty() {
+            let pause = (options.pause.as_secs_f64() * rate as f64) as usize;
+            fade = crossfade.min(pcm.len()).min(samples.len()) / channels * channels;
+            if pause == 0 {
+                start -= fade;
+            } else {
+                let tail = samples.len() - fade;
+                for (i, sample) in samples.iter_mut().skip(tail).enumerate() {
+                    *sample = (*sample as f64 * (1.0 - ramp(i / channels, fade / channels))).round() as i16;
+                }
+                samples.resize(start + pause, 0);
+                start += pause;
+            }
+        }
+        boundaries.extend(shift(part.boundaries, trimmed_start, to_duration(start)));
+        for (i, &sample) in pcm.iter().enumerate() {
+            let gain = if i < fade { ramp(i / channels, fade / channels) } else { 1.0 };
+            match samples.get_mut(start + i) {
+                // Overlapping the end of the previous part.
+                Some(previous) => *previous = (*previous as f64 * (1.0 - gain) + sample as f64 * gain).round() as i16,
+                None => samples.push((sample as f64 * gain).round() as i16),
+            }
+        }
+    }
+    SynthesisOutput {
+        audio: to_bytes(&samples),
+        boundaries,
+        first_audio,
+        ..SynthesisOutput::default()
+    }
+}
+
+/// Gain of frame `i` of a fade in over `frames`, from just above 0 to just below 1.
+fn ramp(i: usize, frames: usize) -> f64 {
+    (i + 1) as f64 / (frames + 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::BoundaryKind;
+
+    fn word(offset_ms: u64) -> Boundary {
+        Boundary {
+            kind: BoundaryKind::Word,
+            offset: Duration::from_millis(offset_ms),
+            duration: Duration::from_millis(100),
+            text: "word".to_owned(),
+        }
+    }
+
+    #[test]
+    fn trims_and_joins_pcm() {
+        let format = OutputFormat::new("raw-1khz-16bit-mono-pcm");
+        let part = |lead: usize| {
+            let mut pcm = vec![0i16; lead];
+            pcm.extend([1000; 10]);
+            pcm.extend([0; 50]);
+            SynthesisOutput { audio: to_bytes(&pcm), boundaries: vec![word(lead as u64)], ..Default::default() }
+        };
+        let options = ConcatOptions { margin: Duration::from_millis(2), pause: Duration::from_millis(5), ..Default::default() };
+        let output = concat_audio(vec![part(30), part(100)], &format, &options).unwrap();
+        // 2 + 10 + 2, pause of 5, 2 + 10 + 2
+        assert_eq!(output.audio.len(), 33 * 2);
+        assert_eq!(output.boundaries[0].offset, Duration::from_millis(2));
+        assert_eq!(output.boundaries[1].offset, Duration::from_millis(21));
+    }
+
+    #[test]
+    fn crossfades_pcm_joins() {
+        let format = OutputFormat::new("raw-1khz-16bit-mono-pcm");
+        let part = |level: i16| SynthesisOutput { audio: to_bytes(&[level; 10]), boundaries: vec![word(0)], ..Default::default() };
+        let options = ConcatOptions { trim: false, crossfade: Duration::from_millis(3), ..Default::default() };
+        let output = concat_audio(vec![part(1000), part(-1000)], &format, &options).unwrap();
+        assert_eq!(to_samples(&output.audio), [1000, 1000, 1000, 1000, 1000, 1000, 1000, 500, 0, -500, -1000, -1000, -1000, -1000, -1000, -1000, -1000]);
+        assert_eq!(output.boundaries[1].offset, Duration::from_millis(7));
+
+        let paused = ConcatOptions { pause: Duration::from_millis(2), ..options };
+        let output = concat_audio(vec![part(1000), part(1000)], &format, &paused).unwrap();
+        assert_eq!(to_samples(&output.audio)[6..15], [1000, 750, 500, 250, 0, 0, 250, 500, 750]);
+        assert_eq!(output.boundaries[1].offset, Duration::from_millis(12));
+    }
+
+    #[test]
+    fn splices_mp3_frames() {
+        // 24 ms frames of 144 bytes; the second borrows 10 bytes from the first.
+        let frame = |begin: u8| {
+            let mut frame = vec![7u8; 144];
+            frame[..5].copy_from_slice(&[0xff, 0xf3, 0x64, 0xc4, begin]);
+            frame
+        };
+        let part = SynthesisOutput { audio: [frame(0), frame(10), frame(0), frame(0)].concat(), boundaries: vec![word(30)], ..Default::default() };
+        let options = ConcatOptions { margin: Duration::ZERO, pause: Duration::from_millis(48), ..Default::default() };
+        let output = concat_audio(vec![part.clone(), part], &OutputFormat::default(), &options).unwrap();
+        // Frames 1 to 3 of each part hold 30..130 ms; the first kept frame borrows from a dropped one.
+        let silence = silent_frame(&frame(0)).unwrap();
+        let part = [silence.clone(), frame(0), frame(0)].concat();
+        assert_eq!(output.audio, [part.clone(), silence.clone(), silence, part].concat());
+        assert_eq!(output.boundaries[1].offset, Duration::from_millis(72 + 48 + 6));
+    }
+}
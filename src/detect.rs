@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+
+use crate::{Client, SynthesisOutput, SynthesisRequest};
+
+/// Default neural voice by language, see [`default_voice`].
+const DEFAULT_VOICES: &[(&str, &str)] = &[
+    ("ar", "ar-SA-ZariyahNeural"), ("de", "de-DE-KatjaNeural"), ("el", "el-GR-AthinaNeural"), ("en", "en-US-AriaNeural"),
+    ("es", "es-ES-ElviraNeural"), ("fr", "fr-FR-DeniseNeural"), ("he", "he-IL-HilaNeural"), ("hi", "hi-IN-SwaraNeural"),
+    ("it", "it-IT-ElsaNeural"), ("ja", "ja-JP-NanamiNeural"), ("ko", "ko-KR-SunHiNeural"), ("nl", "nl-NL-ColetteNeural"),
+    ("pt", "pt-BR-FranciscaNeural"), ("ru", "ru-RU-SvetlanaNeural"), ("th", "th-TH-PremwadeeNeural"),
+    ("uk", "uk-UA-PolinaNeural"), ("zh", "zh-CN-XiaoxiaoNeural"),
+];
+
+/// Frequent short words of the languages written in Latin script.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "of", "to", "in", "it", "you", "that", "was", "for", "with", "this", "are"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ich", "zu", "den", "mit", "ein", "eine", "sie", "auf"]),
+    ("fr", &["le", "la", "les", "et", "est", "un", "une", "des", "du", "je", "pas", "que", "vous", "dans"]),
+    ("es", &["el", "la", "los", "las", "y", "es", "un", "una", "que", "de", "en", "por", "con", "para"]),
+    ("it", &["il", "lo", "la", "gli", "e", "è", "un", "una", "che", "di", "non", "per", "con", "sono"]),
+    ("pt", &["o", "a", "os", "as", "e", "é", "um", "uma", "que", "de", "não", "com", "para", "em"]),
+    ("nl", &["de", "het", "een", "en", "is", "van", "niet", "ik", "dat", "op", "te", "zijn", "met", "voor"]),
+];
+
+/// Writing systems told apart by [`script_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+}
+
+/// `None` for digits, punctuation, spaces and unknown scripts.
+pub(crate) fn script_of(c: char) -> Option<Script> {
+    Some(match c {
+        'a'..='z' | 'A'..='Z' | '\u{c0}'..='\u{24f}' if c.is_alphabetic() => Script::Latin,
+        '\u{3040}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' => Script::Kana,
+        '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' => Script::Han,
+        '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}' => Script::Hangul,
+        '\u{400}'..='\u{4ff}' => Script::Cyrillic,
+        '\u{370}'..='\u{3ff}' => Script::Greek,
+        '\u{600}'..='\u{6ff}' | '\u{750}'..='\u{77f}' => Script::Arabic,
+        '\u{590}'..='\u{5ff}' => Script::Hebrew,
+        '\u{900}'..='\u{97f}' => Script::Devanagari,
+        '\u{e00}'..='\u{e7f}' => Script::Thai,
+        _ => return None,
+    })
+}
+
+/// Language of `text`, eg: "zh", "en", from its main script, and for Latin script from frequent words. `None` if
+/// `text` has no letters of a known script.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in text.chars().filter_map(script_of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    let count = |script| counts.iter().find(|(s, _)| *s == script).map_or(0, |(_, count)| *count);
+    // Japanese mixes kanji and kana, kanji alone is Chinese.
+    let (han, kana) = (count(Script::Han), count(Script::Kana));
+    let (script, _) = counts.iter().map(|&(script, count)| if script == Script::Han { (script, han + kana) } else { (script, count) }).max_by_key(|(_, count)| *count)?;
+    Some(match script {
+        Script::Latin => latin_language(text),
+        Script::Han | Script::Kana => if kana > 0 { "ja" } else { "zh" },
+        Script::Hangul => "ko",
+        Script::Cyrillic => if text.contains(['і', 'ї', 'є', 'ґ']) { "uk" } else { "ru" },
+        Script::Greek => "el",
+        Script::Arabic => "ar",
+        Script::Hebrew => "he",
+        Script::Devanagari => "hi",
+        Script::Thai => "th",
+    })
+}
+
+/// The language with the most [`STOPWORDS`] in `text`, English if none.
+pub(crate) fn latin_language(text: &str) -> &'static str {
+    let words: Vec<String> = text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect();
+    let score = |stopwords: &[&str]| words.iter().filter(|w| stopwords.contains(&w.as_str())).count();
+    STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| (*language, score(stopwords)))
+        .filter(|(_, score)| *score > 0)
+        // The first of equal scores, English before the others.
+        .fold(None, |best: Option<(&str, usize)>, (language, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((language, score)),
+        })
+        .map_or("en", |(language, _)| language)
+}
+
+/// Neural voice for `language`, eg: "zh" or "zh-CN" → "zh-CN-XiaoxiaoNeural".
+pub fn default_voice(language: &str) -> Option<&'static str> {
+    let language = language.split(['-', '_']).next().unwrap_or_default();
+    DEFAULT_VOICES.iter().find(|(l, _)| l.eq_ignore_ascii_case(language)).map(|(_, voice)| *voice)
+}
+
+impl Client {
+    /// Synthesize `text` with the [`default_voice`] of its [detected](detect_language) language, in the default
+    /// output format.
+    pub fn synthesize_auto(&self, text: &str) -> Result<SynthesisOutput> {
+        let language = detect_language(text).ok_or_else(|| anyhow!("can't detect the language of {:?}", text))?;
+        let voice = default_voice(language).ok_or_else(|| anyhow!("no default voice for language {}", language))?;
+        self.synthesize_request(&SynthesisRequest::new(text, voice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_and_voice() {
+        assert_eq!(detect_language("晚上好，欢迎进入直播间。"), Some("zh"));
+        assert_eq!(detect_language("こんばんは、配信へようこそ。"), Some("ja"));
+        assert_eq!(detect_language("안녕하세요"), Some("ko"));
+        assert_eq!(detect_language("Привет, как дела?"), Some("ru"));
+        assert_eq!(detect_language("The weather is nice today."), Some("en"));
+        assert_eq!(detect_language("Das Wetter ist heute nicht schlecht."), Some("de"));
+        assert_eq!(detect_language("Je ne sais pas, c'est la vie."), Some("fr"));
+        assert_eq!(detect_language("Hello"), Some("en"));
+        assert_eq!(detect_language("我们用 Rust 写了一个 TTS 客户端"), Some("zh"));
+        assert_eq!(detect_language("42 !?"), None);
+        assert_eq!(default_voice("zh-CN"), Some("zh-CN-XiaoxiaoNeural"));
+        assert_eq!(default_voice("EN"), Some("en-US-AriaNeural"));
+        assert_eq!(default_voice("xx"), None);
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::pcm::{to_bytes, to_samples};
+use crate::subtitle::Cue;
+use crate::wav::wav_header;
+use crate::{Client, OutputFormat, SynthesisOutput, SynthesisRequest};
+
+const FORMAT: OutputFormat = OutputFormat::RAW_24KHZ_16BIT_MONO_PCM;
+const SAMPLE_RATE: u64 = 24000;
+
+/// Options of [`dub_subtitles`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DubOptions {
+    /// Cues whose speech is longer than their window are synthesized again this much faster at most, eg: 1.5.
+    pub max_speedup: f32,
+    /// Cues synthesized at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for DubOptions {
+    fn default() -> Self {
+        Self {
+            max_speedup: 1.5,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Synthesize each cue with the voice and prosody of `template` and place it at its start time, returning a
+/// 24 kHz 16-bit mono WAV track as long as the last cue.
+///
+/// Speech that doesn't fit its window is sped up through the `rate` prosody (replacing the template's), then
+/// allowed to run on until the next cue starts, where it is cut.
+pub fn dub_subtitles(client: &Client, cues: &[Cue], template: &SynthesisRequest, options: &DubOptions) -> Result<Vec<u8>> {
+    let request = |cue: &Cue| SynthesisRequest {
+        text: cue.text.replace('\n', " "),
+        output_format: FORMAT,
+        ..template.clone()
+    };
+    let spoken: Vec<&Cue> = cues.iter().filter(|c| !c.text.trim().is_empty()).collect();
+    let outputs = client.synthesize_batch(spoken.iter().map(|c| request(c)).collect(), options.concurrency);
+    let mut track: Vec<i16> = Vec::new();
+    for (i, (cue, output)) in spoken.iter().zip(outputs).enumerate() {
+        let mut output = output.with_context(|| format!("cue at {:?}", cue.start))?;
+        let window = cue.end.saturating_sub(cue.start);
+        let speedup = fit_speedup(speech_duration(&output), window, options.max_speedup);
+        if let Some(percent) = speedup {
+            output = client
+                .synthesize_request(&request(cue).with_rate(format!("+{}%", percent)))
+                .with_context(|| format!("cue at {:?}", cue.start))?;
+        }
+        let start = samples(cue.start);
+        let limit = spoken.get(i + 1).map(|next| samples(next.start)).unwrap_or(usize::MAX);
+        let pcm = to_samples(&output.audio);
+        let end = (start + pcm.len()).min(limit.max(start));
+        if track.len() < end {
+            track.resize(end, 0);
+        }
+        track[start..end].copy_from_slice(&pcm[..end - start]);
+    }
+    if let Some(last) = cues.iter().map(|c| samples(c.end)).max() {
+        if track.len() < last {
+            track.resize(last, 0);
+        }
+    }
+    let data = to_bytes(&track);
+    let mut wav = wav_header(SAMPLE_RATE as u32, 16, 1, data.len() as u32);
+    wav.extend(data);
+    Ok(wav)
+}
+
+fn samples(at: Duration) -> usize {
+    (at.as_micros() as u64 * SAMPLE_RATE / 1_000_000) as usize
+}
+
+/// Spoken length: up to the end of the last boundary when known, so trailing silence doesn't count.
+fn speech_duration(output: &SynthesisOutput) -> Duration {
+    output
+        .boundaries
+        .iter()
+        .map(|b| b.offset + b.duration)
+        .max()
+        .or_else(|| FORMAT.duration_of(output.audio.len()))
+        .unwrap_or_default()
+}
+
+/// Rate increase in percent to fit `speech` into `window`, `None` if it already fits.
+fn fit_speedup(speech: Duration, window: Duration, max_speedup: f32) -> Option<u32> {
+    if speech <= window || window.is_zero() || max_speedup <= 1.0 {
+        return None;
+    }
+    let ratio = (speech.as_secs_f32() / window.as_secs_f32()).min(max_speedup);
+    Some(((ratio - 1.0) * 100.0).ceil() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_speedup() {
+        let secs = Duration::from_secs;
+        assert_eq!(fit_speedup(secs(2), secs(3), 1.5), None);
+        assert_eq!(fit_speedup(secs(5), secs(4), 1.5), Some(25));
+        assert_eq!(fit_speedup(secs(9), secs(3), 1.5), Some(50));
+        assert_eq!(samples(Duration::from_millis(1500)), 36000);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt::{self, Write as _};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use tungstenite::Message;
+
+use crate::frame::{parse_binary_frame, parse_headers};
+
+/// Body bytes of binary messages shown in hex.
+const HEX_PREVIEW: usize = 16;
+
+type Output = dyn Fn(&str) + Send + Sync;
+
+/// Destination of a protocol dump: the handshake URL and every message sent and received, with its headers and
+/// body size, see [`crate::Client::with_protocol_dump`]. eg:
+/// ```text
+/// [1f3a9c2e +0.000s] handshake GET wss://speech.platform.bing.com/consumer/speech/synthesize/readaloud/edge/v1?...
+/// [1f3a9c2e +0.412s] > text, 211 byte body
+///     Path:speech.config
+/// [1f3a9c2e +1.250s] < binary, 4320 byte body: 49 44 33 04 00 00 00 00 00 23 54 53 53 45 00 00 ...
+///     X-RequestId:a1b2...
+///     Path:audio
+/// ```
+/// Bodies aren't written, as they contain the synthesized text and audio.
+#[derive(Clone)]
+pub struct ProtocolDump {
+    output: Arc<Output>,
+}
+
+impl ProtocolDump {
+    /// Pass every entry, without trailing newline, to `callback`.
+    pub fn new(callback: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self { output: Arc::new(callback) }
+    }
+
+    /// eg: `ProtocolDump::to_writer(std::io::stderr())`
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        let writer = Mutex::new(writer);
+        Self::new(move |entry| {
+            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+            // A failing dump shouldn't fail the synthesis.
+            let _ = writeln!(writer, "{}", entry).and_then(|_| writer.flush());
+        })
+    }
+
+    /// Append to the file at `path`, creating it if needed.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new().create(true).append(true).open(path).with_context(|| path.display().to_string())?;
+        Ok(Self::to_writer(file))
+    }
+
+    /// Dump of one new connection, its entries tagged and timed from now.
+    pub(crate) fn connection(&self) -> ConnectionDump {
+        let mut tag = uuid::Uuid::new_v4().simple().to_string();
+        tag.truncate(8);
+        ConnectionDump { output: self.output.clone(), tag, started: Instant::now() }
+    }
+}
+
+impl fmt::Debug for ProtocolDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProtocolDump").finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ConnectionDump {
+    output: Arc<Output>,
+    tag: String,
+    started: Instant,
+}
+
+impl ConnectionDump {
+    pub(crate) fn note(&self, text: &str) {
+        (self.output)(&format!("[{} +{:.3}s] {}", self.tag, self.started.elapsed().as_secs_f64(), text));
+    }
+
+    pub(crate) fn sent(&self, message: &Message) {
+        self.note(&describe('>', message));
+    }
+
+    pub(crate) fn received(&self, message: &Message) {
+        self.note(&describe('<', message));
+    }
+}
+
+/// eg: "> text, 211 byte body\n    Path:speech.config"
+fn describe(arrow: char, message: &Message) -> String {
+    let mut s = String::new();
+    let headers = match message {
+        Message::Text(text) => {
+            let (headers, body) = text.split_once("\r\n\r\n").unwrap_or((text, ""));
+            let _ = write!(s, "{} text, {} byte body", arrow, body.len());
+            parse_headers(headers)
+        }
+        Message::Binary(data) => match parse_binary_frame(data) {
+            Ok(frame) => {
+                let _ = write!(s, "{} binary, {} byte body: {}", arrow, frame.body.len(), hex_preview(frame.body));
+                frame.headers
+            }
+            Err(e) => {
+                let _ = write!(s, "{} binary, {} bytes, {}: {}", arrow, data.len(), e, hex_preview(data));
+                Vec::new()
+            }
+        },
+        Message::Ping(data) => {
+            let _ = write!(s, "{} ping, {} bytes", arrow, data.len());
+            Vec::new()
+        }
+        Message::Pong(data) => {
+            let _ = write!(s, "{} pong, {} bytes", arrow, data.len());
+            Vec::new()
+        }
+        Message::Close(Some(frame)) => {
+            let _ = write!(s, "{} close, code: {} reason: {}", arrow, u16::from(frame.code), frame.reason);
+            Vec::new()
+        }
+        Message::Close(None) => {
+            let _ = write!(s, "{} close", arrow);
+            Vec::new()
+        }
+        Message::Frame(frame) => {
+            let _ = write!(s, "{} frame, {} bytes", arrow, frame.len());
+            Vec::new()
+        }
+    };
+    for (name, value) in headers {
+        let _ = write!(s, "\n    {}:{}", name, value);
+    }
+    s
+}
+
+/// eg: "49 44 33 ..."
+fn hex_preview(data: &[u8]) -> String {
+    let mut s = data.iter().take(HEX_PREVIEW).map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+    if data.len() > HEX_PREVIEW {
+        s.push_str(" ...");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_messages() {
+        let text = Message::Text("X-RequestId:42\r\nPath:turn.end\r\n\r\n{}".to_owned());
+        assert_eq!(describe('<', &text), "< text, 2 byte body\n    X-RequestId:42\n    Path:turn.end");
+        let mut binary = vec![0, 12];
+        binary.extend_from_slice(b"Path:audio\r\n");
+        binary.extend_from_slice(&[0xff; 20]);
+        let described = describe('<', &Message::Binary(binary));
+        assert!(described.starts_with("< binary, 20 byte body: ff ff"), "{}", described);
+        assert!(described.ends_with("ff ...\n    Path:audio"), "{}", described);
+        assert_eq!(describe('<', &Message::Binary(vec![1])), "< binary, 1 bytes, bad binary response. response len: 1: 01");
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let collected = entries.clone();
+        ProtocolDump::new(move |entry| collected.lock().unwrap().push(entry.to_owned())).connection().sent(&Message::Ping(Vec::new()));
+        let entries = entries.lock().unwrap();
+        assert!(entries[0].ends_with("s] > ping, 0 bytes"), "{:?}", entries);
+    }
+}
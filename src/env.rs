@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::{Client, DiskCache, SynthesisRequest};
+
+/// Voice of [`SynthesisRequest::from_env`], eg: "en-GB-SoniaNeural".
+pub const ENV_VOICE: &str = "EDGE_TTS_VOICE";
+/// eg: "+10%"
+pub const ENV_RATE: &str = "EDGE_TTS_RATE";
+/// eg: "+5Hz"
+pub const ENV_PITCH: &str = "EDGE_TTS_PITCH";
+/// eg: "-10%"
+pub const ENV_VOLUME: &str = "EDGE_TTS_VOLUME";
+/// eg: "audio-24khz-96kbitrate-mono-mp3"
+pub const ENV_OUTPUT_FORMAT: &str = "EDGE_TTS_OUTPUT_FORMAT";
+/// Socks5 proxy of [`Client::from_env`], eg: "127.0.0.1:1080".
+pub const ENV_PROXY: &str = "EDGE_TTS_PROXY";
+/// See [`Client::with_trusted_client_token`].
+pub const ENV_TOKEN: &str = "EDGE_TTS_TOKEN";
+/// See [`Client::with_endpoint`].
+pub const ENV_ENDPOINT: &str = "EDGE_TTS_ENDPOINT";
+/// See [`Client::with_edge_version`].
+pub const ENV_EDGE_VERSION: &str = "EDGE_TTS_EDGE_VERSION";
+/// Directory of a [`DiskCache`].
+pub const ENV_CACHE_DIR: &str = "EDGE_TTS_CACHE_DIR";
+/// Keep-alive ping interval in seconds, see [`Client::with_keep_alive`].
+pub const ENV_KEEP_ALIVE: &str = "EDGE_TTS_KEEP_ALIVE";
+
+/// Non-empty value of environment variable `name`.
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+impl Client {
+    /// A client configured by the `EDGE_TTS_*` variables that are set, eg: in a container: [`ENV_PROXY`],
+    /// [`ENV_TOKEN`], [`ENV_ENDPOINT`], [`ENV_EDGE_VERSION`], [`ENV_CACHE_DIR`] and [`ENV_KEEP_ALIVE`]. Fails on an
+    /// invalid value, naming the variable.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(var)
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut client = Client::new();
+        if let Some(proxy) = var(ENV_PROXY) {
+            client = client.with_socks5_proxy(proxy);
+        }
+        if let Some(token) = var(ENV_TOKEN) {
+            client = client.with_trusted_client_token(token);
+        }
+        if let Some(url) = var(ENV_ENDPOINT) {
+            client = client.with_endpoint(&url).context(ENV_ENDPOINT)?;
+        }
+        if let Some(version) = var(ENV_EDGE_VERSION) {
+            client = client.with_edge_version(&version).context(ENV_EDGE_VERSION)?;
+        }
+        if let Some(dir) = var(ENV_CACHE_DIR) {
+            client = client.with_disk_cache(DiskCache::new(dir));
+        }
+        if let Some(seconds) = var(ENV_KEEP_ALIVE) {
+            client = client.with_keep_alive(Duration::from_secs(seconds.parse().context(ENV_KEEP_ALIVE)?));
+        }
+        Ok(client)
+    }
+}
+
+impl SynthesisRequest {
+    /// A request for `text` with the voice, prosody and output format of the `EDGE_TTS_*` variables that are set:
+    /// [`ENV_VOICE`] (default: "en-US-AriaNeural"), [`ENV_PITCH`], [`ENV_RATE`], [`ENV_VOLUME`] and
+    /// [`ENV_OUTPUT_FORMAT`].
+    pub fn from_env(text: impl Into<String>) -> Self {
+        Self::from_vars(text, var)
+    }
+
+    fn from_vars(text: impl Into<String>, var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut request = SynthesisRequest::new(text, var(ENV_VOICE).unwrap_or_else(|| "en-US-AriaNeural".to_owned()));
+        request.pitch = var(ENV_PITCH);
+        request.rate = var(ENV_RATE);
+        request.volume = var(ENV_VOLUME);
+        if let Some(format) = var(ENV_OUTPUT_FORMAT) {
+            request.output_format = format.into();
+        }
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn configures_from_variables() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| move |name: &str| pairs.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string());
+        let request = SynthesisRequest::from_vars("Hi", vars(&[(ENV_VOICE, "en-GB-SoniaNeural"), (ENV_RATE, "+10%"), (ENV_OUTPUT_FORMAT, "raw-24khz-16bit-mono-pcm")]));
+        assert_eq!(request, SynthesisRequest::new("Hi", "en-GB-SoniaNeural").with_rate("+10%").with_output_format("raw-24khz-16bit-mono-pcm"));
+        assert_eq!(SynthesisRequest::from_vars("Hi", vars(&[])).voice, "en-US-AriaNeural");
+
+        let server = MockServer::start(vec![MockReply::turn(b"audio")]).unwrap();
+        let client = Client::from_vars(vars(&[(ENV_TOKEN, "0123ABCD")])).unwrap().with_connector(server.connector());
+        assert_eq!(client.synthesize_request(&request).unwrap().audio, b"audio");
+        assert!(server.requests()[0].uri.contains("TrustedClientToken=0123ABCD&"), "{}", server.requests()[0].uri);
+        let error = Client::from_vars(vars(&[(ENV_KEEP_ALIVE, "soon")])).unwrap_err();
+        assert!(error.to_string().contains(ENV_KEEP_ALIVE), "{}", error);
+    }
+
+    #[test]
+    fn helpers_read_the_environment() {
+        let server = MockServer::start(vec![MockReply::turn(b"audio")]).unwrap();
+        let dir = std::env::temp_dir().join(format!("edge-tts-env-{}", uuid::Uuid::new_v4().simple()));
+        std::env::set_var(ENV_ENDPOINT, format!("ws://{}/", server.addr()));
+        std::env::set_var(ENV_CACHE_DIR, &dir);
+        let ssml = crate::build_ssml("Hello", "en-US-AriaNeural", "medium", "medium", "medium");
+        let first = crate::request_audio(&ssml, "audio-24khz-48kbitrate-mono-mp3");
+        let second = crate::request_audio(&ssml, "audio-24khz-48kbitrate-mono-mp3");
+        std::env::remove_var(ENV_ENDPOINT);
+        std::env::remove_var(ENV_CACHE_DIR);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(first.unwrap(), b"audio");
+        assert_eq!(second.unwrap(), b"audio");
+        assert_eq!(server.requests().len(), 1);
+
+        std::env::set_var(ENV_KEEP_ALIVE, "soon");
+        let request_audio = crate::request_audio(&ssml, "audio-24khz-48kbitrate-mono-mp3");
+        let batch = crate::synthesize_batch(vec![SynthesisRequest::new("a", "en-US-AriaNeural"), SynthesisRequest::new("b", "en-US-AriaNeural")], 2);
+        std::env::remove_var(ENV_KEEP_ALIVE);
+        assert!(format!("{:#}", request_audio.unwrap_err()).contains(ENV_KEEP_ALIVE));
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|item| item.as_ref().unwrap_err().to_string().contains(ENV_KEEP_ALIVE)));
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+
+/// Errors of the synthesis protocol. Returned wrapped in [`anyhow::Error`], use `downcast_ref::<Error>()` to match them.
+/// Those of a turn have its [`crate::TurnIds`] as context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Error {
+    /// The service closed the connection before `turn.end`.
+    ConnectionClosedByServer { code: Option<u16>, reason: String },
+    /// The text is longer than the client's [`crate::WordLimit`].
+    InputTooLong { words: usize, max_words: usize },
+    /// Submitting `characters` more would take `tenant` over the `limit` of its [`crate::CharacterQuota`] window,
+    /// where `used` were already submitted.
+    QuotaExceeded { tenant: String, used: u64, characters: u64, limit: u64 },
+    /// A WebSocket message or frame of `size` bytes is over the client's [`crate::Client::with_max_message_size`].
+    MessageTooLarge { size: usize, limit: usize },
+    /// The audio of the turn grew over the client's [`crate::Client::with_max_audio_size`].
+    AudioTooLarge { limit: u64 },
+    /// The first audio of a turn isn't in the `requested` format, eg: after a typo in its name, but `found`, eg:
+    /// "MP3" or "unknown". See [`crate::Client::with_strict_format`].
+    FormatMismatch { requested: String, found: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ConnectionClosedByServer { code: Some(code), reason } => write!(f, "connection closed by server. code: {} reason: {}", code, reason),
+            Error::ConnectionClosedByServer { code: None, .. } => write!(f, "connection closed by server"),
+            Error::InputTooLong { words, max_words } => write!(f, "input too long: {} words, at most {} allowed", words, max_words),
+            Error::QuotaExceeded { tenant, used, characters, limit } => {
+                write!(f, "character quota exceeded: {} used and {} more of {} per window for tenant {:?}", used, characters, limit, tenant)
+            }
+            Error::MessageTooLarge { size, limit } => write!(f, "message too large: {} bytes, at most {} allowed", size, limit),
+            Error::AudioTooLarge { limit } => write!(f, "audio too large: over {} bytes", limit),
+            Error::FormatMismatch { requested, found } => write!(f, "audio is not {} but {}", requested, found),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The failure of one item of a batch.
+#[derive(Debug)]
+pub struct ItemError {
+    /// Position of the item in the input.
+    pub index: usize,
+    /// What the item was, eg: the start of its text.
+    pub context: Option<String>,
+    pub error: anyhow::Error,
+}
+
+/// Every failure of a batch, instead of the first one only.
+///
+/// Displayed grouped by kind, eg:
+/// ```text
+/// 3 of 10 items failed
+///   connection closed by server: items 2, 5
+///   input too long: item 7
+/// ```
+#[derive(Debug)]
+pub struct AggregateError {
+    pub errors: Vec<ItemError>,
+    /// Number of items, failed or not.
+    pub total: usize,
+}
+
+impl AggregateError {
+    /// All values if every result is `Ok`, else all the errors.
+    pub fn collect<T>(results: impl IntoIterator<Item = anyhow::Result<T>>) -> Result<Vec<T>, AggregateError> {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        let mut total = 0;
+        for (index, result) in results.into_iter().enumerate() {
+            total += 1;
+            match result {
+                Ok(value) => values.push(value),
+                Err(error) => errors.push(ItemError { index, context: None, error }),
+            }
+        }
+        if errors.is_empty() {
+            Ok(values)
+        } else {
+            Err(AggregateError { errors, total })
+        }
+    }
+
+    /// Errors grouped by kind, in order of first occurrence.
+    pub fn by_kind(&self) -> Vec<(String, Vec<&ItemError>)> {
+        let mut groups: Vec<(String, Vec<&ItemError>)> = Vec::new();
+        for item in &self.errors {
+            let kind = error_kind(&item.error);
+            match groups.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, items)) => items.push(item),
+                None => groups.push((kind, vec![item])),
+            }
+        }
+        groups
+    }
+}
+
+/// Short description shared by errors of the same kind.
+fn error_kind(error: &anyhow::Error) -> String {
+    if let Some(e) = error.downcast_ref::<Error>() {
+        return match e {
+            Error::ConnectionClosedByServer { .. } => "connection closed by server".to_owned(),
+            Error::InputTooLong { .. } => "input too long".to_owned(),
+            Error::QuotaExceeded { .. } => "quota exceeded".to_owned(),
+            Error::MessageTooLarge { .. } => "message too large".to_owned(),
+            Error::AudioTooLarge { .. } => "audio too large".to_owned(),
+            Error::FormatMismatch { .. } => "format mismatch".to_owned(),
+        };
+    }
+    if error.downcast_ref::<crate::FrameError>().is_some() {
+        return "malformed response".to_owned();
+    }
+    if let Some(e) = error.downcast_ref::<std::io::Error>() {
+        return format!("io error ({:?})", e.kind());
+    }
+    error.root_cause().to_string()
+}
+
+impl fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} items failed", self.errors.len(), self.total)?;
+        for (kind, items) in self.by_kind() {
+            let indices: Vec<String> = items.iter().map(|item| item.index.to_string()).collect();
+            write!(f, "\n  {}: item{} {}", kind, if items.len() == 1 { "" } else { "s" }, indices.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AggregateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.errors.first().map(|item| item.error.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_errors_by_kind() {
+        let closed = || anyhow::Error::from(Error::ConnectionClosedByServer { code: None, reason: String::new() });
+        let results = vec![Ok(0), Err(closed()), Err(anyhow::anyhow!("bad voice")), Ok(3), Err(closed().context("item 4"))];
+        let error = AggregateError::collect(results).unwrap_err();
+        assert_eq!(error.to_string(), "3 of 5 items failed\n  connection closed by server: items 1, 4\n  bad voice: item 2");
+        assert_eq!(AggregateError::collect(vec![Ok(1), Ok(2)]).unwrap(), vec![1, 2]);
+    }
+}
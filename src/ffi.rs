@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+
+use crate::{bundled_voices, get_voice_list, Client, SynthesisRequest};
+
+/// Options of [`edge_tts_synthesize`]. Null fields are left unset.
+///
+/// The C API is declared in `include/edge_tts.h`; build the library with
+/// `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+#[repr(C)]
+#[derive(Debug)]
+pub struct EdgeTtsOptions {
+    /// eg: "+10Hz"
+    pub pitch: *const c_char,
+    /// eg: "+20%"
+    pub rate: *const c_char,
+    /// eg: "-10%"
+    pub volume: *const c_char,
+    /// eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub output_format: *const c_char,
+    /// Socks5 proxy, eg: "127.0.0.1:1080"
+    pub proxy: *const c_char,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, keeping its error or panic for [`edge_tts_last_error`]. 0 on success, else -1.
+fn catch(f: impl FnOnce() -> Result<()>) -> c_int {
+    let message = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return 0,
+        Ok(Err(e)) => format!("{:#}", e),
+        Err(_) => "panicked".to_owned(),
+    };
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+    -1
+}
+
+/// # Safety
+/// `s` is null or a NUL-terminated string.
+unsafe fn optional_str<'a>(s: *const c_char, name: &str) -> Result<Option<&'a str>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s).to_str().map(Some).map_err(|_| anyhow!("{} isn't UTF-8", name))
+}
+
+/// # Safety
+/// See [`edge_tts_synthesize`].
+unsafe fn request(text: *const c_char, voice: *const c_char, options: *const EdgeTtsOptions) -> Result<(SynthesisRequest, Option<String>)> {
+    let text = optional_str(text, "text")?.ok_or_else(|| anyhow!("text is null"))?;
+    let voice = optional_str(voice, "voice")?.unwrap_or("en-US-AriaNeural");
+    let mut request = SynthesisRequest::new(text, voice);
+    let Some(options) = options.as_ref() else { return Ok((request, None)) };
+    request.pitch = optional_str(options.pitch, "pitch")?.map(str::to_owned);
+    request.rate = optional_str(options.rate, "rate")?.map(str::to_owned);
+    request.volume = optional_str(options.volume, "volume")?.map(str::to_owned);
+    if let Some(format) = optional_str(options.output_format, "output_format")? {
+        request.output_format = format.into();
+    }
+    Ok((request, optional_str(options.proxy, "proxy")?.map(str::to_owned)))
+}
+
+/// Synthesize `text` with `voice` (default: "en-US-AriaNeural") and `options`, which may be null. On success, the
+/// audio is stored in `out_buf` and `out_len`, to be freed with [`edge_tts_free_buffer`], and 0 is returned. Else
+/// -1, see [`edge_tts_last_error`].
+///
+/// # Safety
+/// `text` and `voice` are null or NUL-terminated strings, `options` is null or points to valid options, and
+/// `out_buf` and `out_len` are valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn edge_tts_synthesize(text: *const c_char, voice: *const c_char, options: *const EdgeTtsOptions, out_buf: *mut *mut u8, out_len: *mut usize) -> c_int {
+    catch(|| {
+        if out_buf.is_null() || out_len.is_null() {
+            return Err(anyhow!("out_buf or out_len is null"));
+        }
+        let (request, proxy) = request(text, voice, options)?;
+        let client = match proxy {
+            Some(proxy) => Client::new().with_socks5_proxy(proxy),
+            None => Client::new(),
+        };
+        let audio = client.synthesize_request(&request)?.audio.into_boxed_slice();
+        *out_len = audio.len();
+        *out_buf = Box::into_raw(audio).cast();
+        Ok(())
+    })
+}
+
+/// Free audio of [`edge_tts_synthesize`]. Null is ignored.
+///
+/// # Safety
+/// `buf` and `len` were given by [`edge_tts_synthesize`] and aren't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn edge_tts_free_buffer(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// The voice list as a JSON array, eg: `[{"ShortName": "en-US-AriaNeural", "Locale": "en-US", ...}]`, fetched or
+/// else the bundled one. Free it with [`edge_tts_free_string`]. Null on error, see [`edge_tts_last_error`].
+#[no_mangle]
+pub extern "C" fn edge_tts_list_voices() -> *mut c_char {
+    let mut json = ptr::null_mut();
+    catch(|| {
+        let voices = get_voice_list().unwrap_or_else(|_| bundled_voices());
+        json = CString::new(serde_json::to_string(&voices)?)?.into_raw();
+        Ok(())
+    });
+    json
+}
+
+/// Free a string of [`edge_tts_list_voices`]. Null is ignored.
+///
+/// # Safety
+/// `s` was given by [`edge_tts_list_voices`] and isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn edge_tts_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The message of the last error on this thread, eg: "text is null", or null. Valid until the next error on this
+/// thread, not to be freed.
+#[no_mangle]
+pub extern "C" fn edge_tts_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_errors() {
+        let (mut buf, mut len) = (ptr::null_mut(), 0);
+        assert_eq!(unsafe { edge_tts_synthesize(ptr::null(), ptr::null(), ptr::null(), &mut buf, &mut len) }, -1);
+        assert_eq!(unsafe { CStr::from_ptr(edge_tts_last_error()) }.to_str().unwrap(), "text is null");
+        assert!(buf.is_null());
+
+        let (text, rate) = (CString::new("Hello").unwrap(), CString::new("+10%").unwrap());
+        let options = EdgeTtsOptions { pitch: ptr::null(), rate: rate.as_ptr(), volume: ptr::null(), output_format: ptr::null(), proxy: ptr::null() };
+        let (request, proxy) = unsafe { request(text.as_ptr(), ptr::null(), &options) }.unwrap();
+        assert_eq!(request, SynthesisRequest::new("Hello", "en-US-AriaNeural").with_rate("+10%"));
+        assert_eq!(proxy, None);
+        unsafe { edge_tts_free_buffer(ptr::null_mut(), 0) };
+        unsafe { edge_tts_free_string(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,299 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::mp3::{frame_offsets, parse_frame_header};
+use crate::Error;
+
+/// Codec part of an [`OutputFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    Mp3,
+    Opus,
+    Pcm,
+    Alaw,
+    Mulaw,
+    Other,
+}
+
+/// Container part of an [`OutputFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Container {
+    /// Bare MP3 frames.
+    Mp3,
+    /// `audio-*-opus`: bare Opus frames.
+    Opus,
+    Ogg,
+    Webm,
+    /// Headerless samples.
+    Raw,
+    /// WAVE file.
+    Riff,
+    Other,
+}
+
+/// Output format name, eg: "audio-24khz-48kbitrate-mono-mp3". See https://learn.microsoft.com/en-us/azure/ai-services/speech-service/rest-text-to-speech?tabs=streaming#audio-outputs
+///
+/// Any name is accepted, properties are parsed from it on demand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct OutputFormat(Cow<'static, str>);
+
+impl OutputFormat {
+    pub const AUDIO_24KHZ_48KBITRATE_MONO_MP3: OutputFormat = OutputFormat(Cow::Borrowed("audio-24khz-48kbitrate-mono-mp3"));
+    pub const AUDIO_24KHZ_96KBITRATE_MONO_MP3: OutputFormat = OutputFormat(Cow::Borrowed("audio-24khz-96kbitrate-mono-mp3"));
+    pub const AUDIO_48KHZ_192KBITRATE_MONO_MP3: OutputFormat = OutputFormat(Cow::Borrowed("audio-48khz-192kbitrate-mono-mp3"));
+    pub const WEBM_24KHZ_16BIT_MONO_OPUS: OutputFormat = OutputFormat(Cow::Borrowed("webm-24khz-16bit-mono-opus"));
+    pub const OGG_48KHZ_16BIT_MONO_OPUS: OutputFormat = OutputFormat(Cow::Borrowed("ogg-48khz-16bit-mono-opus"));
+    pub const RAW_16KHZ_16BIT_MONO_PCM: OutputFormat = OutputFormat(Cow::Borrowed("raw-16khz-16bit-mono-pcm"));
+    pub const RAW_24KHZ_16BIT_MONO_PCM: OutputFormat = OutputFormat(Cow::Borrowed("raw-24khz-16bit-mono-pcm"));
+    pub const RIFF_24KHZ_16BIT_MONO_PCM: OutputFormat = OutputFormat(Cow::Borrowed("riff-24khz-16bit-mono-pcm"));
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(Cow::Owned(name.into()))
+    }
+
+    /// Default format for a file extension: "mp3", "wav", "ogg" and "opus" (Ogg Opus), "webm" or "pcm" (raw).
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "mp3" => Self::AUDIO_24KHZ_48KBITRATE_MONO_MP3,
+            "wav" => Self::RIFF_24KHZ_16BIT_MONO_PCM,
+            "ogg" | "opus" => Self::OGG_48KHZ_16BIT_MONO_OPUS,
+            "webm" => Self::WEBM_24KHZ_16BIT_MONO_OPUS,
+            "pcm" | "raw" => Self::RAW_24KHZ_16BIT_MONO_PCM,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn parts(&self) -> impl Iterator<Item = &str> {
+        self.0.split('-')
+    }
+
+    pub fn codec(&self) -> Codec {
+        match self.parts().last() {
+            Some("mp3") => Codec::Mp3,
+            Some("opus") => Codec::Opus,
+            Some("pcm") => Codec::Pcm,
+            Some("alaw") => Codec::Alaw,
+            Some("mulaw") => Codec::Mulaw,
+            _ => Codec::Other,
+        }
+    }
+
+    pub fn container(&self) -> Container {
+        match (self.parts().next(), self.codec()) {
+            (Some("audio"), Codec::Mp3) => Container::Mp3,
+            (Some("audio"), Codec::Opus) => Container::Opus,
+            (Some("ogg"), _) => Container::Ogg,
+            (Some("webm"), _) => Container::Webm,
+            (Some("raw"), _) => Container::Raw,
+            (Some("riff"), _) => Container::Riff,
+            _ => Container::Other,
+        }
+    }
+
+    /// Samples per second, eg: 24000 for "24khz", 22050 for "22050hz".
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.parts().find_map(|part| {
+            if let Some(khz) = part.strip_suffix("khz") {
+                khz.parse::<u32>().ok().map(|khz| khz * 1000)
+            } else {
+                part.strip_suffix("hz")?.parse().ok()
+            }
+        })
+    }
+
+    pub fn bits_per_sample(&self) -> Option<u16> {
+        self.parts().find_map(|part| part.strip_suffix("bit")?.parse().ok())
+    }
+
+    pub fn channels(&self) -> u16 {
+        if self.parts().any(|part| part == "stereo") {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Bits per second: the declared bitrate ("48kbitrate", "24kbps") or, for uncompressed samples, rate × depth × channels.
+    pub fn bitrate(&self) -> Option<u32> {
+        let declared = self.parts().find_map(|part| {
+            let kbits = part.strip_suffix("kbitrate").or_else(|| part.strip_suffix("kbps"))?;
+            kbits.parse::<u32>().ok().map(|kbits| kbits * 1000)
+        });
+        match self.codec() {
+            Codec::Pcm | Codec::Alaw | Codec::Mulaw => Some(self.sample_rate()? * self.bits_per_sample()? as u32 * self.channels() as u32),
+            _ => declared,
+        }
+    }
+
+    /// Audio duration of `len` bytes, for formats with a constant bitrate. RIFF header bytes are not subtracted.
+    pub fn duration_of(&self, len: usize) -> Option<Duration> {
+        match self.container() {
+            Container::Mp3 | Container::Opus | Container::Raw | Container::Riff => {
+                let bitrate = self.bitrate()?;
+                Some(Duration::from_secs_f64(len as f64 * 8.0 / bitrate as f64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Usual file extension, eg: "mp3".
+    pub fn extension(&self) -> &'static str {
+        match self.container() {
+            Container::Mp3 => "mp3",
+            Container::Opus => "opus",
+            Container::Ogg => "ogg",
+            Container::Webm => "webm",
+            Container::Riff => "wav",
+            Container::Raw => "pcm",
+            Container::Other => "bin",
+        }
+    }
+
+    /// Media type for HTTP, eg: "audio/mpeg".
+    pub fn mime_type(&self) -> &'static str {
+        match self.container() {
+            Container::Mp3 => "audio/mpeg",
+            Container::Opus | Container::Ogg => "audio/ogg",
+            Container::Webm => "audio/webm",
+            Container::Riff => "audio/wav",
+            Container::Raw if self.codec() == Codec::Pcm => "audio/L16",
+            Container::Raw | Container::Other => "application/octet-stream",
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Fail unless `audio`, the start of a stream, looks like this format: the MP3 sync word, the RIFF, Ogg or EBML
+    /// magic. Headerless formats only fail on audio that starts like one of these.
+    pub(crate) fn check_start(&self, audio: &[u8]) -> Result<(), Error> {
+        let found = sniff(audio);
+        let matches = match self.container() {
+            Container::Mp3 | Container::Riff | Container::Webm => found == Some(self.container()),
+            Container::Ogg => found == Some(Container::Ogg),
+            Container::Raw | Container::Opus | Container::Other => found.is_none(),
+        };
+        if matches {
+            return Ok(());
+        }
+        let found = match found {
+            Some(Container::Mp3) => "MP3",
+            Some(Container::Riff) => "RIFF",
+            Some(Container::Ogg) => "Ogg",
+            Some(Container::Webm) => "WebM",
+            _ => "unknown",
+        };
+        Err(Error::FormatMismatch { requested: self.to_string(), found: found.to_owned() })
+    }
+}
+
+/// Container that `audio` starts like. Bare MP3 needs two frames in a row, so that PCM rarely passes for it.
+fn sniff(audio: &[u8]) -> Option<Container> {
+    match audio {
+        [b'R', b'I', b'F', b'F', ..] => Some(Container::Riff),
+        [b'O', b'g', b'g', b'S', ..] => Some(Container::Ogg),
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => Some(Container::Webm),
+        [b'I', b'D', b'3', ..] => Some(Container::Mp3),
+        _ if frame_offsets(audio).len() >= 2 => Some(Container::Mp3),
+        // A first message of a single frame.
+        _ if parse_frame_header(audio).is_some_and(|header| header.len >= audio.len()) => Some(Container::Mp3),
+        _ => None,
+    }
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::AUDIO_24KHZ_48KBITRATE_MONO_MP3
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for OutputFormat {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl From<&str> for OutputFormat {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for OutputFormat {
+    fn from(s: String) -> Self {
+        Self::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::SynthesisRequest;
+
+    #[test]
+    fn parses_properties() {
+        let mp3 = OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3;
+        assert_eq!((mp3.container(), mp3.codec(), mp3.sample_rate(), mp3.bitrate()), (Container::Mp3, Codec::Mp3, Some(24000), Some(48000)));
+        assert_eq!(mp3.duration_of(6000), Some(Duration::from_secs(1)));
+
+        let pcm = OutputFormat::new("raw-22050hz-16bit-mono-pcm");
+        assert_eq!((pcm.container(), pcm.sample_rate(), pcm.bits_per_sample(), pcm.channels()), (Container::Raw, Some(22050), Some(16), 1));
+        assert_eq!(pcm.bitrate(), Some(22050 * 16));
+
+        let opus = OutputFormat::new("webm-24khz-16bit-24kbps-mono-opus");
+        assert_eq!((opus.container(), opus.codec(), opus.bitrate(), opus.duration_of(3000)), (Container::Webm, Codec::Opus, Some(24000), None));
+        assert_eq!(OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM.extension(), "wav");
+        assert_eq!((mp3.mime_type(), opus.mime_type()), ("audio/mpeg", "audio/webm"));
+        assert_eq!(OutputFormat::from_extension("WAV"), Some(OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM));
+        assert_eq!(OutputFormat::from_extension("flac"), None);
+    }
+
+    #[test]
+    fn checks_the_start_of_audio() {
+        let frame = |len: usize| {
+            let mut frame = vec![0xff, 0xf3, 0x64, 0xc4];
+            frame.resize(len, 0);
+            frame
+        };
+        let mp3 = [frame(144), frame(144)].concat();
+        let mp3_format = OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3;
+        assert!(mp3_format.check_start(&mp3).is_ok());
+        assert!(mp3_format.check_start(&frame(144)).is_ok());
+        assert!(OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM.check_start(b"RIFF\x24\x00\x00\x00WAVE").is_ok());
+        assert!(OutputFormat::WEBM_24KHZ_16BIT_MONO_OPUS.check_start(&[0x1a, 0x45, 0xdf, 0xa3, 0x01]).is_ok());
+        assert!(OutputFormat::OGG_48KHZ_16BIT_MONO_OPUS.check_start(b"OggS\x00").is_ok());
+        assert!(OutputFormat::RAW_24KHZ_16BIT_MONO_PCM.check_start(&[0; 480]).is_ok());
+
+        let error = OutputFormat::RAW_24KHZ_16BIT_MONO_PCM.check_start(&mp3).unwrap_err();
+        assert_eq!(error.to_string(), "audio is not raw-24khz-16bit-mono-pcm but MP3");
+        assert!(OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM.check_start(&mp3).is_err());
+        assert!(mp3_format.check_start(&[0; 480]).is_err());
+
+        let server = MockServer::start(vec![MockReply::turn(&mp3)]).unwrap();
+        let request = SynthesisRequest::new("Hi", "en-US-AriaNeural").with_output_format("raw-24khz-16bit-mono-pmc");
+        let error = server.client().with_strict_format().synthesize_request(&request).unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::FormatMismatch { found, .. }) if found == "MP3"), "{:#}", error);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+
+/// A malformed message from the service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// Binary message shorter than its 2 byte header length prefix.
+    TooShort { frame_len: usize },
+    /// Binary message shorter than the header length it announces.
+    HeaderTooLong { header_len: usize, frame_len: usize },
+    /// A message of the turn without its X-RequestId, or with another one.
+    RequestIdMismatch { path: &'static str },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooShort { frame_len } => write!(f, "bad binary response. response len: {}", frame_len),
+            FrameError::HeaderTooLong { header_len, frame_len } => write!(f, "bad binary response. response len: {} header len: {}", frame_len, header_len),
+            FrameError::RequestIdMismatch { path } => write!(f, "Path:{} no X-RequestId header", path),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+pub(crate) fn parse_headers(s: impl AsRef<str>) -> Vec<(String, String)> {
+    s.as_ref().split("\r\n").filter_map(|s| {
+        if !s.is_empty() {
+            let mut iter = s.splitn(2, ':');
+            let k = iter.next().unwrap_or("").to_owned();
+            let v = iter.next().unwrap_or("").to_owned();
+            Some((k, v))
+        } else {
+            None
+        }
+    }).collect()
+}
+
+/// Text message: `headers\r\n\r\nbody`
+pub(crate) struct TextFrame<'a> {
+    pub headers: Vec<(String, String)>,
+    pub body: &'a str,
+}
+
+/// Binary message: 2 byte big endian header length, headers, body
+pub(crate) struct BinaryFrame<'a> {
+    pub headers: Vec<(String, String)>,
+    pub body: &'a [u8],
+}
+
+pub(crate) trait Headers {
+    fn headers(&self) -> &[(String, String)];
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers().iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.header("Path")
+    }
+
+    fn request_id(&self) -> Option<&str> {
+        self.header("X-RequestId")
+    }
+}
+
+impl Headers for TextFrame<'_> {
+    fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+impl Headers for BinaryFrame<'_> {
+    fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
+
+pub(crate) fn parse_text_frame(s: &str) -> TextFrame<'_> {
+    let (header_str, body) = s.split_once("\r\n\r\n").unwrap_or((s, ""));
+    TextFrame {
+        headers: parse_headers(header_str),
+        body,
+    }
+}
+
+pub(crate) fn parse_binary_frame(s: &[u8]) -> Result<BinaryFrame<'_>, FrameError> {
+    let (len_prefix, rest) = match s {
+        [hi, lo, rest @ ..] => ((*hi as usize) << 8 | *lo as usize, rest),
+        _ => return Err(FrameError::TooShort { frame_len: s.len() }),
+    };
+    if rest.len() < len_prefix {
+        return Err(FrameError::HeaderTooLong { header_len: len_prefix, frame_len: s.len() });
+    }
+    let (header_bytes, body) = rest.split_at(len_prefix);
+    Ok(BinaryFrame {
+        headers: parse_headers(String::from_utf8_lossy(header_bytes)),
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_frame(headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut frame = (headers.len() as u16).to_be_bytes().to_vec();
+        frame.extend(headers.as_bytes());
+        frame.extend(body);
+        frame
+    }
+
+    #[test]
+    fn parses_binary_frame() {
+        let frame = binary_frame("X-RequestId:abc\r\nContent-Type:audio/mpeg\r\nPath:audio\r\n", b"\xff\xf3");
+        let frame = parse_binary_frame(&frame).unwrap();
+        assert_eq!(frame.path(), Some("audio"));
+        assert_eq!(frame.request_id(), Some("abc"));
+        assert_eq!(frame.body, b"\xff\xf3");
+    }
+
+    #[test]
+    fn parses_binary_frame_without_headers_or_body() {
+        let frame = parse_binary_frame(&[0, 0]).unwrap();
+        assert!(frame.headers.is_empty());
+        assert!(frame.body.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_length_prefix() {
+        assert_eq!(parse_binary_frame(&[]).err(), Some(FrameError::TooShort { frame_len: 0 }));
+        assert_eq!(parse_binary_frame(&[0]).err(), Some(FrameError::TooShort { frame_len: 1 }));
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let mut frame = binary_frame("Path:audio\r\n", b"");
+        frame.truncate(frame.len() - 1);
+        assert_eq!(parse_binary_frame(&frame).err(), Some(FrameError::HeaderTooLong { header_len: 12, frame_len: 13 }));
+        assert_eq!(parse_binary_frame(&[0xff, 0xff, 0]).err(), Some(FrameError::HeaderTooLong { header_len: 0xffff, frame_len: 3 }));
+    }
+
+    #[test]
+    fn parses_text_frame() {
+        let frame = parse_text_frame("X-RequestId:abc\r\nPath:turn.end\r\n\r\n{}");
+        assert_eq!(frame.path(), Some("turn.end"));
+        assert_eq!(frame.request_id(), Some("abc"));
+        assert_eq!(frame.body, "{}");
+        assert_eq!(parse_text_frame("Path:turn.start").body, "");
+    }
+
+    #[test]
+    fn never_panics_on_truncated_or_garbled_frames() {
+        let frame = binary_frame("X-RequestId:abc\r\nPath:audio\r\n", b"\xff\xf3\x64\xc4");
+        for len in 0..=frame.len() {
+            let _ = parse_binary_frame(&frame[..len]);
+            let mut garbled = frame[..len].to_vec();
+            garbled.iter_mut().for_each(|b| *b = b.wrapping_mul(31).wrapping_add(7));
+            let _ = parse_binary_frame(&garbled);
+            let _ = crate::ogg::pages(&garbled);
+            let _ = crate::mp3::frame_offsets(&garbled);
+        }
+        for text in ["", "\r\n\r\n", ":", "Path", "Path:\r\n\r\n\r\n"] {
+            let _ = parse_text_frame(text);
+            let _ = crate::metadata::parse_metadata(text);
+        }
+    }
+}
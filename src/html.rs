@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::concat::{concat_audio, ConcatOptions};
+use crate::{Client, OutputFormat, SynthesisOutput, SynthesisRequest};
+
+/// Elements whose content isn't read.
+const SKIPPED: &[&str] = &["aside", "button", "canvas", "footer", "form", "head", "header", "iframe", "nav", "noscript", "script", "select", "style", "svg", "template", "textarea"];
+
+/// Elements that start a new paragraph.
+const BLOCKS: &[&str] = &[
+    "address", "article", "blockquote", "dd", "div", "dl", "dt", "figcaption", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "li", "main", "ol", "p", "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+/// Readable text of an HTML document, paragraphs separated by an empty line.
+///
+/// Only the first `<article>`, or else `<main>`, is read if there is one. Scripts, styles, navigation, headers,
+/// footers and forms are dropped.
+pub fn html_to_text(html: &str) -> String {
+    let root = ["article", "main"].into_iter().find(|name| tags(html).any(|tag| !tag.closing && tag.name == *name));
+    let mut paragraphs: Vec<String> = vec![String::new()];
+    let mut skipped: Option<String> = None;
+    // Open root elements, -1 once past the first one.
+    let mut root_depth = 0;
+    let mut pos = 0;
+    for tag in tags(html) {
+        let in_root = root.is_none() || root_depth > 0;
+        if let (None, true, Some(paragraph)) = (&skipped, in_root, paragraphs.last_mut()) {
+            push_text(paragraph, html.get(pos..tag.start).unwrap_or_default());
+        }
+        pos = tag.end;
+        if root == Some(tag.name.as_str()) && root_depth >= 0 {
+            root_depth += if tag.closing { -1 } else { 1 };
+            if root_depth == 0 {
+                root_depth = -1;
+            }
+        }
+        match &skipped {
+            Some(name) if tag.closing && *name == tag.name => skipped = None,
+            Some(_) => {}
+            None if !tag.closing && !tag.self_closing && SKIPPED.contains(&tag.name.as_str()) => skipped = Some(tag.name),
+            None if tag.name == "br" => paragraphs.last_mut().into_iter().for_each(|p| p.push(' ')),
+            None if BLOCKS.contains(&tag.name.as_str()) && paragraphs.last().is_some_and(|p| !p.trim().is_empty()) => paragraphs.push(String::new()),
+            None => {}
+        }
+    }
+    if let (None, true, Some(paragraph)) = (&skipped, root.is_none() || root_depth > 0, paragraphs.last_mut()) {
+        push_text(paragraph, html.get(pos..).unwrap_or_default());
+    }
+    paragraphs.iter().map(|p| p.trim()).filter(|p| !p.is_empty()).collect::<Vec<_>>().join("\n\n")
+}
+
+/// Append `html` text to `paragraph`, entities decoded and whitespace collapsed.
+fn push_text(paragraph: &mut String, html: &str) {
+    for (i, word) in decode_entities(html).split_whitespace().enumerate() {
+        let glued = i == 0 && !html.starts_with(|c: char| c.is_whitespace());
+        if !paragraph.is_empty() && !glued && !paragraph.ends_with(' ') {
+            paragraph.push(' ');
+        }
+        paragraph.push_str(word);
+    }
+    if html.ends_with(|c: char| c.is_whitespace()) && !paragraph.is_empty() && !paragraph.ends_with(' ') {
+        paragraph.push(' ');
+    }
+}
+
+fn decode_entities(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(amp) = rest.find('&') {
+        text += rest.get(..amp).unwrap_or_default();
+        rest = rest.get(amp..).unwrap_or_default();
+        let entity = rest.get(1..).and_then(|r| r.find(';').filter(|&end| end <= 10).and_then(|end| r.get(..end)));
+        let decoded = entity.and_then(|name| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            _ => match name.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(hex.get(1..)?, 16).ok().and_then(char::from_u32),
+                Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                None => None,
+            },
+        });
+        match (entity, decoded) {
+            (Some(name), Some(c)) => {
+                text.push(c);
+                rest = rest.get(name.len() + 2..).unwrap_or_default();
+            }
+            _ => {
+                text.push('&');
+                rest = rest.get(1..).unwrap_or_default();
+            }
+        }
+    }
+    text + rest
+}
+
+struct Tag {
+    /// Lowercase, eg: "p"
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    /// Byte range in the document.
+    start: usize,
+    end: usize,
+}
+
+/// Tags of `html` in order, comments as tags without a name.
+fn tags(html: &str) -> impl Iterator<Item = Tag> + '_ {
+    let mut pos = 0;
+    std::iter::from_fn(move || loop {
+        let start = pos + html.get(pos..)?.find('<')?;
+        let rest = html.get(start..)?;
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            pos = start + 4 + comment.find("-->").map_or(comment.len(), |i| i + 3);
+            return Some(Tag { name: String::new(), closing: false, self_closing: true, start, end: pos });
+        }
+        let end = start + rest.find('>').map_or(rest.len(), |i| i + 1);
+        pos = end;
+        let inner = html.get(start + 1..end)?.trim_end_matches('>');
+        let closing = inner.starts_with('/');
+        let name: String = inner.trim_start_matches('/').chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        if name.is_empty() && !inner.starts_with('!') && !inner.starts_with('?') {
+            // A lone "<" of the text.
+            pos = start + 1;
+            continue;
+        }
+        return Some(Tag { name: name.to_ascii_lowercase(), closing, self_closing: inner.ends_with('/'), start, end });
+    })
+}
+
+impl Client {
+    /// Read the [text](html_to_text) of `html` with `voice`, with a pause between paragraphs, each synthesized on its own.
+    ///
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn synthesize_html(&self, html: &str, voice: &str, output_format: &str) -> Result<SynthesisOutput> {
+        let text = html_to_text(html);
+        if text.is_empty() {
+            bail!("no readable text in the HTML document");
+        }
+        let format = OutputFormat::new(output_format);
+        let parts = text
+            .split("\n\n")
+            .map(|paragraph| self.synthesize_request(&SynthesisRequest::new(paragraph, voice).with_output_format(format.clone())))
+            .collect::<Result<Vec<_>>>()?;
+        let options = ConcatOptions { pause: Duration::from_millis(500), ..ConcatOptions::default() };
+        concat_audio(parts, &format, &options)
+    }
+
+    /// Download the web page at `url` and [`Client::synthesize_html`] it.
+    pub fn read_article(&self, url: &str, voice: &str, output_format: &str) -> Result<SynthesisOutput> {
+        let html = ureq::get(url).call()?.into_string()?;
+        self.synthesize_html(&html, voice, output_format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_article_text() {
+        let html = r#"<!DOCTYPE html><html><head><title>Skip</title><style>p { color: red }</style></head>
+            <body><nav><a href="/">Home</a></nav>
+            <article><h1>Title &amp; more</h1>
+            <p>First <b>bold</b> paragraph,<br>second line.</p>
+            <script>if (a < b) { document.write("<p>no</p>") }</script>
+            <!-- <p>comment</p> -->
+            <p>R&D: 1 &lt; 2&#x21; &#8212; done</p></article>
+            <footer>Copyright</footer><p>After the article</p></body></html>"#;
+        assert_eq!(html_to_text(html), "Title & more\n\nFirst bold paragraph, second line.\n\nR&D: 1 < 2! — done");
+        assert_eq!(html_to_text("<div>One</div><div>Two <i>three</i>.</div>"), "One\n\nTwo three.");
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use crate::mp3::id3v2_len;
+
+/// ID3v2 text tags, written by [`write_id3`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Id3Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// eg: "3" or "3/12"
+    pub track: Option<String>,
+}
+
+/// One `CHAP` frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// Copy of `mp3` with an ID3v2.3 tag holding `tags` and `chapters` (with a table of contents) in place of any
+/// existing ID3v2 tag. Only the first 255 chapters are listed in the table of contents.
+pub fn write_id3(mp3: &[u8], tags: &Id3Tags, chapters: &[Chapter]) -> Vec<u8> {
+    let mut frames = Vec::new();
+    for (id, value) in [(b"TIT2", &tags.title), (b"TPE1", &tags.artist), (b"TALB", &tags.album), (b"TRCK", &tags.track)] {
+        if let Some(value) = value {
+            frames.extend(frame(id, &text_body(value)));
+        }
+    }
+    if !chapters.is_empty() {
+        let mut toc = b"toc\0".to_vec();
+        // Top-level, ordered.
+        toc.push(0b11);
+        let listed = chapters.len().min(255);
+        toc.push(listed as u8);
+        for i in 0..listed {
+            toc.extend(format!("chp{}\0", i).as_bytes());
+        }
+        frames.extend(frame(b"CTOC", &toc));
+        for (i, chapter) in chapters.iter().enumerate() {
+            let mut chap = format!("chp{}\0", i).into_bytes();
+            chap.extend((chapter.start.as_millis() as u32).to_be_bytes());
+            chap.extend((chapter.end.as_millis() as u32).to_be_bytes());
+            // Byte offsets unused.
+            chap.extend([0xff; 8]);
+            chap.extend(frame(b"TIT2", &text_body(&chapter.title)));
+            frames.extend(frame(b"CHAP", &chap));
+        }
+    }
+    let mut tag = b"ID3\x03\x00\x00".to_vec();
+    tag.extend(syncsafe(frames.len() as u32));
+    tag.extend(frames);
+    let audio = id3v2_len(mp3).and_then(|len| mp3.get(len..)).unwrap_or(mp3);
+    tag.extend_from_slice(audio);
+    tag
+}
+
+fn frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut frame = id.to_vec();
+    frame.extend((body.len() as u32).to_be_bytes());
+    frame.extend([0, 0]);
+    frame.extend(body);
+    frame
+}
+
+/// UTF-16 with BOM, the only Unicode encoding of ID3v2.3.
+fn text_body(text: &str) -> Vec<u8> {
+    let mut body = vec![1, 0xff, 0xfe];
+    body.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    body
+}
+
+fn syncsafe(n: u32) -> [u8; 4] {
+    [(n >> 21) as u8 & 0x7f, (n >> 14) as u8 & 0x7f, (n >> 7) as u8 & 0x7f, n as u8 & 0x7f]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_tag_and_chapters() {
+        let tags = Id3Tags { title: Some("Book".to_owned()), ..Default::default() };
+        let chapters = [Chapter { title: "One".to_owned(), start: Duration::ZERO, end: Duration::from_secs(2) }];
+        let old = write_id3(b"\xff\xf3audio", &Id3Tags { album: Some("old".to_owned()), ..Default::default() }, &[]);
+        let mp3 = write_id3(&old, &tags, &chapters);
+        assert_eq!(id3v2_len(&mp3), Some(mp3.len() - 7));
+        assert!(mp3.ends_with(b"\xff\xf3audio"));
+        let body = &mp3[10..mp3.len() - 7];
+        assert!(body.starts_with(b"TIT2\x00\x00\x00\x0b\x00\x00\x01\xff\xfeB\x00"));
+        assert!(body.windows(4).any(|w| w == b"CTOC"));
+        let chap = body.windows(4).position(|w| w == b"CHAP").unwrap();
+        assert_eq!(&body[chap + 10..chap + 22], b"chp0\0\x00\x00\x00\x00\x00\x00\x07");
+        assert!(!body.windows(3).any(|w| w == b"o\x00l"));
+    }
+}
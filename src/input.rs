@@ -0,0 +1,227 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// When text read from a streaming source (eg: stdin, a pipe, a log file) is handed off for synthesis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Every non-empty line is a chunk.
+    Line,
+    /// Lines are collected until an empty line.
+    Paragraph,
+    /// Lines are collected until no new input arrives for the given duration.
+    Idle(Duration),
+    /// Everything until EOF is one chunk.
+    Eof,
+}
+
+/// Iterator over text chunks read from a streaming source. See [`text_chunks`].
+pub struct TextChunks {
+    rx: Receiver<std::io::Result<String>>,
+    policy: FlushPolicy,
+    pending: String,
+}
+
+/// Split `reader` into chunks according to `policy`.
+///
+/// The reader is consumed on a background thread, so [`FlushPolicy::Idle`] fires even while a read is blocked.
+/// Whatever is pending when the source reaches EOF is always flushed.
+pub fn text_chunks<R: Read + Send + 'static>(reader: R, policy: FlushPolicy) -> TextChunks {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let is_err = line.is_err();
+            if tx.send(line).is_err() || is_err {
+                break;
+            }
+        }
+    });
+    TextChunks {
+        rx,
+        policy,
+        pending: String::new(),
+    }
+}
+
+impl TextChunks {
+    fn push(&mut self, line: &str) {
+        if !self.pending.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending.push_str(line);
+    }
+
+    fn flush(&mut self) -> Option<std::io::Result<String>> {
+        if self.pending.trim().is_empty() {
+            self.pending.clear();
+            None
+        } else {
+            Some(Ok(std::mem::take(&mut self.pending)))
+        }
+    }
+}
+
+impl Iterator for TextChunks {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.policy {
+                FlushPolicy::Idle(timeout) if !self.pending.is_empty() => match self.rx.recv_timeout(timeout) {
+                    Ok(line) => Some(line),
+                    Err(RecvTimeoutError::Timeout) => match self.flush() {
+                        Some(chunk) => return Some(chunk),
+                        None => continue,
+                    },
+                    Err(RecvTimeoutError::Disconnected) => None,
+                },
+                _ => self.rx.recv().ok(),
+            };
+            match line {
+                None => return self.flush(),
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(line)) => match self.policy {
+                    FlushPolicy::Line => {
+                        if !line.trim().is_empty() {
+                            return Some(Ok(line));
+                        }
+                    }
+                    FlushPolicy::Paragraph => {
+                        if line.trim().is_empty() {
+                            if let Some(chunk) = self.flush() {
+                                return Some(chunk);
+                            }
+                        } else {
+                            self.push(&line);
+                        }
+                    }
+                    FlushPolicy::Idle(_) | FlushPolicy::Eof => self.push(&line),
+                },
+            }
+        }
+    }
+}
+
+/// A file read like `tail -f`: at EOF reads wait for more data instead of returning 0. See [`follow_file`].
+pub struct FollowFile {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    poll_interval: Duration,
+}
+
+/// Follow the file at `path`, starting at its current end unless `from_start`.
+///
+/// If the file shrinks (truncated or rotated), reading starts over from the beginning of the file now at `path`.
+pub fn follow_file(path: impl AsRef<Path>, from_start: bool) -> std::io::Result<FollowFile> {
+    let path = path.as_ref().to_owned();
+    let mut file = File::open(&path)?;
+    let pos = if from_start { 0 } else { file.seek(SeekFrom::End(0))? };
+    Ok(FollowFile {
+        path,
+        file,
+        pos,
+        poll_interval: Duration::from_millis(250),
+    })
+}
+
+impl FollowFile {
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+}
+
+impl Read for FollowFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            thread::sleep(self.poll_interval);
+            // A missing file is probably being rotated, keep waiting for it.
+            if let Ok(metadata) = std::fs::metadata(&self.path) {
+                if metadata.len() < self.pos {
+                    self.file = File::open(&self.path)?;
+                    self.pos = 0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::{Cursor, ErrorKind, Write};
+
+    use super::*;
+
+    fn chunks(text: &'static str, policy: FlushPolicy) -> Vec<String> {
+        text_chunks(Cursor::new(text), policy).map(Result::unwrap).collect()
+    }
+
+    /// Returns each part after its delay, or the error of an empty part, then EOF.
+    struct Slow(VecDeque<(Duration, &'static [u8])>);
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some((delay, part)) = self.0.pop_front() else { return Ok(0) };
+            thread::sleep(delay);
+            if part.is_empty() {
+                return Err(ErrorKind::ConnectionReset.into());
+            }
+            buf[..part.len()].copy_from_slice(part);
+            Ok(part.len())
+        }
+    }
+
+    #[test]
+    fn flushes_by_policy() {
+        let text = "one\n\ntwo\nthree\n\n\nfour";
+        assert_eq!(chunks(text, FlushPolicy::Line), ["one", "two", "three", "four"]);
+        // What is pending at EOF is flushed, without a closing empty line.
+        assert_eq!(chunks(text, FlushPolicy::Paragraph), ["one", "two\nthree", "four"]);
+        assert_eq!(chunks(text, FlushPolicy::Eof), ["one\n\ntwo\nthree\n\n\nfour"]);
+        assert!(chunks("\n \n", FlushPolicy::Eof).is_empty());
+        assert!(chunks("", FlushPolicy::Line).is_empty());
+
+        let slow = Slow(VecDeque::from([(Duration::ZERO, &b"one\ntwo\n"[..]), (Duration::from_millis(300), b"three\n")]));
+        let idle: Vec<String> = text_chunks(slow, FlushPolicy::Idle(Duration::from_millis(100))).map(Result::unwrap).collect();
+        assert_eq!(idle, ["one\ntwo", "three"]);
+    }
+
+    #[test]
+    fn ends_with_read_errors() {
+        let failing = Slow(VecDeque::from([(Duration::ZERO, &b"one\n"[..]), (Duration::ZERO, b""), (Duration::ZERO, b"two\n")]));
+        let mut chunks = text_chunks(failing, FlushPolicy::Line);
+        assert_eq!(chunks.next().unwrap().unwrap(), "one");
+        assert_eq!(chunks.next().unwrap().unwrap_err().kind(), ErrorKind::ConnectionReset);
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn follows_appends_and_truncation() {
+        let path = std::env::temp_dir().join(format!("edge-tts-follow-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&path, "old\n").unwrap();
+        let mut follow = follow_file(&path, false).unwrap().with_poll_interval(Duration::from_millis(10));
+        let mut read = || {
+            let mut buf = [0; 16];
+            let n = follow.read(&mut buf).unwrap();
+            buf[..n].to_vec()
+        };
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"new\n").unwrap();
+        assert_eq!(read(), b"new\n");
+        // Rotated: shorter than what was read, so read from the start again.
+        std::fs::write(&path, "r\n").unwrap();
+        assert_eq!(read(), b"r\n");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(follow_file(&path, true).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::metadata::Boundary;
+use crate::storage::{FsStorage, Storage};
+use crate::{Client, SynthesisOutput, SynthesisRequest};
+
+/// Progress of a batch in a [`Storage`], so that it survives crashes: `state.json` lists the requests and what
+/// became of them, `<digest>.audio` and `<digest>.json` hold the audio and boundaries of each finished one.
+///
+/// Start it with [`Client::run_job`], continue it with [`Client::resume_job`].
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    storage: Arc<dyn Storage>,
+    namespace: String,
+}
+
+/// See [`BatchJob`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobState {
+    pub items: Vec<JobItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobItem {
+    pub request: SynthesisRequest,
+    /// Of what determines the audio, see [`crate::SynthKey::digest`].
+    pub digest: String,
+    pub status: JobStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Done { bytes: u64, duration: Option<Duration> },
+    /// The error message of the last attempt.
+    Failed(String),
+}
+
+impl JobState {
+    pub fn is_complete(&self) -> bool {
+        self.items.iter().all(|item| matches!(item.status, JobStatus::Done { .. }))
+    }
+
+    /// eg: `{"items": [{"text": "Hello", "voice": "en-US-AriaNeural", "output_format": "...", "digest": "<hex>", "status": "done", "bytes": 1200}]}`
+    pub fn to_json(&self) -> Value {
+        let items: Vec<Value> = self
+            .items
+            .iter()
+            .map(|item| {
+                let r = &item.request;
+                let mut value = json!({
+                    "text": r.text, "voice": r.voice, "pitch": r.pitch, "rate": r.rate, "volume": r.volume,
+                    "output_format": r.output_format.as_str(), "digest": item.digest,
+                });
+                let status = match &item.status {
+                    JobStatus::Pending => json!({ "status": "pending" }),
+                    JobStatus::Done { bytes, duration } => json!({ "status": "done", "bytes": bytes, "duration": duration.map(|d| d.as_secs_f64()) }),
+                    JobStatus::Failed(error) => json!({ "status": "failed", "error": error }),
+                };
+                if let (Some(value), Some(status)) = (value.as_object_mut(), status.as_object()) {
+                    value.extend(status.clone());
+                }
+                value
+            })
+            .collect();
+        json!({ "items": items })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let items = value.get("items").and_then(Value::as_array).ok_or_else(|| anyhow!("job state without items"))?;
+        let items = items
+            .iter()
+            .map(|item| {
+                let text = |key: &str| item.get(key).and_then(Value::as_str).map(str::to_owned);
+                let required = |key: &str| text(key).ok_or_else(|| anyhow!("job item without {}", key));
+                let mut request = SynthesisRequest::new(required("text")?, required("voice")?).with_output_format(required("output_format")?);
+                request.pitch = text("pitch");
+                request.rate = text("rate");
+                request.volume = text("volume");
+                let status = match text("status").as_deref() {
+                    Some("done") => JobStatus::Done {
+                        bytes: item.get("bytes").and_then(Value::as_u64).unwrap_or_default(),
+                        duration: item.get("duration").and_then(Value::as_f64).and_then(|d| Duration::try_from_secs_f64(d).ok()),
+                    },
+                    Some("failed") => JobStatus::Failed(text("error").unwrap_or_default()),
+                    _ => JobStatus::Pending,
+                };
+                Ok(JobItem { request, digest: required("digest")?, status })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { items })
+    }
+}
+
+impl BatchJob {
+    const STATE: &'static str = "state.json";
+
+    /// Files directly in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_storage(Arc::new(FsStorage::new(dir)), "")
+    }
+
+    /// eg: `namespace`: "jobs/nightly"
+    pub fn with_storage(storage: Arc<dyn Storage>, namespace: impl Into<String>) -> Self {
+        Self { storage, namespace: namespace.into() }
+    }
+
+    /// `None` before the job was started.
+    pub fn state(&self) -> Result<Option<JobState>> {
+        match self.storage.get(&self.namespace, Self::STATE)? {
+            Some(json) => Ok(Some(JobState::from_json(&serde_json::from_slice(&json)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn save_state(&self, state: &JobState) -> Result<()> {
+        self.storage.put(&self.namespace, Self::STATE, state.to_json().to_string().as_bytes())
+    }
+
+    /// Audio and boundaries of a finished item, `None` if it isn't stored.
+    pub fn output(&self, item: &JobItem) -> Result<Option<SynthesisOutput>> {
+        let Some(audio) = self.storage.get(&self.namespace, &format!("{}.audio", item.digest))? else { return Ok(None) };
+        let boundaries = match self.storage.get(&self.namespace, &format!("{}.json", item.digest))? {
+            Some(json) => serde_json::from_slice::<Value>(&json)?.as_array().map(Vec::as_slice).unwrap_or_default().iter().filter_map(Boundary::from_json).collect(),
+            None => Vec::new(),
+        };
+        let duration = match item.status {
+            JobStatus::Done { duration, .. } => duration,
+            _ => None,
+        };
+        Ok(Some(SynthesisOutput { audio, boundaries, duration, ..Default::default() }))
+    }
+
+    fn store(&self, digest: &str, output: &SynthesisOutput) -> Result<()> {
+        let boundaries = Value::Array(output.boundaries.iter().map(Boundary::to_json).collect());
+        self.storage.put(&self.namespace, &format!("{}.json", digest), boundaries.to_string().as_bytes())?;
+        self.storage.put(&self.namespace, &format!("{}.audio", digest), &output.audio)
+    }
+}
+
+impl Client {
+    /// Start `job` over with `requests`, see [`Client::resume_job`].
+    pub fn run_job(&self, job: &BatchJob, requests: Vec<SynthesisRequest>, concurrency: usize) -> Result<JobState> {
+        let items = requests
+            .into_iter()
+            .map(|request| {
+                let digest = self.synth_key(&self.request_ssml(&request), request.output_format.as_str()).digest();
+                JobItem { request, digest, status: JobStatus::Pending }
+            })
+            .collect();
+        job.save_state(&JobState { items })?;
+        self.resume_job(job, concurrency)
+    }
+
+    /// Synthesize the items of `job` that aren't done yet, on up to `concurrency` threads, saving the state after
+    /// each. A failed item doesn't stop the others and is attempted again by the next resume.
+    pub fn resume_job(&self, job: &BatchJob, concurrency: usize) -> Result<JobState> {
+        let state = job.state()?.ok_or_else(|| anyhow!("the job wasn't started"))?;
+        let stored = |item: &JobItem| job.storage.stat(&job.namespace, &format!("{}.audio", item.digest)).ok().flatten().is_some();
+        let pending: Vec<usize> = state
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| !matches!(item.status, JobStatus::Done { .. }) || !stored(item))
+            .map(|(i, _)| i)
+            .collect();
+        let state = Mutex::new(state);
+        let next = AtomicUsize::new(0);
+        let workers = concurrency.clamp(1, pending.len().max(1));
+        let saved: Result<()> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| loop {
+                        let Some(&i) = pending.get(next.fetch_add(1, Ordering::Relaxed)) else { return Ok(()) };
+                        let request = state.lock().unwrap_or_else(|e| e.into_inner()).items[i].request.clone();
+                        let digest = self.synth_key(&self.request_ssml(&request), request.output_format.as_str()).digest();
+                        let status = match self.synthesize_request(&request).and_then(|output| job.store(&digest, &output).map(|_| output)) {
+                            Ok(output) => JobStatus::Done { bytes: output.audio.len() as u64, duration: output.duration },
+                            Err(e) => JobStatus::Failed(format!("{:#}", e)),
+                        };
+                        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                        state.items[i].digest = digest;
+                        state.items[i].status = status;
+                        job.save_state(&state)?;
+                    })
+                })
+                .collect();
+            // A worker only panics if synthesis itself panicked, so propagate it.
+            handles.into_iter().try_for_each(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+        });
+        saved?;
+        Ok(state.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn resumes_failed_items() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-job-{}", uuid::Uuid::new_v4()));
+        let job = BatchJob::new(&dir);
+        let requests: Vec<SynthesisRequest> = ["one", "two", "three"].into_iter().map(|text| SynthesisRequest::new(text, "en-US-AriaNeural").with_rate("+10%")).collect();
+        let closed = vec![MockReply::Close { code: 1011, reason: "overloaded".to_owned() }];
+        let server = MockServer::start(vec![MockReply::turn(b"1"), closed, MockReply::turn(b"3")]).unwrap();
+        let state = server.client().run_job(&job, requests, 1).unwrap();
+        assert!(!state.is_complete());
+        assert!(matches!(&state.items[1].status, JobStatus::Failed(error) if error.contains("overloaded")), "{:?}", state.items[1]);
+        assert_eq!(job.state().unwrap().unwrap(), state);
+        let server = MockServer::start(vec![MockReply::turn(b"2")]).unwrap();
+        let state = server.client().resume_job(&job, 4).unwrap();
+        assert!(state.is_complete());
+        assert_eq!(server.requests().len(), 1);
+        assert!(server.requests()[0].ssml.contains(">two<"));
+        let audio: Vec<Vec<u8>> = state.items.iter().map(|item| job.output(item).unwrap().unwrap().audio).collect();
+        assert_eq!(audio, [b"1", b"2", b"3"]);
+        assert_eq!(state.items[2].request.rate.as_deref(), Some("+10%"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use serde_json::{json, Value};
+
+use crate::{Boundary, BoundaryKind};
+
+/// Where a word is in the input text and in the audio, see [`karaoke_words`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KaraokeWord {
+    /// Range of the word in the text, in characters, eg: to highlight it.
+    pub char_start: usize,
+    pub char_end: usize,
+    pub audio_start_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// The words of `boundaries` located in `text`, the plain text that was synthesized rather than its SSML, eg: for a
+/// read-along UI highlighting each word as it is spoken.
+///
+/// Words are searched in order through the whole text, so the output of [`crate::Client::synthesize_long`], whose
+/// chunks are synthesized separately, maps onto its request's text. A word that isn't found, eg: a number spoken
+/// differently than written, gets an empty range at the end of the previous word.
+pub fn karaoke_words(boundaries: &[Boundary], text: &str) -> Vec<KaraokeWord> {
+    let (mut bytes, mut chars) = (0, 0);
+    let mut words = Vec::new();
+    for word in boundaries.iter().filter(|b| b.kind == BoundaryKind::Word) {
+        let found = match word.text.as_str() {
+            "" => None,
+            needle => text.get(bytes..).and_then(|rest| rest.find(needle)),
+        };
+        let (char_start, char_end) = match found {
+            Some(at) => {
+                let start = chars + text.get(bytes..bytes + at).unwrap_or_default().chars().count();
+                bytes += at + word.text.len();
+                chars = start + word.text.chars().count();
+                (start, chars)
+            }
+            None => (chars, chars),
+        };
+        words.push(KaraokeWord {
+            char_start,
+            char_end,
+            audio_start_ms: word.offset.as_millis() as u64,
+            duration_ms: word.duration.as_millis() as u64,
+        });
+    }
+    words
+}
+
+/// Compact JSON array of [`karaoke_words`], eg: `[{"audio_start_ms":50,"char_end":5,"char_start":0,"duration_ms":300}]`.
+pub fn to_karaoke_json(boundaries: &[Boundary], text: &str) -> String {
+    let words: Vec<Value> = karaoke_words(boundaries, text)
+        .iter()
+        .map(|word| {
+            json!({
+                "char_start": word.char_start,
+                "char_end": word.char_end,
+                "audio_start_ms": word.audio_start_ms,
+                "duration_ms": word.duration_ms,
+            })
+        })
+        .collect();
+    Value::from(words).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn maps_words_onto_the_text() {
+        let word = |ms: u64, text: &str| Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(ms), duration: Duration::from_millis(200), text: text.to_owned() };
+        // As joined from two chunks, "Café au lait." and "Bon 3 fois.", the second starting at 1 s.
+        let text = "Café au lait.\nBon 3 fois.";
+        let boundaries = [word(50, "Café"), word(300, "au"), word(500, "lait"), word(1050, "Bon"), word(1300, "trois"), word(1500, "fois")];
+        let words = karaoke_words(&boundaries, text);
+        let spans: Vec<(usize, usize)> = words.iter().map(|w| (w.char_start, w.char_end)).collect();
+        assert_eq!(spans, [(0, 4), (5, 7), (8, 12), (14, 17), (17, 17), (20, 24)]);
+        let highlighted: String = text.chars().skip(words[5].char_start).take(words[5].char_end - words[5].char_start).collect();
+        assert_eq!(highlighted, "fois");
+        assert_eq!((words[3].audio_start_ms, words[3].duration_ms), (1050, 200));
+
+        let json = to_karaoke_json(&boundaries[..2], text);
+        assert_eq!(json, r#"[{"audio_start_ms":50,"char_end":4,"char_start":0,"duration_ms":200},{"audio_start_ms":300,"char_end":7,"char_start":5,"duration_ms":200}]"#);
+        assert_eq!(to_karaoke_json(&[], text), "[]");
+    }
+}
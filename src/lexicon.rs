@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+/// How a [`Lexicon`] word is said.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pronunciation {
+    /// eg: "ˈɛndʒɪnˈɛks" for "nginx"
+    Ipa(String),
+    /// Other words read instead, eg: "sequel" for "SQL".
+    Alias(String),
+}
+
+/// Pronunciations of words the voices get wrong, eg: product names, applied by [`crate::Ssml::text_with_lexicon`]
+/// and [`crate::Client::with_lexicon`]. The Edge service doesn't fetch lexicon files, so the words are wrapped in
+/// `<phoneme>` and `<sub>` elements instead.
+///
+/// Words match whole and ASCII case-insensitively; a word may have spaces, eg: "Visual Studio". The longest match
+/// wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lexicon {
+    entries: Vec<(String, Pronunciation)>,
+}
+
+impl Lexicon {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Say `word` as `ipa`, eg: `.with_ipa("nginx", "ˈɛndʒɪnˈɛks")`.
+    pub fn with_ipa(self, word: &str, ipa: &str) -> Self {
+        self.with(word, Pronunciation::Ipa(ipa.to_owned()))
+    }
+
+    /// Read `alias` instead of `word`, eg: `.with_alias("SQL", "sequel")`.
+    pub fn with_alias(self, word: &str, alias: &str) -> Self {
+        self.with(word, Pronunciation::Alias(alias.to_owned()))
+    }
+
+    /// Say `word` as `pronunciation`, replacing an earlier entry of it.
+    pub fn with(mut self, word: &str, pronunciation: Pronunciation) -> Self {
+        self.entries.retain(|(w, _)| !w.eq_ignore_ascii_case(word));
+        if !word.is_empty() {
+            self.entries.push((word.to_owned(), pronunciation));
+            self.entries.sort_by_key(|(w, _)| std::cmp::Reverse(w.len()));
+        }
+        self
+    }
+
+    /// A dictionary of one word per line, an IPA pronunciation between slashes or else an alias, eg:
+    ///
+    /// ```text
+    /// # Product names
+    /// nginx = /ˈɛndʒɪnˈɛks/
+    /// SQL = sequel
+    /// ```
+    pub fn parse(dictionary: &str) -> Result<Self> {
+        let mut lexicon = Self::new();
+        for (number, line) in dictionary.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (word, said) = line.split_once('=').ok_or_else(|| anyhow!("line {}: expected word = /ipa/ or word = alias", number + 1))?;
+            let (word, said) = (word.trim(), said.trim());
+            if word.is_empty() || said.is_empty() {
+                return Err(anyhow!("line {}: empty word or pronunciation", number + 1));
+            }
+            lexicon = match said.strip_prefix('/').and_then(|said| said.strip_suffix('/')) {
+                Some(ipa) => lexicon.with_ipa(word, ipa.trim()),
+                None => lexicon.with_alias(word, said),
+            };
+        }
+        Ok(lexicon)
+    }
+
+    /// [`Lexicon::parse`] of the file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Escaped `text` with the words of the lexicon in `<phoneme>` and `<sub>` elements.
+    pub(crate) fn to_ssml(&self, text: &str) -> String {
+        let mut ssml = String::new();
+        let mut plain = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let at_word_start = !text.get(..i).and_then(|before| before.chars().next_back()).is_some_and(is_word_char);
+            let entry = self.entries.iter().find(|(word, _)| {
+                at_word_start
+                    && text.get(i..i + word.len()).is_some_and(|s| s.eq_ignore_ascii_case(word))
+                    && !text.get(i + word.len()..).and_then(|after| after.chars().next()).is_some_and(is_word_char)
+            });
+            match entry {
+                Some((word, pronunciation)) => {
+                    ssml += &escape_str_pcdata(&text[plain..i]);
+                    let matched = &text[i..i + word.len()];
+                    ssml += &match pronunciation {
+                        Pronunciation::Ipa(ipa) => format!("<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>", escape_str_attribute(ipa), escape_str_pcdata(matched)),
+                        Pronunciation::Alias(alias) => format!("<sub alias=\"{}\">{}</sub>", escape_str_attribute(alias), escape_str_pcdata(matched)),
+                    };
+                    i += word.len();
+                    plain = i;
+                }
+                None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        ssml + &escape_str_pcdata(&text[plain..])
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::{SynthesisRequest, VoicePresets};
+
+    #[test]
+    fn wraps_whole_words() {
+        let lexicon = Lexicon::new().with_ipa("nginx", "ˈɛndʒɪnˈɛks").with_ipa("SQL", "ˈsiːkwəl").with_ipa("SQL Server", "ˈsiːkwəl ˈsɜːrvər");
+        assert_eq!(
+            lexicon.to_ssml("Nginx & SQL Server, not nginxes or MySQL."),
+            "<phoneme alphabet=\"ipa\" ph=\"ˈɛndʒɪnˈɛks\">Nginx</phoneme> &amp; <phoneme alphabet=\"ipa\" ph=\"ˈsiːkwəl ˈsɜːrvər\">SQL Server</phoneme>, not nginxes or MySQL."
+        );
+        assert_eq!(Lexicon::new().to_ssml("a < b"), "a &lt; b");
+
+        let lexicon = Lexicon::parse("# Product names\nnginx = /ˈɛndʒɪnˈɛks/\n\nSQL = sequel\n").unwrap();
+        assert_eq!(lexicon, Lexicon::new().with_ipa("nginx", "ˈɛndʒɪnˈɛks").with_alias("SQL", "sequel"));
+        assert_eq!(lexicon.to_ssml("SQL"), "<sub alias=\"sequel\">SQL</sub>");
+        assert!(Lexicon::parse("nginx").is_err());
+        assert!(Lexicon::parse("nginx = ").is_err());
+    }
+
+    #[test]
+    fn applies_to_requests() {
+        let server = MockServer::start(vec![MockReply::turn(&[0, 64])]).unwrap();
+        let client = server.client().with_voice_presets(VoicePresets::empty()).with_lexicon(Lexicon::new().with_alias("SQL", "sequel"));
+        client.synthesize_request(&SynthesisRequest::new("SQL & more", "en-US-AriaNeural")).unwrap();
+        let ssml = &server.requests()[0].ssml;
+        assert!(ssml.contains("<prosody pitch=\"default\" rate=\"default\" volume=\"default\"><sub alias=\"sequel\">SQL</sub> &amp; more</prosody>"), "{}", ssml);
+
+        let server = MockServer::start(vec![MockReply::turn(&[0, 64])]).unwrap();
+        let client = server.client().with_voice_presets(VoicePresets::empty()).with_lexicon(Lexicon::new().with_alias("SQL", "sequel")).with_pause_markers();
+        client.synthesize_request(&SynthesisRequest::new("SQL[pause:1s] more", "en-US-AriaNeural")).unwrap();
+        let ssml = &server.requests()[0].ssml;
+        assert!(ssml.contains("<sub alias=\"sequel\">SQL</sub><break time=\"1000ms\"/> more</prosody>"), "{}", ssml);
+    }
+}
@@ -1,7 +1,207 @@
 #[cfg(feature = "voice_list")]
 mod voice_list;
-mod synthesize;
+mod trace;
+// Malformed data from the service must never panic the host application, so the modules reading it deny what could.
+macro_rules! panic_free {
+    ($($(#[$attr:meta])* mod $name:ident;)*) => {
+        $($(#[$attr])* #[cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used, clippy::panic, clippy::string_slice))] mod $name;)*
+    };
+}
+panic_free! {
+    mod clock;
+    mod synthesize;
+    mod metadata;
+    mod frame;
+    mod stream;
+    mod mp3;
+    mod resume;
+    mod ogg;
+    #[cfg(feature = "remux")]
+    mod remux;
+}
+mod browser;
+mod backend;
+mod input;
+mod announcer;
+mod error;
+mod storage;
+mod cache;
+mod format;
+mod request;
+mod batch;
+mod rate_limit;
+mod quota;
+mod subtitle;
+mod speech_marks;
+mod karaoke;
+mod wav;
+mod dub;
+mod limit;
+mod save;
+mod preview;
+mod pcm;
+mod concat;
+mod silence;
+mod presets;
+mod reader;
+mod channel;
+mod writer;
+mod sink;
+mod metrics;
+mod dump;
+mod markdown;
+mod audiobook;
+mod job;
+mod pipeline;
+mod session;
+mod turn_info;
+mod pool;
+mod env;
+mod ssml;
+mod lexicon;
+mod normalize;
+mod progress;
+mod observer;
+#[cfg(feature = "id3")]
+mod id3;
+#[cfg(feature = "captions")]
+mod captions;
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+mod notifications;
+#[cfg(feature = "cli")]
+mod tail;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "bot")]
+mod bot;
+#[cfg(feature = "ffmpeg")]
+mod video;
+#[cfg(feature = "pptx")]
+mod pptx;
+#[cfg(feature = "bundle")]
+mod bundle;
+#[cfg(feature = "loudness")]
+mod loudness;
+#[cfg(feature = "dsp")]
+mod resample;
+#[cfg(feature = "dsp")]
+mod stretch;
+#[cfg(feature = "dsp")]
+mod mix;
+#[cfg(feature = "bytes")]
+mod audio_bytes;
+#[cfg(feature = "async")]
+mod audio_stream;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "azure")]
+mod azure;
+#[cfg(feature = "detect")]
+mod detect;
+#[cfg(feature = "detect")]
+mod mixed;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+mod openai;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 #[cfg(feature = "voice_list")]
-pub use voice_list::{get_voice_list};
-pub use synthesize::{build_ssml, request_audio, request_audio_via_socks5_proxy};
+pub use voice_list::{bundled_voices, find_voices, get_voice_list, Gender, VoiceListCache};
+pub use synthesize::{build_ssml, request_audio, request_audio_via_socks5_proxy, Client, SynthesisEvent, SynthesisOutput, TurnIds};
+pub use input::{follow_file, text_chunks, FollowFile, FlushPolicy, TextChunks};
+pub use metadata::{Boundary, BoundaryKind, MetadataOptions};
+pub use announcer::{Announcer, Player};
+pub use frame::FrameError;
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+pub use notifications::{listen_notifications, Notification, NotificationFilter, Notifications};
+#[cfg(feature = "cli")]
+pub use tail::TailFilter;
+pub use error::{AggregateError, Error, ItemError};
+pub use format::{Codec, Container, OutputFormat};
+pub use request::SynthesisRequest;
+pub use batch::synthesize_batch;
+pub use rate_limit::RateLimiter;
+pub use quota::{CharacterQuota, QuotaUsage};
+pub use subtitle::{cues_from_boundaries, parse_srt, to_lrc, to_srt, to_ttml, to_webvtt, Cue, SubtitleFormat, SubtitleTiming};
+pub use speech_marks::to_speech_marks;
+pub use karaoke::{karaoke_words, to_karaoke_json, KaraokeWord};
+pub use dub::{dub_subtitles, DubOptions};
+pub use limit::{count_words, truncate_words, WordLimit};
+pub use save::{synthesize_to_file, SaveOptions, SavedAudio};
+pub use wav::pcm_to_wav;
+pub use mp3::validate_mp3;
+pub use concat::{concat_audio, ConcatOptions};
+pub use markdown::{markdown_to_ssml, CodeBlocks, MarkdownOptions};
+pub use audiobook::{Audiobook, AudiobookManifest, ManifestChapter};
+pub use job::{BatchJob, JobItem, JobState, JobStatus};
+pub use pool::Pool;
+pub use session::Session;
+pub use turn_info::TurnInfo;
+pub use ssml::{validate_ssml, BreakStrength, DateFormat, SayAs, Ssml, TimeFormat};
+pub use lexicon::{Lexicon, Pronunciation};
+pub use normalize::{Emojis, TextNormalizer};
+pub use progress::Progress;
+pub use env::{ENV_CACHE_DIR, ENV_EDGE_VERSION, ENV_ENDPOINT, ENV_KEEP_ALIVE, ENV_OUTPUT_FORMAT, ENV_PITCH, ENV_PROXY, ENV_RATE, ENV_TOKEN, ENV_VOICE, ENV_VOLUME};
+pub use silence::{adjust_silence, SilenceOptions};
+pub use presets::{VoicePreset, VoicePresets};
+pub use reader::AudioReader;
+pub use writer::WrittenAudio;
+pub use sink::{AudioSink, FileSink};
+pub use metrics::{ErrorCategory, MetricsObserver, TurnMetrics};
+pub use observer::EventObserver;
+pub use dump::ProtocolDump;
+pub use backend::TtsBackend;
+pub use browser::{edge_version, set_edge_version, DEFAULT_EDGE_VERSION};
+#[cfg(feature = "edge_update")]
+pub use browser::update_edge_version;
+pub use stream::{Connector, Stream};
+pub use cache::{CacheStats, CachedClient, DiskCache, LruCache, SynthKey};
+pub use storage::{FsStorage, Storage, StorageEntry};
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteStorage;
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttClient, MqttOptions};
+#[cfg(feature = "bot")]
+pub use bot::{discord_send_voice_message, synthesize_voice_message, telegram_send_voice, VoiceMessage, VOICE_MESSAGE_FORMAT};
+#[cfg(feature = "captions")]
+pub use captions::{play_with_captions, CaptionFile, CaptionOptions, CaptionSink, ObsCaptionTarget, ObsCaptions};
+#[cfg(feature = "ffmpeg")]
+pub use video::{narrate_video, parse_narration_script, NarrationOptions, NarrationSegment};
+#[cfg(feature = "pptx")]
+pub use pptx::{embed_narration, narrate_pptx, read_speaker_notes, NarrationManifest, SlideNarration, SlideNotes};
+#[cfg(feature = "id3")]
+pub use id3::{write_id3, Chapter, Id3Tags};
+#[cfg(feature = "bundle")]
+pub use bundle::write_bundle;
+#[cfg(feature = "async")]
+pub use audio_stream::AudioStream;
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3Sink};
+#[cfg(feature = "remux")]
+pub use remux::webm_to_ogg_opus;
+#[cfg(feature = "dsp")]
+pub use resample::convert_pcm;
+#[cfg(feature = "dsp")]
+pub use stretch::stretch_pcm;
+#[cfg(feature = "dsp")]
+pub use mix::{mix_background, MixOptions};
+#[cfg(feature = "loudness")]
+pub use loudness::{integrated_loudness, normalize_loudness, EBU_R128_TARGET};
+#[cfg(feature = "azure")]
+pub use azure::AzureBackend;
+#[cfg(feature = "detect")]
+pub use detect::{default_voice, detect_language};
+#[cfg(feature = "detect")]
+pub use mixed::{build_mixed_ssml, language_runs, LanguageRun, VoiceMap};
+#[cfg(feature = "html")]
+pub use html::html_to_text;
+#[cfg(feature = "server")]
+pub use server::HttpServer;
+#[cfg(feature = "ffi")]
+pub use ffi::{edge_tts_free_buffer, edge_tts_free_string, edge_tts_last_error, edge_tts_list_voices, edge_tts_synthesize, EdgeTtsOptions};
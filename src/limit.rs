@@ -0,0 +1,130 @@
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::Error;
+
+type Reducer = dyn Fn(&str, usize) -> Result<String> + Send + Sync;
+
+/// Maximum number of words a [`crate::Client`] synthesizes in one request.
+///
+/// Longer text goes through the reducer, eg: to summarize it, and fails with [`Error::InputTooLong`] if there is
+/// none or its result is still too long.
+///
+/// ```
+/// use edge_tts::{truncate_words, Client, WordLimit};
+///
+/// let client = Client::new().with_word_limit(WordLimit::new(5000).with_reducer(|text, max| Ok(truncate_words(text, max))));
+/// ```
+#[derive(Clone)]
+pub struct WordLimit {
+    max_words: usize,
+    reducer: Option<Arc<Reducer>>,
+}
+
+impl WordLimit {
+    pub fn new(max_words: usize) -> Self {
+        Self { max_words, reducer: None }
+    }
+
+    /// Call `reducer(text, max_words)` on text over the limit and synthesize what it returns instead.
+    pub fn with_reducer(mut self, reducer: impl Fn(&str, usize) -> Result<String> + Send + Sync + 'static) -> Self {
+        self.reducer = Some(Arc::new(reducer));
+        self
+    }
+
+    pub fn max_words(&self) -> usize {
+        self.max_words
+    }
+
+    /// `text` itself if within the limit, else the reduced text.
+    pub(crate) fn apply(&self, text: &str) -> Result<Option<String>> {
+        if count_words(text) <= self.max_words {
+            return Ok(None);
+        }
+        let reduced = match &self.reducer {
+            Some(reducer) => reducer(text, self.max_words)?,
+            None => text.to_owned(),
+        };
+        self.check(&reduced)?;
+        Ok(Some(reduced))
+    }
+
+    pub(crate) fn check(&self, text: &str) -> Result<()> {
+        let words = count_words(text);
+        if words > self.max_words {
+            return Err(Error::InputTooLong { words, max_words: self.max_words }.into());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for WordLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WordLimit").field("max_words", &self.max_words).field("reducer", &self.reducer.is_some()).finish()
+    }
+}
+
+/// Whitespace separated words, with every CJK character counted as a word.
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().map(|word| word.chars().filter(|&c| is_cjk(c)).count().max(1)).sum()
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}')
+}
+
+/// The longest prefix of `text` within `max_words`, cut at the last paragraph break or else sentence end past its
+/// first third, else after the last word.
+pub fn truncate_words(text: &str, max_words: usize) -> String {
+    if count_words(text) <= max_words {
+        return text.to_owned();
+    }
+    let mut words = 0;
+    let mut end = 0;
+    'tokens: for word in text.split_whitespace() {
+        let start = word.as_ptr() as usize - text.as_ptr() as usize;
+        if !word.chars().any(is_cjk) {
+            if words == max_words {
+                break;
+            }
+            words += 1;
+            end = start + word.len();
+            continue;
+        }
+        for (at, c) in word.char_indices() {
+            if is_cjk(c) {
+                if words == max_words {
+                    break 'tokens;
+                }
+                words += 1;
+            }
+            end = start + at + c.len_utf8();
+        }
+    }
+    let prefix = &text[..end];
+    let sentence_end = prefix.rfind(['.', '!', '?', '。', '！', '？']).map(|at| at + prefix[at..].chars().next().map_or(1, char::len_utf8));
+    let cut = prefix.rfind("\n\n").into_iter().chain(sentence_end).find(|&at| at >= end / 3).unwrap_or(end);
+    text[..cut].trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_truncates() {
+        assert_eq!(count_words("Hello there, 你好世界 !"), 7);
+        assert_eq!(truncate_words("One two. Three four five", 4), "One two.");
+        assert_eq!(truncate_words("Intro one.\n\nTwo. Three four", 4), "Intro one.");
+        assert_eq!(truncate_words("Hi.\n\nOne two three four", 4), "Hi.\n\nOne two three");
+        assert_eq!(truncate_words("one two three", 2), "one two");
+        assert_eq!(truncate_words("你好。世界", 3), "你好。");
+        let limit = WordLimit::new(2);
+        assert!(limit.apply("one two").unwrap().is_none());
+        assert_eq!(limit.apply("one two three").unwrap_err().downcast_ref::<Error>(), Some(&Error::InputTooLong { words: 3, max_words: 2 }));
+        let limit = limit.with_reducer(|text, max| Ok(truncate_words(text, max)));
+        assert_eq!(limit.apply("one two three").unwrap().as_deref(), Some("one two"));
+    }
+}
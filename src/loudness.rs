@@ -0,0 +1,126 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use crate::pcm::{to_bytes, to_samples};
+use crate::silence::is_pcm16;
+use crate::wav::{wav_data, wav_header};
+use crate::{Container, OutputFormat, SynthesisOutput};
+
+/// Integrated loudness target of EBU R128 broadcast, in LUFS.
+pub const EBU_R128_TARGET: f64 = -23.0;
+
+/// Second-order IIR filter, direct form I.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The two K-weighting stages of ITU-R BS.1770 for `sample_rate`: a high shelf for the head, then a high pass.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// Integrated loudness in LUFS of interleaved `samples`, gated as EBU R128 specifies. `None` for audio shorter than
+/// one 400 ms block or quieter than the -70 LUFS gate throughout. Channels are weighted equally, as for L, R and C.
+pub fn integrated_loudness(samples: &[i16], sample_rate: u32, channels: u16) -> Option<f64> {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    // Squared K-weighted samples, summed over channels.
+    let mut power = vec![0.0; frames];
+    for channel in 0..channels {
+        let [mut shelf, mut high_pass] = k_weighting(sample_rate);
+        for (frame, power) in power.iter_mut().enumerate() {
+            let x = samples.get(frame * channels + channel).copied().unwrap_or(0) as f64 / 32768.0;
+            let y = high_pass.process(shelf.process(x));
+            *power += y * y;
+        }
+    }
+    // 400 ms blocks overlapping by 75%.
+    let block = (sample_rate as usize * 4 / 10).max(1);
+    let step = (block / 4).max(1);
+    let blocks: Vec<f64> = (0..)
+        .map(|i| i * step)
+        .take_while(|start| start + block <= frames)
+        .map(|start| power.get(start..start + block).unwrap_or_default().iter().sum::<f64>() / block as f64)
+        .collect();
+    let loudness = |mean_square: f64| -0.691 + 10.0 * mean_square.log10();
+    let gated_mean = |threshold: f64| {
+        let gated: Vec<f64> = blocks.iter().copied().filter(|&z| loudness(z) > threshold).collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+    let relative_gate = loudness(gated_mean(-70.0)?) - 10.0;
+    gated_mean(relative_gate).map(loudness)
+}
+
+/// Scale `output`, raw or RIFF 16-bit PCM in `format`, to `target_lufs` integrated loudness, eg:
+/// [`EBU_R128_TARGET`]. The gain is reduced as needed to keep the sample peak below full scale, so loud targets may
+/// be missed. Audio without measurable loudness is returned as is.
+pub fn normalize_loudness(output: SynthesisOutput, format: &OutputFormat, target_lufs: f64) -> Result<SynthesisOutput> {
+    let (true, Some(sample_rate)) = (is_pcm16(format), format.sample_rate()) else { bail!("{} is not 16-bit PCM", format) };
+    let riff = format.container() == Container::Riff;
+    let samples = to_samples(if riff { wav_data(&output.audio).unwrap_or_default() } else { &output.audio });
+    let Some(measured) = integrated_loudness(&samples, sample_rate, format.channels()) else { return Ok(output) };
+    let peak = samples.iter().map(|s| (*s as f64).abs()).fold(1.0, f64::max);
+    let gain = 10f64.powf((target_lufs - measured) / 20.0).min(i16::MAX as f64 / peak);
+    let scaled: Vec<i16> = samples.iter().map(|&s| (s as f64 * gain).round() as i16).collect();
+    let mut audio = to_bytes(&scaled);
+    if riff {
+        audio = [wav_header(sample_rate, 16, format.channels(), audio.len() as u32), audio].concat();
+    }
+    Ok(SynthesisOutput { audio, ..output }.with_duration(format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f64, seconds: usize) -> Vec<i16> {
+        (0..48000 * seconds).map(|i| ((2.0 * PI * 997.0 * i as f64 / 48000.0).sin() * amplitude * 32767.0) as i16).collect()
+    }
+
+    #[test]
+    fn measures_and_normalizes() {
+        // A 997 Hz sine peaking at -20 dBFS is -23 LUFS, after the +0.7 dB of K-weighting and the -0.691 offset.
+        let loudness = integrated_loudness(&sine(0.1, 3), 48000, 1).unwrap();
+        assert!((loudness - -23.0).abs() < 0.1, "{}", loudness);
+        assert_eq!(integrated_loudness(&vec![0; 48000], 48000, 1), None);
+        let format = OutputFormat::new("raw-48khz-16bit-mono-pcm");
+        let output = SynthesisOutput { audio: to_bytes(&sine(0.1, 3)), ..Default::default() };
+        let normalized = normalize_loudness(output, &format, -16.0).unwrap();
+        let loudness = integrated_loudness(&to_samples(&normalized.audio), 48000, 1).unwrap();
+        assert!((loudness - -16.0).abs() < 0.1, "{}", loudness);
+    }
+}
@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+/// What [`markdown_to_ssml`] does with code blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeBlocks {
+    Skip,
+    /// At this rate, eg: "-30%", between pauses.
+    ReadSlowly(String),
+}
+
+/// Options of [`markdown_to_ssml`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownOptions {
+    pub code_blocks: CodeBlocks,
+    /// Before and after headings, which are emphasized.
+    pub heading_pause: Duration,
+    /// After paragraphs, lists, code blocks and quotes.
+    pub paragraph_pause: Duration,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            code_blocks: CodeBlocks::Skip,
+            heading_pause: Duration::from_millis(700),
+            paragraph_pause: Duration::from_millis(400),
+        }
+    }
+}
+
+/// SSML narrating `markdown` with `voice`, eg: "en-US-AriaNeural", for [`crate::Client::synthesize`].
+///
+/// Headings are emphasized between pauses, links and images read as their text, list items as numbered sentences,
+/// and inline formatting and HTML tags are dropped. Fenced code blocks follow [`MarkdownOptions::code_blocks`].
+pub fn markdown_to_ssml(markdown: &str, voice: &str, options: &MarkdownOptions) -> String {
+    let mut ssml = format!("<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xmlns:mstts=\"https://www.w3.org/2001/mstts\" xml:lang=\"en-US\"><voice name=\"{}\">", escape_str_attribute(voice));
+    let block_break = format!("<break time=\"{}ms\"/>", options.paragraph_pause.as_millis());
+    let heading_break = format!("<break time=\"{}ms\"/>", options.heading_pause.as_millis());
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list_items = 0;
+    let mut lines = markdown.lines();
+    let flush = |ssml: &mut String, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            *ssml += &sentence(&inline(&paragraph.join(" ")));
+            *ssml += &block_break;
+            paragraph.clear();
+        }
+    };
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let item = list_item(trimmed);
+        if item.is_none() && list_items > 0 && !trimmed.is_empty() && !line.starts_with([' ', '\t']) {
+            ssml += &block_break;
+            list_items = 0;
+        }
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|fence| trimmed.starts_with(fence)) {
+            flush(&mut ssml, &mut paragraph);
+            let code: Vec<&str> = lines.by_ref().take_while(|line| !line.trim().starts_with(fence)).collect();
+            if let CodeBlocks::ReadSlowly(rate) = &options.code_blocks {
+                ssml += &format!("{}<prosody rate=\"{}\">{}</prosody>{}", block_break, escape_str_attribute(rate), escape_str_pcdata(&code.join("\n")), block_break);
+            }
+        } else if let Some(heading) = heading(trimmed) {
+            flush(&mut ssml, &mut paragraph);
+            ssml += &format!("{}<emphasis level=\"strong\">{}</emphasis>{}", heading_break, sentence(&inline(heading)), heading_break);
+        } else if let Some((number, text)) = item {
+            flush(&mut ssml, &mut paragraph);
+            list_items = number.unwrap_or(list_items + 1);
+            ssml += &format!("{}{}. {}", if ssml.ends_with('>') { "" } else { " " }, list_items, sentence(&inline(text)));
+        } else if trimmed.is_empty() || is_rule(trimmed) {
+            flush(&mut ssml, &mut paragraph);
+        } else if list_items > 0 {
+            // A continuation of the last item.
+            ssml += &format!(" {}", sentence(&inline(trimmed)));
+        } else {
+            let quote = trimmed.trim_start_matches(['>', ' ']);
+            match quote.strip_prefix('|') {
+                // A table row, read as its cells. The row under the header only has dashes.
+                Some(row) => {
+                    flush(&mut ssml, &mut paragraph);
+                    if !row.chars().all(|c| matches!(c, '-' | ':' | '|' | ' ')) {
+                        let cells: Vec<&str> = row.trim_end_matches('|').split('|').map(str::trim).collect();
+                        ssml += &sentence(&inline(&cells.join(", ")));
+                        ssml += &block_break;
+                    }
+                }
+                None => paragraph.push(quote),
+            }
+        }
+    }
+    flush(&mut ssml, &mut paragraph);
+    ssml + "</voice></speak>"
+}
+
+/// eg: "Title" of "## Title ##"
+fn heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    let text = text.strip_prefix(' ').filter(|_| (1..=6).contains(&level))?.trim().trim_end_matches('#').trim_end();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Number, if ordered, and text of a list item, eg: "- item", "* item", "1. item", "2) item".
+fn list_item(line: &str) -> Option<(Option<usize>, &str)> {
+    if let Some(text) = line.strip_prefix(['-', '*', '+']).filter(|text| text.starts_with(' ')) {
+        return (!is_rule(line)).then(|| (None, text.trim()));
+    }
+    let text = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let number = line.get(..line.len() - text.len())?.parse().ok()?;
+    let text = text.strip_prefix(['.', ')']).filter(|text| text.starts_with(' '))?;
+    Some((Some(number), text.trim()))
+}
+
+/// eg: "---", "* * *"
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ["-", "*", "_"].into_iter().any(|mark| marks.chars().all(|c| c.to_string() == mark))
+}
+
+/// `ssml` ending like a sentence, so that it's read with a falling tone.
+fn sentence(ssml: &str) -> String {
+    let ssml = ssml.trim();
+    let text_end = ssml.trim_end_matches("</emphasis>").chars().last();
+    match text_end {
+        Some(c) if c.is_alphanumeric() || c == ')' || c == '"' || c == '\'' => format!("{}.", ssml),
+        _ => ssml.to_owned(),
+    }
+}
+
+/// SSML of inline Markdown: emphasis for strong text, text of links, images and code spans, without HTML tags.
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut ssml = String::new();
+    let mut plain = String::new();
+    let mut i = 0;
+    let find = |from: usize, pattern: &[char]| (from..chars.len()).find(|&j| chars.get(j..j + pattern.len()) == Some(pattern));
+    let text_of = |from: usize, to: usize| chars.get(from..to).unwrap_or_default().iter().collect::<String>();
+    let markup = |ssml: &mut String, plain: &mut String, inner: String| {
+        *ssml += &escape_str_pcdata(plain);
+        plain.clear();
+        *ssml += &inner;
+    };
+    while let Some(&c) = chars.get(i) {
+        let next = chars.get(i + 1).copied();
+        let word_start = i == 0 || chars.get(i - 1).is_some_and(|c| !c.is_alphanumeric());
+        match c {
+            '\\' if next.is_some_and(|c| c.is_ascii_punctuation()) => {
+                plain.extend(next);
+                i += 2;
+            }
+            '`' => {
+                let ticks = chars.get(i..).unwrap_or_default().iter().take_while(|&&c| c == '`').count();
+                let fence = vec!['`'; ticks];
+                match find(i + ticks, &fence) {
+                    Some(end) => {
+                        plain += text_of(i + ticks, end).trim();
+                        i = end + ticks;
+                    }
+                    None => {
+                        plain += &text_of(i, i + ticks);
+                        i += ticks;
+                    }
+                }
+            }
+            '!' | '[' if c == '[' || next == Some('[') => {
+                let open = if c == '!' { i + 1 } else { i };
+                match link(&chars, open) {
+                    Some((label, end)) => {
+                        markup(&mut ssml, &mut plain, inline(&text_of(open + 1, label)));
+                        i = end;
+                    }
+                    None => {
+                        plain.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' if next == Some(c) && (c == '*' || word_start) => match find(i + 2, &[c, c]).filter(|&end| end > i + 2) {
+                Some(end) => {
+                    markup(&mut ssml, &mut plain, format!("<emphasis level=\"moderate\">{}</emphasis>", inline(&text_of(i + 2, end))));
+                    i = end + 2;
+                }
+                None => {
+                    plain += &text_of(i, i + 2);
+                    i += 2;
+                }
+            },
+            '*' | '_' if (c == '*' || word_start) && next.is_some_and(|c| !c.is_whitespace()) => match find(i + 1, &[c]) {
+                Some(end) => {
+                    markup(&mut ssml, &mut plain, inline(&text_of(i + 1, end)));
+                    i = end + 1;
+                }
+                None => {
+                    plain.push(c);
+                    i += 1;
+                }
+            },
+            // HTML tags and autolinks.
+            '<' if next.is_some_and(|c| c.is_ascii_alphabetic() || c == '/') => match find(i, &['>']).filter(|&end| !text_of(i + 1, end).contains('<')) {
+                Some(end) => i = end + 1,
+                None => {
+                    plain.push(c);
+                    i += 1;
+                }
+            },
+            _ => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+    ssml + &escape_str_pcdata(&plain)
+}
+
+/// For "[label](url)" or "[label][ref]" at `open`: the index of "]" and of the end.
+fn link(chars: &[char], open: usize) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let label = (open..chars.len()).find(|&j| {
+        match chars.get(j) {
+            Some('[') => depth += 1,
+            Some(']') => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    let close = match chars.get(label + 1) {
+        Some('(') => ')',
+        Some('[') => ']',
+        _ => return None,
+    };
+    let end = (label + 2..chars.len()).find(|&j| chars.get(j) == Some(&close))?;
+    Some((label, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn narrates_markdown() {
+        let markdown = "# Getting *started*\n\nInstall the [CLI](https://example.com) with **cargo**:\n\n```sh\ncargo install edge-tts\n```\n\n- Fast\n- Small & `simple`\n\n3. snake_case names\n\n> Quoted <b>text</b>\n\n---\n| a | b |\n|---|---|\n| 1 | 2 |";
+        let ssml = markdown_to_ssml(markdown, "en-US-AriaNeural", &MarkdownOptions::default());
+        let body = ssml.split("<voice name=\"en-US-AriaNeural\">").nth(1).unwrap().trim_end_matches("</voice></speak>");
+        assert_eq!(
+            body,
+            "<break time=\"700ms\"/><emphasis level=\"strong\">Getting started.</emphasis><break time=\"700ms\"/>\
+             Install the CLI with <emphasis level=\"moderate\">cargo</emphasis>:<break time=\"400ms\"/>\
+             1. Fast. 2. Small &amp; simple. 3. snake_case names.<break time=\"400ms\"/>\
+             Quoted text.<break time=\"400ms\"/>a, b.<break time=\"400ms\"/>1, 2.<break time=\"400ms\"/>"
+        );
+        let options = MarkdownOptions { code_blocks: CodeBlocks::ReadSlowly("-30%".to_owned()), ..Default::default() };
+        assert!(markdown_to_ssml(markdown, "en-US-AriaNeural", &options).contains("<prosody rate=\"-30%\">cargo install edge-tts</prosody>"));
+    }
+}
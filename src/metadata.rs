@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// `metadataoptions` of the speech.config message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetadataOptions {
+    pub sentence_boundary_enabled: bool,
+    pub word_boundary_enabled: bool,
+    /// Viseme events, eg: to animate an avatar's mouth, as [`BoundaryKind::Viseme`].
+    pub viseme_enabled: bool,
+    /// Events of the `<bookmark>` elements, see [`crate::Ssml::bookmark`], as [`BoundaryKind::Bookmark`].
+    pub bookmark_enabled: bool,
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            sentence_boundary_enabled: false,
+            word_boundary_enabled: true,
+            viseme_enabled: false,
+            bookmark_enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundaryKind {
+    Word,
+    Sentence,
+    /// A mouth position of the audio from its offset, eg: 0 for silence, 21 for "p", "b" and "m"; see
+    /// https://learn.microsoft.com/azure/ai-services/speech-service/how-to-speech-synthesis-viseme#map-phonemes-to-visemes
+    /// Such boundaries have no duration nor text.
+    Viseme(u32),
+    /// The audio offset of a `<bookmark>`, its mark as text. No duration.
+    Bookmark,
+}
+
+/// A boundary, viseme or bookmark event from a `Path:audio.metadata` message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Boundary {
+    pub kind: BoundaryKind,
+    /// Audio offset of the start of the word/sentence.
+    pub offset: Duration,
+    pub duration: Duration,
+    /// eg: "Hello"
+    pub text: String,
+}
+
+/// The service reports times in ticks of 100ns.
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_nanos(ticks.saturating_mul(100))
+}
+
+/// Parse the JSON body of a `Path:audio.metadata` message. Unknown metadata types are skipped.
+pub(crate) fn parse_metadata(body: &str) -> Result<Vec<Boundary>> {
+    let value: Value = serde_json::from_str(body)?;
+    let items = value.get("Metadata").and_then(Value::as_array).ok_or_else(|| anyhow!("audio.metadata without Metadata array"))?;
+    let mut boundaries = Vec::new();
+    for item in items {
+        let kind = match item.get("Type").and_then(Value::as_str) {
+            Some(kind @ ("WordBoundary" | "SentenceBoundary" | "Viseme" | "Bookmark")) => kind,
+            _ => continue,
+        };
+        let data = item.get("Data").ok_or_else(|| anyhow!("audio.metadata item without Data"))?;
+        let kind = match kind {
+            "WordBoundary" => BoundaryKind::Word,
+            "SentenceBoundary" => BoundaryKind::Sentence,
+            "Bookmark" => BoundaryKind::Bookmark,
+            _ => {
+                let id = data.get("VisemeId").and_then(Value::as_u64).and_then(|id| u32::try_from(id).ok());
+                BoundaryKind::Viseme(id.ok_or_else(|| anyhow!("viseme without VisemeId"))?)
+            }
+        };
+        let offset = data.get("Offset").and_then(Value::as_u64).ok_or_else(|| anyhow!("audio.metadata item without Offset"))?;
+        let duration = data.get("Duration").and_then(Value::as_u64).unwrap_or(0);
+        let text = data.pointer("/text/Text").or_else(|| data.get("Bookmark")).and_then(Value::as_str).unwrap_or("").to_owned();
+        boundaries.push(Boundary {
+            kind,
+            offset: ticks_to_duration(offset),
+            duration: ticks_to_duration(duration),
+            text,
+        });
+    }
+    Ok(boundaries)
+}
+
+impl Boundary {
+    /// Viseme id of a [`BoundaryKind::Viseme`].
+    pub fn viseme_id(&self) -> Option<u32> {
+        match self.kind {
+            BoundaryKind::Viseme(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut value = serde_json::json!({
+            "kind": match self.kind {
+                BoundaryKind::Word => "WordBoundary",
+                BoundaryKind::Sentence => "SentenceBoundary",
+                BoundaryKind::Viseme(_) => "Viseme",
+                BoundaryKind::Bookmark => "Bookmark",
+            },
+            "offset": self.offset.as_nanos() as u64 / 100,
+            "duration": self.duration.as_nanos() as u64 / 100,
+            "text": self.text,
+        });
+        if let (BoundaryKind::Viseme(id), Some(object)) = (self.kind, value.as_object_mut()) {
+            object.insert("viseme_id".to_owned(), id.into());
+        }
+        value
+    }
+
+    pub(crate) fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            kind: match value.get("kind")?.as_str()? {
+                "WordBoundary" => BoundaryKind::Word,
+                "SentenceBoundary" => BoundaryKind::Sentence,
+                "Bookmark" => BoundaryKind::Bookmark,
+                "Viseme" => BoundaryKind::Viseme(u32::try_from(value.get("viseme_id")?.as_u64()?).ok()?),
+                _ => return None,
+            },
+            offset: ticks_to_duration(value.get("offset")?.as_u64()?),
+            duration: ticks_to_duration(value.get("duration")?.as_u64()?),
+            text: value.get("text")?.as_str()?.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::{build_ssml, Client};
+
+    #[test]
+    fn parses_sentences() {
+        let body = r#"{"Metadata": [
+            {"Type": "SentenceBoundary", "Data": {"Offset": 1000000, "Duration": 20000000, "text": {"Text": "Hello there.", "Length": 12, "BoundaryType": "SentenceBoundary"}}},
+            {"Type": "SessionEnd", "Data": {"Offset": 21000000}}
+        ]}"#;
+        let sentence = Boundary { kind: BoundaryKind::Sentence, offset: Duration::from_millis(100), duration: Duration::from_secs(2), text: "Hello there.".to_owned() };
+        assert_eq!(parse_metadata(body).unwrap(), vec![sentence.clone()]);
+        assert_eq!(Boundary::from_json(&sentence.to_json()), Some(sentence));
+        assert!(parse_metadata(r#"{"Metadata": [{"Type": "SentenceBoundary", "Data": {"Duration": 1}}]}"#).is_err());
+        assert!(parse_metadata(r#"{"Metadata": [{"Type": "SentenceBoundary"}]}"#).is_err());
+        assert!(parse_metadata(r#"{"Type": "SentenceBoundary"}"#).is_err());
+        assert!(parse_metadata("{").is_err());
+
+        let turn = vec![MockReply::Text { path: "audio.metadata".to_owned(), body: body.to_owned() }, MockReply::Audio(b"audio".to_vec()), MockReply::TurnEnd];
+        let server = MockServer::start(vec![turn]).unwrap();
+        let options = MetadataOptions { sentence_boundary_enabled: true, word_boundary_enabled: false, ..Default::default() };
+        let output = server.client().with_metadata_options(options).synthesize(&build_ssml("Hello there.", "en-US-AriaNeural", "default", "default", "default"), "audio-24khz-48kbitrate-mono-mp3").unwrap();
+        assert_eq!(output.boundaries.iter().map(|b| (b.kind, b.text.as_str())).collect::<Vec<_>>(), [(BoundaryKind::Sentence, "Hello there.")]);
+        let config: Value = serde_json::from_str(&server.requests()[0].speech_config).unwrap();
+        let metadata = &config["context"]["synthesis"]["audio"]["metadataoptions"];
+        assert_eq!((&metadata["sentenceBoundaryEnabled"], &metadata["wordBoundaryEnabled"]), (&Value::Bool(true), &Value::Bool(false)));
+    }
+
+    #[test]
+    fn parses_visemes() {
+        let body = r#"{"Metadata": [{"Type": "Viseme", "Data": {"Offset": 500000, "VisemeId": 21, "IsLastAnimation": false}}]}"#;
+        let viseme = Boundary { kind: BoundaryKind::Viseme(21), offset: Duration::from_millis(50), duration: Duration::ZERO, text: String::new() };
+        assert_eq!(parse_metadata(body).unwrap(), vec![viseme.clone()]);
+        assert_eq!(Boundary::from_json(&viseme.to_json()), Some(viseme));
+        assert!(parse_metadata(r#"{"Metadata": [{"Type": "Viseme", "Data": {"Offset": 0}}]}"#).is_err());
+
+        let turn = vec![MockReply::Text { path: "audio.metadata".to_owned(), body: body.to_owned() }, MockReply::Audio(b"audio".to_vec()), MockReply::TurnEnd];
+        let server = MockServer::start(vec![turn]).unwrap();
+        let client: Client = server.client().with_metadata_options(MetadataOptions { viseme_enabled: true, ..Default::default() });
+        let output = client.synthesize(&build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default"), "audio-24khz-48kbitrate-mono-mp3").unwrap();
+        assert_eq!(output.boundaries.iter().filter_map(Boundary::viseme_id).collect::<Vec<_>>(), [21]);
+        assert!(server.requests()[0].speech_config.contains(r#""visemeEnabled":true"#));
+    }
+
+    #[test]
+    fn parses_bookmarks() {
+        let body = r#"{"Metadata": [{"Type": "Bookmark", "Data": {"Offset": 12000000, "Bookmark": "slide-2"}}]}"#;
+        let bookmark = Boundary { kind: BoundaryKind::Bookmark, offset: Duration::from_millis(1200), duration: Duration::ZERO, text: "slide-2".to_owned() };
+        assert_eq!(parse_metadata(body).unwrap(), vec![bookmark.clone()]);
+        assert_eq!(Boundary::from_json(&bookmark.to_json()), Some(bookmark));
+        let config = Client::new().with_metadata_options(MetadataOptions { bookmark_enabled: true, ..Default::default() }).speech_config("audio-24khz-48kbitrate-mono-mp3");
+        assert!(config.contains(r#""bookmarkEnabled":true"#), "{}", config);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{Error, FrameError};
+
+/// Receives measurements of every turn a [`crate::Client`] runs, eg: to export them to prometheus, labelled by
+/// [`TurnMetrics::voice`]. See [`crate::Client::with_metrics`].
+///
+/// Cache hits run no turn and aren't reported. With [`crate::Client::with_resume`] each attempt is its own turn.
+/// Callbacks run on the synthesizing thread, so they should be quick.
+pub trait MetricsObserver: Debug + Send + Sync {
+    /// The WebSocket connection is open, `elapsed` after the turn began, including the rate limiter wait.
+    fn connected(&self, voice: &str, elapsed: Duration) {
+        let _ = (voice, elapsed);
+    }
+
+    /// The first audio arrived, `elapsed` after the turn began.
+    fn first_audio(&self, voice: &str, elapsed: Duration) {
+        let _ = (voice, elapsed);
+    }
+
+    /// The turn reached `turn.end`, or was stopped early by its consumer.
+    fn completed(&self, metrics: &TurnMetrics) {
+        let _ = metrics;
+    }
+
+    /// The turn failed; `metrics` has what was received until then.
+    fn failed(&self, metrics: &TurnMetrics, category: ErrorCategory) {
+        let _ = (metrics, category);
+    }
+}
+
+/// Measurements of one turn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TurnMetrics {
+    /// First voice of the SSML, eg: "en-US-AriaNeural".
+    pub voice: String,
+    pub output_format: String,
+    /// Characters of the SSML text content.
+    pub characters: usize,
+    pub connect_time: Option<Duration>,
+    pub first_audio: Option<Duration>,
+    pub audio_bytes: u64,
+    pub elapsed: Duration,
+    /// The consumer stopped the turn before `turn.end`.
+    pub stopped: bool,
+}
+
+/// What kind of failure ended a turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Connecting or sending the request failed, eg: DNS, TLS, a rejected handshake.
+    Connect,
+    /// The service closed the connection before `turn.end`.
+    ConnectionClosed,
+    /// A message couldn't be parsed or belonged to another request.
+    MalformedResponse,
+    Io,
+    Other,
+}
+
+impl ErrorCategory {
+    /// Failure after the connection was open.
+    pub fn of(error: &anyhow::Error) -> Self {
+        if matches!(error.downcast_ref::<Error>(), Some(Error::ConnectionClosedByServer { .. })) {
+            return ErrorCategory::ConnectionClosed;
+        }
+        if error.downcast_ref::<FrameError>().is_some() {
+            return ErrorCategory::MalformedResponse;
+        }
+        let io = error.chain().any(|e| {
+            e.downcast_ref::<std::io::Error>().is_some() || matches!(e.downcast_ref::<tungstenite::Error>(), Some(tungstenite::Error::Io(_)))
+        });
+        if io {
+            return ErrorCategory::Io;
+        }
+        ErrorCategory::Other
+    }
+
+    /// eg: "connection_closed", for metric labels.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Connect => "connect",
+            ErrorCategory::ConnectionClosed => "connection_closed",
+            ErrorCategory::MalformedResponse => "malformed_response",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+/// Measures one turn and reports it to the observer, once.
+#[derive(Debug)]
+pub(crate) struct TurnRecorder {
+    observer: Arc<dyn MetricsObserver>,
+    metrics: TurnMetrics,
+    started: Instant,
+    reported: bool,
+}
+
+impl TurnRecorder {
+    pub(crate) fn start(observer: Arc<dyn MetricsObserver>, ssml: &str, output_format: &str) -> Self {
+        let metrics = TurnMetrics {
+            voice: ssml_voice(ssml).unwrap_or_default(),
+            output_format: output_format.to_owned(),
+            characters: ssml_characters(ssml),
+            ..TurnMetrics::default()
+        };
+        Self { observer, metrics, started: Instant::now(), reported: false }
+    }
+
+    pub(crate) fn connected(&mut self) {
+        let elapsed = self.started.elapsed();
+        self.metrics.connect_time = Some(elapsed);
+        self.observer.connected(&self.metrics.voice, elapsed);
+    }
+
+    pub(crate) fn audio(&mut self, len: usize) {
+        if self.metrics.first_audio.is_none() {
+            let elapsed = self.started.elapsed();
+            self.metrics.first_audio = Some(elapsed);
+            self.observer.first_audio(&self.metrics.voice, elapsed);
+        }
+        self.metrics.audio_bytes += len as u64;
+    }
+
+    pub(crate) fn completed(&mut self, stopped: bool) {
+        if !std::mem::replace(&mut self.reported, true) {
+            self.metrics.elapsed = self.started.elapsed();
+            self.metrics.stopped = stopped;
+            self.observer.completed(&self.metrics);
+        }
+    }
+
+    pub(crate) fn failed(&mut self, category: ErrorCategory) {
+        if !std::mem::replace(&mut self.reported, true) {
+            self.metrics.elapsed = self.started.elapsed();
+            self.observer.failed(&self.metrics, category);
+        }
+    }
+}
+
+/// Value of the `name` attribute of the first `<voice>` element.
+fn ssml_voice(ssml: &str) -> Option<String> {
+    let tag = ssml.get(ssml.find("<voice")?..)?;
+    let tag = tag.get(..tag.find('>')?)?;
+    let value = tag.get(tag.find("name=")? + 5..)?;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = value.get(1..)?;
+    Some(value.get(..value.find(quote)?)?.to_owned())
+}
+
+/// Characters outside of tags, an entity counting as one.
+fn ssml_characters(ssml: &str) -> usize {
+    let mut count = 0;
+    let mut in_tag = false;
+    let mut in_entity = false;
+    for c in ssml.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if in_tag => {}
+            '&' => {
+                in_entity = true;
+                count += 1;
+            }
+            ';' if in_entity => in_entity = false,
+            _ if in_entity => {}
+            _ => count += 1,
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::build_ssml;
+
+    #[derive(Debug, Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl MetricsObserver for Collect {
+        fn first_audio(&self, voice: &str, _: Duration) {
+            self.0.lock().unwrap().push(format!("first audio {}", voice));
+        }
+
+        fn completed(&self, metrics: &TurnMetrics) {
+            self.0.lock().unwrap().push(format!("completed {} {}", metrics.characters, metrics.audio_bytes));
+        }
+
+        fn failed(&self, metrics: &TurnMetrics, category: ErrorCategory) {
+            self.0.lock().unwrap().push(format!("failed {} {}", metrics.voice, category.as_str()));
+        }
+    }
+
+    #[test]
+    fn reports_each_turn_once() {
+        let observer = Arc::new(Collect::default());
+        let ssml = build_ssml("Fish & chips", "en-GB-SoniaNeural", "default", "default", "default");
+        let mut turn = TurnRecorder::start(observer.clone(), &ssml, "audio-24khz-48kbitrate-mono-mp3");
+        turn.connected();
+        turn.audio(100);
+        turn.audio(50);
+        turn.completed(false);
+        turn.failed(ErrorCategory::Io);
+        let closed = anyhow::Error::from(Error::ConnectionClosedByServer { code: None, reason: String::new() });
+        TurnRecorder::start(observer.clone(), &ssml, "").failed(ErrorCategory::of(&closed));
+        assert_eq!(*observer.0.lock().unwrap(), ["first audio en-GB-SoniaNeural", "completed 12 150", "failed en-GB-SoniaNeural connection_closed"]);
+        assert_eq!(ErrorCategory::of(&anyhow::anyhow!("bad voice")), ErrorCategory::Other);
+    }
+}
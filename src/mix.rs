@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::metadata::Boundary;
+use crate::pcm::{to_bytes, to_samples};
+use crate::silence::is_pcm16;
+use crate::wav::{wav_data, wav_header};
+use crate::{Container, OutputFormat, SynthesisOutput};
+
+/// Blocks over which speech is detected and the background gain is moved.
+const BLOCK: Duration = Duration::from_millis(10);
+
+/// Options of [`mix_background`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixOptions {
+    /// Gain of the background, in dB, eg: -6.
+    pub background_db: f32,
+    /// Further gain of the background while speech plays, in dB, eg: -12. 0 doesn't duck.
+    pub duck_db: f32,
+    /// How long the background takes to duck before speech and to come back up after it.
+    pub fade: Duration,
+    /// Background alone before the speech, eg: for the intro of a podcast.
+    pub lead_in: Duration,
+    /// Background alone after the speech.
+    pub tail: Duration,
+    /// Repeat a background shorter than the mix, else silence follows it.
+    pub repeat: bool,
+    /// Speech quieter than this many dBFS, over 10 ms, doesn't duck the background.
+    pub speech_threshold_db: f32,
+}
+
+impl Default for MixOptions {
+    fn default() -> Self {
+        Self {
+            background_db: -6.0,
+            duck_db: -12.0,
+            fade: Duration::from_millis(300),
+            lead_in: Duration::ZERO,
+            tail: Duration::ZERO,
+            repeat: true,
+            speech_threshold_db: -45.0,
+        }
+    }
+}
+
+/// Overlay `speech`, raw or RIFF 16-bit PCM in `format`, onto `background`, eg: music for a podcast intro or a
+/// guided meditation, ducked under the speech. `background` is 16-bit PCM with the sample rate and channels of
+/// `format`, with or without a RIFF header; see [`crate::convert_pcm`] to convert it. The boundaries move by the
+/// lead-in.
+pub fn mix_background(speech: SynthesisOutput, format: &OutputFormat, background: &[u8], options: &MixOptions) -> Result<SynthesisOutput> {
+    let (true, Some(sample_rate)) = (is_pcm16(format), format.sample_rate()) else { bail!("{} is not 16-bit PCM", format) };
+    let riff = format.container() == Container::Riff;
+    let channels = format.channels().max(1) as usize;
+    let voice = to_samples(if riff { wav_data(&speech.audio).unwrap_or_default() } else { &speech.audio });
+    let background = to_samples(wav_data(background).unwrap_or(background));
+    let frames = |duration: Duration| (duration.as_secs_f64() * sample_rate as f64).round() as usize;
+    let (lead_in, block) = (frames(options.lead_in) * channels, frames(BLOCK).max(1) * channels);
+    let len = lead_in + voice.len() + frames(options.tail) * channels;
+
+    // Whether speech plays in each block, or will within the fade, so that ducking is done when it starts.
+    let threshold = 10f64.powf(options.speech_threshold_db as f64 / 20.0) * i16::MAX as f64;
+    let loud: Vec<bool> = (0..len.div_ceil(block))
+        .map(|i| {
+            let samples = voice.get((i * block).saturating_sub(lead_in)..((i + 1) * block).saturating_sub(lead_in).min(voice.len())).unwrap_or_default();
+            let power = samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len().max(1) as f64;
+            power.sqrt() > threshold
+        })
+        .collect();
+    let fade_blocks = (options.fade.as_secs_f64() / BLOCK.as_secs_f64()).round().max(1.0) as usize;
+    let step = 1.0 / fade_blocks as f64;
+    let mut ducking = 0.0f64;
+    let gains: Vec<f64> = (0..loud.len())
+        .map(|i| {
+            let ducks = loud.get(i..(i + fade_blocks).min(loud.len())).is_some_and(|ahead| ahead.contains(&true));
+            ducking = if ducks { (ducking + step).min(1.0) } else { (ducking - step).max(0.0) };
+            10f64.powf((options.background_db as f64 + options.duck_db as f64 * ducking) / 20.0)
+        })
+        .collect();
+
+    let mixed: Vec<i16> = (0..len)
+        .map(|i| {
+            let voice = i.checked_sub(lead_in).and_then(|i| voice.get(i)).copied().unwrap_or(0) as f64;
+            let background = match (background.len(), options.repeat) {
+                (0, _) => 0,
+                (n, true) => background[i % n],
+                (_, false) => background.get(i).copied().unwrap_or(0),
+            } as f64;
+            (voice + background * gains[i / block]).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        })
+        .collect();
+    let mut audio = to_bytes(&mixed);
+    if riff {
+        audio = [wav_header(sample_rate, 16, channels as u16, audio.len() as u32), audio].concat();
+    }
+    let boundaries = speech.boundaries.into_iter().map(|b| Boundary { offset: b.offset + options.lead_in, ..b }).collect();
+    Ok(SynthesisOutput { audio, boundaries, ..speech }.with_duration(format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundaryKind;
+
+    #[test]
+    fn ducks_background_under_speech() {
+        let format = OutputFormat::new("raw-8khz-16bit-mono-pcm");
+        // 1 s of silence, 1 s of speech, 1 s of silence.
+        let voice: Vec<i16> = (0..24000).map(|i| if (8000..16000).contains(&i) { if i % 2 == 0 { 8000 } else { -8000 } } else { 0 }).collect();
+        let word = Boundary { kind: BoundaryKind::Word, offset: Duration::from_secs(1), duration: Duration::from_millis(500), text: "Hi".to_owned() };
+        let speech = SynthesisOutput { audio: to_bytes(&voice), boundaries: vec![word], ..Default::default() };
+        let background = to_bytes(&[1000; 4000]);
+        let options = MixOptions { background_db: 0.0, duck_db: -20.0, lead_in: Duration::from_millis(500), ..Default::default() };
+
+        let mixed = mix_background(speech, &format, &background, &options).unwrap();
+        assert_eq!(mixed.duration, Some(Duration::from_millis(3500)));
+        assert_eq!(mixed.boundaries[0].offset, Duration::from_millis(1500));
+        let samples = to_samples(&mixed.audio);
+        // Full background, repeated, before and after the speech, a tenth of it under the speech.
+        assert_eq!((samples[0], samples[8000], samples[27000]), (1000, 1000, 1000));
+        assert_eq!((samples[16000] - 8000, samples[16001] + 8000), (100, 100));
+        // Ducked by the time speech starts, after ramping down.
+        assert!((100..1000).contains(&samples[11000]), "{}", samples[11000]);
+
+        let once = MixOptions { repeat: false, ..options };
+        let samples = to_samples(&mix_background(SynthesisOutput::default(), &format, &background, &once).unwrap().audio);
+        assert_eq!(samples.len(), 4000);
+        assert!(mix_background(SynthesisOutput::default(), &OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3, &background, &once).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+
+use crate::concat::{concat_audio, ConcatOptions};
+use crate::detect::{default_voice, latin_language, script_of, Script};
+use crate::{Client, OutputFormat, SynthesisOutput, SynthesisRequest};
+
+/// Text in one language, see [`language_runs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageRun {
+    /// eg: "zh", "en"
+    pub language: &'static str,
+    pub text: String,
+}
+
+/// Split `text` where its script changes, eg: "你好。Hello!" into "你好。" in "zh" and "Hello!" in "en". Spaces,
+/// digits and punctuation stay with the run before them, so the runs join back into `text`.
+pub fn language_runs(text: &str) -> Vec<LanguageRun> {
+    // Japanese mixes kanji and kana: one run, told apart from Chinese once complete.
+    let group = |script| match script {
+        Script::Kana => Script::Han,
+        script => script,
+    };
+    let mut runs: Vec<(Option<Script>, String)> = Vec::new();
+    for c in text.chars() {
+        let script = script_of(c).map(group);
+        match runs.last_mut() {
+            Some((last, run)) if script.is_none() || *last == script || last.is_none() => {
+                if last.is_none() {
+                    *last = script;
+                }
+                run.push(c);
+            }
+            _ => runs.push((script, c.to_string())),
+        }
+    }
+    let mut languages: Vec<LanguageRun> = Vec::new();
+    for (script, text) in runs {
+        let language = match script {
+            Some(Script::Latin) => latin_language(&text),
+            _ => crate::detect::detect_language(&text).unwrap_or("en"),
+        };
+        match languages.last_mut() {
+            Some(last) if last.language == language => last.text.push_str(&text),
+            _ => languages.push(LanguageRun { language, text }),
+        }
+    }
+    languages
+}
+
+/// Voice by language, for [`Client::synthesize_mixed`]. Languages without a voice get their
+/// [`default_voice`](crate::default_voice).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoiceMap {
+    /// By lowercase language.
+    voices: HashMap<String, String>,
+}
+
+impl VoiceMap {
+    /// eg: `language`: "en", `voice`: "en-GB-RyanNeural"
+    pub fn with_voice(mut self, language: &str, voice: impl Into<String>) -> Self {
+        self.voices.insert(language.to_lowercase(), voice.into());
+        self
+    }
+
+    pub fn voice(&self, language: &str) -> Option<&str> {
+        self.voices.get(&language.to_lowercase()).map(String::as_str).or_else(|| default_voice(language))
+    }
+
+    fn voice_of(&self, run: &LanguageRun) -> Result<&str> {
+        self.voice(run.language).ok_or_else(|| anyhow!("no voice for language {}", run.language))
+    }
+}
+
+/// One SSML document with a `<voice>` element per run of `text`. The Edge endpoint only reads single voice SSML,
+/// this is for other [`TtsBackend`](crate::TtsBackend)s, eg: `AzureBackend`.
+pub fn build_mixed_ssml(text: &str, voices: &VoiceMap) -> Result<String> {
+    let mut ssml = String::from("<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xmlns:mstts=\"https://www.w3.org/2001/mstts\" xml:lang=\"en-US\">");
+    for run in language_runs(text) {
+        ssml += &format!("<voice name=\"{}\">{}</voice>", escape_str_attribute(voices.voice_of(&run)?), escape_str_pcdata(&run.text));
+    }
+    Ok(ssml + "</speak>")
+}
+
+impl Client {
+    /// Synthesize each of the [`language_runs`] of `text` with its voice of `voices`, and join the parts in order,
+    /// see [`concat_audio`].
+    ///
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn synthesize_mixed(&self, text: &str, voices: &VoiceMap, output_format: &str) -> Result<SynthesisOutput> {
+        let format = OutputFormat::new(output_format);
+        let parts = language_runs(text)
+            .into_iter()
+            .filter(|run| run.text.chars().any(char::is_alphanumeric))
+            .map(|run| self.synthesize_request(&SynthesisRequest::new(run.text.trim(), voices.voice_of(&run)?).with_output_format(format.clone())))
+            .collect::<Result<Vec<_>>>()?;
+        concat_audio(parts, &format, &ConcatOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn switches_voices_per_language() {
+        let text = "你好，世界。Hello, how are you? 再见！";
+        let runs = language_runs(text);
+        assert_eq!(runs.iter().map(|r| (r.language, r.text.as_str())).collect::<Vec<_>>(), [("zh", "你好，世界。"), ("en", "Hello, how are you? "), ("zh", "再见！")]);
+        assert_eq!(language_runs("こんにちは世界"), [LanguageRun { language: "ja", text: "こんにちは世界".to_owned() }]);
+        let voices = VoiceMap::default().with_voice("EN", "en-GB-RyanNeural");
+        let ssml = build_mixed_ssml(text, &voices).unwrap();
+        assert!(ssml.contains("<voice name=\"zh-CN-XiaoxiaoNeural\">你好，世界。</voice><voice name=\"en-GB-RyanNeural\">"), "{}", ssml);
+        let server = MockServer::start(vec![MockReply::turn(&[0, 64, 0, 64]), MockReply::turn(&[0, 32]), MockReply::turn(&[0, 16])]).unwrap();
+        let output = server.client().with_voice_presets(crate::VoicePresets::empty()).synthesize_mixed(text, &voices, "raw-24khz-16bit-mono-pcm").unwrap();
+        assert!(!output.audio.is_empty());
+        let ssml: Vec<String> = server.requests().into_iter().map(|r| r.ssml).collect();
+        assert_eq!(ssml.len(), 3);
+        assert!(ssml[1].contains("en-GB-RyanNeural") && ssml[1].contains(">Hello, how are you?<"), "{:?}", ssml);
+        assert!(ssml[2].contains("zh-CN-XiaoxiaoNeural") && ssml[2].contains(">再见！<"), "{:?}", ssml);
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{bail, Result};
+
+/// Header fields of one MPEG audio layer III frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FrameHeader {
+    pub sample_rate: u32,
+    /// Bits per second.
+    pub bitrate: u32,
+    pub samples: u32,
+    /// Frame length in bytes, header included.
+    pub len: usize,
+    /// Bytes of header and CRC before the side information.
+    pub side_info_offset: usize,
+    /// Bytes of header, CRC and side information before the main data.
+    pub main_data_offset: usize,
+}
+
+const BITRATES_V1: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// Parse the 4-byte header at the start of `data`. Only layer III with a fixed bitrate index is accepted.
+pub(crate) fn parse_frame_header(data: &[u8]) -> Option<FrameHeader> {
+    let header = u32::from_be_bytes(data.get(..4)?.try_into().ok()?);
+    if header >> 21 != 0x7ff || (header >> 17) & 0b11 != 0b01 {
+        return None;
+    }
+    // 0b11: MPEG-1, 0b10: MPEG-2, 0b00: MPEG-2.5
+    let version = (header >> 19) & 0b11;
+    let (bitrates, rates, samples) = match version {
+        0b11 => (&BITRATES_V1, [44100, 48000, 32000], 1152),
+        0b10 => (&BITRATES_V2, [22050, 24000, 16000], 576),
+        0b00 => (&BITRATES_V2, [11025, 12000, 8000], 576),
+        _ => return None,
+    };
+    let bitrate = *bitrates.get(((header >> 12) & 0xf) as usize)? * 1000;
+    let sample_rate = *rates.get(((header >> 10) & 0b11) as usize)?;
+    if bitrate == 0 {
+        return None;
+    }
+    let padding = ((header >> 9) & 1) as usize;
+    let len = (samples / 8 * bitrate / sample_rate) as usize + padding;
+    let crc = if (header >> 16) & 1 == 0 { 2 } else { 0 };
+    let mono = (header >> 6) & 0b11 == 0b11;
+    let side_info = match (version == 0b11, mono) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+    Some(FrameHeader { sample_rate, bitrate, samples, len, side_info_offset: 4 + crc, main_data_offset: 4 + crc + side_info })
+}
+
+impl FrameHeader {
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64(self.samples as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Bytes of main data `frame` takes from the frames before it (the bit reservoir).
+pub(crate) fn main_data_begin(frame: &[u8]) -> Option<usize> {
+    let header = parse_frame_header(frame)?;
+    let side_info = frame.get(header.side_info_offset..)?;
+    let (first, second) = (*side_info.first()? as usize, *side_info.get(1)? as usize);
+    Some(if header.samples == 1152 { first << 1 | second >> 7 } else { first })
+}
+
+/// A frame decoding to silence, with the stream parameters of `like`: no CRC, zero side information and main data.
+pub(crate) fn silent_frame(like: &[u8]) -> Option<Vec<u8>> {
+    let mut header: [u8; 4] = like.get(..4)?.try_into().ok()?;
+    // Protection bit set: no CRC. Padding bit cleared.
+    header[1] |= 1;
+    header[2] &= !0b10;
+    let len = parse_frame_header(&header)?.len;
+    let mut frame = header.to_vec();
+    frame.resize(len, 0);
+    Some(frame)
+}
+
+/// Consecutive frames from the start of `data`, stopping at the first byte that isn't a frame.
+pub(crate) fn frames(data: &[u8]) -> Vec<(FrameHeader, &[u8])> {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..).and_then(parse_frame_header) {
+        match data.get(offset..offset + header.len) {
+            Some(frame) => frames.push((header, frame)),
+            None => break,
+        }
+        offset += header.len;
+    }
+    frames
+}
+
+/// Byte offsets of consecutive frames from the start of `data`, stopping at the first byte that isn't a frame.
+pub(crate) fn frame_offsets(data: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..).and_then(parse_frame_header) {
+        offsets.push(offset);
+        offset += header.len;
+    }
+    offsets
+}
+
+/// Length of the ID3v2 tag at the start of `data`, footer included.
+pub(crate) fn id3v2_len(data: &[u8]) -> Option<usize> {
+    match data {
+        [b'I', b'D', b'3', _, _, flags, size @ ..] if size.len() >= 4 => {
+            let size = size.iter().take(4).fold(0usize, |acc, &b| acc << 7 | (b & 0x7f) as usize);
+            Some(10 + size + if flags & 0x10 != 0 { 10 } else { 0 })
+        }
+        _ => None,
+    }
+}
+
+/// Copy of `mp3` with only complete frames: a truncated last frame and bytes between frames are dropped. Leading
+/// ID3v2 and trailing ID3v1 tags are kept. Fails if there is no frame at all.
+pub fn validate_mp3(mp3: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(mp3.len());
+    let mut offset = 0;
+    if let Some(tag) = id3v2_len(mp3).and_then(|len| mp3.get(..len)) {
+        out.extend_from_slice(tag);
+        offset = tag.len();
+    }
+    let mut frames = 0;
+    while let Some(rest) = mp3.get(offset..).filter(|rest| !rest.is_empty()) {
+        if rest.len() == 128 && rest.starts_with(b"TAG") {
+            out.extend_from_slice(rest);
+            break;
+        }
+        match parse_frame_header(rest) {
+            Some(header) => match rest.get(..header.len) {
+                Some(frame) => {
+                    out.extend_from_slice(frame);
+                    offset += header.len;
+                    frames += 1;
+                }
+                None => break,
+            },
+            // Resynchronize on the next frame header.
+            None => offset += 1,
+        }
+    }
+    if frames == 0 {
+        bail!("no mp3 frames");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers() {
+        // MPEG-2 layer III, 48 kbit/s, 24 kHz: the default output format.
+        let header = parse_frame_header(&[0xff, 0xf3, 0x64, 0xc4]).unwrap();
+        assert_eq!((header.sample_rate, header.bitrate, header.samples, header.len, header.main_data_offset), (24000, 48000, 576, 144, 13));
+        let mut data = [0u8; 144 * 2 + 10];
+        data[..4].copy_from_slice(&[0xff, 0xf3, 0x64, 0xc4]);
+        data[144..148].copy_from_slice(&[0xff, 0xf3, 0x64, 0xc4]);
+        assert_eq!(frame_offsets(&data), vec![0, 144]);
+        assert_eq!(parse_frame_header(b"ID3\x04"), None);
+    }
+
+    #[test]
+    fn strips_truncated_frames() {
+        let frame = |fill: u8| {
+            let mut frame = vec![fill; 144];
+            frame[..4].copy_from_slice(&[0xff, 0xf3, 0x64, 0xc4]);
+            frame
+        };
+        let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x02ab".to_vec();
+        mp3.extend(frame(1));
+        mp3.extend(b"junk");
+        mp3.extend(frame(2));
+        let expected = [&mp3[..12], &frame(1), &frame(2)].concat();
+        mp3.extend(&frame(3)[..100]);
+        assert_eq!(validate_mp3(&mp3).unwrap(), expected);
+        assert!(validate_mp3(b"not an mp3").is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Minimal MQTT 3.1.1 client: QoS 0 publish/subscribe over plain TCP, enough for the speech bridge.
+///
+/// A background thread sends a PINGREQ whenever nothing was sent for half the keep-alive, so the broker keeps the
+/// connection while the caller is busy, eg: synthesizing a long text.
+pub struct MqttClient {
+    stream: TcpStream,
+    writer: Arc<Writer>,
+    pinger: Option<JoinHandle<()>>,
+    next_packet_id: u16,
+}
+
+/// The sending half of the connection, shared with the pinger.
+struct Writer {
+    state: Mutex<WriterState>,
+    /// Signalled when the client goes away.
+    closed: Condvar,
+}
+
+struct WriterState {
+    stream: TcpStream,
+    last_sent: Instant,
+    closed: bool,
+}
+
+impl Writer {
+    fn lock(&self) -> MutexGuard<'_, WriterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let mut state = self.lock();
+        state.stream.write_all(packet)?;
+        state.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Ping every `interval` without other packets, until closed or the connection fails.
+    fn ping_every(&self, interval: Duration) {
+        let mut state = self.lock();
+        while !state.closed {
+            let idle = state.last_sent.elapsed();
+            if idle >= interval {
+                if state.stream.write_all(&packet(PINGREQ, &[])).is_err() {
+                    return;
+                }
+                state.last_sent = Instant::now();
+                continue;
+            }
+            state = self.closed.wait_timeout(state, interval - idle).unwrap_or_else(|e| e.into_inner()).0;
+        }
+    }
+}
+
+/// Credentials and session options of [`MqttClient::connect`].
+#[derive(Debug, Clone)]
+pub struct MqttOptions {
+    /// eg: "edge-tts"
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive: Duration,
+}
+
+impl Default for MqttOptions {
+    fn default() -> Self {
+        Self {
+            client_id: "edge-tts".to_owned(),
+            username: None,
+            password: None,
+            keep_alive: Duration::from_secs(60),
+        }
+    }
+}
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xc0;
+const DISCONNECT: u8 = 0xe0;
+
+fn push_remaining_length(buf: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        buf.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn push_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend((s.len() as u16).to_be_bytes());
+    buf.extend(s.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut buf = vec![header];
+    push_remaining_length(&mut buf, body.len());
+    buf.extend(body);
+    buf
+}
+
+fn read_str(body: &[u8]) -> Result<(String, &[u8])> {
+    match body {
+        [hi, lo, rest @ ..] => {
+            let len = (*hi as usize) << 8 | *lo as usize;
+            if rest.len() < len {
+                bail!("truncated mqtt string");
+            }
+            let (s, rest) = rest.split_at(len);
+            Ok((String::from_utf8_lossy(s).into_owned(), rest))
+        }
+        _ => bail!("truncated mqtt string"),
+    }
+}
+
+impl MqttClient {
+    /// `addr`: broker address, eg: "127.0.0.1:1883"
+    pub fn connect(addr: impl ToSocketAddrs, options: &MqttOptions) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let writer = Arc::new(Writer {
+            state: Mutex::new(WriterState { stream: stream.try_clone()?, last_sent: Instant::now(), closed: false }),
+            closed: Condvar::new(),
+        });
+        let mut client = Self {
+            stream,
+            writer,
+            pinger: None,
+            next_packet_id: 1,
+        };
+        let mut body = Vec::new();
+        push_str(&mut body, "MQTT");
+        body.push(4);
+        let mut flags = 0x02;
+        if options.username.is_some() {
+            flags |= 0x80;
+        }
+        if options.password.is_some() {
+            flags |= 0x40;
+        }
+        body.push(flags);
+        body.extend((options.keep_alive.as_secs().min(u16::MAX as u64) as u16).to_be_bytes());
+        push_str(&mut body, &options.client_id);
+        if let Some(username) = &options.username {
+            push_str(&mut body, username);
+        }
+        if let Some(password) = &options.password {
+            push_str(&mut body, password);
+        }
+        client.writer.send(&packet(CONNECT, &body))?;
+        let (header, body) = client.read_packet()?;
+        match (header & 0xf0, body.as_slice()) {
+            (CONNACK, [_, 0]) => {
+                let interval = options.keep_alive / 2;
+                if !interval.is_zero() {
+                    let writer = client.writer.clone();
+                    client.pinger = Some(thread::spawn(move || writer.ping_every(interval)));
+                }
+                Ok(client)
+            }
+            (CONNACK, [_, code]) => Err(anyhow!("mqtt connection refused. return code: {}", code)),
+            _ => Err(anyhow!("mqtt broker didn't answer CONNECT with CONNACK")),
+        }
+    }
+
+    pub fn subscribe(&mut self, topic: &str) -> Result<()> {
+        let packet_id = self.next_packet_id;
+        self.next_packet_id = self.next_packet_id.wrapping_add(1).max(1);
+        let mut body = packet_id.to_be_bytes().to_vec();
+        push_str(&mut body, topic);
+        body.push(0);
+        self.writer.send(&packet(SUBSCRIBE, &body))
+    }
+
+    /// Publish with QoS 0.
+    pub fn publish(&mut self, topic: &str, payload: &[u8], retain: bool) -> Result<()> {
+        let mut body = Vec::new();
+        push_str(&mut body, topic);
+        body.extend(payload);
+        self.writer.send(&packet(PUBLISH | retain as u8, &body))
+    }
+
+    /// Next message on a subscribed topic, as `(topic, payload)`.
+    pub fn next_message(&mut self) -> Result<(String, Vec<u8>)> {
+        loop {
+            let (header, body) = self.read_packet()?;
+            match header & 0xf0 {
+                PUBLISH => {
+                    let qos = (header >> 1) & 0x03;
+                    let (topic, rest) = read_str(&body)?;
+                    let payload = if qos > 0 {
+                        let (packet_id, payload) = rest.split_at(rest.len().min(2));
+                        if qos == 1 {
+                            self.writer.send(&packet(PUBACK, packet_id))?;
+                        }
+                        payload
+                    } else {
+                        rest
+                    };
+                    return Ok((topic, payload.to_vec()));
+                }
+                SUBACK if body.get(2) == Some(&0x80) => bail!("mqtt subscription refused"),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn disconnect(self) -> Result<()> {
+        self.writer.send(&packet(DISCONNECT, &[]))
+    }
+
+    fn read_packet(&mut self) -> Result<(u8, Vec<u8>)> {
+        let mut header = [0u8; 1];
+        self.stream.read_exact(&mut header)?;
+        let mut len = 0usize;
+        for shift in (0..28).step_by(7) {
+            let mut byte = [0u8; 1];
+            self.stream.read_exact(&mut byte)?;
+            len |= ((byte[0] & 0x7f) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                let mut body = vec![0u8; len];
+                self.stream.read_exact(&mut body)?;
+                return Ok((header[0], body));
+            }
+        }
+        Err(anyhow!("malformed mqtt remaining length"))
+    }
+}
+
+impl Drop for MqttClient {
+    fn drop(&mut self) {
+        self.writer.lock().closed = true;
+        self.writer.closed.notify_all();
+        if let Some(pinger) = self.pinger.take() {
+            let _ = pinger.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_remaining_length() {
+        for (len, expected) in [(0, vec![0x00]), (127, vec![0x7f]), (128, vec![0x80, 0x01]), (16_383, vec![0xff, 0x7f]), (2_097_152, vec![0x80, 0x80, 0x80, 0x01])] {
+            let mut buf = Vec::new();
+            push_remaining_length(&mut buf, len);
+            assert_eq!(buf, expected);
+        }
+    }
+
+    #[test]
+    fn reads_strings() {
+        let (s, rest) = read_str(b"\x00\x03abcdef").unwrap();
+        assert_eq!(s, "abc");
+        assert_eq!(rest, b"def");
+        assert!(read_str(b"\x00\x05abc").is_err());
+        assert!(read_str(b"\x00").is_err());
+    }
+
+    /// Packet types a broker received until DISCONNECT, with the times of the PINGREQs. Accepts the CONNECT.
+    fn start_broker(listener: std::net::TcpListener) -> JoinHandle<(Vec<u8>, Vec<Instant>)> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (mut types, mut pings) = (Vec::new(), Vec::new());
+            loop {
+                let mut header = [0u8; 2];
+                stream.read_exact(&mut header).unwrap();
+                let mut body = vec![0u8; header[1] as usize];
+                stream.read_exact(&mut body).unwrap();
+                types.push(header[0] & 0xf0);
+                match header[0] & 0xf0 {
+                    CONNECT => stream.write_all(&[CONNACK, 2, 0, 0]).unwrap(),
+                    PINGREQ => pings.push(Instant::now()),
+                    DISCONNECT => return (types, pings),
+                    _ => {}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn pings_while_the_caller_is_busy() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let broker = start_broker(listener);
+        let client = MqttClient::connect(addr, &MqttOptions { keep_alive: Duration::from_secs(1), ..Default::default() }).unwrap();
+        // Busy, eg: synthesizing, and not reading the connection.
+        let connected = Instant::now();
+        thread::sleep(Duration::from_millis(1300));
+        client.disconnect().unwrap();
+        let (types, pings) = broker.join().unwrap();
+        assert_eq!((types.first(), types.last()), (Some(&CONNECT), Some(&DISCONNECT)));
+        assert!(pings.len() >= 2, "{:?}", types);
+        assert!(pings[0] - connected >= Duration::from_millis(400), "{:?}", pings[0] - connected);
+
+        // Publishing often enough needs no pings, and a zero keep-alive none at all.
+        for keep_alive in [Duration::from_secs(1), Duration::ZERO] {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let broker = start_broker(listener);
+            let mut client = MqttClient::connect(addr, &MqttOptions { keep_alive, ..Default::default() }).unwrap();
+            for _ in 0..4 {
+                thread::sleep(Duration::from_millis(200));
+                client.publish("t", b"x", false).unwrap();
+            }
+            client.disconnect().unwrap();
+            assert_eq!(broker.join().unwrap().0, [CONNECT, PUBLISH, PUBLISH, PUBLISH, PUBLISH, DISCONNECT]);
+        }
+    }
+}
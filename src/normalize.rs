@@ -0,0 +1,320 @@
+/// What [`TextNormalizer`] does with emojis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Emojis {
+    /// Left to the voice, which mostly skips them.
+    #[default]
+    Keep,
+    Strip,
+    /// Common ones said by name, eg: "👍" as "thumbs up", the others stripped.
+    Verbalize,
+}
+
+/// Preprocessing of raw text, eg: scraped from the web, to sound natural, applied to requests by
+/// [`crate::Client::with_normalizer`]:
+///
+/// ```
+/// use edge_tts::TextNormalizer;
+///
+/// let normalizer = TextNormalizer::for_locale("en-US");
+/// assert_eq!(normalizer.normalize("Dr.  Smith paid $5  for 2km 👍"), "Doctor Smith paid 5 dollars for 2 kilometers 👍");
+/// ```
+///
+/// Abbreviations match whole words and their case; units and currencies follow or precede a number, eg: "5 kg",
+/// "5kg", "$5", "5 $".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextNormalizer {
+    abbreviations: Vec<(String, String)>,
+    /// Symbol, singular and plural.
+    units: Vec<(String, String, String)>,
+    currencies: Vec<(String, String, String)>,
+    emojis: Emojis,
+    collapse_whitespace: bool,
+}
+
+const ENGLISH_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Ms.", "Miz"),
+    ("Prof.", "Professor"),
+    ("Jr.", "Junior"),
+    ("Sr.", "Senior"),
+    ("vs.", "versus"),
+    ("etc.", "et cetera"),
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("approx.", "approximately"),
+];
+
+const ENGLISH_UNITS: &[(&str, &str, &str)] = &[
+    ("%", "percent", "percent"),
+    ("°C", "degree Celsius", "degrees Celsius"),
+    ("°F", "degree Fahrenheit", "degrees Fahrenheit"),
+    ("km/h", "kilometer per hour", "kilometers per hour"),
+    ("mph", "mile per hour", "miles per hour"),
+    ("km", "kilometer", "kilometers"),
+    ("cm", "centimeter", "centimeters"),
+    ("mm", "millimeter", "millimeters"),
+    ("m", "meter", "meters"),
+    ("mi", "mile", "miles"),
+    ("ft", "foot", "feet"),
+    ("kg", "kilogram", "kilograms"),
+    ("mg", "milligram", "milligrams"),
+    ("g", "gram", "grams"),
+    ("lbs", "pound", "pounds"),
+    ("lb", "pound", "pounds"),
+    ("ml", "milliliter", "milliliters"),
+    ("KB", "kilobyte", "kilobytes"),
+    ("MB", "megabyte", "megabytes"),
+    ("GB", "gigabyte", "gigabytes"),
+    ("TB", "terabyte", "terabytes"),
+    ("kHz", "kilohertz", "kilohertz"),
+    ("MHz", "megahertz", "megahertz"),
+    ("GHz", "gigahertz", "gigahertz"),
+    ("Hz", "hertz", "hertz"),
+];
+
+const ENGLISH_CURRENCIES: &[(&str, &str, &str)] = &[
+    ("$", "dollar", "dollars"),
+    ("€", "euro", "euros"),
+    ("£", "pound", "pounds"),
+    ("¥", "yen", "yen"),
+    ("₹", "rupee", "rupees"),
+];
+
+const EMOJI_NAMES: &[(&str, &str)] = &[
+    ("👍", "thumbs up"),
+    ("👎", "thumbs down"),
+    ("❤", "heart"),
+    ("😀", "grinning face"),
+    ("😂", "face with tears of joy"),
+    ("😊", "smiling face"),
+    ("😍", "heart eyes"),
+    ("😢", "crying face"),
+    ("🤔", "thinking face"),
+    ("🙏", "folded hands"),
+    ("👋", "waving hand"),
+    ("👏", "clapping hands"),
+    ("🎉", "party popper"),
+    ("🔥", "fire"),
+    ("🚀", "rocket"),
+    ("⭐", "star"),
+    ("✅", "check mark"),
+    ("❌", "cross mark"),
+    ("💯", "hundred points"),
+];
+
+impl TextNormalizer {
+    /// A normalizer doing nothing, to build up with the `with_*` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whitespace collapsed, and for English locales, eg: "en-GB", its abbreviations, units and currencies. Other
+    /// locales have no built-in words yet.
+    pub fn for_locale(locale: &str) -> Self {
+        let mut normalizer = Self::new().with_collapsed_whitespace();
+        if locale.get(..2).is_some_and(|language| language.eq_ignore_ascii_case("en")) {
+            for (abbreviation, expansion) in ENGLISH_ABBREVIATIONS {
+                normalizer = normalizer.with_abbreviation(abbreviation, expansion);
+            }
+            for (symbol, singular, plural) in ENGLISH_UNITS {
+                normalizer = normalizer.with_unit(symbol, singular, plural);
+            }
+            for (symbol, singular, plural) in ENGLISH_CURRENCIES {
+                normalizer = normalizer.with_currency(symbol, singular, plural);
+            }
+        }
+        normalizer
+    }
+
+    /// eg: `.with_abbreviation("Dr.", "Doctor")`
+    pub fn with_abbreviation(mut self, abbreviation: &str, expansion: &str) -> Self {
+        self.abbreviations.push((abbreviation.to_owned(), expansion.to_owned()));
+        // Longest first, eg: "Mrs." before "Mr.".
+        self.abbreviations.sort_by_key(|(abbreviation, _)| std::cmp::Reverse(abbreviation.len()));
+        self
+    }
+
+    /// eg: `.with_unit("km", "kilometer", "kilometers")`, said after the number.
+    pub fn with_unit(mut self, symbol: &str, singular: &str, plural: &str) -> Self {
+        self.units.push((symbol.to_owned(), singular.to_owned(), plural.to_owned()));
+        self.units.sort_by_key(|(symbol, ..)| std::cmp::Reverse(symbol.len()));
+        self
+    }
+
+    /// eg: `.with_currency("$", "dollar", "dollars")`, said after the number.
+    pub fn with_currency(mut self, symbol: &str, singular: &str, plural: &str) -> Self {
+        self.currencies.push((symbol.to_owned(), singular.to_owned(), plural.to_owned()));
+        self.currencies.sort_by_key(|(symbol, ..)| std::cmp::Reverse(symbol.len()));
+        self
+    }
+
+    pub fn with_emojis(mut self, emojis: Emojis) -> Self {
+        self.emojis = emojis;
+        self
+    }
+
+    /// Runs of spaces and tabs as one space, lines trimmed and blank lines as one.
+    pub fn with_collapsed_whitespace(mut self) -> Self {
+        self.collapse_whitespace = true;
+        self
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = self.expand_abbreviations(text);
+        text = self.expand_numbers(&text);
+        text = match self.emojis {
+            Emojis::Keep => text,
+            Emojis::Strip => text.chars().filter(|&c| !is_emoji(c)).collect(),
+            Emojis::Verbalize => verbalize_emojis(&text),
+        };
+        if self.collapse_whitespace {
+            text = collapse_whitespace(&text);
+        }
+        text
+    }
+
+    fn expand_abbreviations(&self, text: &str) -> String {
+        if self.abbreviations.is_empty() {
+            return text.to_owned();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let at_word_start = !out.chars().next_back().is_some_and(is_word_char);
+            let found = self.abbreviations.iter().find(|(abbreviation, _)| {
+                at_word_start && rest.starts_with(abbreviation.as_str()) && !rest[abbreviation.len()..].chars().next().is_some_and(is_word_char)
+            });
+            match found {
+                Some((abbreviation, expansion)) => {
+                    out += expansion;
+                    rest = &rest[abbreviation.len()..];
+                }
+                None => {
+                    out.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        out
+    }
+
+    /// Currencies before or after a number, and units after it, said after it.
+    fn expand_numbers(&self, text: &str) -> String {
+        if self.units.is_empty() && self.currencies.is_empty() {
+            return text.to_owned();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let after_word = out.chars().next_back().is_some_and(is_word_char);
+            // eg: "$5"
+            let prefix = self.currencies.iter().find_map(|(symbol, singular, plural)| {
+                let number = number_len(rest.strip_prefix(symbol.as_str())?);
+                (!after_word && number > 0).then(|| (symbol.len() + number, &rest[symbol.len()..symbol.len() + number], singular, plural))
+            });
+            if let Some((len, number, singular, plural)) = prefix {
+                out += &format!("{} {}", number, if number == "1" { singular } else { plural });
+                rest = &rest[len..];
+                continue;
+            }
+            let number = if after_word { 0 } else { number_len(rest) };
+            if number == 0 {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let (digits, after) = rest.split_at(number);
+            out += digits;
+            rest = after;
+            // eg: "5 kg", "5kg", "5 $"
+            let spaced = rest.strip_prefix(' ').unwrap_or(rest);
+            let suffix = self.units.iter().chain(&self.currencies).find(|(symbol, ..)| {
+                spaced.starts_with(symbol.as_str()) && !spaced[symbol.len()..].chars().next().is_some_and(is_word_char)
+            });
+            if let Some((symbol, singular, plural)) = suffix {
+                out += &format!(" {}", if digits == "1" { singular } else { plural });
+                rest = &spaced[symbol.len()..];
+            }
+        }
+        out
+    }
+}
+
+/// Length of the number `s` starts with, eg: 8 for "1,234.50 and", 0 if none.
+fn number_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let separator = (c == '.' || c == ',') && len > 0 && chars.peek().is_some_and(|(_, next)| next.is_ascii_digit());
+        if !c.is_ascii_digit() && !separator {
+            break;
+        }
+        len = i + 1;
+    }
+    len
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B50 | 0x2B55 | 0xFE0F | 0x200D | 0x20E3)
+}
+
+fn verbalize_emojis(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match EMOJI_NAMES.iter().find(|(emoji, _)| emoji.starts_with(c)) {
+            Some((_, name)) => {
+                if !out.is_empty() && !out.ends_with(char::is_whitespace) {
+                    out.push(' ');
+                }
+                out += name;
+                out.push(' ');
+            }
+            None if is_emoji(c) => {}
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines().map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ")) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, SynthesisRequest, VoicePresets};
+
+    #[test]
+    fn normalizes_text() {
+        let english = TextNormalizer::for_locale("en-GB");
+        assert_eq!(english.normalize("Mrs. Smith vs. Mr. Jones"), "Missus Smith versus Mister Jones");
+        assert_eq!(english.normalize("It costs $1,234.50, or €1 and 5 £"), "It costs 1,234.50 dollars, or 1 euro and 5 pounds");
+        assert_eq!(english.normalize("1km, 5 kg, 20% and 30°C at 3GHz; 2 men, 4x4"), "1 kilometer, 5 kilograms, 20 percent and 30 degrees Celsius at 3 gigahertz; 2 men, 4x4");
+        assert_eq!(english.normalize("  Hello\t world  \n\n\n\nBye\n\n"), "Hello world\n\nBye");
+        assert_eq!(TextNormalizer::for_locale("de-DE").normalize("5 km  Dr. X"), "5 km Dr. X");
+
+        let emojis = TextNormalizer::new().with_emojis(Emojis::Verbalize).with_collapsed_whitespace();
+        assert_eq!(emojis.normalize("Great👍 🦀 ❤️!"), "Great thumbs up heart !");
+        assert_eq!(TextNormalizer::new().with_emojis(Emojis::Strip).normalize("Hi 👋🏽!"), "Hi !");
+
+        let client = Client::new().with_voice_presets(VoicePresets::empty()).with_normalizer(english);
+        let request = SynthesisRequest::new("Dr.  Who", "en-US-AriaNeural");
+        assert_eq!(client.prepare(&request).unwrap().text, "Doctor Who");
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::{BufRead, BufReader, Lines};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use anyhow::{anyhow, Result};
+
+/// A desktop notification sent through `org.freedesktop.Notifications.Notify`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notification {
+    /// eg: "notify-send", "Thunderbird"
+    pub app_name: String,
+    pub summary: String,
+    /// May contain simple markup, see [`Notification::to_speech`].
+    pub body: String,
+}
+
+impl Notification {
+    /// Text to announce, eg: "Thunderbird: New message. Lunch?". Markup tags are stripped from the body.
+    pub fn to_speech(&self, include_body: bool) -> String {
+        let mut text = if self.app_name.is_empty() {
+            self.summary.clone()
+        } else {
+            format!("{}: {}", self.app_name, self.summary)
+        };
+        let body = strip_markup(&self.body);
+        if include_body && !body.trim().is_empty() {
+            text.push_str(". ");
+            text.push_str(body.trim());
+        }
+        text
+    }
+}
+
+fn strip_markup(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&amp;", "&").replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'")
+}
+
+/// Per-app filter. Empty `allow_apps` allows every app not in `deny_apps`. Names compare case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationFilter {
+    pub allow_apps: Vec<String>,
+    pub deny_apps: Vec<String>,
+}
+
+impl NotificationFilter {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        let app = notification.app_name.as_str();
+        (self.allow_apps.is_empty() || self.allow_apps.iter().any(|a| a.eq_ignore_ascii_case(app)))
+            && !self.deny_apps.iter().any(|a| a.eq_ignore_ascii_case(app))
+    }
+}
+
+/// Notifications read from `dbus-monitor` on the session bus. See [`listen_notifications`].
+pub struct Notifications {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+    parser: MonitorParser,
+}
+
+/// Watch the session bus for desktop notifications. Requires the `dbus-monitor` program.
+pub fn listen_notifications() -> Result<Notifications> {
+    let mut child = Command::new("dbus-monitor")
+        .args(["--session", "interface='org.freedesktop.Notifications',member='Notify'"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("failed to start dbus-monitor: {}", e))?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("dbus-monitor has no stdout"))?;
+    Ok(Notifications {
+        child,
+        lines: BufReader::new(stdout).lines(),
+        parser: MonitorParser::default(),
+    })
+}
+
+impl Iterator for Notifications {
+    type Item = Result<Notification>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next() {
+                Some(Ok(line)) => {
+                    if let Some(notification) = self.parser.push_line(&line) {
+                        return Some(Ok(notification));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => return self.parser.finish().map(Ok),
+            }
+        }
+    }
+}
+
+impl Drop for Notifications {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Collects the top level `string` arguments of Notify method calls from `dbus-monitor` output.
+#[derive(Default)]
+struct MonitorParser {
+    in_notify: bool,
+    strings: Vec<String>,
+    /// A string argument spanning several lines, not yet closed.
+    open_string: Option<String>,
+}
+
+impl MonitorParser {
+    fn push_line(&mut self, line: &str) -> Option<Notification> {
+        if let Some(open) = &mut self.open_string {
+            open.push('\n');
+            match line.strip_suffix('"') {
+                Some(rest) => {
+                    open.push_str(rest);
+                    self.strings.extend(self.open_string.take());
+                }
+                None => open.push_str(line),
+            }
+            return None;
+        }
+        if !line.starts_with(' ') {
+            let done = self.finish();
+            self.in_notify = line.starts_with("method call") && line.contains("member=Notify");
+            return done;
+        }
+        // Arguments of the call itself have 3 spaces of indentation, nested values more.
+        if self.in_notify && !line.starts_with("    ") {
+            if let Some(value) = line.trim_start().strip_prefix("string \"") {
+                match value.strip_suffix('"') {
+                    Some(value) => self.strings.push(value.to_owned()),
+                    None => self.open_string = Some(value.to_owned()),
+                }
+            }
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Option<Notification> {
+        let strings = std::mem::take(&mut self.strings);
+        self.open_string = None;
+        if !std::mem::take(&mut self.in_notify) {
+            return None;
+        }
+        // Notify(app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout)
+        let mut strings = strings.into_iter();
+        let app_name = strings.next()?;
+        let _app_icon = strings.next()?;
+        Some(Notification {
+            app_name,
+            summary: strings.next()?,
+            body: strings.next().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR_OUTPUT: &str = r#"signal time=1697000000.000000 sender=org.freedesktop.DBus -> destination=:1.100 serial=2 path=/org/freedesktop/DBus; interface=org.freedesktop.DBus; member=NameAcquired
+   string ":1.100"
+method call time=1697000001.000000 sender=:1.101 -> destination=:1.20 serial=7 path=/org/freedesktop/Notifications; interface=org.freedesktop.Notifications; member=Notify
+   string "notify-send"
+   uint32 0
+   string ""
+   string "Build finished"
+   string "All <b>42</b> tests
+passed"
+   array [
+   ]
+   array [
+      dict entry(
+         string "urgency"
+         variant             byte 1
+      )
+   ]
+   int32 -1
+method call time=1697000002.000000 sender=:1.102 -> destination=:1.20 serial=8 path=/org/freedesktop/Notifications; interface=org.freedesktop.Notifications; member=Notify
+   string "Thunderbird"
+   uint32 0
+   string "mail"
+   string "New message"
+   string ""
+   array [
+   ]
+   array [
+   ]
+   int32 5000
+"#;
+
+    #[test]
+    fn parses_monitor_output() {
+        let mut parser = MonitorParser::default();
+        let mut notifications: Vec<Notification> = MONITOR_OUTPUT.lines().filter_map(|line| parser.push_line(line)).collect();
+        notifications.extend(parser.finish());
+        assert_eq!(notifications, vec![
+            Notification { app_name: "notify-send".to_owned(), summary: "Build finished".to_owned(), body: "All <b>42</b> tests\npassed".to_owned() },
+            Notification { app_name: "Thunderbird".to_owned(), summary: "New message".to_owned(), body: "".to_owned() },
+        ]);
+        assert_eq!(notifications[0].to_speech(true), "notify-send: Build finished. All 42 tests\npassed");
+        assert_eq!(notifications[1].to_speech(true), "Thunderbird: New message");
+    }
+
+    #[test]
+    fn filters_by_app() {
+        let notification = Notification { app_name: "Thunderbird".to_owned(), ..Default::default() };
+        assert!(NotificationFilter::default().matches(&notification));
+        assert!(NotificationFilter { allow_apps: vec!["thunderbird".to_owned()], deny_apps: vec![] }.matches(&notification));
+        assert!(!NotificationFilter { allow_apps: vec!["slack".to_owned()], deny_apps: vec![] }.matches(&notification));
+        assert!(!NotificationFilter { allow_apps: vec![], deny_apps: vec!["Thunderbird".to_owned()] }.matches(&notification));
+    }
+}
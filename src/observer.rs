@@ -0,0 +1,95 @@
+use std::fmt::Debug;
+
+use crate::Boundary;
+
+/// Sees the protocol events of every Edge turn a [`crate::Client`] runs, as they are read, eg: for logging, analytics
+/// or live captions. See [`crate::Client::with_event_observer`].
+///
+/// Turns of another [`crate::TtsBackend`] and cache hits aren't seen. Callbacks run on the synthesizing thread, so
+/// they should be quick.
+pub trait EventObserver: Debug + Send + Sync {
+    /// The WebSocket connection with this ConnectionId is open.
+    fn connected(&self, connection_id: &str) {
+        let _ = connection_id;
+    }
+
+    /// `turn.start` of the turn with this X-RequestId.
+    fn turn_started(&self, request_id: &str) {
+        let _ = request_id;
+    }
+
+    /// One audio message.
+    fn audio(&self, request_id: &str, audio: &[u8]) {
+        let _ = (request_id, audio);
+    }
+
+    /// One `audio.metadata` message.
+    fn metadata(&self, request_id: &str, boundaries: &[Boundary]) {
+        let _ = (request_id, boundaries);
+    }
+
+    /// `turn.end`.
+    fn turn_ended(&self, request_id: &str) {
+        let _ = request_id;
+    }
+
+    /// Connecting or the turn failed; the error has the [`crate::TurnIds`] once the turn started.
+    fn error(&self, error: &anyhow::Error) {
+        let _ = error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::{Error, SynthesisRequest};
+
+    #[derive(Debug, Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl EventObserver for Collect {
+        fn connected(&self, _: &str) {
+            self.0.lock().unwrap().push("connected".to_owned());
+        }
+
+        fn turn_started(&self, _: &str) {
+            self.0.lock().unwrap().push("turn.start".to_owned());
+        }
+
+        fn audio(&self, _: &str, audio: &[u8]) {
+            self.0.lock().unwrap().push(format!("audio {}", audio.len()));
+        }
+
+        fn metadata(&self, _: &str, boundaries: &[Boundary]) {
+            self.0.lock().unwrap().push(format!("metadata {}", boundaries[0].text));
+        }
+
+        fn turn_ended(&self, request_id: &str) {
+            self.0.lock().unwrap().push(format!("turn.end {}", request_id.len()));
+        }
+
+        fn error(&self, error: &anyhow::Error) {
+            let closed = matches!(error.downcast_ref::<Error>(), Some(Error::ConnectionClosedByServer { .. }));
+            self.0.lock().unwrap().push(format!("error closed={}", closed));
+        }
+    }
+
+    #[test]
+    fn sees_each_event() {
+        let word = MockReply::Word { text: "Hi".to_owned(), offset: Duration::ZERO, duration: Duration::from_millis(100) };
+        let mut turn = MockReply::turn(b"abc");
+        turn.insert(1, word);
+        let closed = vec![MockReply::Close { code: 1011, reason: String::new() }];
+        let server = MockServer::start(vec![turn, closed]).unwrap();
+        let observer = Arc::new(Collect::default());
+        let client = server.client().with_event_observer(observer.clone());
+        let request = SynthesisRequest::new("Hi", "en-US-AriaNeural");
+        client.synthesize_request(&request).unwrap();
+        assert!(client.synthesize_request(&request).is_err());
+        assert_eq!(*observer.0.lock().unwrap(), ["connected", "turn.start", "metadata Hi", "audio 3", "turn.end 32", "connected", "error closed=true"]);
+    }
+}
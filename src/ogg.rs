@@ -0,0 +1,126 @@
+// Packet access is only used by voice messages.
+#![cfg_attr(not(feature = "bot"), allow(dead_code))]
+
+use anyhow::{bail, Result};
+
+/// One page of an Ogg stream.
+pub(crate) struct Page<'a> {
+    pub granule_position: u64,
+    pub segment_table: &'a [u8],
+    pub data: &'a [u8],
+}
+
+impl Page<'_> {
+    /// Packets (and a trailing partial packet continued on the next page) of this page.
+    pub fn segments(&self) -> impl Iterator<Item = (&[u8], bool)> {
+        let mut offset = 0usize;
+        let mut start = 0usize;
+        let data = self.data;
+        self.segment_table.iter().filter_map(move |&lacing| {
+            offset += lacing as usize;
+            if lacing < 255 {
+                let packet = data.get(start..offset).unwrap_or_default();
+                start = offset;
+                Some((packet, true))
+            } else if offset == data.len() {
+                Some((data.get(start..offset).unwrap_or_default(), false))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Split `data` into Ogg pages.
+pub(crate) fn pages(mut data: &[u8]) -> Result<Vec<Page<'_>>> {
+    let mut pages = Vec::new();
+    while !data.is_empty() {
+        let (n_segments, granule) = match (data.get(..4), data.get(6..14), data.get(26)) {
+            (Some(b"OggS"), Some(granule), Some(&n)) => (n as usize, granule),
+            _ => bail!("bad ogg page"),
+        };
+        let segment_table = match data.get(27..27 + n_segments) {
+            Some(table) => table,
+            None => bail!("truncated ogg page"),
+        };
+        let data_len: usize = segment_table.iter().map(|&n| n as usize).sum();
+        let start = 27 + n_segments;
+        let page_data = match data.get(start..start + data_len) {
+            Some(page_data) => page_data,
+            None => bail!("truncated ogg page"),
+        };
+        let mut granule_position = [0u8; 8];
+        granule_position.copy_from_slice(granule);
+        pages.push(Page {
+            granule_position: u64::from_le_bytes(granule_position),
+            segment_table,
+            data: page_data,
+        });
+        data = data.get(start + data_len..).unwrap_or_default();
+    }
+    Ok(pages)
+}
+
+/// Packets of an Ogg stream, joined across pages.
+pub(crate) fn packets(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut packets = Vec::new();
+    let mut partial = Vec::new();
+    for page in pages(data)? {
+        for (segment, complete) in page.segments() {
+            partial.extend_from_slice(segment);
+            if complete {
+                packets.push(std::mem::take(&mut partial));
+            }
+        }
+    }
+    Ok(packets)
+}
+
+/// Playback duration of an Ogg Opus stream in 48 kHz samples, from the last granule position minus the pre-skip.
+pub(crate) fn opus_duration_samples(data: &[u8]) -> Result<u64> {
+    let pages = pages(data)?;
+    let pre_skip = match pages.first().map(|p| p.data) {
+        Some([b'O', b'p', b'u', b's', b'H', b'e', b'a', b'd', _, _, lo, hi, ..]) => u16::from_le_bytes([*lo, *hi]) as u64,
+        _ => bail!("not an ogg opus stream"),
+    };
+    let last = pages.iter().rev().map(|p| p.granule_position).find(|&g| g != u64::MAX).unwrap_or(0);
+    Ok(last.saturating_sub(pre_skip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Page with a zero CRC, which the parser doesn't check.
+    fn page(granule_position: u64, segment_table: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\x00\x00".to_vec();
+        page.extend(granule_position.to_le_bytes());
+        page.extend([0u8; 12]);
+        page.push(segment_table.len() as u8);
+        page.extend(segment_table);
+        page.extend(data);
+        page
+    }
+
+    #[test]
+    fn joins_packets_across_pages() {
+        let mut head = b"OpusHead\x01\x01".to_vec();
+        head.extend(312u16.to_le_bytes());
+        head.extend([0u8; 7]);
+        let mut stream = page(0, &[head.len() as u8], &head);
+        stream.extend(page(u64::MAX, &[255], &[1; 255]));
+        stream.extend(page(48312, &[10, 3], &[2; 13]));
+        let packets = packets(&stream).unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[1].len(), 265);
+        assert_eq!(packets[2], vec![2; 3]);
+        assert_eq!(opus_duration_samples(&stream).unwrap(), 48000);
+    }
+
+    #[test]
+    fn rejects_truncated_pages() {
+        let stream = page(0, &[10], &[0; 10]);
+        assert!(pages(&stream[..stream.len() - 1]).is_err());
+        assert!(pages(b"OggS").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{OutputFormat, SynthesisRequest};
+
+/// Edge voices of the OpenAI voice names.
+const OPENAI_VOICES: &[(&str, &str)] = &[
+    ("alloy", "en-US-AriaNeural"),
+    ("ash", "en-US-AndrewNeural"),
+    ("ballad", "en-GB-RyanNeural"),
+    ("coral", "en-US-JennyNeural"),
+    ("echo", "en-US-GuyNeural"),
+    ("fable", "en-GB-SoniaNeural"),
+    ("nova", "en-US-MichelleNeural"),
+    ("onyx", "en-US-ChristopherNeural"),
+    ("sage", "en-US-EmmaNeural"),
+    ("shimmer", "en-AU-NatashaNeural"),
+    ("verse", "en-US-BrianNeural"),
+];
+
+/// Body of `POST /v1/audio/speech`, see https://platform.openai.com/docs/api-reference/audio/createSpeech
+#[derive(Debug, Deserialize)]
+struct SpeechBody {
+    #[serde(default)]
+    model: String,
+    input: String,
+    voice: String,
+    response_format: Option<String>,
+    speed: Option<f64>,
+}
+
+/// The request of an OpenAI speech `body`, with the voice of `voices`, else of [`OPENAI_VOICES`], else the Edge
+/// voice it names, and the pitch and volume of `defaults`.
+///
+/// "tts-1-hd" gets 192 kbit/s MP3. `speed` 0.25 to 4.0 is the rate, eg: 1.5 as "+50%". Errors are messages for
+/// [`error_json`].
+pub(crate) fn speech_request(body: &[u8], voices: &[(String, String)], defaults: &SynthesisRequest) -> Result<SynthesisRequest, String> {
+    let body: SpeechBody = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let voice = voices
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(&body.voice))
+        .map(|(_, voice)| voice.as_str())
+        .or_else(|| OPENAI_VOICES.iter().find(|(name, _)| name.eq_ignore_ascii_case(&body.voice)).map(|(_, voice)| *voice))
+        .unwrap_or(&body.voice);
+    let output_format = match body.response_format.as_deref().unwrap_or("mp3") {
+        "mp3" if body.model == "tts-1-hd" => OutputFormat::AUDIO_48KHZ_192KBITRATE_MONO_MP3,
+        "mp3" => OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3,
+        "opus" => OutputFormat::OGG_48KHZ_16BIT_MONO_OPUS,
+        "wav" => OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM,
+        // 24kHz 16-bit little-endian, like OpenAI's.
+        "pcm" => OutputFormat::RAW_24KHZ_16BIT_MONO_PCM,
+        other => return Err(format!("unsupported response_format {}, expected mp3, opus, wav or pcm", other)),
+    };
+    let rate = match body.speed {
+        Some(speed) if !(0.25..=4.0).contains(&speed) => return Err(format!("speed {} is not between 0.25 and 4.0", speed)),
+        Some(speed) => Some(format!("{:+.0}%", (speed - 1.0) * 100.0)),
+        None => defaults.rate.clone(),
+    };
+    Ok(SynthesisRequest {
+        text: body.input,
+        voice: voice.to_owned(),
+        pitch: defaults.pitch.clone(),
+        rate,
+        volume: defaults.volume.clone(),
+        style: defaults.style.clone(),
+        style_degree: defaults.style_degree.clone(),
+        output_format,
+    })
+}
+
+/// An error reply in OpenAI's shape, eg: `{"error": {"message": "...", "type": "invalid_request_error", ...}}`.
+pub(crate) fn error_json(status: u16, message: &str) -> Vec<u8> {
+    let kind = if status < 500 { "invalid_request_error" } else { "api_error" };
+    json!({ "error": { "message": message, "type": kind, "param": null, "code": null } }).to_string().into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_speech_requests() {
+        let defaults = SynthesisRequest::new("", "en-US-AriaNeural").with_volume("-10%");
+        let body = br#"{"model": "tts-1-hd", "input": "Hello", "voice": "onyx", "speed": 1.5}"#;
+        let request = speech_request(body, &[], &defaults).unwrap();
+        assert_eq!(request, SynthesisRequest::new("Hello", "en-US-ChristopherNeural").with_rate("+50%").with_volume("-10%").with_output_format(OutputFormat::AUDIO_48KHZ_192KBITRATE_MONO_MP3));
+
+        let voices = [("onyx".to_owned(), "en-GB-ThomasNeural".to_owned())];
+        let body = br#"{"model": "tts-1", "input": "Hello", "voice": "onyx", "response_format": "pcm", "speed": 0.5}"#;
+        let request = speech_request(body, &voices, &defaults).unwrap();
+        assert_eq!((request.voice.as_str(), request.rate.as_deref(), request.output_format), ("en-GB-ThomasNeural", Some("-50%"), OutputFormat::RAW_24KHZ_16BIT_MONO_PCM));
+        let request = speech_request(br#"{"input": "Hi", "voice": "zh-CN-XiaoxiaoNeural"}"#, &[], &defaults).unwrap();
+        assert_eq!(request.voice, "zh-CN-XiaoxiaoNeural");
+        assert!(speech_request(br#"{"input": "Hi", "voice": "alloy", "response_format": "flac"}"#, &[], &defaults).is_err());
+        assert!(speech_request(br#"{"input": "Hi", "voice": "alloy", "speed": 5}"#, &[], &defaults).is_err());
+    }
+}
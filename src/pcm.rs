@@ -0,0 +1,33 @@
+use std::ops::Range;
+
+/// Little-endian 16-bit samples of `bytes`. A trailing odd byte is dropped.
+pub(crate) fn to_samples(bytes: &[u8]) -> Vec<i16> {
+    bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect()
+}
+
+pub(crate) fn to_bytes(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+/// Samples from the first to the last one louder than `threshold_db` dBFS, eg: -50. `None` if all are quieter.
+pub(crate) fn loud_range(samples: &[i16], threshold_db: f32) -> Option<Range<usize>> {
+    let threshold = (10f32.powf(threshold_db / 20.0) * i16::MAX as f32) as i32;
+    let loud = |s: &i16| (*s as i32).abs() > threshold;
+    let start = samples.iter().position(loud)?;
+    let end = samples.iter().rposition(loud)? + 1;
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_loud_range() {
+        let samples = [0, 3, -2, 500, 0, -800, 10, 0];
+        // -40 dBFS is about 327.
+        assert_eq!(loud_range(&samples, -40.0), Some(3..6));
+        assert_eq!(loud_range(&samples, -20.0), None);
+        assert_eq!(to_samples(&to_bytes(&samples)), samples);
+    }
+}
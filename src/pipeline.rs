@@ -0,0 +1,59 @@
+use anyhow::{bail, Result};
+
+use crate::{Client, SynthesisOutput, SynthesisRequest};
+
+impl Client {
+    /// Synthesize `requests` over one connection instead of one each: the SSML of all of them is sent at once and
+    /// the replies, which may interleave, are told apart by their X-RequestId. Outputs are in the order of
+    /// `requests`.
+    ///
+    /// All requests need the same output format, which is set once per connection. Cached requests aren't sent.
+    /// There is no resume nor fallback: if the connection fails, everything not cached fails.
+    pub fn synthesize_pipelined(&self, requests: &[SynthesisRequest]) -> Result<Vec<SynthesisOutput>> {
+        let Some(first) = requests.first() else { return Ok(Vec::new()) };
+        let format = &first.output_format;
+        if let Some(other) = requests.iter().find(|r| r.output_format != *format) {
+            bail!("pipelined requests need the same output format, not {} and {}", format, other.output_format);
+        }
+        let ssml = requests.iter().map(|r| self.prepare(r).map(|r| self.request_ssml(&r))).collect::<Result<Vec<_>>>()?;
+        let mut outputs: Vec<Option<SynthesisOutput>> = ssml.iter().map(|ssml| self.disk_cache.as_ref().and_then(|cache| cache.get(&self.synth_key(ssml, format.as_str())))).collect();
+        let pending: Vec<usize> = (0..ssml.len()).filter(|&i| outputs[i].is_none()).collect();
+        if !pending.is_empty() {
+            for &i in &pending {
+                self.charge_quota(&ssml[i])?;
+            }
+            let received = self.pipeline(&pending.iter().map(|&i| ssml[i].as_str()).collect::<Vec<_>>(), format.as_str())?;
+            for (i, output) in pending.into_iter().zip(received) {
+                if let Some(cache) = &self.disk_cache {
+                    let _ = cache.put(&self.synth_key(&ssml[i], format.as_str()), &output);
+                }
+                outputs[i] = Some(output);
+            }
+        }
+        outputs.into_iter().map(|output| self.post_process(output.unwrap_or_default(), format.as_str())).collect()
+    }
+
+    /// Send every SSML on one connection and collect the replies by request id, then close it.
+    fn pipeline(&self, ssml: &[&str], output_format: &str) -> Result<Vec<SynthesisOutput>> {
+        self.session(output_format)?.turns(ssml)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn pipelines_turns_on_one_connection() {
+        let server = MockServer::start(vec![MockReply::turn(b"audio"), vec![MockReply::Close { code: 1011, reason: "second connection".to_owned() }]]).unwrap();
+        let requests: Vec<SynthesisRequest> = ["one", "two", "three"].into_iter().map(|text| SynthesisRequest::new(text, "en-US-AriaNeural")).collect();
+        let outputs = server.client().synthesize_pipelined(&requests).unwrap();
+        assert_eq!(outputs.iter().map(|o| o.audio.as_slice()).collect::<Vec<_>>(), [b"audio"; 3]);
+        let ssml: Vec<String> = server.requests().into_iter().map(|r| r.ssml).collect();
+        assert_eq!(ssml.len(), 3);
+        assert!(ssml[0].contains(">one<") && ssml[2].contains(">three<"), "{:?}", ssml);
+        let mixed = [requests[0].clone(), requests[1].clone().with_output_format("raw-24khz-16bit-mono-pcm")];
+        assert!(server.client().synthesize_pipelined(&mixed).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tungstenite::WebSocket;
+
+use crate::dump::{ConnectionDump, ProtocolDump};
+use crate::stream::Stream;
+use crate::synthesize::{close, process_turn, speech_config_message, Turn};
+use crate::trace::trace_event;
+use crate::{Client, Error, OutputFormat, SynthesisEvent, SynthesisOutput, SynthesisRequest};
+
+/// Connections of a [`Client`] opened ahead of time, so that a synthesis doesn't wait for the TCP, TLS and
+/// WebSocket handshakes, eg: for a voice assistant.
+///
+/// Once started by [`Pool::warm_up`] or the first synthesis, a background thread keeps `size` idle connections,
+/// replacing those older than the max age before their Sec-MS-GEC token expires or the service drops them. Each
+/// connection serves one turn. Clones share the connections; the thread stops once every clone is dropped.
+///
+/// With [`Pool::with_output_format`], speech.config is sent when connecting too, so that a synthesis only sends its
+/// SSML; [`SynthesisOutput::first_audio`] tells how long the first audio took.
+#[derive(Debug, Clone)]
+pub struct Pool {
+    client: Client,
+    size: usize,
+    max_age: Duration,
+    output_format: Option<String>,
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    idle: Mutex<Vec<Idle>>,
+    /// Notified when a connection is taken.
+    taken: Condvar,
+    started: AtomicBool,
+}
+
+struct Idle {
+    socket: WebSocket<Box<dyn Stream>>,
+    connection_id: String,
+    dump: Option<ConnectionDump>,
+    connected: Instant,
+    /// Output format of the speech.config already sent.
+    configured: Option<String>,
+    /// Of the client, to close it when the pool is dropped.
+    close_timeout: Duration,
+}
+
+impl fmt::Debug for Idle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Idle").field("connected", &self.connected).finish_non_exhaustive()
+    }
+}
+
+impl Pool {
+    /// Keep `size` connections of `client`, replaced after 2 minutes.
+    pub fn new(client: Client, size: usize) -> Self {
+        Self { client, size, max_age: Duration::from_secs(120), output_format: None, shared: Arc::default() }
+    }
+
+    /// Send speech.config for `output_format` when connecting rather than with each SSML. Syntheses in another
+    /// format still send their own.
+    ///
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn with_output_format(mut self, output_format: &str) -> Self {
+        self.output_format = Some(output_format.to_owned());
+        self
+    }
+
+    /// Replace idle connections after `max_age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Open connections until `size` are idle, and start keeping them.
+    pub fn warm_up(&self) -> Result<()> {
+        for _ in self.idle()..self.size {
+            let idle = self.open()?;
+            self.shared.lock().push(idle);
+        }
+        self.start();
+        Ok(())
+    }
+
+    /// Idle connections that aren't too old to be used.
+    pub fn idle(&self) -> usize {
+        self.shared.lock().iter().filter(|idle| idle.connected.elapsed() < self.max_age).count()
+    }
+
+    /// Like [`Client::synthesize`], on an idle connection if there is one. No fallback nor resume.
+    pub fn synthesize(&self, ssml: &str, output_format: &str) -> Result<SynthesisOutput> {
+        self.start();
+        let key = self.client.synth_key(ssml, output_format);
+        if let Some(output) = self.client.disk_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return self.client.post_process(output, output_format);
+        }
+        self.client.charge_quota(ssml)?;
+        let started = Instant::now();
+        let output = match self.take() {
+            Some(idle) => match self.run(idle, ssml, output_format, started) {
+                // The service may have dropped the connection while it was idle.
+                Err(e) if is_dropped(&e) => {
+                    trace_event!(debug, error = %e, "pooled connection dropped, connecting again");
+                    self.run(self.open()?, ssml, output_format, started)?
+                }
+                output => output?,
+            },
+            None => self.run(self.open()?, ssml, output_format, started)?,
+        };
+        if let Some(cache) = &self.client.disk_cache {
+            let _ = cache.put(&key, &output);
+        }
+        self.client.post_process(output, output_format)
+    }
+
+    /// Like [`Client::synthesize_request`], see [`Pool::synthesize`].
+    pub fn synthesize_request(&self, request: &SynthesisRequest) -> Result<SynthesisOutput> {
+        let request = self.client.prepare(request)?;
+        self.synthesize(&self.client.request_ssml(&request), request.output_format.as_str())
+    }
+
+    fn open(&self) -> Result<Idle> {
+        let dump = self.client.protocol_dump.as_ref().map(ProtocolDump::connection);
+        let (mut socket, connection_id) = self.client.connect(dump.as_ref())?;
+        if let Some(output_format) = &self.output_format {
+            let message = speech_config_message(&self.client.speech_config(output_format));
+            if let Some(dump) = &dump {
+                dump.sent(&message);
+            }
+            socket.send(message)?;
+        }
+        Ok(Idle { socket, connection_id, dump, connected: Instant::now(), configured: self.output_format.clone(), close_timeout: self.client.close_timeout() })
+    }
+
+    /// The oldest idle connection that isn't too old.
+    fn take(&self) -> Option<Idle> {
+        let mut idle = self.shared.lock();
+        idle.retain(|idle| idle.connected.elapsed() < self.max_age);
+        let taken = (!idle.is_empty()).then(|| idle.remove(0));
+        self.shared.taken.notify_all();
+        taken
+    }
+
+    /// Run a turn on `idle`, [`SynthesisOutput::first_audio`] counted from `started`.
+    fn run(&self, idle: Idle, ssml: &str, output_format: &str, started: Instant) -> Result<SynthesisOutput> {
+        if let Some(interval) = self.client.keep_alive {
+            idle.socket.get_ref().set_read_timeout(Some(interval))?;
+        }
+        let speech_config = (idle.configured.as_deref() != Some(output_format)).then(|| self.client.speech_config(output_format));
+        let mut turn = Turn::start(ssml, speech_config.as_deref(), idle.socket, idle.connection_id, idle.dump)?;
+        turn.observer = self.client.event_observer.clone();
+        turn.max_audio = self.client.max_audio_size;
+        turn.close_timeout = self.client.close_timeout();
+        turn.expected_format = self.client.strict_format.then(|| OutputFormat::new(output_format));
+        let mut output = SynthesisOutput::default();
+        let (_, ids, info) = process_turn(turn, &mut |event| {
+            match event {
+                SynthesisEvent::Audio(audio) => {
+                    output.first_audio.get_or_insert_with(|| started.elapsed());
+                    output.audio.extend_from_slice(audio)
+                }
+                SynthesisEvent::Boundaries(boundaries) => output.boundaries.extend(boundaries),
+            }
+            ControlFlow::Continue(())
+        })?;
+        output.ids = Some(ids);
+        output.turn_info = Some(info);
+        Ok(output)
+    }
+
+    fn start(&self) {
+        if self.shared.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let (pool, shared) = (Pool { shared: Arc::default(), ..self.clone() }, Arc::downgrade(&self.shared));
+        thread::spawn(move || pool.refill(shared));
+    }
+
+    /// Keep the idle connections of `shared` until it is dropped. `self` is a pool without connections of its own.
+    fn refill(&self, shared: Weak<Shared>) {
+        let mut failures = 0;
+        while let Some(shared) = shared.upgrade() {
+            let wait = if shared.lock().iter().filter(|idle| idle.connected.elapsed() < self.max_age).count() < self.size {
+                match self.open() {
+                    Ok(idle) => {
+                        failures = 0;
+                        let mut connections = shared.lock();
+                        connections.retain(|idle| idle.connected.elapsed() < self.max_age);
+                        connections.push(idle);
+                        continue;
+                    }
+                    Err(_) => {
+                        failures += 1;
+                        trace_event!(warn, failures, "failed to open a pooled connection");
+                        Duration::from_secs(1 << failures.min(5))
+                    }
+                }
+            } else {
+                // Until the oldest one is due for replacement.
+                shared.lock().first().map_or(self.max_age, |idle| self.max_age.saturating_sub(idle.connected.elapsed()))
+            };
+            // Woken early when a connection is taken; not for longer so that a dropped pool is noticed.
+            let connections = shared.lock();
+            let _ = shared.taken.wait_timeout(connections, wait.min(Duration::from_secs(1)));
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Vec<Idle>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Shared {
+    /// Close the idle connections properly.
+    fn drop(&mut self) {
+        for idle in self.lock().iter_mut() {
+            close(&mut idle.socket, idle.close_timeout);
+        }
+    }
+}
+
+/// Whether `error` is of a connection that was closed before the turn.
+fn is_dropped(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<Error>(), Some(Error::ConnectionClosedByServer { .. })) || error.downcast_ref::<tungstenite::Error>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn uses_idle_connections() {
+        let dropped = vec![MockReply::Close { code: 1000, reason: "idle".to_owned() }];
+        let server = MockServer::start(vec![dropped, MockReply::turn(b"audio")]).unwrap();
+        let pool = Pool::new(server.client(), 2).with_output_format("audio-24khz-48kbitrate-mono-mp3");
+        pool.warm_up().unwrap();
+        assert_eq!(pool.idle(), 2);
+        let output = pool.synthesize_request(&SynthesisRequest::new("Hello", "en-US-AriaNeural")).unwrap();
+        assert_eq!(output.audio, b"audio");
+        assert!(output.first_audio.is_some());
+        assert!(server.requests()[0].speech_config.contains("audio-24khz-48kbitrate-mono-mp3"));
+        assert_eq!(pool.synthesize_request(&SynthesisRequest::new("Again", "en-US-AriaNeural")).unwrap().audio, b"audio");
+        assert!(server.requests().iter().any(|r| r.ssml.contains(">Again<")));
+    }
+}
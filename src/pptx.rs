@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use xml::reader::{EventReader, XmlEvent};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::{Client, SynthesisRequest};
+
+const REL_SLIDE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide";
+const REL_NOTES_SLIDE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesSlide";
+const REL_AUDIO: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/audio";
+const REL_IMAGE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+const REL_MEDIA: &str = "http://schemas.microsoft.com/office/2007/relationships/media";
+
+/// Transparent 1×1 PNG used as the icon of embedded audio.
+const ICON_PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x00\x01\x00\x00\x00\x01\x08\x06\x00\x00\x00\x1f\x15\xc4\x89\x00\x00\x00\rIDATx\x9cc\x00\x01\x00\x00\x05\x00\x01\r\n-\xb4\x00\x00\x00\x00IEND\xaeB`\x82";
+const ICON_PATH: &str = "ppt/media/edge-tts-icon.png";
+
+/// Speaker notes of one slide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlideNotes {
+    /// 1-based position in the deck.
+    pub slide: usize,
+    /// Zip path of the slide part, eg: "ppt/slides/slide3.xml"
+    pub part: String,
+    /// Paragraphs joined with '\n', empty if the slide has no notes.
+    pub text: String,
+}
+
+/// Speaker notes of every slide of the PPTX at `path`, in presentation order.
+pub fn read_speaker_notes(path: &Path) -> Result<Vec<SlideNotes>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let presentation = read_entry(&mut archive, "ppt/presentation.xml")?;
+    let rels = parse_rels(&read_entry(&mut archive, "ppt/_rels/presentation.xml.rels")?, "ppt")?;
+    let mut notes = Vec::new();
+    for (i, id) in slide_ids(&presentation)?.iter().enumerate() {
+        let part = match rels.get(id) {
+            Some((kind, target)) if kind == REL_SLIDE => target.clone(),
+            _ => bail!("presentation.xml: no slide relationship {}", id),
+        };
+        let (dir, name) = part.rsplit_once('/').unwrap_or(("", &part));
+        let slide_rels = match read_entry(&mut archive, &format!("{}/_rels/{}.rels", dir, name)) {
+            Ok(xml) => parse_rels(&xml, dir)?,
+            Err(_) => HashMap::new(),
+        };
+        let text = match slide_rels.values().find(|(kind, _)| kind == REL_NOTES_SLIDE) {
+            Some((_, notes_part)) => notes_text(&read_entry(&mut archive, notes_part)?)?,
+            None => String::new(),
+        };
+        notes.push(SlideNotes { slide: i + 1, part, text });
+    }
+    Ok(notes)
+}
+
+fn read_entry<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String> {
+    let mut s = String::new();
+    archive.by_name(name).with_context(|| format!("pptx has no {}", name))?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+/// `r:id`s of `<p:sldId>` in order.
+fn slide_ids(presentation: &str) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for event in EventReader::from_str(presentation) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event? {
+            if name.local_name == "sldId" {
+                if let Some(id) = attributes.iter().find(|a| a.name.local_name == "id" && a.name.prefix.as_deref() == Some("r")) {
+                    ids.push(id.value.clone());
+                }
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Relationship id → (type, zip path), with targets resolved against `dir`.
+fn parse_rels(rels: &str, dir: &str) -> Result<HashMap<String, (String, String)>> {
+    let mut map = HashMap::new();
+    for event in EventReader::from_str(rels) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event? {
+            if name.local_name == "Relationship" {
+                let attr = |key: &str| attributes.iter().find(|a| a.name.local_name == key).map(|a| a.value.clone());
+                if let (Some(id), Some(kind), Some(target)) = (attr("Id"), attr("Type"), attr("Target")) {
+                    map.insert(id, (kind, resolve_target(dir, &target)));
+                }
+            }
+        }
+    }
+    Ok(map)
+}
+
+fn resolve_target(dir: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_owned();
+    }
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in target.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Text of the body placeholder of a notes slide. Slide number and thumbnail placeholders are skipped.
+fn notes_text(notes: &str) -> Result<String> {
+    let mut paragraphs = Vec::new();
+    let mut in_body = false;
+    let mut in_text = false;
+    let mut paragraph = String::new();
+    for event in EventReader::from_str(notes) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "sp" => in_body = false,
+                "ph" => in_body = attributes.iter().any(|a| a.name.local_name == "type" && a.value == "body"),
+                "t" => in_text = in_body,
+                "br" if in_body => paragraph.push('\n'),
+                _ => {}
+            },
+            XmlEvent::Characters(s) | XmlEvent::Whitespace(s) if in_text => paragraph.push_str(&s),
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "t" => in_text = false,
+                "p" if in_body => {
+                    let text = std::mem::take(&mut paragraph);
+                    if !text.trim().is_empty() {
+                        paragraphs.push(text.trim().to_owned());
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(paragraphs.join("\n"))
+}
+
+/// Narration audio of one slide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlideNarration {
+    pub slide: usize,
+    pub part: String,
+    pub text: String,
+    pub file: PathBuf,
+    /// Offset in the concatenated narration of the whole deck.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Result of [`narrate_pptx`], written as `manifest.json` next to the audio files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NarrationManifest {
+    pub slides: Vec<SlideNarration>,
+}
+
+impl NarrationManifest {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "slides": self.slides.iter().map(|s| json!({
+                "slide": s.slide,
+                "file": s.file.file_name().map(|f| f.to_string_lossy().into_owned()),
+                "text": s.text,
+                "start_ms": s.start.as_millis() as u64,
+                "duration_ms": s.duration.as_millis() as u64,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Synthesize the speaker notes of every slide of `pptx` with the voice, prosody and format of `template`, writing
+/// `slide-NNN.<ext>` files and `manifest.json` to `out_dir`. Slides without notes are skipped.
+pub fn narrate_pptx(client: &Client, pptx: &Path, template: &SynthesisRequest, out_dir: &Path, concurrency: usize) -> Result<NarrationManifest> {
+    let notes: Vec<SlideNotes> = read_speaker_notes(pptx)?.into_iter().filter(|n| !n.text.is_empty()).collect();
+    let requests = notes
+        .iter()
+        .map(|n| SynthesisRequest {
+            text: n.text.clone(),
+            ..template.clone()
+        })
+        .collect();
+    fs::create_dir_all(out_dir)?;
+    let format = &template.output_format;
+    let mut manifest = NarrationManifest::default();
+    let mut start = Duration::ZERO;
+    for (notes, result) in notes.into_iter().zip(client.synthesize_batch(requests, concurrency)) {
+        let output = result.with_context(|| format!("slide {}", notes.slide))?;
+        let duration = output.duration.unwrap_or_default();
+        let file = out_dir.join(format!("slide-{:03}.{}", notes.slide, format.extension()));
+        fs::write(&file, &output.audio)?;
+        manifest.slides.push(SlideNarration {
+            slide: notes.slide,
+            part: notes.part,
+            text: notes.text,
+            file,
+            start,
+            duration,
+        });
+        start += duration;
+    }
+    fs::write(out_dir.join("manifest.json"), serde_json::to_string_pretty(&manifest.to_json())?)?;
+    Ok(manifest)
+}
+
+/// Copy `pptx` to `output` with each slide's narration of `manifest` embedded as an audio object. MP3 narration only.
+pub fn embed_narration(pptx: &Path, manifest: &NarrationManifest, output: &Path) -> Result<()> {
+    if pptx == output {
+        bail!("output must differ from the input pptx");
+    }
+    let mut archive = ZipArchive::new(File::open(pptx)?)?;
+    let mut replaced: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut added: Vec<(String, Vec<u8>)> = vec![(ICON_PATH.to_owned(), ICON_PNG.to_vec())];
+    for slide in &manifest.slides {
+        if slide.file.extension().and_then(|e| e.to_str()) != Some("mp3") {
+            bail!("slide {}: only mp3 narration can be embedded", slide.slide);
+        }
+        let media = format!("ppt/media/edge-tts-slide{}.mp3", slide.slide);
+        added.push((media.clone(), fs::read(&slide.file)?));
+        let (dir, name) = slide.part.rsplit_once('/').ok_or_else(|| anyhow!("bad slide part {}", slide.part))?;
+        let rels_part = format!("{}/_rels/{}.rels", dir, name);
+        let rels = read_entry(&mut archive, &rels_part).unwrap_or_else(|_| {
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"></Relationships>"#.to_owned()
+        });
+        let media_target = format!("../media/edge-tts-slide{}.mp3", slide.slide);
+        let rels = insert_before(&rels, "</Relationships>", &format!(
+            r#"<Relationship Id="rIdEdgeTtsAudio" Type="{}" Target="{}"/><Relationship Id="rIdEdgeTtsMedia" Type="{}" Target="{}"/><Relationship Id="rIdEdgeTtsIcon" Type="{}" Target="../media/edge-tts-icon.png"/>"#,
+            REL_AUDIO, media_target, REL_MEDIA, media_target, REL_IMAGE
+        ))?;
+        let xml = read_entry(&mut archive, &slide.part)?;
+        let xml = insert_before(&xml, "</p:spTree>", &audio_pic(next_shape_id(&xml)))?;
+        replaced.insert(rels_part, rels.into_bytes());
+        replaced.insert(slide.part.clone(), xml.into_bytes());
+    }
+    let content_types = read_entry(&mut archive, "[Content_Types].xml")?;
+    let mut defaults = String::new();
+    for (extension, content_type) in [("mp3", "audio/mpeg"), ("png", "image/png")] {
+        if !content_types.contains(&format!("Extension=\"{}\"", extension)) {
+            defaults.push_str(&format!(r#"<Default Extension="{}" ContentType="{}"/>"#, extension, content_type));
+        }
+    }
+    replaced.insert("[Content_Types].xml".to_owned(), insert_before(&content_types, "</Types>", &defaults)?.into_bytes());
+
+    let tmp = output.with_extension("tmp");
+    let mut writer = ZipWriter::new(File::create(&tmp)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut copied = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name().to_owned();
+        if added.iter().any(|(added, _)| *added == name) {
+            continue;
+        }
+        match replaced.get(&name) {
+            Some(data) => {
+                drop(entry);
+                writer.start_file(name.as_str(), options)?;
+                writer.write_all(data)?;
+            }
+            None => writer.raw_copy_file(entry)?,
+        }
+        copied.insert(name);
+    }
+    // Added media, and relationship parts of slides which had none.
+    let new_parts = replaced.iter().filter(|(name, _)| !copied.contains(*name));
+    for (name, data) in added.iter().map(|(name, data)| (name, data)).chain(new_parts) {
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(data)?;
+    }
+    writer.finish()?;
+    fs::rename(&tmp, output)?;
+    Ok(())
+}
+
+fn insert_before(xml: &str, closing_tag: &str, content: &str) -> Result<String> {
+    let at = xml.rfind(closing_tag).ok_or_else(|| anyhow!("missing {}", closing_tag))?;
+    Ok(format!("{}{}{}", &xml[..at], content, &xml[at..]))
+}
+
+/// One more than the largest shape id of a slide.
+fn next_shape_id(slide: &str) -> u32 {
+    slide
+        .match_indices("<p:cNvPr id=\"")
+        .filter_map(|(at, tag)| slide[at + tag.len()..].split('"').next()?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(1)
+        + 1
+}
+
+fn audio_pic(id: u32) -> String {
+    format!(
+        concat!(
+            r#"<p:pic><p:nvPicPr><p:cNvPr id="{}" name="Narration"><a:hlinkClick r:id="" action="ppaction://media"/></p:cNvPr>"#,
+            r#"<p:cNvPicPr><a:picLocks noChangeAspect="1"/></p:cNvPicPr><p:nvPr><a:audioFile r:link="rIdEdgeTtsAudio"/>"#,
+            r#"<p:extLst><p:ext uri="{{DAA4B4D4-6D71-4841-9C94-3DA282948610}}"><p14:media xmlns:p14="http://schemas.microsoft.com/office/powerpoint/2010/main" r:embed="rIdEdgeTtsMedia"/></p:ext></p:extLst>"#,
+            r#"</p:nvPr></p:nvPicPr><p:blipFill><a:blip r:embed="rIdEdgeTtsIcon"/><a:stretch><a:fillRect/></a:stretch></p:blipFill>"#,
+            r#"<p:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="457200" cy="457200"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></p:spPr></p:pic>"#,
+        ),
+        id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_notes_body() {
+        let notes = r#"<p:notes xmlns:a="a" xmlns:p="p"><p:cSld><p:spTree>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="sldImg"/></p:nvPr></p:nvSpPr></p:sp>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:txBody>
+                <a:p><a:r><a:t>Welcome to </a:t></a:r><a:r><a:t>the course.</a:t></a:r></a:p><a:p></a:p><a:p><a:r><a:t>Next: setup.</a:t></a:r></a:p>
+            </p:txBody></p:sp>
+            <p:sp><p:nvSpPr><p:nvPr><p:ph type="sldNum"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>3</a:t></a:r></a:p></p:txBody></p:sp>
+        </p:spTree></p:cSld></p:notes>"#;
+        assert_eq!(notes_text(notes).unwrap(), "Welcome to the course.\nNext: setup.");
+        assert_eq!(resolve_target("ppt/slides", "../notesSlides/notesSlide1.xml"), "ppt/notesSlides/notesSlide1.xml");
+        assert_eq!(next_shape_id(r#"<p:cNvPr id="1" name=""/><p:cNvPr id="7" name="Title"/>"#), 8);
+    }
+}
@@ -0,0 +1,74 @@
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::resume::cut_len;
+use crate::synthesize::SynthesisEvent;
+use crate::{Client, SynthesisOutput, SynthesisRequest};
+
+impl Client {
+    /// Synthesize only about the first `max_seconds` of `request`: the connection is closed once that much audio has
+    /// arrived, eg: for voice samples in a voice picker.
+    ///
+    /// Needs a format with a known bitrate (MP3 or raw PCM). Previews aren't cached.
+    pub fn synthesize_preview(&self, request: &SynthesisRequest, max_seconds: f64) -> Result<SynthesisOutput> {
+        let request = &*self.prepare(request)?;
+        let format = &request.output_format;
+        let max = Duration::try_from_secs_f64(max_seconds.max(0.0)).map_err(|e| anyhow!("bad preview length: {}", e))?;
+        let budget = match format.bitrate() {
+            Some(bitrate) if cut_len(format, &[], Duration::ZERO).is_some() => (max.as_secs_f64() * bitrate as f64 / 8.0) as usize,
+            _ => bail!("preview needs an mp3 or raw pcm output format, not {}", format),
+        };
+        let mut output = SynthesisOutput::default();
+        self.connect_and_stream(&self.request_ssml(request), format.as_str(), &mut |event| {
+            match event {
+                SynthesisEvent::Audio(audio) => output.audio.extend_from_slice(audio),
+                SynthesisEvent::Boundaries(boundaries) => output.boundaries.extend(boundaries),
+            }
+            if output.audio.len() >= budget {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })?;
+        if let Some(len) = cut_len(format, &output.audio, max) {
+            output.audio.truncate(len);
+        }
+        output.boundaries.retain(|b| b.offset < max);
+        Ok(output.with_duration(format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn refuses_formats_and_lengths_it_cant_cut() {
+        let request = SynthesisRequest::new("Hello there.", "en-US-AriaNeural");
+        // Both fail before connecting.
+        let error = Client::new().synthesize_preview(&request.clone().with_output_format("webm-24khz-16bit-mono-opus"), 1.0).unwrap_err();
+        assert!(error.to_string().contains("needs an mp3 or raw pcm"), "{}", error);
+        let error = Client::new().synthesize_preview(&request, f64::INFINITY).unwrap_err();
+        assert!(error.to_string().contains("bad preview length"), "{}", error);
+    }
+
+    #[test]
+    fn stops_at_the_preview_length() {
+        // 24 kHz 16-bit mono is 48000 bytes/s: 0.1 s is 4800 bytes.
+        let audio = MockReply::Audio(vec![1; 3000]);
+        let server = MockServer::start(vec![vec![audio.clone(), audio.clone(), MockReply::Wait(Duration::from_secs(3)), audio, MockReply::TurnEnd]]).unwrap();
+        let client = server.client().with_close_timeout(Duration::from_millis(200));
+        let request = SynthesisRequest::new("Hello there.", "en-US-AriaNeural").with_output_format("raw-24khz-16bit-mono-pcm");
+        let started = Instant::now();
+        let output = client.synthesize_preview(&request, 0.1).unwrap();
+        // Without waiting for the rest of the turn, nor for the acknowledgement of the Close.
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        assert_eq!(output.audio.len(), 4800);
+        assert_eq!(output.duration, Some(Duration::from_millis(100)));
+    }
+}
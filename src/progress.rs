@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::audiobook::chunk_text;
+use crate::concat::can_concat;
+use crate::{concat_audio, BoundaryKind, Client, ConcatOptions, SynthesisOutput, SynthesisRequest};
+
+/// Where [`Client::synthesize_long`] is, reported after each chunk, eg: for a progress bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Of the chunk just synthesized, from 0.
+    pub chunk_index: usize,
+    pub total_chunks: usize,
+    /// Audio of the chunks so far.
+    pub bytes_so_far: u64,
+    /// Of the last word so far, in the audio of the chunks so far before they are joined. `None` without word
+    /// boundaries.
+    pub last_word_offset: Option<Duration>,
+}
+
+impl Progress {
+    /// Chunks done out of all, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        (self.chunk_index + 1) as f64 / self.total_chunks.max(1) as f64
+    }
+}
+
+impl Client {
+    /// Like [`Client::synthesize_request`] for text of any length: it is synthesized in chunks of at most
+    /// `max_chunk_chars` characters, split after sentence ends, calling `on_progress` after each, then joined with
+    /// [`concat_audio`], so only MP3 and 16-bit PCM can have several chunks; other formats fail before the first.
+    pub fn synthesize_long(&self, request: &SynthesisRequest, max_chunk_chars: usize, mut on_progress: impl FnMut(&Progress)) -> Result<SynthesisOutput> {
+        let chunks = chunk_text(&request.text, max_chunk_chars);
+        if chunks.len() > 1 && !can_concat(&request.output_format) {
+            bail!("can't join {} chunks of {}, use an mp3 or 16-bit pcm format", chunks.len(), request.output_format);
+        }
+        let mut parts = Vec::with_capacity(chunks.len());
+        let mut progress = Progress { chunk_index: 0, total_chunks: chunks.len(), bytes_so_far: 0, last_word_offset: None };
+        let mut elapsed = Duration::ZERO;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let output = self.synthesize_request(&SynthesisRequest { text: chunk, ..request.clone() })?;
+            let last_word = output.boundaries.iter().filter(|b| b.kind == BoundaryKind::Word).map(|b| b.offset).max();
+            progress.chunk_index = i;
+            progress.bytes_so_far += output.audio.len() as u64;
+            progress.last_word_offset = last_word.map(|offset| elapsed + offset).or(progress.last_word_offset);
+            elapsed += output.duration.unwrap_or_default();
+            on_progress(&progress);
+            parts.push(output);
+        }
+        match parts.len() {
+            0 => Ok(SynthesisOutput::default()),
+            1 => Ok(parts.remove(0)),
+            _ => concat_audio(parts, &request.output_format, &ConcatOptions::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::VoicePresets;
+
+    #[test]
+    fn reports_progress() {
+        let loud = [0, 64].repeat(2400);
+        let word = MockReply::Word { text: "Two".to_owned(), offset: Duration::from_millis(20), duration: Duration::from_millis(50) };
+        let server = MockServer::start(vec![MockReply::turn(&loud), vec![word, MockReply::Audio(loud.clone()), MockReply::TurnEnd]]).unwrap();
+        let client = server.client().with_voice_presets(VoicePresets::empty());
+        let request = SynthesisRequest::new("One. Two.", "en-US-AriaNeural").with_output_format("raw-24khz-16bit-mono-pcm");
+        let mut reports = Vec::new();
+        let output = client.synthesize_long(&request, 5, |progress| reports.push(*progress)).unwrap();
+        assert_eq!(server.requests().len(), 2);
+        assert!(server.requests()[1].ssml.contains(">Two.<"));
+        assert_eq!(
+            reports,
+            [
+                Progress { chunk_index: 0, total_chunks: 2, bytes_so_far: 4800, last_word_offset: None },
+                Progress { chunk_index: 1, total_chunks: 2, bytes_so_far: 9600, last_word_offset: Some(Duration::from_millis(120)) },
+            ]
+        );
+        assert_eq!(reports[1].fraction(), 1.0);
+        assert!(!output.audio.is_empty());
+
+        // Refused before anything is synthesized.
+        let webm = request.clone().with_output_format("webm-24khz-16bit-mono-opus");
+        assert!(client.synthesize_long(&webm, 5, |_| {}).is_err());
+        assert_eq!(server.requests().len(), 2);
+        assert!(client.synthesize_long(&SynthesisRequest { text: "One.".to_owned(), ..webm }, 5, |_| {}).is_ok());
+    }
+}
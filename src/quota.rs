@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::Error;
+
+/// Characters of text submitted per fixed time window, by tenant, shared by every client using it, see
+/// [`crate::Client::with_quota`].
+///
+/// With a limit, each tenant has its own budget per window: a submission over it fails with
+/// [`Error::QuotaExceeded`], or waits for the next window with [`CharacterQuota::with_waiting`].
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use edge_tts::{CharacterQuota, Client};
+///
+/// let quota = Arc::new(CharacterQuota::new(Duration::from_secs(3600)).with_limit(100_000));
+/// let client = Client::new().with_quota(quota.clone(), "tenant-a");
+/// println!("{:?}", quota.usage("tenant-a"));
+/// ```
+#[derive(Debug)]
+pub struct CharacterQuota {
+    window: Duration,
+    limit: Option<u64>,
+    wait: bool,
+    tenants: Mutex<HashMap<String, Counter>>,
+}
+
+#[derive(Debug)]
+struct Counter {
+    window_start: Instant,
+    window_chars: u64,
+    total_chars: u64,
+}
+
+/// Counters of a tenant, see [`CharacterQuota::usage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    /// In the current window.
+    pub window_chars: u64,
+    /// Since the quota was created.
+    pub total_chars: u64,
+    /// Until the current window ends, zero if none started.
+    pub window_remaining: Duration,
+}
+
+impl CharacterQuota {
+    /// Count characters per `window`, without a limit.
+    pub fn new(window: Duration) -> Self {
+        Self { window, limit: None, wait: false, tenants: Mutex::new(HashMap::new()) }
+    }
+
+    /// `characters` per tenant and window.
+    pub fn with_limit(mut self, characters: u64) -> Self {
+        self.limit = Some(characters);
+        self
+    }
+
+    /// Wait for the next window instead of failing when over the limit.
+    pub fn with_waiting(mut self) -> Self {
+        self.wait = true;
+        self
+    }
+
+    pub fn usage(&self, tenant: &str) -> QuotaUsage {
+        self.usage_at(tenant, Instant::now())
+    }
+
+    /// Usage of every tenant that submitted text, by name.
+    pub fn tenants(&self) -> Vec<(String, QuotaUsage)> {
+        let now = Instant::now();
+        let names: Vec<String> = self.lock().keys().cloned().collect();
+        let mut tenants: Vec<(String, QuotaUsage)> = names.into_iter().map(|name| (name.clone(), self.usage_at(&name, now))).collect();
+        tenants.sort_by(|a, b| a.0.cmp(&b.0));
+        tenants
+    }
+
+    /// Count `characters` for `tenant` if within the limit, otherwise return how long until the window ends, or
+    /// `None` if they would never fit.
+    pub fn try_charge(&self, tenant: &str, characters: u64) -> Result<(), Option<Duration>> {
+        self.try_charge_at(tenant, characters, Instant::now())
+    }
+
+    pub(crate) fn charge(&self, tenant: &str, characters: u64) -> Result<()> {
+        loop {
+            match self.try_charge(tenant, characters) {
+                Ok(()) => return Ok(()),
+                Err(Some(wait)) if self.wait => thread::sleep(wait),
+                Err(_) => {
+                    let used = self.usage(tenant).window_chars;
+                    return Err(Error::QuotaExceeded { tenant: tenant.to_owned(), used, characters, limit: self.limit.unwrap_or_default() }.into());
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Counter>> {
+        self.tenants.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn usage_at(&self, tenant: &str, now: Instant) -> QuotaUsage {
+        match self.lock().get_mut(tenant) {
+            Some(counter) => {
+                self.roll(counter, now);
+                QuotaUsage {
+                    window_chars: counter.window_chars,
+                    total_chars: counter.total_chars,
+                    window_remaining: (counter.window_start + self.window).saturating_duration_since(now),
+                }
+            }
+            None => QuotaUsage::default(),
+        }
+    }
+
+    /// Start a new window if the current one is over.
+    fn roll(&self, counter: &mut Counter, now: Instant) {
+        if now.saturating_duration_since(counter.window_start) >= self.window {
+            counter.window_start = now;
+            counter.window_chars = 0;
+        }
+    }
+
+    fn try_charge_at(&self, tenant: &str, characters: u64, now: Instant) -> Result<(), Option<Duration>> {
+        let mut tenants = self.lock();
+        let counter = tenants.entry(tenant.to_owned()).or_insert(Counter { window_start: now, window_chars: 0, total_chars: 0 });
+        self.roll(counter, now);
+        if let Some(limit) = self.limit {
+            if characters > limit {
+                return Err(None);
+            }
+            if counter.window_chars + characters > limit {
+                return Err(Some((counter.window_start + self.window).saturating_duration_since(now)));
+            }
+        }
+        counter.window_chars += characters;
+        counter.total_chars += characters;
+        Ok(())
+    }
+}
+
+/// Characters of the text of `ssml`, an entity counting as one.
+pub(crate) fn text_chars(ssml: &str) -> u64 {
+    let (mut count, mut in_tag, mut in_entity) = (0, false, false);
+    for c in ssml.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if in_tag => {}
+            '&' => {
+                in_entity = true;
+                count += 1;
+            }
+            ';' if in_entity => in_entity = false,
+            _ if in_entity => {}
+            _ => count += 1,
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_limits_per_window() {
+        assert_eq!(text_chars("<speak><voice name=\"a\">Tom &amp; Jerry</voice></speak>"), 11);
+        let quota = CharacterQuota::new(Duration::from_secs(60)).with_limit(10);
+        let start = Instant::now();
+        assert!(quota.try_charge_at("a", 6, start).is_ok());
+        assert!(quota.try_charge_at("b", 10, start).is_ok());
+        assert_eq!(quota.try_charge_at("a", 6, start + Duration::from_secs(20)), Err(Some(Duration::from_secs(40))));
+        assert_eq!(quota.try_charge_at("a", 11, start), Err(None));
+        assert!(quota.try_charge_at("a", 6, start + Duration::from_secs(60)).is_ok());
+        assert_eq!(quota.usage_at("a", start + Duration::from_secs(70)), QuotaUsage { window_chars: 6, total_chars: 12, window_remaining: Duration::from_secs(50) });
+        assert_eq!(quota.tenants().iter().map(|(name, usage)| (name.as_str(), usage.total_chars)).collect::<Vec<_>>(), [("a", 12), ("b", 10)]);
+        let error = quota.charge("b", 1).unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::QuotaExceeded { used: 10, limit: 10, .. })), "{}", error);
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting how often connections are opened, shared by every clone of a [`crate::Client`] using it.
+///
+/// ```
+/// use std::sync::Arc;
+/// use edge_tts::{Client, RateLimiter};
+///
+/// let client = Client::new().with_rate_limiter(Arc::new(RateLimiter::per_minute(60)));
+/// ```
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    burst: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// `requests` per `period`, with bursts of up to `requests`.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1) as f64;
+        Self {
+            rate: requests / period.as_secs_f64().max(f64::MIN_POSITIVE),
+            burst: requests,
+            state: Mutex::new(Bucket {
+                tokens: requests,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Allow at most `burst` requests back to back instead of a full period's worth.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1) as f64;
+        let bucket = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        bucket.tokens = bucket.tokens.min(self.burst);
+        self
+    }
+
+    /// Take a token if one is available, otherwise return how long until one is, eg: to sleep on in async code.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Block until a token is available and take it.
+    pub fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            thread::sleep(wait);
+        }
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = bucket.updated.max(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refills_at_rate() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(1)).with_burst(2);
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at(start).is_ok());
+        assert!(limiter.try_acquire_at(start).is_ok());
+        let wait = limiter.try_acquire_at(start).unwrap_err();
+        assert!(wait > Duration::from_millis(490) && wait <= Duration::from_millis(500));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(500)).is_ok());
+        // Idle time refills no more than the burst.
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.try_acquire_at(later).is_ok());
+        assert!(limiter.try_acquire_at(later).is_ok());
+        assert!(limiter.try_acquire_at(later).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::io::{self, Read};
+
+use anyhow::Result;
+
+use crate::metadata::Boundary;
+use crate::stream::Stream;
+use crate::synthesize::{Turn, SynthesisEvent};
+use crate::{Client, SynthesisRequest};
+
+/// Audio of one synthesis as a blocking [`Read`], eg: for `rodio::Decoder::new` or [`std::io::copy`]. Each read
+/// waits for the next audio message only when the previous one has been read, so nothing is buffered ahead.
+///
+/// Reads end (return 0) at the end of the turn. Dropping the reader early closes the connection.
+pub struct AudioReader {
+    turn: Turn<Box<dyn Stream>>,
+    chunk: Vec<u8>,
+    /// Bytes of `chunk` already read.
+    position: usize,
+    boundaries: Vec<Boundary>,
+}
+
+impl AudioReader {
+    /// Boundaries received so far.
+    pub fn boundaries(&self) -> &[Boundary] {
+        &self.boundaries
+    }
+
+    /// Read the next audio message into `chunk`. `false` at the end of the turn.
+    fn fill(&mut self) -> Result<bool> {
+        loop {
+            match self.turn.next_event()? {
+                Some(SynthesisEvent::Audio(audio)) => {
+                    self.chunk.clear();
+                    self.chunk.extend_from_slice(audio);
+                    self.position = 0;
+                    return Ok(true);
+                }
+                Some(SynthesisEvent::Boundaries(boundaries)) => self.boundaries.extend(boundaries),
+                None => return Ok(false),
+            }
+        }
+    }
+}
+
+impl Read for AudioReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            if buf.is_empty() || !self.fill().map_err(io::Error::other)? {
+                return Ok(0);
+            }
+        }
+        let available = self.chunk.get(self.position..).unwrap_or_default();
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl Drop for AudioReader {
+    fn drop(&mut self) {
+        self.turn.stop();
+    }
+}
+
+impl Client {
+    /// Connect and send `request`, returning a reader over its audio. The disk cache and post-processing aren't used.
+    pub fn synthesize_reader(&self, request: &SynthesisRequest) -> Result<AudioReader> {
+        let request = self.prepare(request)?;
+        Ok(AudioReader {
+            turn: self.start_turn(&self.request_ssml(&request), request.output_format.as_str())?,
+            chunk: Vec::new(),
+            position: 0,
+            boundaries: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn reads_audio_messages_lazily() {
+        let word = MockReply::Word { text: "Hi".to_owned(), offset: Duration::from_millis(50), duration: Duration::from_millis(100) };
+        let server = MockServer::start(vec![vec![MockReply::Audio(b"abc".to_vec()), word, MockReply::Audio(b"de".to_vec()), MockReply::Audio(b"f".to_vec()), MockReply::TurnEnd]]).unwrap();
+        let mut reader = server.client().synthesize_reader(&SynthesisRequest::new("Hi", "en-US-AriaNeural")).unwrap();
+        // Reads stop at the end of each message, and an empty buffer reads nothing without waiting for one.
+        let mut start = [0; 4];
+        assert_eq!(reader.read(&mut start).unwrap(), 3);
+        assert_eq!(&start[..3], b"abc");
+        assert_eq!(reader.read(&mut []).unwrap(), 0);
+        assert!(reader.boundaries().is_empty());
+
+        let mut rest = Vec::new();
+        io::copy(&mut reader, &mut rest).unwrap();
+        assert_eq!(rest, b"def");
+        assert_eq!(reader.boundaries().iter().map(|b| b.text.as_str()).collect::<Vec<_>>(), ["Hi"]);
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+        drop(reader);
+        assert_eq!(server.closes(), 1);
+
+        // Dropped after the first bytes, before the end of the turn.
+        let server = MockServer::start(vec![vec![MockReply::Audio(b"abc".to_vec()), MockReply::Wait(Duration::from_millis(200)), MockReply::Audio(b"de".to_vec()), MockReply::TurnEnd]]).unwrap();
+        let mut reader = server.client().synthesize_reader(&SynthesisRequest::new("Hi", "en-US-AriaNeural")).unwrap();
+        reader.read_exact(&mut [0; 2]).unwrap();
+        assert_eq!(server.closes(), 0);
+        drop(reader);
+        assert_eq!(server.closes(), 1);
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, bail, Result};
+
+const EBML: u32 = 0x1a45_dfa3;
+const SEGMENT: u32 = 0x1853_8067;
+const TRACKS: u32 = 0x1654_ae6b;
+const TRACK_ENTRY: u32 = 0xae;
+const TRACK_NUMBER: u32 = 0xd7;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63a2;
+const CODEC_DELAY: u32 = 0x56aa;
+const AUDIO: u32 = 0xe1;
+const CHANNELS: u32 = 0x9f;
+const CLUSTER: u32 = 0x1f43_b675;
+const BLOCK_GROUP: u32 = 0xa0;
+const BLOCK: u32 = 0xa1;
+const SIMPLE_BLOCK: u32 = 0xa3;
+
+/// Elements whose children are read in place, so that the unknown sizes of streamed segments and clusters
+/// don't matter.
+const MASTERS: [u32; 6] = [SEGMENT, TRACKS, TRACK_ENTRY, AUDIO, CLUSTER, BLOCK_GROUP];
+
+/// Bitstream serial number of the Ogg streams written.
+const SERIAL: u32 = 0x6564_6765;
+
+/// Most packets per Ogg page, about a second of 20 ms packets.
+const PAGE_PACKETS: usize = 50;
+
+#[derive(Debug, Default)]
+struct TrackEntry {
+    number: u64,
+    opus: bool,
+    opus_head: Option<Vec<u8>>,
+    /// Nanoseconds.
+    codec_delay: u64,
+    channels: u8,
+}
+
+/// Repackage the Opus packets of a WebM stream, eg: of "webm-24khz-16bit-mono-opus", into an Ogg Opus stream
+/// (`.ogg`/`.opus`), for tools that only take that. Nothing is decoded.
+///
+/// Only the first Opus track is kept. Laced blocks aren't supported; the service doesn't send any.
+pub fn webm_to_ogg_opus(webm: &[u8]) -> Result<Vec<u8>> {
+    let (tracks, blocks) = read_webm(webm)?;
+    let track = tracks.iter().find(|track| track.opus).ok_or_else(|| anyhow!("no Opus track in the WebM stream"))?;
+    let opus_head = match &track.opus_head {
+        Some(head) if head.starts_with(b"OpusHead") => head.clone(),
+        _ => opus_head(track)?,
+    };
+    let mut vendor = b"OpusTags".to_vec();
+    vendor.extend((env!("CARGO_PKG_NAME").len() as u32).to_le_bytes());
+    vendor.extend(env!("CARGO_PKG_NAME").as_bytes());
+    vendor.extend(0u32.to_le_bytes());
+
+    let mut ogg = Vec::new();
+    let mut sequence = 0;
+    write_page(&mut ogg, 0x02, 0, &mut sequence, &[&opus_head]);
+    write_page(&mut ogg, 0, 0, &mut sequence, &[&vendor]);
+    let packets: Vec<&[u8]> = blocks.into_iter().filter(|(number, _)| *number == track.number).map(|(_, packet)| packet).collect();
+    let mut granule_position = 0;
+    let mut page = Vec::new();
+    for (i, packet) in packets.iter().enumerate() {
+        granule_position += packet_samples(packet);
+        page.push(*packet);
+        let last = i + 1 == packets.len();
+        if last || page.len() == PAGE_PACKETS || segments(&page) + segments(packets.get(i + 1..i + 2).unwrap_or_default()) > 255 {
+            write_page(&mut ogg, if last { 0x04 } else { 0 }, granule_position, &mut sequence, &page);
+            page.clear();
+        }
+    }
+    if packets.is_empty() {
+        write_page(&mut ogg, 0x04, 0, &mut sequence, &[]);
+    }
+    Ok(ogg)
+}
+
+/// Track number and frame of a block.
+type Block<'a> = (u64, &'a [u8]);
+
+/// Tracks and every block.
+fn read_webm(data: &[u8]) -> Result<(Vec<TrackEntry>, Vec<Block<'_>>)> {
+    if element_id(data).map(|(id, _)| id) != Some(EBML) {
+        bail!("not a WebM stream");
+    }
+    let mut tracks: Vec<TrackEntry> = Vec::new();
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    while let Some(rest) = data.get(pos..).filter(|rest| !rest.is_empty()) {
+        let (id, id_len) = element_id(rest).ok_or_else(|| anyhow!("bad WebM element at byte {}", pos))?;
+        let (size, size_len) = vint(rest.get(id_len..).unwrap_or_default()).ok_or_else(|| anyhow!("bad WebM element size at byte {}", pos))?;
+        let start = pos + id_len + size_len;
+        if MASTERS.contains(&id) {
+            if id == TRACK_ENTRY {
+                tracks.push(TrackEntry::default());
+            }
+            pos = start;
+            continue;
+        }
+        let size = size.ok_or_else(|| anyhow!("WebM element {:x} of unknown size", id))? as usize;
+        let body = data.get(start..start.saturating_add(size)).ok_or_else(|| anyhow!("truncated WebM element {:x}", id))?;
+        let track = tracks.last_mut();
+        match (id, track) {
+            (TRACK_NUMBER, Some(track)) => track.number = uint(body),
+            (CODEC_ID, Some(track)) => track.opus = body == b"A_OPUS",
+            (CODEC_PRIVATE, Some(track)) => track.opus_head = Some(body.to_vec()),
+            (CODEC_DELAY, Some(track)) => track.codec_delay = uint(body),
+            (CHANNELS, Some(track)) => track.channels = uint(body) as u8,
+            (SIMPLE_BLOCK | BLOCK, _) => blocks.push(block(body)?),
+            _ => {}
+        }
+        pos = start + size;
+    }
+    Ok((tracks, blocks))
+}
+
+/// Track number and frame of a SimpleBlock or Block.
+fn block(body: &[u8]) -> Result<Block<'_>> {
+    let (track, len) = vint(body).and_then(|(track, len)| Some((track?, len))).ok_or_else(|| anyhow!("bad WebM block"))?;
+    // Then a 16-bit timecode and the flags.
+    let (flags, frame) = match body.get(len + 2..) {
+        Some([flags, frame @ ..]) => (*flags, frame),
+        _ => bail!("truncated WebM block"),
+    };
+    if flags & 0x06 != 0 {
+        bail!("laced WebM blocks aren't supported");
+    }
+    Ok((track, frame))
+}
+
+/// OpusHead for a track without one in its CodecPrivate.
+fn opus_head(track: &TrackEntry) -> Result<Vec<u8>> {
+    if !(1..=2).contains(&track.channels) {
+        bail!("WebM Opus track of {} channels without an OpusHead", track.channels);
+    }
+    let pre_skip = (track.codec_delay * 48_000 / 1_000_000_000) as u16;
+    let mut head = b"OpusHead\x01".to_vec();
+    head.push(track.channels);
+    head.extend(pre_skip.to_le_bytes());
+    head.extend(48_000u32.to_le_bytes());
+    head.extend([0, 0, 0]);
+    Ok(head)
+}
+
+/// ID of the element at the start of `data` with its length, marker bits kept.
+fn element_id(data: &[u8]) -> Option<(u32, usize)> {
+    let len = data.first()?.leading_zeros() as usize + 1;
+    if len > 4 {
+        return None;
+    }
+    let id = data.get(..len)?.iter().fold(0u32, |id, &b| id << 8 | b as u32);
+    Some((id, len))
+}
+
+/// Variable-length integer at the start of `data` with its length; `None` for the all-ones "unknown" value.
+fn vint(data: &[u8]) -> Option<(Option<u64>, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let marker = 1u64 << (7 * len);
+    let value = data.get(..len)?.iter().fold(0u64, |value, &b| value << 8 | b as u64) & (marker - 1);
+    Some(((value != marker - 1).then_some(value), len))
+}
+
+fn uint(body: &[u8]) -> u64 {
+    body.iter().fold(0, |value, &b| value << 8 | b as u64)
+}
+
+/// Samples at 48 kHz of an Opus packet, from its TOC byte.
+fn packet_samples(packet: &[u8]) -> u64 {
+    let Some(&toc) = packet.first() else { return 0 };
+    let config = toc >> 3;
+    // SILK, hybrid, then CELT modes, each with its frame sizes in turn.
+    let frame = match (config, config % 4) {
+        (0..=11, 0) | (12..=15, 0 | 2) | (16.., 2) => 480,
+        (0..=11, 1) | (12..=15, _) | (16.., 3) => 960,
+        (0..=11, 2) => 1920,
+        (0..=11, _) => 2880,
+        (_, 0) => 120,
+        _ => 240,
+    };
+    let frames = match toc & 0b11 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map_or(0, |&count| count & 0x3f) as u64,
+    };
+    frame * frames
+}
+
+/// Lacing values of `packets`.
+fn segments(packets: &[&[u8]]) -> usize {
+    packets.iter().map(|packet| packet.len() / 255 + 1).sum()
+}
+
+fn write_page(ogg: &mut Vec<u8>, header_type: u8, granule_position: u64, sequence: &mut u32, packets: &[&[u8]]) {
+    let start = ogg.len();
+    ogg.extend(b"OggS\x00");
+    ogg.push(header_type);
+    ogg.extend(granule_position.to_le_bytes());
+    ogg.extend(SERIAL.to_le_bytes());
+    ogg.extend(sequence.to_le_bytes());
+    // CRC, filled in below.
+    ogg.extend([0; 4]);
+    ogg.push(segments(packets) as u8);
+    for packet in packets {
+        ogg.extend(std::iter::repeat_n(255, packet.len() / 255));
+        ogg.push((packet.len() % 255) as u8);
+    }
+    for packet in packets {
+        ogg.extend_from_slice(packet);
+    }
+    let crc = ogg.get(start..).map_or(0, crc);
+    if let Some(field) = ogg.get_mut(start + 22..start + 26) {
+        field.copy_from_slice(&crc.to_le_bytes());
+    }
+    *sequence += 1;
+}
+
+/// CRC-32 of Ogg pages: polynomial 0x04c11db7, not reflected, starting from 0.
+fn crc(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &b| {
+        (0..8).fold(crc ^ (b as u32) << 24, |crc, _| if crc & 0x8000_0000 != 0 { crc << 1 ^ 0x04c1_1db7 } else { crc << 1 })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ogg::{opus_duration_samples, packets};
+
+    fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut element = id.to_vec();
+        element.push(0x80 | body.len() as u8);
+        element.extend(body);
+        element
+    }
+
+    #[test]
+    fn remuxes_webm_opus() {
+        let mut head = b"OpusHead\x01\x01".to_vec();
+        head.extend(312u16.to_le_bytes());
+        head.extend([0x80, 0xbb, 0, 0, 0, 0, 0]);
+        let entry = [element(&[0xd7], &[1]), element(&[0x86], b"A_OPUS"), element(&[0x63, 0xa2], &head)].concat();
+        let mut webm = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+        // Segment and cluster of unknown size, as streamed.
+        webm.extend([0x18, 0x53, 0x80, 0x67, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        webm.extend(element(&[0x16, 0x54, 0xae, 0x6b], &element(&[0xae], &entry)));
+        webm.extend([0x1f, 0x43, 0xb6, 0x75, 0xff]);
+        webm.extend(element(&[0xe7], &[0]));
+        for i in 0..3 {
+            // Track 1, timecode, keyframe flag, then a 20 ms CELT packet.
+            webm.extend(element(&[0xa3], &[0x81, 0, i * 20, 0x80, 0xf8, i]));
+        }
+
+        let ogg = webm_to_ogg_opus(&webm).unwrap();
+        let packets = packets(&ogg).unwrap();
+        assert_eq!(packets[0], head);
+        assert!(packets[1].starts_with(b"OpusTags"));
+        assert_eq!(&packets[2..], [[0xf8, 0], [0xf8, 1], [0xf8, 2]]);
+        assert_eq!(opus_duration_samples(&ogg).unwrap(), 3 * 960 - 312);
+        assert_eq!(crc(b"123456789"), 0x89a1_897f);
+        let mut first = ogg[..28 + head.len()].to_vec();
+        let stored = u32::from_le_bytes(first[22..26].try_into().unwrap());
+        first[22..26].fill(0);
+        assert_eq!(crc(&first), stored);
+
+        assert!(webm_to_ogg_opus(b"OggS").is_err());
+    }
+}
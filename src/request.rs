@@ -0,0 +1,130 @@
+use crate::{build_ssml, Lexicon, OutputFormat, Ssml};
+
+/// Text, voice, prosody and output format of one synthesis.
+///
+/// Unset prosody values are sent as "default".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SynthesisRequest {
+    pub text: String,
+    /// eg: "zh-CN-XiaoxiaoNeural"
+    pub voice: String,
+    /// eg: "high", "+10Hz"
+    pub pitch: Option<String>,
+    /// eg: "slow", "+20%"
+    pub rate: Option<String>,
+    /// eg: "loud", "-10%"
+    pub volume: Option<String>,
+    /// Speaking style of voices that have some, eg: "cheerful" or "narration-professional".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub style: Option<String>,
+    /// Intensity of the style, from "0.01" to "2", eg: "1.5".
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub style_degree: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output_format: OutputFormat,
+}
+
+impl SynthesisRequest {
+    pub fn new(text: impl Into<String>, voice: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            voice: voice.into(),
+            pitch: None,
+            rate: None,
+            volume: None,
+            style: None,
+            style_degree: None,
+            output_format: OutputFormat::default(),
+        }
+    }
+
+    pub fn with_pitch(mut self, pitch: impl Into<String>) -> Self {
+        self.pitch = Some(pitch.into());
+        self
+    }
+
+    pub fn with_rate(mut self, rate: impl Into<String>) -> Self {
+        self.rate = Some(rate.into());
+        self
+    }
+
+    pub fn with_volume(mut self, volume: impl Into<String>) -> Self {
+        self.volume = Some(volume.into());
+        self
+    }
+
+    pub fn with_style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    pub fn with_style_degree(mut self, style_degree: impl Into<String>) -> Self {
+        self.style_degree = Some(style_degree.into());
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: impl Into<OutputFormat>) -> Self {
+        self.output_format = output_format.into();
+        self
+    }
+
+    pub fn to_ssml(&self) -> String {
+        if self.style.is_some() {
+            return self.ssml().text(&self.text).to_string();
+        }
+        build_ssml(
+            &self.text,
+            &self.voice,
+            self.pitch.as_deref().unwrap_or("default"),
+            self.rate.as_deref().unwrap_or("default"),
+            self.volume.as_deref().unwrap_or("default"),
+        )
+    }
+
+    /// [`SynthesisRequest::to_ssml`] with the words of `lexicon` said its way.
+    pub fn to_ssml_with_lexicon(&self, lexicon: &Lexicon) -> String {
+        if lexicon.is_empty() {
+            return self.to_ssml();
+        }
+        self.ssml().text_with_lexicon(&self.text, lexicon).to_string()
+    }
+
+    /// [`Ssml`] of the voice and prosody, without text.
+    pub(crate) fn ssml(&self) -> Ssml {
+        let mut ssml = Ssml::new(&self.voice);
+        if let Some(pitch) = &self.pitch {
+            ssml = ssml.with_pitch(pitch);
+        }
+        if let Some(rate) = &self.rate {
+            ssml = ssml.with_rate(rate);
+        }
+        if let Some(volume) = &self.volume {
+            ssml = ssml.with_volume(volume);
+        }
+        if let Some(style) = &self.style {
+            ssml = ssml.with_style(style, self.style_degree.as_deref());
+        }
+        ssml
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::{Boundary, BoundaryKind, Error};
+    use std::time::Duration;
+
+    #[test]
+    fn round_trips_through_json() {
+        let request: SynthesisRequest = serde_json::from_str(r#"{"text": "Hello", "voice": "en-US-AriaNeural", "rate": "+10%"}"#).unwrap();
+        assert_eq!(request, SynthesisRequest::new("Hello", "en-US-AriaNeural").with_rate("+10%"));
+        let json = serde_json::to_value(request.clone().with_output_format("raw-24khz-16bit-mono-pcm")).unwrap();
+        assert_eq!(json["output_format"], "raw-24khz-16bit-mono-pcm");
+        assert_eq!(serde_json::from_value::<SynthesisRequest>(json).unwrap().output_format.as_str(), "raw-24khz-16bit-mono-pcm");
+        let boundary = Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(100), duration: Duration::from_millis(300), text: "Hello".to_owned() };
+        assert_eq!(serde_json::from_str::<Boundary>(&serde_json::to_string(&boundary).unwrap()).unwrap(), boundary);
+        let error = Error::InputTooLong { words: 12, max_words: 10 };
+        assert_eq!(serde_json::from_str::<Error>(&serde_json::to_string(&error).unwrap()).unwrap(), error);
+    }
+}
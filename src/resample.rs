@@ -0,0 +1,136 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use crate::pcm::{to_bytes, to_samples};
+use crate::silence::is_pcm16;
+use crate::wav::{wav_data, wav_header};
+use crate::{Container, OutputFormat, SynthesisOutput};
+
+/// Taps of the interpolation filter on each side of a sample, at the lower of the two rates.
+const HALF_TAPS: usize = 16;
+
+/// Convert `output`, raw or RIFF 16-bit PCM in `format`, to `sample_rate` and mono or stereo `channels`, eg: 8000
+/// Hz mono for telephony or 48000 Hz stereo for video. Returns it with its new format, eg:
+/// "raw-48khz-16bit-stereo-pcm"; boundaries keep their times.
+///
+/// Resampling uses a windowed sinc filter cutting below the lower Nyquist frequency. Stereo becomes mono by
+/// averaging both channels.
+pub fn convert_pcm(output: SynthesisOutput, format: &OutputFormat, sample_rate: u32, channels: u16) -> Result<(SynthesisOutput, OutputFormat)> {
+    let (true, Some(from_rate)) = (is_pcm16(format), format.sample_rate()) else { bail!("{} is not 16-bit PCM", format) };
+    if sample_rate == 0 || !(1..=2).contains(&channels) {
+        bail!("can't convert to {} Hz with {} channels", sample_rate, channels);
+    }
+    let riff = format.container() == Container::Riff;
+    let samples = to_samples(if riff { wav_data(&output.audio).unwrap_or_default() } else { &output.audio });
+    let mut planes = deinterleave(&samples, format.channels());
+    if channels == 1 && planes.len() == 2 {
+        planes = vec![planes[0].iter().zip(&planes[1]).map(|(&l, &r)| ((l as i32 + r as i32) / 2) as i16).collect()];
+    }
+    let mut planes: Vec<Vec<i16>> = planes.iter().map(|plane| resample(plane, from_rate, sample_rate)).collect();
+    if channels == 2 && planes.len() == 1 {
+        planes.push(planes[0].clone());
+    }
+    let mut audio = to_bytes(&interleave(&planes));
+    if riff {
+        audio = [wav_header(sample_rate, 16, channels, audio.len() as u32), audio].concat();
+    }
+    let rate = match sample_rate % 1000 {
+        0 => format!("{}khz", sample_rate / 1000),
+        _ => format!("{}hz", sample_rate),
+    };
+    let layout = if channels == 2 { "stereo" } else { "mono" };
+    let converted = OutputFormat::new(format!("{}-{}-16bit-{}-pcm", if riff { "riff" } else { "raw" }, rate, layout));
+    let output = SynthesisOutput { audio, ..output }.with_duration(&converted);
+    Ok((output, converted))
+}
+
+/// One plane per channel of interleaved `samples`.
+pub(crate) fn deinterleave<T: Copy>(samples: &[T], channels: u16) -> Vec<Vec<T>> {
+    let channels = channels.max(1) as usize;
+    (0..channels).map(|c| samples.iter().skip(c).step_by(channels).copied().collect()).collect()
+}
+
+/// Interleaved samples of `planes`, as long as the shortest.
+pub(crate) fn interleave<T: Copy>(planes: &[Vec<T>]) -> Vec<T> {
+    let len = planes.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len).flat_map(|i| planes.iter().map(move |plane| plane[i])).collect()
+}
+
+/// `samples` of one channel from rate `from` to rate `to`.
+pub(crate) fn resample(samples: &[i16], from: u32, to: u32) -> Vec<i16> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = to as f64 / from as f64;
+    // Cutoff as a fraction of the input rate, a little below the lower Nyquist frequency.
+    let cutoff = 0.5 * ratio.min(1.0) * 0.95;
+    let half_width = HALF_TAPS as f64 / ratio.min(1.0);
+    let len = (samples.len() as f64 * ratio).round() as usize;
+    (0..len)
+        .map(|i| {
+            let center = i as f64 / ratio;
+            let first = (center - half_width).ceil().max(0.0) as usize;
+            let last = ((center + half_width).floor() as usize).min(samples.len() - 1);
+            let (mut sum, mut weights) = (0.0, 0.0);
+            for (j, &sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+                let x = j as f64 - center;
+                let sinc = if x == 0.0 { 1.0 } else { (2.0 * PI * cutoff * x).sin() / (PI * x) / (2.0 * cutoff) };
+                // Blackman window over the filter's width.
+                let t = (x / half_width + 1.0) / 2.0;
+                let window = 0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos();
+                sum += sample as f64 * sinc * window;
+                weights += sinc * window;
+            }
+            // Normalized so that the filter has unity gain, also at the edges.
+            (sum / weights).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(hz: f64, rate: u32, seconds: f64) -> Vec<i16> {
+        (0..(rate as f64 * seconds) as usize).map(|i| ((2.0 * PI * hz * i as f64 / rate as f64).sin() * 10000.0) as i16).collect()
+    }
+
+    /// Amplitude of `samples` at `hz`, by correlation.
+    fn level(samples: &[i16], hz: f64, rate: u32) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (i, &s) in samples.iter().enumerate() {
+            let phase = 2.0 * PI * hz * i as f64 / rate as f64;
+            re += s as f64 * phase.cos();
+            im += s as f64 * phase.sin();
+        }
+        2.0 * (re * re + im * im).sqrt() / samples.len() as f64
+    }
+
+    #[test]
+    fn converts_rate_and_channels() {
+        let format = OutputFormat::RAW_24KHZ_16BIT_MONO_PCM;
+        let audio = [sine(440.0, 24000, 0.5), sine(6000.0, 24000, 0.5)].concat();
+        let output = SynthesisOutput { audio: to_bytes(&audio), ..Default::default() };
+
+        let (phone, phone_format) = convert_pcm(output.clone(), &format, 8000, 1).unwrap();
+        assert_eq!(phone_format.as_str(), "raw-8khz-16bit-mono-pcm");
+        assert_eq!(phone.duration, Some(std::time::Duration::from_secs(1)));
+        let samples = to_samples(&phone.audio);
+        assert!((level(&samples[100..3900], 440.0, 8000) - 10000.0).abs() < 300.0);
+        // 6 kHz is over the 4 kHz Nyquist frequency of 8 kHz: filtered out rather than aliased to 2 kHz.
+        assert!(level(&samples[4100..7900], 2000.0, 8000) < 300.0);
+
+        let (video, video_format) = convert_pcm(output, &format, 48000, 2).unwrap();
+        assert_eq!((video_format.sample_rate(), video_format.channels()), (Some(48000), 2));
+        let samples = to_samples(&video.audio);
+        assert_eq!(samples.len(), 48000 * 2);
+        assert!(samples.chunks(2).all(|frame| frame[0] == frame[1]));
+        let left: Vec<i16> = samples.iter().step_by(2).copied().take(24000).collect();
+        assert!((level(&left[200..23800], 440.0, 48000) - 10000.0).abs() < 300.0);
+
+        let (back, _) = convert_pcm(video, &video_format, 48000, 1).unwrap();
+        assert_eq!(to_samples(&back.audio), left.into_iter().chain(samples.iter().step_by(2).copied().skip(24000)).collect::<Vec<_>>());
+        assert!(convert_pcm(SynthesisOutput::default(), &OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3, 8000, 1).is_err());
+    }
+}
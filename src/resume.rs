@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::metadata::{Boundary, BoundaryKind};
+use crate::mp3::frame_offsets;
+use crate::trace::trace_event;
+use crate::{Client, Container, Error, OutputFormat, SynthesisOutput, SynthesisRequest};
+
+/// Where an interrupted turn can continue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ResumePoint {
+    /// Bytes of the request text already spoken.
+    text_len: usize,
+    /// Bytes of audio to keep.
+    audio_len: usize,
+    /// Duration of the kept audio.
+    duration: Duration,
+}
+
+impl Client {
+    pub(crate) fn synthesize_resuming(&self, request: &SynthesisRequest) -> Result<SynthesisOutput> {
+        let format = &request.output_format;
+        let mut output = SynthesisOutput::default();
+        let mut elapsed = Duration::ZERO;
+        let mut remaining = request.text.as_str();
+        let mut resumes = 0;
+        loop {
+            let ssml = self.request_ssml(&SynthesisRequest {
+                text: remaining.to_owned(),
+                ..request.clone()
+            });
+            let mut part = SynthesisOutput::default();
+            let err = match self.connect_and_synthesize(&ssml, format.as_str(), &mut part) {
+                Ok(()) => {
+                    append(&mut output, part, elapsed, None);
+                    return Ok(output);
+                }
+                Err(e) => e,
+            };
+            // Another turn would fail the same way.
+            let hopeless = matches!(err.downcast_ref::<Error>(), Some(Error::MessageTooLarge { .. } | Error::AudioTooLarge { .. } | Error::FormatMismatch { .. }));
+            let point = match resume_point(remaining, &part, format) {
+                Some(point) if resumes < self.max_resumes && !hopeless => point,
+                _ => return Err(err),
+            };
+            trace_event!(info, resumes, spoken_bytes = point.text_len, error = %err, "resuming cut-off turn");
+            append(&mut output, part, elapsed, Some(point));
+            elapsed += point.duration;
+            remaining = remaining.get(point.text_len..).unwrap_or_default();
+            if remaining.trim().is_empty() {
+                return Ok(output);
+            }
+            resumes += 1;
+        }
+    }
+}
+
+/// Append `part`, or only what comes before `cut`, with boundaries shifted by `elapsed`.
+fn append(output: &mut SynthesisOutput, mut part: SynthesisOutput, elapsed: Duration, cut: Option<ResumePoint>) {
+    if let Some(cut) = cut {
+        part.audio.truncate(cut.audio_len);
+        part.boundaries.retain(|b| b.offset + b.duration <= cut.duration);
+    }
+    output.first_audio = output.first_audio.or(part.first_audio);
+    output.ids = part.ids.or(output.ids.take());
+    output.turn_info = part.turn_info.or(output.turn_info.take());
+    output.audio.extend(part.audio);
+    output.boundaries.extend(part.boundaries.into_iter().map(|b| Boundary {
+        offset: b.offset + elapsed,
+        ..b
+    }));
+}
+
+/// Find the end of the last word fully received in `part`, in `text` and in the audio. `None` if the format can't
+/// be cut and joined.
+fn resume_point(text: &str, part: &SynthesisOutput, format: &OutputFormat) -> Option<ResumePoint> {
+    let received = format.duration_of(part.audio.len())?;
+    let mut text_len = 0;
+    let mut cut = Duration::ZERO;
+    let words: Vec<&Boundary> = part.boundaries.iter().filter(|b| b.kind == BoundaryKind::Word).collect();
+    for (i, word) in words.iter().enumerate() {
+        let end = word.offset + word.duration;
+        if end > received {
+            break;
+        }
+        let Some(at) = text.get(text_len..).and_then(|rest| rest.find(word.text.as_str())) else { break };
+        text_len += at + word.text.len();
+        // Cut halfway through the pause before the next word, so neither is clipped.
+        cut = match words.get(i + 1) {
+            Some(next) if next.offset > end && next.offset <= received => end + (next.offset - end) / 2,
+            _ => end,
+        };
+    }
+    if text_len == 0 {
+        return Some(ResumePoint { text_len: 0, audio_len: 0, duration: Duration::ZERO });
+    }
+    let audio_len = cut_len(format, &part.audio, cut)?;
+    Some(ResumePoint {
+        text_len,
+        audio_len,
+        duration: format.duration_of(audio_len)?,
+    })
+}
+
+/// Bytes of `audio` to keep to cut it at `at`, on a frame or sample boundary. `None` for formats that can't be cut.
+pub(crate) fn cut_len(format: &OutputFormat, audio: &[u8], at: Duration) -> Option<usize> {
+    let bytes = (at.as_secs_f64() * format.bitrate()? as f64 / 8.0) as usize;
+    let len = match format.container() {
+        Container::Mp3 => frame_offsets(audio).into_iter().find(|&offset| offset >= bytes).unwrap_or(audio.len()),
+        Container::Raw => {
+            let block = (format.bits_per_sample()? as usize / 8 * format.channels() as usize).max(1);
+            bytes / block * block
+        }
+        _ => return None,
+    };
+    Some(len.min(audio.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    fn word(text: &str, offset_ms: u64, duration_ms: u64) -> Boundary {
+        Boundary {
+            kind: BoundaryKind::Word,
+            offset: Duration::from_millis(offset_ms),
+            duration: Duration::from_millis(duration_ms),
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn resumes_after_last_complete_word() {
+        let format = OutputFormat::RAW_16KHZ_16BIT_MONO_PCM;
+        // 1 s of audio received; "three" ends after it.
+        let part = SynthesisOutput {
+            audio: vec![0; 32000],
+            boundaries: vec![word("One", 100, 200), word("two", 400, 200), word("three", 900, 300)],
+            ..Default::default()
+        };
+        let point = resume_point("One, two, three four.", &part, &format).unwrap();
+        assert_eq!(point.text_len, "One, two".len());
+        // Halfway between the end of "two" (600 ms) and "three" (900 ms).
+        assert_eq!(point.duration, Duration::from_millis(750));
+        assert_eq!(point.audio_len, 24000);
+
+        let nothing = SynthesisOutput { audio: vec![0; 100], ..Default::default() };
+        assert_eq!(resume_point("One", &nothing, &format).unwrap().audio_len, 0);
+        assert_eq!(resume_point("One", &part, &OutputFormat::WEBM_24KHZ_16BIT_MONO_OPUS), None);
+    }
+
+    #[test]
+    fn stops_at_size_limits() {
+        let mut turn = MockReply::turn(b"abc");
+        turn.insert(1, MockReply::Audio(b"def".to_vec()));
+        let server = MockServer::start(vec![turn, vec![MockReply::Audio(vec![0; 100]), MockReply::TurnEnd]]).unwrap();
+        let request = SynthesisRequest::new("One two", "en-US-AriaNeural").with_output_format("raw-24khz-16bit-mono-pcm");
+        let error = server.client().with_resume(1).with_max_audio_size(4).synthesize_request(&request).unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::AudioTooLarge { limit: 4 })), "{:#}", error);
+        let error = server.client().with_max_message_size(64).synthesize_request(&request).unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::MessageTooLarge { limit: 64, .. })), "{:#}", error);
+        assert_eq!(server.requests().len(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::audiobook::chunk_text;
+use crate::concat::can_concat;
+#[cfg(feature = "id3")]
+use crate::id3::{write_id3, Chapter, Id3Tags};
+#[cfg(feature = "id3")]
+use crate::truncate_words;
+use crate::{concat_audio, ogg, Client, ConcatOptions, Container, OutputFormat, SynthesisRequest};
+
+/// Words of a chunk in the title of its chapter.
+#[cfg(feature = "id3")]
+const CHAPTER_TITLE_WORDS: usize = 8;
+
+/// What [`Client::synthesize_to_file`] wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedAudio {
+    pub bytes: u64,
+    /// `None` for WebM and formats without a constant bitrate.
+    pub duration: Option<Duration>,
+}
+
+/// Options of [`Client::synthesize_to_file`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Synthesize the text in chunks of at most this many characters, split after sentence ends, like
+    /// [`Client::synthesize_long`]. 0 for one chunk.
+    pub max_chunk_chars: usize,
+    /// Tag the file with these and a chapter frame for each chunk, titled by its first words. Needs an .mp3 path.
+    #[cfg(feature = "id3")]
+    pub id3: Option<Id3Tags>,
+}
+
+impl Client {
+    /// Speak `text` with `voice` into `path`, in the format of its extension (see [`OutputFormat::from_extension`]).
+    ///
+    /// The audio is written to a temporary file next to `path` and renamed, so `path` is never left half written.
+    pub fn synthesize_to_file(&self, text: &str, voice: &str, path: impl AsRef<Path>, options: &SaveOptions) -> Result<SavedAudio> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let format = OutputFormat::from_extension(extension).ok_or_else(|| anyhow!("unknown audio file extension: {}", path.display()))?;
+        #[cfg(feature = "id3")]
+        if options.id3.is_some() && format.container() != Container::Mp3 {
+            return Err(anyhow!("ID3 tags need an .mp3 file: {}", path.display()));
+        }
+        let chunks = match options.max_chunk_chars {
+            0 => vec![text.to_owned()],
+            max => chunk_text(text, max),
+        };
+        if chunks.len() > 1 && !can_concat(&format) {
+            return Err(anyhow!("can't join {} chunks of {}, use an mp3 or 16-bit pcm format", chunks.len(), format));
+        }
+        let mut parts = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            parts.push(self.synthesize_request(&SynthesisRequest::new(chunk.as_str(), voice).with_output_format(format.clone()))?);
+        }
+        #[cfg(feature = "id3")]
+        let durations: Vec<Duration> = parts.iter().map(|part| audio_duration(&format, &part.audio).unwrap_or_default()).collect();
+        let audio = match parts.len() {
+            1 => parts.remove(0).audio,
+            // Untrimmed, so chapters start where their chunk does.
+            _ => concat_audio(parts, &format, &ConcatOptions { trim: false, ..Default::default() })?.audio,
+        };
+        let duration = audio_duration(&format, &audio);
+        #[cfg(feature = "id3")]
+        let audio = match &options.id3 {
+            Some(tags) => {
+                let mut start = Duration::ZERO;
+                let chapters: Vec<Chapter> = chunks
+                    .iter()
+                    .zip(durations)
+                    .map(|(chunk, duration)| {
+                        let chapter = Chapter { title: truncate_words(chunk, CHAPTER_TITLE_WORDS), start, end: start + duration };
+                        start = chapter.end;
+                        chapter
+                    })
+                    .collect();
+                write_id3(&audio, tags, &chapters)
+            }
+            None => audio,
+        };
+        write_atomic(path, &audio)?;
+        Ok(SavedAudio { bytes: audio.len() as u64, duration })
+    }
+}
+
+/// Write `data` to a temporary file next to `path` and rename it.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let tmp = path.with_extension(format!("{}.tmp", extension));
+    fs::write(&tmp, data)?;
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// [`Client::synthesize_to_file`] with [`Client::from_env`].
+pub fn synthesize_to_file(text: &str, voice: &str, path: impl AsRef<Path>, options: &SaveOptions) -> Result<SavedAudio> {
+    Client::from_env()?.synthesize_to_file(text, voice, path, options)
+}
+
+pub(crate) fn audio_duration(format: &OutputFormat, audio: &[u8]) -> Option<Duration> {
+    match format.container() {
+        Container::Ogg => ogg::opus_duration_samples(audio).ok().map(|samples| Duration::from_secs_f64(samples as f64 / 48000.0)),
+        // The service sends the canonical 44-byte header.
+        Container::Riff => format.duration_of(audio.len().saturating_sub(44)),
+        _ => format.duration_of(audio.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{DiskCache, SynthesisOutput};
+
+    #[test]
+    fn writes_the_format_of_the_extension() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-save-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        // Nothing listens on the proxy, so only what is cached can be saved.
+        let proxy = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let client = Client::new().with_socks5_proxy(proxy.to_string()).with_disk_cache(DiskCache::new(dir.join("cache")));
+        // 0.1 s of 24 kHz 16-bit mono.
+        let wav = [vec![0; 44], vec![1; 4800]].concat();
+        let request = SynthesisRequest::new("Hello", "en-US-AriaNeural").with_output_format(OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM);
+        let key = client.synth_key(&request.to_ssml(), request.output_format.as_str());
+        DiskCache::new(dir.join("cache")).put(&key, &SynthesisOutput { audio: wav.clone(), ..Default::default() }).unwrap();
+
+        let path = dir.join("hello.WAV");
+        let saved = client.synthesize_to_file("Hello", "en-US-AriaNeural", &path, &SaveOptions::default()).unwrap();
+        assert_eq!(saved, SavedAudio { bytes: wav.len() as u64, duration: Some(Duration::from_millis(100)) });
+        assert_eq!(fs::read(&path).unwrap(), wav);
+        // Only the renamed file is left, next to the cache.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        // Nothing is written when the synthesis fails, nor for unknown extensions, which fail before connecting.
+        assert!(client.synthesize_to_file("Bye", "en-US-AriaNeural", dir.join("bye.mp3"), &SaveOptions::default()).is_err());
+        for name in ["hello.flac", "hello"] {
+            let error = client.synthesize_to_file("Hello", "en-US-AriaNeural", dir.join(name), &SaveOptions::default()).unwrap_err();
+            assert!(error.to_string().contains("unknown audio file extension"), "{}", error);
+        }
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "id3")]
+    #[test]
+    fn tags_chapters_of_chunks() {
+        use crate::testing::{MockReply, MockServer};
+
+        // 24 ms frames of 48 kbit/s, 24 kHz MP3.
+        let frames = |n: usize| [0xff, 0xf3, 0x64, 0xc4, 0].into_iter().chain([7; 139]).collect::<Vec<u8>>().repeat(n);
+        let server = MockServer::start(vec![MockReply::turn(&frames(2)), MockReply::turn(&frames(3))]).unwrap();
+        let dir = std::env::temp_dir().join(format!("edge-tts-chapters-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.mp3");
+        let tags = Id3Tags { title: Some("Book".to_owned()), ..Default::default() };
+        let options = SaveOptions { max_chunk_chars: 20, id3: Some(tags) };
+        let saved = server.client().synthesize_to_file("First chapter here. Then the second.", "en-US-AriaNeural", &path, &options).unwrap();
+        assert_eq!(server.requests().iter().map(|r| r.ssml.contains("First chapter here.")).collect::<Vec<_>>(), [true, false]);
+        assert_eq!(saved.duration, Some(Duration::from_millis(120)));
+        let mp3 = fs::read(&path).unwrap();
+        assert_eq!(saved.bytes, mp3.len() as u64);
+        assert!(mp3.ends_with(&frames(5)));
+        // Element id, start and end in ms.
+        for (id, times) in [(&b"chp0\0"[..], [0u32, 48]), (b"chp1\0", [48, 120])] {
+            let at = mp3.windows(id.len()).rposition(|w| w == id).unwrap() + id.len();
+            assert_eq!(mp3[at..at + 8], [times[0].to_be_bytes(), times[1].to_be_bytes()].concat());
+        }
+
+        // Tags need an MP3 and chunks a format that can be joined, checked before connecting.
+        let error = server.client().synthesize_to_file("Hi", "en-US-AriaNeural", dir.join("book.wav"), &options).unwrap_err();
+        assert!(error.to_string().contains("ID3 tags need an .mp3 file"), "{}", error);
+        let chunked = SaveOptions { max_chunk_chars: 20, id3: None };
+        let error = server.client().synthesize_to_file("First chapter here. Then the second.", "en-US-AriaNeural", dir.join("book.webm"), &chunked).unwrap_err();
+        assert!(error.to_string().contains("can't join 2 chunks"), "{}", error);
+        assert_eq!(server.requests().len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn measures_the_saved_audio() {
+        // 48 kbit/s is 6000 bytes/s, and 24 kHz 16-bit mono 48000 bytes/s after the 44-byte header.
+        assert_eq!(audio_duration(&OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3, &[0; 3000]), Some(Duration::from_millis(500)));
+        assert_eq!(audio_duration(&OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM, &[0; 4844]), Some(Duration::from_millis(100)));
+        assert_eq!(audio_duration(&OutputFormat::RIFF_24KHZ_16BIT_MONO_PCM, &[0; 10]), Some(Duration::ZERO));
+        assert_eq!(audio_duration(&OutputFormat::WEBM_24KHZ_16BIT_MONO_OPUS, &[0; 3000]), None);
+    }
+}
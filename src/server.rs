@@ -0,0 +1,444 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::thread;
+
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::openai;
+use crate::trace::trace_event;
+use crate::{bundled_voices, get_voice_list, Boundary, BoundaryKind, Client, SynthesisEvent, SynthesisRequest, VoiceListCache};
+
+/// Largest request line and headers accepted.
+const MAX_HEAD: u64 = 16 * 1024;
+/// Largest request body accepted.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// A small HTTP/1.1 server in front of a [`Client`], so that programs in other languages can use it as a local
+/// sidecar, eg: `edge-tts serve`:
+///
+/// - `POST /synthesize` with a JSON body, eg: `{"text": "Hello", "voice": "en-US-AriaNeural", "rate": "+10%",
+///   "output_format": "audio-24khz-48kbitrate-mono-mp3"}`, replies with the audio. Only `text` is required. With
+///   `"stream": true`, the audio is sent in chunks as it arrives.
+/// - `GET /events?text=Hello&voice=...`, or `POST /events` with the same body, replies with server-sent events for
+///   live captions, eg: in a browser's `EventSource`: `audio` events with base64 audio, `boundary` events like
+///   `{"kind": "word", "offset_ms": 100, "duration_ms": 320, "text": "Hello"}`, then `end`, or `error`.
+/// - `POST /v1/audio/speech` takes OpenAI's speech requests, eg: `{"model": "tts-1", "input": "Hello", "voice":
+///   "alloy", "response_format": "mp3", "speed": 1.2}`, so that tools speaking that API can use the server instead.
+///   OpenAI voices are mapped to Edge ones, see [`HttpServer::with_openai_voice`], and other voice names are taken as
+///   Edge voices. The mp3, opus, wav and pcm formats are supported.
+/// - `GET /voices` replies with the voice list as JSON.
+///
+/// Streamed syntheses don't use the disk cache nor post-processing.
+///
+/// Errors are replied as JSON, eg: `{"error": "missing field `text`"}`. Each connection is served on its own thread
+/// and closed after one request.
+#[derive(Debug, Clone)]
+pub struct HttpServer {
+    client: Client,
+    defaults: SynthesisRequest,
+    voices: Option<VoiceListCache>,
+    openai_voices: Vec<(String, String)>,
+}
+
+/// Body of `POST /synthesize`.
+#[derive(Debug, Deserialize)]
+struct SynthesizeBody {
+    text: String,
+    voice: Option<String>,
+    pitch: Option<String>,
+    rate: Option<String>,
+    volume: Option<String>,
+    style: Option<String>,
+    style_degree: Option<String>,
+    output_format: Option<String>,
+    #[serde(default)]
+    stream: bool,
+}
+
+struct HttpRequest {
+    method: String,
+    target: String,
+    body: Vec<u8>,
+}
+
+enum Reply {
+    Full(Response),
+    /// Audio sent in chunks as it arrives.
+    Chunked(SynthesisRequest),
+    /// Audio and boundaries sent as server-sent events.
+    Events(SynthesisRequest),
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl HttpServer {
+    /// Serve syntheses of `client`, with "en-US-AriaNeural" and MP3 unless a request gives its own.
+    pub fn new(client: Client) -> Self {
+        Self { client, defaults: SynthesisRequest::new("", "en-US-AriaNeural"), voices: None, openai_voices: Vec::new() }
+    }
+
+    /// Voice, prosody and output format of requests that don't give theirs. Its text is ignored.
+    pub fn with_defaults(mut self, defaults: SynthesisRequest) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Serve `GET /voices` from `cache` instead of fetching the list each time.
+    pub fn with_voice_list(mut self, cache: VoiceListCache) -> Self {
+        self.voices = Some(cache);
+        self
+    }
+
+    /// Synthesize OpenAI voice `name` with Edge `voice` rather than the built-in choice.
+    ///
+    /// eg: `name`: "alloy", `voice`: "en-GB-SoniaNeural"
+    pub fn with_openai_voice(mut self, name: &str, voice: &str) -> Self {
+        self.openai_voices.push((name.to_owned(), voice.to_owned()));
+        self
+    }
+
+    /// Serve the connections of `listener` until accepting one fails.
+    pub fn serve(&self, listener: TcpListener) -> anyhow::Result<()> {
+        for stream in listener.incoming() {
+            let (server, stream) = (self.clone(), stream?);
+            thread::spawn(move || server.handle(stream));
+        }
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream) {
+        let mut reader = BufReader::new(stream);
+        let reply = match read_request(&mut reader) {
+            Ok(request) => self.respond(&request),
+            Err(response) => Reply::Full(response),
+        };
+        let stream = reader.get_mut();
+        let _ = match reply {
+            Reply::Full(response) => {
+                trace_event!(debug, status = response.status, bytes = response.body.len(), "http response");
+                response.write_to(stream)
+            }
+            Reply::Chunked(request) => self.stream(&request, stream, false),
+            Reply::Events(request) => self.stream(&request, stream, true),
+        };
+    }
+
+    fn respond(&self, request: &HttpRequest) -> Reply {
+        let (path, query) = request.target.split_once('?').unwrap_or((&request.target, ""));
+        let parsed = match (request.method.as_str(), path) {
+            ("POST", "/synthesize" | "/events") => self.request(&request.body),
+            ("GET", "/events") => self.request(&query_json(query)),
+            ("GET", "/voices") => return Reply::Full(self.voices()),
+            ("POST", "/v1/audio/speech") => return Reply::Full(self.openai_speech(&request.body)),
+            (_, "/synthesize" | "/events" | "/voices" | "/v1/audio/speech") => return Reply::Full(Response::error(405, "method not allowed")),
+            _ => return Reply::Full(Response::error(404, "not found")),
+        };
+        match parsed {
+            Err(e) => Reply::Full(Response::error(400, &e.to_string())),
+            Ok((request, _)) if path == "/events" => Reply::Events(request),
+            Ok((request, true)) => Reply::Chunked(request),
+            Ok((request, false)) => Reply::Full(self.synthesize(&request, Response::error)),
+        }
+    }
+
+    /// The audio of `request`, or an `error` reply.
+    fn synthesize(&self, request: &SynthesisRequest, error: fn(u16, &str) -> Response) -> Response {
+        match self.client.synthesize_request(request) {
+            Ok(output) => Response { status: 200, content_type: request.output_format.mime_type(), body: output.audio },
+            Err(e) => {
+                let message = format!("{:#}", e);
+                trace_event!(warn, error = %message, "http synthesis failed");
+                error(502, &message)
+            }
+        }
+    }
+
+    fn openai_speech(&self, body: &[u8]) -> Response {
+        match openai::speech_request(body, &self.openai_voices, &self.defaults) {
+            Ok(request) => self.synthesize(&request, Response::openai_error),
+            Err(message) => Response::openai_error(400, &message),
+        }
+    }
+
+    /// Run a turn of `request`, sending its audio in chunks as it arrives, or with `events` its audio and boundaries
+    /// as server-sent events. Before any data, an error is replied as usual; after, audio is cut short while events
+    /// end with an `error` event.
+    fn stream(&self, request: &SynthesisRequest, stream: &mut TcpStream, events: bool) -> io::Result<()> {
+        let content_type = if events { "text/event-stream" } else { request.output_format.mime_type() };
+        let (mut started, mut failed) = (false, None);
+        let result = self.client.prepare(request).and_then(|request| {
+            self.client.connect_and_stream(&self.client.request_ssml(&request), request.output_format.as_str(), &mut |event| {
+                let data = match event {
+                    SynthesisEvent::Audio(audio) if events => server_event("audio", &base64::engine::general_purpose::STANDARD.encode(audio)),
+                    SynthesisEvent::Audio(audio) => audio.to_vec(),
+                    SynthesisEvent::Boundaries(boundaries) if events => boundaries.iter().flat_map(|b| server_event("boundary", &boundary_json(b))).collect(),
+                    SynthesisEvent::Boundaries(_) => return ControlFlow::Continue(()),
+                };
+                match send(stream, &mut started, content_type, &data) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    // The client went away.
+                    Err(e) => {
+                        failed = Some(e);
+                        ControlFlow::Break(())
+                    }
+                }
+            })
+        });
+        if let Some(e) = failed {
+            return Err(e);
+        }
+        match result {
+            Err(e) if !started => Response::error(502, &format!("{:#}", e)).write_to(stream),
+            Err(e) if events => {
+                send(stream, &mut started, content_type, &server_event("error", &json!({ "error": format!("{:#}", e) }).to_string()))?;
+                write_chunk(stream, &[])
+            }
+            Err(_) => Ok(()),
+            Ok(_) => {
+                send(stream, &mut started, content_type, if events { b"event: end\ndata: {}\n\n" } else { b"" })?;
+                write_chunk(stream, &[])
+            }
+        }
+    }
+
+    /// `body` over the defaults, and whether to stream it.
+    fn request(&self, body: &[u8]) -> serde_json::Result<(SynthesisRequest, bool)> {
+        let body: SynthesizeBody = serde_json::from_slice(body)?;
+        let defaults = &self.defaults;
+        let request = SynthesisRequest {
+            text: body.text,
+            voice: body.voice.unwrap_or_else(|| defaults.voice.clone()),
+            pitch: body.pitch.or_else(|| defaults.pitch.clone()),
+            rate: body.rate.or_else(|| defaults.rate.clone()),
+            volume: body.volume.or_else(|| defaults.volume.clone()),
+            style: body.style.or_else(|| defaults.style.clone()),
+            style_degree: body.style_degree.or_else(|| defaults.style_degree.clone()),
+            output_format: body.output_format.map_or_else(|| defaults.output_format.clone(), Into::into),
+        };
+        Ok((request, body.stream))
+    }
+
+    /// The cached list if any, else a fetched one, else [`bundled_voices`].
+    fn voices(&self) -> Response {
+        let voices = match &self.voices {
+            Some(cache) => cache.voices(),
+            None => get_voice_list().unwrap_or_else(|_| bundled_voices()),
+        };
+        match serde_json::to_vec(&voices) {
+            Ok(body) => Response::json(200, body),
+            Err(e) => Response::error(500, &e.to_string()),
+        }
+    }
+}
+
+/// Request line, headers and body, or the error to reply.
+fn read_request(reader: &mut BufReader<TcpStream>) -> Result<HttpRequest, Response> {
+    let bad = |message: &str| Response::error(400, message);
+    let mut head = reader.by_ref().take(MAX_HEAD);
+    let mut line = String::new();
+    head.read_line(&mut line).map_err(|e| bad(&e.to_string()))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(bad("invalid request line"));
+    };
+    let (method, target) = (method.to_owned(), target.to_owned());
+    let (mut length, mut expect_continue) = (0, false);
+    loop {
+        line.clear();
+        if head.read_line(&mut line).map_err(|e| bad(&e.to_string()))? == 0 {
+            return Err(bad("headers too long or truncated"));
+        }
+        let Some((name, value)) = line.trim_end().split_once(':') else { break };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value.parse().map_err(|_| bad("invalid content-length"))?;
+        } else if name.eq_ignore_ascii_case("expect") {
+            expect_continue = value.eq_ignore_ascii_case("100-continue");
+        }
+    }
+    if length > MAX_BODY {
+        return Err(Response::error(413, "body too large"));
+    }
+    if expect_continue && length > 0 {
+        let _ = reader.get_mut().write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| bad(&e.to_string()))?;
+    Ok(HttpRequest { method, target, body })
+}
+
+/// A query string as a JSON body, eg: "text=Hello+world&rate=%2B10%25" as `{"text": "Hello world", "rate": "+10%"}`.
+fn query_json(query: &str) -> Vec<u8> {
+    let fields: Map<String, Value> = url::form_urlencoded::parse(query.as_bytes()).filter(|(key, _)| key != "stream").map(|(key, value)| (key.into_owned(), Value::String(value.into_owned()))).collect();
+    Value::Object(fields).to_string().into_bytes()
+}
+
+/// eg: "event: audio\ndata: SUQz...\n\n"
+fn server_event(event: &str, data: &str) -> Vec<u8> {
+    format!("event: {}\ndata: {}\n\n", event, data).into_bytes()
+}
+
+fn boundary_json(boundary: &Boundary) -> String {
+    let kind = match boundary.kind {
+        BoundaryKind::Word => "word",
+        BoundaryKind::Sentence => "sentence",
+        BoundaryKind::Viseme(_) => "viseme",
+        BoundaryKind::Bookmark => "bookmark",
+    };
+    let mut value = json!({
+        "kind": kind,
+        "offset_ms": boundary.offset.as_millis() as u64,
+        "duration_ms": boundary.duration.as_millis() as u64,
+        "text": boundary.text,
+    });
+    if let Some(id) = boundary.viseme_id() {
+        value["viseme_id"] = id.into();
+    }
+    value.to_string()
+}
+
+/// Send `data` as a chunk, after the head of a chunked reply if not `started` yet. Empty data only sends the head.
+fn send(stream: &mut TcpStream, started: &mut bool, content_type: &str, data: &[u8]) -> io::Result<()> {
+    if !*started {
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n", content_type)?;
+        *started = true;
+    }
+    if data.is_empty() {
+        return stream.flush();
+    }
+    write_chunk(stream, data)
+}
+
+/// One chunk of a chunked body; empty for the last one.
+fn write_chunk(stream: &mut TcpStream, data: &[u8]) -> io::Result<()> {
+    write!(stream, "{:x}\r\n", data.len())?;
+    stream.write_all(data)?;
+    stream.write_all(b"\r\n")?;
+    stream.flush()
+}
+
+impl Response {
+    fn json(status: u16, body: Vec<u8>) -> Self {
+        Self { status, content_type: "application/json", body }
+    }
+
+    /// eg: `{"error": "not found"}`
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }).to_string().into_bytes())
+    }
+
+    fn openai_error(status: u16, message: &str) -> Self {
+        Self::json(status, openai::error_json(status, message))
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            502 => "Bad Gateway",
+            _ => "Internal Server Error",
+        };
+        write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", self.status, reason, self.content_type, self.body.len())?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::voice_list::Voice;
+
+    fn post(addr: SocketAddr, request: &str) -> (String, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        (String::from_utf8_lossy(&response[..end]).into_owned(), response[end + 4..].to_vec())
+    }
+
+    #[test]
+    fn serves_syntheses_and_voices() {
+        let edge = MockServer::start(vec![MockReply::turn(b"audio")]).unwrap();
+        let dir = std::env::temp_dir().join(format!("edge-tts-server-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let voices = vec![Voice { short_name: "en-GB-SoniaNeural".to_owned(), ..Default::default() }];
+        std::fs::write(dir.join("voices.json"), serde_json::to_vec(&voices).unwrap()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(edge.client()).with_defaults(SynthesisRequest::new("", "en-GB-SoniaNeural").with_rate("+10%")).with_voice_list(VoiceListCache::new(&dir));
+        thread::spawn(move || server.serve(listener));
+
+        let body = r#"{"text": "Hello", "pitch": "+5Hz"}"#;
+        let (head, audio) = post(addr, &format!("POST /synthesize HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+        assert!(head.starts_with("HTTP/1.1 200") && head.contains("Content-Type: audio/mpeg"), "{}", head);
+        assert_eq!(audio, b"audio");
+        let ssml = &edge.requests()[0].ssml;
+        assert!(ssml.contains("en-GB-SoniaNeural") && ssml.contains("+10%") && ssml.contains("+5Hz"), "{}", ssml);
+
+        let (head, error) = post(addr, "POST /synthesize HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(head.starts_with("HTTP/1.1 400"), "{}", head);
+        assert!(String::from_utf8_lossy(&error).contains("missing field `text`"));
+        let (head, listed) = post(addr, "GET /voices HTTP/1.1\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 200"), "{}", head);
+        assert_eq!(serde_json::from_slice::<Vec<Voice>>(&listed).unwrap(), voices);
+        assert!(post(addr, "GET /nothing HTTP/1.1\r\n\r\n").0.starts_with("HTTP/1.1 404"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// The body of a chunked reply.
+    fn dechunk(mut body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        loop {
+            let line = body.windows(2).position(|w| w == b"\r\n").unwrap();
+            let len = usize::from_str_radix(std::str::from_utf8(&body[..line]).unwrap(), 16).unwrap();
+            if len == 0 {
+                return data;
+            }
+            data.extend_from_slice(&body[line + 2..line + 2 + len]);
+            body = &body[line + 4 + len..];
+        }
+    }
+
+    #[test]
+    fn streams_audio_and_events() {
+        let turn = vec![
+            MockReply::Text { path: "turn.start".to_owned(), body: "{}".to_owned() },
+            MockReply::Word { text: "Hello".to_owned(), offset: Duration::from_millis(100), duration: Duration::from_millis(320) },
+            MockReply::Audio(b"audio".to_vec()),
+            MockReply::TurnEnd,
+        ];
+        let edge = MockServer::start(vec![turn]).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(edge.client());
+        thread::spawn(move || server.serve(listener));
+
+        let body = r#"{"text": "Hello", "stream": true}"#;
+        let (head, audio) = post(addr, &format!("POST /synthesize HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+        assert!(head.contains("Transfer-Encoding: chunked") && head.contains("Content-Type: audio/mpeg"), "{}", head);
+        assert_eq!(dechunk(&audio), b"audio");
+
+        let (head, events) = post(addr, "GET /events?text=Hello&voice=en-GB-SoniaNeural HTTP/1.1\r\n\r\n");
+        assert!(head.contains("Content-Type: text/event-stream"), "{}", head);
+        let events = String::from_utf8(dechunk(&events)).unwrap();
+        assert!(events.contains("event: boundary\ndata: {\"duration_ms\":320,\"kind\":\"word\",\"offset_ms\":100,\"text\":\"Hello\"}\n\n"), "{}", events);
+        assert!(events.contains("event: audio\ndata: YXVkaW8=\n\n") && events.ends_with("event: end\ndata: {}\n\n"), "{}", events);
+        assert!(edge.requests()[1].ssml.contains("en-GB-SoniaNeural"));
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::time::Instant;
+
+use anyhow::{bail, Result};
+use tungstenite::error::CapacityError;
+use tungstenite::{Message, WebSocket};
+
+use crate::dump::{ConnectionDump, ProtocolDump};
+use crate::frame::{parse_binary_frame, parse_text_frame, FrameError, Headers};
+use crate::metadata::parse_metadata;
+use crate::stream::Stream;
+use crate::synthesize::{close, random_request_id, speech_config_message};
+use crate::trace::trace_event;
+use crate::turn_info::TurnInfo;
+use crate::{Client, Error, OutputFormat, SynthesisOutput, TurnIds};
+
+/// One connection to the service for any number of turns, between [`Client::synthesize`] and the raw socket, eg:
+/// for a dialogue without a handshake per line. It sends speech.config when needed, gives each SSML its own
+/// X-RequestId and tells the replies apart by it.
+///
+/// Unlike [`Client::synthesize`], there is no cache, resume, fallback nor post-processing. An error ends the
+/// session: later calls fail, so open another. The connection is closed on drop.
+pub struct Session {
+    client: Client,
+    socket: WebSocket<Box<dyn Stream>>,
+    connection_id: String,
+    dump: Option<ConnectionDump>,
+    output_format: String,
+    /// Whether speech.config for `output_format` was sent.
+    configured: bool,
+    /// Before connecting, to time the first audio of the first turn like [`SynthesisOutput::first_audio`] does.
+    opened: Option<Instant>,
+    failed: bool,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session").field("connection_id", &self.connection_id).field("output_format", &self.output_format).finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    /// Open a [`Session`] whose turns are in `output_format`.
+    ///
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3"
+    pub fn session(&self, output_format: &str) -> Result<Session> {
+        let opened = Instant::now();
+        let dump = self.protocol_dump.as_ref().map(ProtocolDump::connection);
+        let (socket, connection_id) = self.connect(dump.as_ref())?;
+        if let Some(interval) = self.keep_alive {
+            socket.get_ref().set_read_timeout(Some(interval))?;
+        }
+        Ok(Session {
+            client: self.clone(),
+            socket,
+            connection_id,
+            dump,
+            output_format: output_format.to_owned(),
+            configured: false,
+            opened: Some(opened),
+            failed: false,
+        })
+    }
+}
+
+impl Session {
+    /// ConnectionId of the session, as in [`TurnIds`].
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    /// Run later turns in `output_format` instead, sending speech.config again.
+    pub fn set_output_format(&mut self, output_format: &str) {
+        if self.output_format != output_format {
+            self.output_format = output_format.to_owned();
+            self.configured = false;
+        }
+    }
+
+    /// Run one turn and wait for its `turn.end`.
+    pub fn synthesize(&mut self, ssml: &str) -> Result<SynthesisOutput> {
+        Ok(self.synthesize_all(&[ssml])?.into_iter().next().unwrap_or_default())
+    }
+
+    /// Send every SSML at once and collect the replies, which may interleave, in the order of `ssml`.
+    pub fn synthesize_all(&mut self, ssml: &[&str]) -> Result<Vec<SynthesisOutput>> {
+        for ssml in ssml {
+            self.client.charge_quota(ssml)?;
+        }
+        self.turns(ssml)
+    }
+
+    /// [`Session::synthesize_all`] without charging the quota.
+    pub(crate) fn turns(&mut self, ssml: &[&str]) -> Result<Vec<SynthesisOutput>> {
+        if self.failed {
+            bail!("session {} ended after an error", self.connection_id);
+        }
+        let outputs = self.run(ssml);
+        if let Err(e) = &outputs {
+            self.failed = true;
+            if let Some(observer) = &self.client.event_observer {
+                observer.error(e);
+            }
+        }
+        outputs
+    }
+
+    fn send(&mut self, message: Message) -> Result<()> {
+        if let Some(dump) = &self.dump {
+            dump.sent(&message);
+        }
+        Ok(self.socket.send(message)?)
+    }
+
+    fn run(&mut self, ssml: &[&str]) -> Result<Vec<SynthesisOutput>> {
+        let started = self.opened.take().unwrap_or_else(Instant::now);
+        if !self.configured {
+            let speech_config = self.client.speech_config(&self.output_format);
+            self.send(speech_config_message(&speech_config))?;
+            self.configured = true;
+        }
+        let ids: Vec<String> = ssml.iter().map(|_| random_request_id()).collect();
+        for (id, ssml) in ids.iter().zip(ssml) {
+            self.send(Message::Text(format!("X-RequestId:{}\r\nContent-Type:application/ssml+xml\r\nPath:ssml\r\n\r\n{}", id, ssml)))?;
+        }
+        trace_event!(debug, turns = ids.len(), connection_id = self.connection_id, "sent session ssml");
+        let mut outputs: Vec<SynthesisOutput> = ids
+            .iter()
+            .map(|id| SynthesisOutput {
+                ids: Some(TurnIds { request_id: id.clone(), connection_id: self.connection_id.clone() }),
+                turn_info: Some(TurnInfo::default()),
+                ..SynthesisOutput::default()
+            })
+            .collect();
+        let mut finished = vec![false; ids.len()];
+        let turn = |id: Option<&str>, path: &'static str| ids.iter().position(|i| Some(i.as_str()) == id).ok_or(FrameError::RequestIdMismatch { path });
+        let observer = self.client.event_observer.clone();
+        while finished.contains(&false) {
+            let message = match self.socket.read() {
+                Ok(message) => message,
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    self.send(Message::Ping(Vec::new()))?;
+                    continue;
+                }
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Err(Error::ConnectionClosedByServer { code: None, reason: String::new() }.into());
+                }
+                Err(tungstenite::Error::Capacity(CapacityError::MessageTooLong { size, max_size })) => {
+                    return Err(Error::MessageTooLarge { size, limit: max_size }.into());
+                }
+                Err(e) => return Err(anyhow::Error::from(e).context("socket read error")),
+            };
+            if let Some(dump) = &self.dump {
+                dump.received(&message);
+            }
+            match message {
+                Message::Text(text) => {
+                    let frame = parse_text_frame(&text);
+                    match frame.path() {
+                        Some("turn.start") => {
+                            let i = turn(frame.request_id(), "turn.start")?;
+                            outputs[i].turn_info.get_or_insert_with(TurnInfo::default).read_text("turn.start", frame.body);
+                            if let Some(observer) = &observer {
+                                observer.turn_started(&ids[i]);
+                            }
+                        }
+                        Some("response") => {
+                            let i = turn(frame.request_id(), "response")?;
+                            outputs[i].turn_info.get_or_insert_with(TurnInfo::default).read_text("response", frame.body);
+                        }
+                        Some("turn.end") => {
+                            let i = turn(frame.request_id(), "turn.end")?;
+                            finished[i] = true;
+                            if let Some(observer) = &observer {
+                                observer.turn_ended(&ids[i]);
+                            }
+                        }
+                        Some("audio.metadata") => {
+                            let (i, boundaries) = (turn(frame.request_id(), "audio.metadata")?, parse_metadata(frame.body)?);
+                            if let Some(observer) = &observer {
+                                observer.metadata(&ids[i], &boundaries);
+                            }
+                            outputs[i].boundaries.extend(boundaries);
+                        }
+                        _ => {}
+                    }
+                }
+                Message::Binary(data) => {
+                    let frame = parse_binary_frame(&data)?;
+                    if frame.path() == Some("audio") {
+                        let i = turn(frame.request_id(), "audio")?;
+                        if let Some(observer) = &observer {
+                            observer.audio(&ids[i], frame.body);
+                        }
+                        let output = &mut outputs[i];
+                        if self.client.strict_format && output.audio.is_empty() && !frame.body.is_empty() {
+                            OutputFormat::new(self.output_format.as_str()).check_start(frame.body)?;
+                        }
+                        output.turn_info.get_or_insert_with(TurnInfo::default).read_audio(&frame);
+                        output.first_audio.get_or_insert_with(|| started.elapsed());
+                        output.audio.extend_from_slice(frame.body);
+                        if let Some(limit) = self.client.max_audio_size.filter(|&limit| output.audio.len() as u64 > limit) {
+                            return Err(Error::AudioTooLarge { limit }.into());
+                        }
+                    }
+                }
+                Message::Ping(_) => self.socket.flush()?,
+                Message::Close(frame) => {
+                    return Err(Error::ConnectionClosedByServer {
+                        code: frame.as_ref().map(|f| u16::from(f.code)),
+                        reason: frame.map(|f| f.reason.into_owned()).unwrap_or_default(),
+                    }
+                    .into());
+                }
+                _ => {}
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.socket.can_write() {
+            close(&mut self.socket, self.client.close_timeout());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_ssml;
+    use crate::testing::{MockReply, MockServer};
+
+    #[test]
+    fn runs_turns_on_one_connection() {
+        let server = MockServer::start(vec![MockReply::turn(b"audio"), vec![MockReply::Close { code: 1011, reason: String::new() }]]).unwrap();
+        let client = server.client();
+        let mut session = client.session("audio-24khz-48kbitrate-mono-mp3").unwrap();
+        let (hello, bye) = (build_ssml("Hello", "en-US-AriaNeural", "default", "default", "default"), build_ssml("Bye", "en-US-AriaNeural", "default", "default", "default"));
+        let first = session.synthesize(&hello).unwrap();
+        assert_eq!(first.audio, b"audio");
+        assert_eq!(first.ids.unwrap().connection_id, session.connection_id());
+        session.set_output_format("raw-24khz-16bit-mono-pcm");
+        let outputs = session.synthesize_all(&[&bye, &hello]).unwrap();
+        assert_eq!(outputs.iter().map(|o| o.audio.as_slice()).collect::<Vec<_>>(), [b"audio"; 2]);
+        drop(session);
+        let requests = server.requests();
+        assert_eq!(requests.iter().map(|r| r.ssml.contains("Bye")).collect::<Vec<_>>(), [false, true, false]);
+        assert!(requests[0].speech_config.contains("mp3") && requests[2].speech_config.contains("pcm"));
+
+        let mut closed = client.session("audio-24khz-48kbitrate-mono-mp3").unwrap();
+        assert!(closed.synthesize(&hello).is_err());
+        assert!(closed.synthesize(&hello).unwrap_err().to_string().contains("ended after an error"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::metadata::Boundary;
+use crate::pcm::{loud_range, to_bytes, to_samples};
+use crate::wav::{wav_data, wav_header};
+use crate::{Codec, Container, OutputFormat, SynthesisOutput};
+
+/// Silence post-processing of 16-bit PCM output, see [`adjust_silence`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SilenceOptions {
+    /// Remove leading and trailing samples quieter than this many dBFS, eg: -50.
+    pub trim_threshold_db: Option<f32>,
+    pub pad_start: Duration,
+    pub pad_end: Duration,
+}
+
+impl SilenceOptions {
+    pub fn trim(threshold_db: f32) -> Self {
+        Self {
+            trim_threshold_db: Some(threshold_db),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_padding(mut self, start: Duration, end: Duration) -> Self {
+        self.pad_start = start;
+        self.pad_end = end;
+        self
+    }
+
+    fn is_noop(&self) -> bool {
+        self.trim_threshold_db.is_none() && self.pad_start.is_zero() && self.pad_end.is_zero()
+    }
+}
+
+/// Whether [`adjust_silence`] supports `format`.
+pub(crate) fn is_pcm16(format: &OutputFormat) -> bool {
+    matches!(format.container(), Container::Raw | Container::Riff) && format.codec() == Codec::Pcm && format.bits_per_sample() == Some(16)
+}
+
+/// Trim and pad the silence of `output`, raw or RIFF 16-bit PCM in `format`, moving the boundaries along. Audio
+/// that is silent throughout is trimmed to nothing.
+pub fn adjust_silence(output: SynthesisOutput, format: &OutputFormat, options: &SilenceOptions) -> Result<SynthesisOutput> {
+    let (true, Some(sample_rate)) = (is_pcm16(format), format.sample_rate()) else { bail!("{} is not 16-bit PCM", format) };
+    if options.is_noop() {
+        return Ok(output);
+    }
+    let riff = format.container() == Container::Riff;
+    let channels = format.channels().max(1) as usize;
+    let samples = to_samples(if riff { wav_data(&output.audio).unwrap_or_default() } else { &output.audio });
+    let range = match options.trim_threshold_db {
+        // Whole sample frames of all channels.
+        Some(threshold) => loud_range(&samples, threshold)
+            .map(|r| r.start / channels * channels..r.end.div_ceil(channels) * channels)
+            .unwrap_or(0..0),
+        None => 0..samples.len(),
+    };
+    let frames = |d: Duration| (d.as_secs_f64() * sample_rate as f64).round() as usize * channels;
+    let mut adjusted = vec![0i16; frames(options.pad_start)];
+    adjusted.extend_from_slice(samples.get(range.clone()).unwrap_or_default());
+    adjusted.resize(adjusted.len() + frames(options.pad_end), 0);
+    let trimmed = Duration::from_secs_f64((range.start / channels) as f64 / sample_rate as f64);
+    let boundaries = output
+        .boundaries
+        .into_iter()
+        .map(|b| Boundary {
+            offset: (b.offset + options.pad_start).saturating_sub(trimmed),
+            ..b
+        })
+        .collect();
+    let mut audio = to_bytes(&adjusted);
+    if riff {
+        audio = [wav_header(sample_rate, 16, channels as u16, audio.len() as u32), audio].concat();
+    }
+    Ok(SynthesisOutput { audio, boundaries, duration: None, first_audio: output.first_audio, ids: output.ids, turn_info: output.turn_info }.with_duration(format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundaryKind;
+
+    #[test]
+    fn trims_and_pads() {
+        let format = OutputFormat::new("riff-1khz-16bit-mono-pcm");
+        let mut samples = vec![0i16; 20];
+        samples.extend([5000; 10]);
+        samples.extend([0; 30]);
+        let data = to_bytes(&samples);
+        let output = SynthesisOutput {
+            audio: [wav_header(1000, 16, 1, data.len() as u32), data].concat(),
+            boundaries: vec![Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(20), duration: Duration::from_millis(10), text: "a".to_owned() }],
+            ..Default::default()
+        };
+        let options = SilenceOptions::trim(-50.0).with_padding(Duration::from_millis(5), Duration::from_millis(3));
+        let adjusted = adjust_silence(output, &format, &options).unwrap();
+        assert_eq!(to_samples(wav_data(&adjusted.audio).unwrap()), [vec![0; 5], vec![5000; 10], vec![0; 3]].concat());
+        assert_eq!(adjusted.boundaries[0].offset, Duration::from_millis(5));
+        assert_eq!(adjusted.duration, Some(Duration::from_millis(18)));
+        assert!(adjust_silence(SynthesisOutput::default(), &OutputFormat::default(), &options).is_err());
+    }
+}
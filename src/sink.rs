@@ -0,0 +1,135 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::writer::WrittenAudio;
+use crate::{Client, SynthesisRequest};
+
+/// Destination of streamed audio, see [`Client::synthesize_to_sink`].
+///
+/// Every [`Write`] is a sink, eg: `Vec<u8>` or a socket. [`FileSink`] and `S3Sink` (feature `s3`) only make the
+/// audio visible once it is complete.
+pub trait AudioSink {
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<()>;
+
+    /// All audio was written.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The synthesis failed; discard what was written if possible.
+    fn abort(&mut self) {}
+}
+
+impl<W: Write + ?Sized> AudioSink for W {
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        Ok(self.write_all(chunk)?)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(self.flush()?)
+    }
+}
+
+/// Audio written to a temporary file next to `path`, renamed to `path` when complete and removed on failure.
+#[derive(Debug)]
+pub struct FileSink {
+    path: PathBuf,
+    tmp: PathBuf,
+    file: Option<File>,
+}
+
+impl FileSink {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let tmp = path.with_extension(format!("{}.tmp", extension));
+        let file = File::create(&tmp).with_context(|| tmp.display().to_string())?;
+        Ok(Self { path, tmp, file: Some(file) })
+    }
+}
+
+impl AudioSink for FileSink {
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        match &mut self.file {
+            Some(file) => Ok(file.write_all(chunk)?),
+            None => anyhow::bail!("{} already finished", self.path.display()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            file.sync_all()?;
+            drop(file);
+            fs::rename(&self.tmp, &self.path).with_context(|| self.path.display().to_string())?;
+        }
+        Ok(())
+    }
+
+    fn abort(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// [`Write`] over an [`AudioSink`].
+struct SinkWriter<'a>(&'a mut dyn AudioSink);
+
+impl Write for SinkWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_chunk(buf).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Stream the audio of `request` into `sink`, then [`AudioSink::finish`] it, or [`AudioSink::abort`] it if the
+    /// synthesis or the sink fails.
+    pub fn synthesize_to_sink(&self, request: &SynthesisRequest, sink: &mut dyn AudioSink) -> Result<WrittenAudio> {
+        match self.synthesize_to_writer(request, &mut SinkWriter(&mut *sink)).and_then(|written| Ok((written, sink.finish()?))) {
+            Ok((written, ())) => Ok(written),
+            Err(e) => {
+                sink.abort();
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_sink_renames_when_finished() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-sink-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.mp3");
+        let mut sink = FileSink::create(&path).unwrap();
+        sink.write_chunk(b"ab").unwrap();
+        assert!(!path.exists());
+        sink.finish().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"ab");
+        let mut failed = FileSink::create(dir.join("b.mp3")).unwrap();
+        failed.write_chunk(b"x").unwrap();
+        failed.abort();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let mut memory = Vec::new();
+        AudioSink::write_chunk(&mut memory, b"cd").unwrap();
+        assert_eq!(memory, b"cd");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
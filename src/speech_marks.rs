@@ -0,0 +1,112 @@
+use serde_json::json;
+
+use crate::{Boundary, BoundaryKind};
+
+/// Newline-delimited JSON speech marks of `boundaries` in the format of Amazon Polly, eg:
+/// `{"end":5,"start":0,"time":6,"type":"word","value":"Hello"}`, for tools built around Polly.
+///
+/// `time` is the audio offset in milliseconds, `start` and `end` the UTF-8 byte range of a word or sentence in
+/// `text`, the input of the synthesis, found by searching it in order; both are the end of the previous mark
+/// when it isn't found, eg: for words of escaped SSML. Bookmarks are `ssml` marks spanning their element, and
+/// visemes are Polly's closest visemes, eg: "p" for 21, without a range.
+pub fn to_speech_marks(boundaries: &[Boundary], text: &str) -> String {
+    let mut marks = String::new();
+    // Sentences contain words, so each kind is searched from its own position.
+    let (mut words, mut sentences, mut bookmarks) = (0, 0, 0);
+    for boundary in boundaries {
+        let time = boundary.offset.as_millis() as u64;
+        let (kind, cursor) = match boundary.kind {
+            BoundaryKind::Word => ("word", &mut words),
+            BoundaryKind::Sentence => ("sentence", &mut sentences),
+            BoundaryKind::Bookmark => ("ssml", &mut bookmarks),
+            BoundaryKind::Viseme(id) => {
+                marks.push_str(&json!({ "time": time, "type": "viseme", "value": polly_viseme(id) }).to_string());
+                marks.push('\n');
+                continue;
+            }
+        };
+        let (start, end) = match boundary.kind {
+            BoundaryKind::Bookmark => find_bookmark(text, *cursor, &boundary.text),
+            _ => text.get(*cursor..).and_then(|rest| rest.find(&boundary.text)).map(|at| (*cursor + at, *cursor + at + boundary.text.len())),
+        }
+        .unwrap_or((*cursor, *cursor));
+        *cursor = end;
+        marks.push_str(&json!({ "time": time, "type": kind, "start": start, "end": end, "value": boundary.text }).to_string());
+        marks.push('\n');
+    }
+    marks
+}
+
+/// The byte range of the element, eg: `<bookmark mark="name"/>`, whose mark is `name`.
+fn find_bookmark(text: &str, from: usize, name: &str) -> Option<(usize, usize)> {
+    let mut at = from;
+    while let Some(found) = text.get(at..)?.find("<bookmark") {
+        let start = at + found;
+        let end = start + text.get(start..)?.find('>')? + 1;
+        let element = text.get(start..end)?;
+        if element.contains(&format!("\"{}\"", name)) || element.contains(&format!("'{}'", name)) {
+            return Some((start, end));
+        }
+        at = end;
+    }
+    None
+}
+
+/// Polly's viseme for an Azure viseme id of US English, eg: "sil" for 0.
+fn polly_viseme(id: u32) -> &'static str {
+    match id {
+        1 | 5 => "@",
+        2 | 9 | 11 => "a",
+        3 | 10 => "O",
+        4 => "E",
+        6 => "i",
+        7 => "u",
+        8 => "o",
+        12 | 20 => "k",
+        13 => "r",
+        14 | 19 => "t",
+        15 => "s",
+        16 => "S",
+        17 => "T",
+        18 => "f",
+        21 => "p",
+        _ => "sil",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn writes_polly_speech_marks() {
+        let boundary = |kind: BoundaryKind, ms: u64, text: &str| Boundary {
+            kind,
+            offset: Duration::from_millis(ms),
+            duration: Duration::ZERO,
+            text: text.to_owned(),
+        };
+        let text = r#"<speak>Hello <bookmark mark="m1"/>"wide" world.</speak>"#;
+        let boundaries = [
+            boundary(BoundaryKind::Sentence, 50, "Hello \"wide\" world."),
+            boundary(BoundaryKind::Word, 50, "Hello"),
+            boundary(BoundaryKind::Viseme(21), 60, ""),
+            boundary(BoundaryKind::Bookmark, 400, "m1"),
+            boundary(BoundaryKind::Word, 400, "\"wide\""),
+            boundary(BoundaryKind::Word, 900, "world"),
+            boundary(BoundaryKind::Word, 1200, "missing"),
+        ];
+        let marks = to_speech_marks(&boundaries, text);
+        assert_eq!(marks.lines().collect::<Vec<_>>(), [
+            r#"{"end":0,"start":0,"time":50,"type":"sentence","value":"Hello \"wide\" world."}"#,
+            r#"{"end":12,"start":7,"time":50,"type":"word","value":"Hello"}"#,
+            r#"{"time":60,"type":"viseme","value":"p"}"#,
+            r#"{"end":34,"start":13,"time":400,"type":"ssml","value":"m1"}"#,
+            r#"{"end":40,"start":34,"time":400,"type":"word","value":"\"wide\""}"#,
+            r#"{"end":46,"start":41,"time":900,"type":"word","value":"world"}"#,
+            r#"{"end":46,"start":46,"time":1200,"type":"word","value":"missing"}"#,
+        ]);
+    }
+}
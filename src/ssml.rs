@@ -0,0 +1,441 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use xml::common::Position;
+use xml::escape::{escape_str_attribute, escape_str_pcdata};
+use xml::name::OwnedName;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::Lexicon;
+
+/// Elements the Edge service reads, with their attributes. `<audio>` and `<lexicon>` are rejected or ignored by it.
+const ELEMENTS: &[(&str, &[&str])] = &[
+    ("speak", &["version", "xml:lang"]),
+    ("voice", &["name"]),
+    ("prosody", &["pitch", "rate", "volume", "contour", "range"]),
+    ("break", &["time", "strength"]),
+    ("mstts:silence", &["type", "value"]),
+    ("mstts:express-as", &["style", "styledegree", "role"]),
+    ("phoneme", &["alphabet", "ph"]),
+    ("sub", &["alias"]),
+    ("say-as", &["interpret-as", "format", "detail"]),
+    ("bookmark", &["mark"]),
+    ("emphasis", &["level"]),
+    ("lang", &["xml:lang"]),
+    ("p", &[]),
+    ("s", &[]),
+];
+
+/// SSML of one voice built piece by piece, for content that [`crate::build_ssml`] can't express, eg:
+///
+/// ```
+/// use edge_tts::Ssml;
+///
+/// let ssml = Ssml::new("en-US-AriaNeural").with_rate("+10%").text("First slide.").bookmark("slide-2").text("Second slide.");
+/// assert!(ssml.to_string().contains("First slide.<bookmark mark=\"slide-2\"/>Second slide."));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ssml {
+    voice: String,
+    pitch: Option<String>,
+    rate: Option<String>,
+    volume: Option<String>,
+    /// Style and style degree.
+    style: Option<(String, Option<String>)>,
+    /// Escaped content of the prosody element.
+    body: String,
+}
+
+/// How [`Ssml::say_as`] reads its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SayAs {
+    /// eg: "2/3/2025" with [`DateFormat::Mdy`] as February third.
+    Date(DateFormat),
+    /// eg: "1:30pm" with [`TimeFormat::Hms12`].
+    Time(TimeFormat),
+    /// eg: "(888) 555-1212"
+    Telephone,
+    /// eg: "10" as ten.
+    Cardinal,
+    /// eg: "10" as tenth.
+    Ordinal,
+    /// Letter by letter, eg: "SSML".
+    Characters,
+}
+
+/// Order of the day, month and year of a [`SayAs::Date`], eg: `Mdy` for "2/3/2025".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateFormat {
+    Mdy,
+    Dmy,
+    Ymd,
+    Md,
+    Dm,
+    Ym,
+    My,
+    D,
+    M,
+    Y,
+}
+
+/// 12 or 24-hour clock of a [`SayAs::Time`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeFormat {
+    Hms12,
+    Hms24,
+}
+
+/// Length of a [`Ssml::break_strength`] pause, from none to the one after a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BreakStrength {
+    None,
+    XWeak,
+    Weak,
+    Medium,
+    Strong,
+    XStrong,
+}
+
+impl BreakStrength {
+    /// eg: "x-weak"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakStrength::None => "none",
+            BreakStrength::XWeak => "x-weak",
+            BreakStrength::Weak => "weak",
+            BreakStrength::Medium => "medium",
+            BreakStrength::Strong => "strong",
+            BreakStrength::XStrong => "x-strong",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [BreakStrength::None, BreakStrength::XWeak, BreakStrength::Weak, BreakStrength::Medium, BreakStrength::Strong, BreakStrength::XStrong]
+            .into_iter()
+            .find(|strength| strength.as_str() == s)
+    }
+}
+
+impl SayAs {
+    /// `interpret-as` and `format` attributes.
+    fn attributes(self) -> String {
+        let (interpret_as, format) = match self {
+            SayAs::Date(format) => ("date", Some(format.as_str())),
+            SayAs::Time(TimeFormat::Hms12) => ("time", Some("hms12")),
+            SayAs::Time(TimeFormat::Hms24) => ("time", Some("hms24")),
+            SayAs::Telephone => ("telephone", None),
+            SayAs::Cardinal => ("cardinal", None),
+            SayAs::Ordinal => ("ordinal", None),
+            SayAs::Characters => ("characters", None),
+        };
+        match format {
+            Some(format) => format!("interpret-as=\"{}\" format=\"{}\"", interpret_as, format),
+            None => format!("interpret-as=\"{}\"", interpret_as),
+        }
+    }
+}
+
+impl DateFormat {
+    /// eg: "mdy"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DateFormat::Mdy => "mdy",
+            DateFormat::Dmy => "dmy",
+            DateFormat::Ymd => "ymd",
+            DateFormat::Md => "md",
+            DateFormat::Dm => "dm",
+            DateFormat::Ym => "ym",
+            DateFormat::My => "my",
+            DateFormat::D => "d",
+            DateFormat::M => "m",
+            DateFormat::Y => "y",
+        }
+    }
+}
+
+impl Ssml {
+    /// `voice`: eg: "en-US-AriaNeural"
+    pub fn new(voice: &str) -> Self {
+        Self { voice: voice.to_owned(), pitch: None, rate: None, volume: None, style: None, body: String::new() }
+    }
+
+    /// See [`crate::build_ssml`] for the values of pitch, rate and volume; "default" if unset.
+    pub fn with_pitch(mut self, pitch: &str) -> Self {
+        self.pitch = Some(pitch.to_owned());
+        self
+    }
+
+    pub fn with_rate(mut self, rate: &str) -> Self {
+        self.rate = Some(rate.to_owned());
+        self
+    }
+
+    pub fn with_volume(mut self, volume: &str) -> Self {
+        self.volume = Some(volume.to_owned());
+        self
+    }
+
+    /// Speak in `style` of the voice, eg: "cheerful", with `degree` from "0.01" to "2" as its intensity, "1" if unset.
+    pub fn with_style(mut self, style: &str, degree: Option<&str>) -> Self {
+        self.style = Some((style.to_owned(), degree.map(str::to_owned)));
+        self
+    }
+
+    /// Plain text, escaped.
+    pub fn text(mut self, text: &str) -> Self {
+        self.body += &escape_str_pcdata(text);
+        self
+    }
+
+    /// Plain text with the words of `lexicon` said its way.
+    pub fn text_with_lexicon(mut self, text: &str, lexicon: &Lexicon) -> Self {
+        self.body += &lexicon.to_ssml(text);
+        self
+    }
+
+    /// `text` said as `ipa`, eg: `.phoneme("nginx", "ˈɛndʒɪnˈɛks")`.
+    pub fn phoneme(mut self, text: &str, ipa: &str) -> Self {
+        self.body += &format!("<phoneme alphabet=\"ipa\" ph=\"{}\">{}</phoneme>", escape_str_attribute(ipa), escape_str_pcdata(text));
+        self
+    }
+
+    /// Plain text with the words of `lexicon` said its way and markers like `[pause:1s]`, `[pause:500ms]` or
+    /// `[pause:strong]` (see [`BreakStrength`]) as breaks, eg: for the pacing of a podcast script. Other brackets are
+    /// text.
+    pub fn text_with_pauses(mut self, text: &str, lexicon: &Lexicon) -> Self {
+        let mut rest = text;
+        while let Some(start) = rest.find("[pause:") {
+            let (before, marker) = rest.split_at(start);
+            let pause = marker["[pause:".len()..].split_once(']').and_then(|(value, after)| Some((Pause::parse(value.trim())?, after)));
+            match pause {
+                Some((pause, after)) => {
+                    self.body += &lexicon.to_ssml(before);
+                    self.body += &pause.to_ssml();
+                    rest = after;
+                }
+                None => {
+                    self.body += &lexicon.to_ssml(&rest[..=start]);
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        self.body += &lexicon.to_ssml(rest);
+        self
+    }
+
+    /// A pause of `time`, eg: `.break_time(Duration::from_millis(500))` for `<break time="500ms"/>`.
+    pub fn break_time(mut self, time: Duration) -> Self {
+        self.body += &Pause::Time(time).to_ssml();
+        self
+    }
+
+    /// A pause as long as `strength` says, eg: [`BreakStrength::Strong`] for the one after a sentence.
+    pub fn break_strength(mut self, strength: BreakStrength) -> Self {
+        self.body += &Pause::Strength(strength).to_ssml();
+        self
+    }
+
+    /// `alias` read instead of `text`, eg: `.sub("SQL", "sequel")`.
+    pub fn sub(mut self, text: &str, alias: &str) -> Self {
+        self.body += &format!("<sub alias=\"{}\">{}</sub>", escape_str_attribute(alias), escape_str_pcdata(text));
+        self
+    }
+
+    /// `text` read as `say_as`, eg: `.say_as("2/3/2025", SayAs::Date(DateFormat::Dmy))` for the 2nd of March.
+    pub fn say_as(mut self, text: &str, say_as: SayAs) -> Self {
+        self.body += &format!("<say-as {}>{}</say-as>", say_as.attributes(), escape_str_pcdata(text));
+        self
+    }
+
+    /// A `<bookmark mark="..."/>`, reported with its audio offset as a [`crate::BoundaryKind::Bookmark`] when
+    /// [`crate::MetadataOptions::bookmark_enabled`] is set, eg: to change slides at that point.
+    pub fn bookmark(mut self, mark: &str) -> Self {
+        self.body += &format!("<bookmark mark=\"{}\"/>", escape_str_attribute(mark));
+        self
+    }
+}
+
+enum Pause {
+    Time(Duration),
+    Strength(BreakStrength),
+}
+
+impl Pause {
+    /// eg: "1s", "500ms", "1.5s" or "strong"
+    fn parse(s: &str) -> Option<Self> {
+        let seconds = match s.strip_suffix("ms") {
+            Some(ms) => ms.parse::<f64>().ok()? / 1000.0,
+            None => match s.strip_suffix('s').and_then(|secs| secs.parse::<f64>().ok()) {
+                Some(secs) => secs,
+                None => return BreakStrength::parse(s).map(Pause::Strength),
+            },
+        };
+        Duration::try_from_secs_f64(seconds).ok().map(Pause::Time)
+    }
+
+    fn to_ssml(&self) -> String {
+        match self {
+            Pause::Time(time) => format!("<break time=\"{}ms\"/>", time.as_millis()),
+            Pause::Strength(strength) => format!("<break strength=\"{}\"/>", strength.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for Ssml {
+    /// The same document as [`crate::build_ssml`] for plain text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prosody = |value: &Option<String>| escape_str_attribute(value.as_deref().unwrap_or("default")).into_owned();
+        let (express_as, end_express_as) = match &self.style {
+            Some((style, None)) => (format!("<mstts:express-as style=\"{}\">", escape_str_attribute(style)), "</mstts:express-as>"),
+            Some((style, Some(degree))) => (
+                format!("<mstts:express-as style=\"{}\" styledegree=\"{}\">", escape_str_attribute(style), escape_str_attribute(degree)),
+                "</mstts:express-as>",
+            ),
+            None => (String::new(), ""),
+        };
+        write!(
+            f,
+            "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xmlns:mstts=\"https://www.w3.org/2001/mstts\" xml:lang=\"en-US\"><voice name=\"{}\">{}<prosody pitch=\"{}\" rate=\"{}\" volume=\"{}\">{}</prosody>{}</voice></speak>",
+            escape_str_attribute(&self.voice),
+            express_as,
+            prosody(&self.pitch),
+            prosody(&self.rate),
+            prosody(&self.volume),
+            self.body,
+            end_express_as
+        )
+    }
+}
+
+/// Check `ssml` before spending quota on it: it must be well-formed, with only the elements and attributes of the
+/// Edge service, text only inside a `<voice>`, and voices in `voices`, or of the shape "en-US-AriaNeural" if
+/// `voices` is empty. Fails with every problem found, one per line, eg: "1:120: unsupported element <audio>".
+pub fn validate_ssml(ssml: &str, voices: &[&str]) -> Result<()> {
+    let mut problems = Vec::new();
+    let mut reader = EventReader::from_str(ssml);
+    let mut open: Vec<String> = Vec::new();
+    let mut voice_count = 0;
+    loop {
+        let event = reader.next();
+        // Of the event just read.
+        let position = reader.position();
+        let at = format!("{}:{}", position.row + 1, position.column + 1);
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                problems.push(format!("{}: {}", at, e.msg()));
+                break;
+            }
+        };
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let element = qualified(&name);
+                match ELEMENTS.iter().find(|(e, _)| *e == element) {
+                    None => problems.push(format!("{}: unsupported element <{}>", at, element)),
+                    Some((_, allowed)) => {
+                        for attribute in attributes.iter().map(|a| qualified(&a.name)).filter(|a| !allowed.contains(&a.as_str())) {
+                            problems.push(format!("{}: unsupported attribute {} of <{}>", at, attribute, element));
+                        }
+                    }
+                }
+                if open.is_empty() != (element == "speak") {
+                    problems.push(format!("{}: <speak> must be the root element, not inside it", at));
+                }
+                if element == "voice" {
+                    voice_count += 1;
+                    match attributes.iter().find(|a| a.name.local_name == "name") {
+                        Some(voice) if !is_voice(&voice.value, voices) => problems.push(format!("{}: unknown voice {}", at, voice.value)),
+                        Some(_) => {}
+                        None => problems.push(format!("{}: <voice> has no name", at)),
+                    }
+                }
+                open.push(element);
+            }
+            XmlEvent::EndElement { .. } => {
+                open.pop();
+            }
+            XmlEvent::Characters(text) if !open.iter().any(|e| e == "voice") => problems.push(format!("{}: text outside <voice>: {}", at, text.trim())),
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+    if problems.is_empty() && voice_count == 0 {
+        problems.push("no <voice> element".to_owned());
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!("{}", problems.join("\n")));
+    }
+    Ok(())
+}
+
+/// eg: "mstts:silence"
+fn qualified(name: &OwnedName) -> String {
+    match &name.prefix {
+        Some(prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+fn is_voice(name: &str, voices: &[&str]) -> bool {
+    if !voices.is_empty() {
+        return voices.iter().any(|voice| voice.eq_ignore_ascii_case(name));
+    }
+    // eg: "Microsoft Server Speech Text to Speech Voice (zh-CN, XiaoxiaoNeural)"
+    if let Some(inner) = name.strip_prefix("Microsoft Server Speech Text to Speech Voice (").and_then(|rest| rest.strip_suffix(')')) {
+        return inner.split_once(", ").is_some_and(|(locale, voice)| is_voice(&format!("{}-{}", locale, voice), &[]));
+    }
+    let mut parts = name.split('-');
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && !region.is_empty()
+        && region.chars().all(|c| c.is_ascii_alphanumeric())
+        && parts.next_back().is_some_and(|voice| voice.len() > "Neural".len() && voice.ends_with("Neural"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_ssml;
+
+    #[test]
+    fn builds_ssml() {
+        assert_eq!(Ssml::new("en-US-AriaNeural").with_pitch("+5Hz").text("a < b").to_string(), build_ssml("a < b", "en-US-AriaNeural", "+5Hz", "default", "default"));
+        let ssml = Ssml::new("en-US-AriaNeural").text("Next").bookmark("slide \"2\"").to_string();
+        assert!(ssml.contains("Next<bookmark mark=\"slide &quot;2&quot;\"/></prosody>"), "{}", ssml);
+        let ssml = Ssml::new("en-US-AriaNeural").say_as("2/3/2025", SayAs::Date(DateFormat::Dmy)).say_as("1:30pm", SayAs::Time(TimeFormat::Hms12)).say_as("3", SayAs::Ordinal).to_string();
+        assert!(ssml.contains(r#"<say-as interpret-as="date" format="dmy">2/3/2025</say-as><say-as interpret-as="time" format="hms12">1:30pm</say-as><say-as interpret-as="ordinal">3</say-as>"#), "{}", ssml);
+        let lexicon = Lexicon::new().with_ipa("nginx", "ˈɛndʒɪnˈɛks");
+        let ssml = Ssml::new("en-US-AriaNeural").phoneme("tomato", "təˈmɑːtoʊ").text_with_lexicon(" on nginx", &lexicon).to_string();
+        assert!(ssml.contains(r#"<phoneme alphabet="ipa" ph="təˈmɑːtoʊ">tomato</phoneme> on <phoneme alphabet="ipa" ph="ˈɛndʒɪnˈɛks">nginx</phoneme>"#), "{}", ssml);
+
+        let ssml = Ssml::new("en-US-AriaNeural").break_time(Duration::from_millis(500)).break_strength(BreakStrength::XStrong).to_string();
+        assert!(ssml.contains(r#"<break time="500ms"/><break strength="x-strong"/>"#), "{}", ssml);
+        let ssml = Ssml::new("en-US-AriaNeural").text_with_pauses("Welcome [pause:1.5s]to [x] nginx[pause:200ms][pause:strong] [pause:soon] [pause:", &lexicon).to_string();
+        assert!(
+            ssml.contains(r#">Welcome <break time="1500ms"/>to [x] <phoneme alphabet="ipa" ph="ˈɛndʒɪnˈɛks">nginx</phoneme><break time="200ms"/><break strength="strong"/> [pause:soon] [pause:</prosody>"#),
+            "{}",
+            ssml
+        );
+    }
+
+    #[test]
+    fn validates_ssml() {
+        let ssml = Ssml::new("en-US-AriaNeural").text("a < b").phoneme("tomato", "təˈmɑːtoʊ").break_time(Duration::from_secs(1)).to_string();
+        validate_ssml(&ssml, &[]).unwrap();
+        validate_ssml(&ssml, &["en-us-arianeural"]).unwrap();
+        assert_eq!(validate_ssml(&ssml, &["en-US-GuyNeural"]).unwrap_err().to_string(), "1:127: unknown voice en-US-AriaNeural");
+        validate_ssml(&build_ssml("Hi", "Microsoft Server Speech Text to Speech Voice (zh-CN, XiaoxiaoNeural)", "default", "default", "default"), &[]).unwrap();
+
+        let ssml = r#"<speak version="1.0" xml:lang="en-US">Hi<voice name="en-US-Aria"><audio src="a.mp3"/><break duration="1s"/></voice></speak>"#;
+        let problems = validate_ssml(ssml, &[]).unwrap_err().to_string();
+        assert_eq!(
+            problems.lines().collect::<Vec<_>>(),
+            ["1:39: text outside <voice>: Hi", "1:41: unknown voice en-US-Aria", "1:66: unsupported element <audio>", "1:86: unsupported attribute duration of <break>"]
+        );
+        assert!(validate_ssml("<speak><voice name=\"en-US-AriaNeural\">Hi</speak>", &[]).unwrap_err().to_string().starts_with("1:41: "));
+        assert_eq!(validate_ssml("<speak version=\"1.0\"></speak>", &[]).unwrap_err().to_string(), "no <voice> element");
+    }
+}
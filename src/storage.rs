@@ -0,0 +1,253 @@
+use std::fmt::Debug;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+use std::time::SystemTime;
+#[cfg(feature = "sqlite")]
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+
+/// A stored value, as listed by [`Storage::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub key: String,
+    pub len: u64,
+    /// Time of the last `put`.
+    pub modified: SystemTime,
+}
+
+/// Byte values by key, grouped in namespaces, eg: "cache" or "jobs". The empty namespace is the root.
+///
+/// Keys and namespace segments (separated by '/') are non-empty, don't start with '.' and don't contain '/' or
+/// '\\'. A `put` replaces the value at once: readers see the old or the new value, never a part.
+pub trait Storage: Debug + Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>>;
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()>;
+    /// Deleting a missing key succeeds.
+    fn delete(&self, namespace: &str, key: &str) -> Result<()>;
+    /// Entries of `namespace`, in no particular order. Nested namespaces aren't included.
+    fn list(&self, namespace: &str) -> Result<Vec<StorageEntry>>;
+
+    fn stat(&self, namespace: &str, key: &str) -> Result<Option<StorageEntry>> {
+        Ok(self.list(namespace)?.into_iter().find(|e| e.key == key))
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        bail!("invalid storage key or namespace: {:?}", name);
+    }
+    Ok(())
+}
+
+fn check_namespace(namespace: &str) -> Result<()> {
+    match namespace {
+        "" => Ok(()),
+        _ => namespace.split('/').try_for_each(check_name),
+    }
+}
+
+/// [`Storage`] of one file per value: `<root>/<namespace>/<key>`.
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    root: PathBuf,
+}
+
+impl FsStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn dir(&self, namespace: &str) -> Result<PathBuf> {
+        check_namespace(namespace)?;
+        Ok(self.root.join(namespace))
+    }
+
+    fn path(&self, namespace: &str, key: &str) -> Result<PathBuf> {
+        check_name(key)?;
+        Ok(self.dir(namespace)?.join(key))
+    }
+}
+
+impl Storage for FsStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(namespace, key)?) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()> {
+        let path = self.path(namespace, key)?;
+        fs::create_dir_all(self.dir(namespace)?)?;
+        // Names starting with '.' are never keys, so the temporary file isn't listed.
+        let tmp = self.dir(namespace)?.join(format!(".{}.tmp-{}", key, uuid::Uuid::new_v4().simple()));
+        fs::write(&tmp, value)?;
+        if let Err(e) = fs::rename(&tmp, path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(namespace, key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<StorageEntry>> {
+        let dir = match fs::read_dir(self.dir(namespace)?) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            match entry.file_name().into_string() {
+                Ok(key) if metadata.is_file() && check_name(&key).is_ok() => entries.push(StorageEntry {
+                    key,
+                    len: metadata.len(),
+                    modified: metadata.modified()?,
+                }),
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+
+    fn stat(&self, namespace: &str, key: &str) -> Result<Option<StorageEntry>> {
+        match fs::metadata(self.path(namespace, key)?) {
+            Ok(metadata) => Ok(Some(StorageEntry {
+                key: key.to_owned(),
+                len: metadata.len(),
+                modified: metadata.modified()?,
+            })),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// [`Storage`] in one SQLite database file.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: rusqlite::Connection) -> Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS storage (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value BLOB NOT NULL,
+                modified_ms INTEGER NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+        )?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, rusqlite::Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn to_entry(key: String, len: i64, modified_ms: i64) -> StorageEntry {
+    StorageEntry {
+        key,
+        len: len as u64,
+        modified: UNIX_EPOCH + Duration::from_millis(modified_ms as u64),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        use rusqlite::OptionalExtension;
+        let connection = self.lock();
+        let mut statement = connection.prepare_cached("SELECT value FROM storage WHERE namespace = ? AND key = ?")?;
+        Ok(statement.query_row((namespace, key), |row| row.get(0)).optional()?)
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()> {
+        check_namespace(namespace)?;
+        check_name(key)?;
+        let modified_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+        self.lock().execute("INSERT OR REPLACE INTO storage VALUES (?, ?, ?, ?)", (namespace, key, value, modified_ms))?;
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        self.lock().execute("DELETE FROM storage WHERE namespace = ? AND key = ?", (namespace, key))?;
+        Ok(())
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<StorageEntry>> {
+        let connection = self.lock();
+        let mut statement = connection.prepare_cached("SELECT key, length(value), modified_ms FROM storage WHERE namespace = ?")?;
+        let entries = statement.query_map([namespace], |row| Ok(to_entry(row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(entries.collect::<Result<_, _>>()?)
+    }
+
+    fn stat(&self, namespace: &str, key: &str) -> Result<Option<StorageEntry>> {
+        use rusqlite::OptionalExtension;
+        let connection = self.lock();
+        let mut statement = connection.prepare_cached("SELECT key, length(value), modified_ms FROM storage WHERE namespace = ? AND key = ?")?;
+        Ok(statement
+            .query_row((namespace, key), |row| Ok(to_entry(row.get(0)?, row.get(1)?, row.get(2)?)))
+            .optional()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(storage: &dyn Storage) {
+        assert_eq!(storage.get("jobs", "a").unwrap(), None);
+        storage.put("jobs", "a", b"one").unwrap();
+        storage.put("jobs", "a", b"two").unwrap();
+        storage.put("jobs/done", "b", b"three").unwrap();
+        storage.put("", "c", b"").unwrap();
+        assert_eq!(storage.get("jobs", "a").unwrap(), Some(b"two".to_vec()));
+        let keys: Vec<_> = storage.list("jobs").unwrap().into_iter().map(|e| (e.key, e.len)).collect();
+        assert_eq!(keys, vec![("a".to_owned(), 3)]);
+        assert_eq!(storage.stat("jobs/done", "b").unwrap().map(|e| e.len), Some(5));
+        storage.delete("jobs", "a").unwrap();
+        storage.delete("jobs", "a").unwrap();
+        assert_eq!(storage.get("jobs", "a").unwrap(), None);
+        assert!(storage.put("jobs", "../x", b"").is_err());
+        assert!(storage.put("../jobs", "x", b"").is_err());
+    }
+
+    #[test]
+    fn stores_in_namespaces() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-storage-{}", uuid::Uuid::new_v4().simple()));
+        exercise(&FsStorage::new(&dir));
+        fs::remove_dir_all(dir).unwrap();
+        #[cfg(feature = "sqlite")]
+        exercise(&SqliteStorage::open_in_memory().unwrap());
+    }
+}
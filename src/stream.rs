@@ -0,0 +1,72 @@
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use socks::Socks5Stream;
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{HandshakeError, WebSocket};
+
+/// Byte stream under the WebSocket.
+pub trait Stream: Read + Write + Send + Debug {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+/// Opens the byte stream a [`crate::Client`] speaks the WebSocket protocol on, see [`crate::Client::with_connector`].
+/// eg: `testing::MockServer::connector` (feature `testing`) connects to a local scripted server instead of the service.
+pub trait Connector: Debug + Send + Sync {
+    /// A stream to the host of `url`, with TLS already set up for `wss://`.
+    fn connect(&self, url: &url::Url) -> Result<Box<dyn Stream>>;
+}
+
+impl Stream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl Stream for Socks5Stream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.get_ref().set_read_timeout(timeout)
+    }
+}
+
+impl<S: Stream> Stream for native_tls::TlsStream<S> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.get_ref().set_read_timeout(timeout)
+    }
+}
+
+impl<S: Stream + ?Sized> Stream for Box<S> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+}
+
+/// Open a TCP (or TLS for `wss://`) stream to the host of `url`, optionally through a socks5 proxy.
+pub(crate) fn connect_stream(url: &url::Url, socks5_proxy: Option<&str>) -> Result<Box<dyn Stream>> {
+    let host = url.host_str().ok_or_else(|| anyhow!("no host in url {}", url))?;
+    let port = url.port_or_known_default().ok_or_else(|| anyhow!("no port in url {}", url))?;
+    let stream: Box<dyn Stream> = match socks5_proxy {
+        None => Box::new(TcpStream::connect((host, port))?),
+        Some(proxy_addr) => Box::new(Socks5Stream::connect(proxy_addr, (host, port))?),
+    };
+    if url.scheme() != "wss" {
+        return Ok(stream);
+    }
+    let tls_connector = native_tls::TlsConnector::new()?;
+    match tls_connector.connect(host, stream) {
+        Ok(tls_stream) => Ok(Box::new(tls_stream)),
+        Err(native_tls::HandshakeError::Failure(e)) => Err(e.into()),
+        Err(native_tls::HandshakeError::WouldBlock(_)) => Err(anyhow!("tls handshake interrupted")),
+    }
+}
+
+pub(crate) fn websocket_handshake(request: tungstenite::http::Request<()>, stream: Box<dyn Stream>, config: Option<WebSocketConfig>) -> Result<WebSocket<Box<dyn Stream>>> {
+    match tungstenite::client::client_with_config(request, stream, config) {
+        Ok((socket, _)) => Ok(socket),
+        Err(HandshakeError::Failure(e)) => Err(e.into()),
+        Err(HandshakeError::Interrupted(_)) => Err(anyhow!("websocket handshake interrupted")),
+    }
+}
@@ -0,0 +1,139 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+use crate::metadata::Boundary;
+use crate::pcm::{to_bytes, to_samples};
+use crate::resample::{deinterleave, interleave, resample};
+use crate::silence::is_pcm16;
+use crate::wav::{wav_data, wav_header};
+use crate::{Container, OutputFormat, SynthesisOutput};
+
+/// Change the tempo of `output`, raw or RIFF 16-bit PCM in `format`, by `speed`, eg: 1.1 for 10% faster, and its
+/// pitch by `semitones`, eg: -2, each without the other, for finer steps than the service's rate and pitch. The
+/// boundaries move along.
+///
+/// The tempo is changed by WSOLA, overlapping 40 ms windows where they match best, and the pitch by resampling
+/// that: fine for speech within about half to twice the speed and an octave, with artifacts beyond.
+pub fn stretch_pcm(output: SynthesisOutput, format: &OutputFormat, speed: f32, semitones: f32) -> Result<SynthesisOutput> {
+    let (true, Some(sample_rate)) = (is_pcm16(format), format.sample_rate()) else { bail!("{} is not 16-bit PCM", format) };
+    if !(speed.is_finite() && speed > 0.0 && semitones.is_finite()) {
+        bail!("can't stretch by a speed of {} and {} semitones", speed, semitones);
+    }
+    if speed == 1.0 && semitones == 0.0 {
+        return Ok(output);
+    }
+    let (speed, pitch) = (speed as f64, 2f64.powf(semitones as f64 / 12.0));
+    let riff = format.container() == Container::Riff;
+    let samples = to_samples(if riff { wav_data(&output.audio).unwrap_or_default() } else { &output.audio });
+    let planes: Vec<Vec<f64>> = deinterleave(&samples, format.channels()).into_iter().map(|plane| plane.into_iter().map(f64::from).collect()).collect();
+    // Stretched to `pitch / speed` of the length, then resampled to `1 / pitch` of that, which raises the pitch.
+    let stretched = wsola(&planes, speed / pitch, sample_rate);
+    let planes: Vec<Vec<i16>> = stretched
+        .iter()
+        .map(|plane| {
+            let plane: Vec<i16> = plane.iter().map(|&s| s.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16).collect();
+            resample(&plane, (sample_rate as f64 * pitch).round() as u32, sample_rate)
+        })
+        .collect();
+    let mut audio = to_bytes(&interleave(&planes));
+    if riff {
+        audio = [wav_header(sample_rate, 16, format.channels(), audio.len() as u32), audio].concat();
+    }
+    let boundaries = output.boundaries.into_iter().map(|b| Boundary { offset: b.offset.div_f64(speed), duration: b.duration.div_f64(speed), ..b }).collect();
+    Ok(SynthesisOutput { audio, boundaries, ..output }.with_duration(format))
+}
+
+/// `planes` played `tempo` times as fast at the same pitch.
+fn wsola(planes: &[Vec<f64>], tempo: f64, sample_rate: u32) -> Vec<Vec<f64>> {
+    let input_len = planes.first().map_or(0, Vec::len);
+    let window_len = (sample_rate as usize / 25).max(16) & !1;
+    let hop = window_len / 2;
+    let tolerance = window_len / 4;
+    let output_len = (input_len as f64 / tempo).round() as usize;
+    // A periodic Hann window: overlapping by half, windows add up to 1.
+    let window: Vec<f64> = (0..window_len).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / window_len as f64).cos()).collect();
+    let sample = |plane: &[f64], i: usize| plane.get(i).copied().unwrap_or(0.0);
+    let mut output = vec![vec![0.0; output_len + window_len]; planes.len()];
+    let mut previous = 0;
+    for frame in 0..output_len.div_ceil(hop) {
+        let position = match frame {
+            0 => 0,
+            _ => {
+                // The window whose start sounds most like the continuation of the previous one.
+                let natural = previous + hop;
+                let ideal = (frame * hop) as f64 * tempo;
+                let first = (ideal as usize).saturating_sub(tolerance);
+                let last = (ideal as usize + tolerance).min(input_len.saturating_sub(hop)).max(first);
+                let plane = &planes[0];
+                let score = |candidate: usize| {
+                    let (mut correlation, mut energy) = (0.0, 1e-9);
+                    for i in 0..hop {
+                        let s = sample(plane, candidate + i);
+                        correlation += sample(plane, natural + i) * s;
+                        energy += s * s;
+                    }
+                    correlation / energy.sqrt()
+                };
+                (first..=last).max_by(|&a, &b| score(a).total_cmp(&score(b))).unwrap_or(first)
+            }
+        };
+        for (plane, output) in planes.iter().zip(&mut output) {
+            for (i, &weight) in window.iter().enumerate() {
+                // The first window doesn't fade in.
+                let weight = if frame == 0 && i < hop { 1.0 } else { weight };
+                output[frame * hop + i] += weight * sample(plane, position + i);
+            }
+        }
+        previous = position;
+    }
+    for output in &mut output {
+        output.truncate(output_len);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::BoundaryKind;
+
+    fn sine(hz: f64, seconds: f64) -> Vec<i16> {
+        (0..(24000.0 * seconds) as usize).map(|i| ((2.0 * PI * hz * i as f64 / 24000.0).sin() * 10000.0) as i16).collect()
+    }
+
+    /// Amplitude of `samples` at `hz`, by correlation.
+    fn level(samples: &[i16], hz: f64) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (i, &s) in samples.iter().enumerate() {
+            let phase = 2.0 * PI * hz * i as f64 / 24000.0;
+            re += s as f64 * phase.cos();
+            im += s as f64 * phase.sin();
+        }
+        2.0 * (re * re + im * im).sqrt() / samples.len() as f64
+    }
+
+    #[test]
+    fn changes_tempo_and_pitch() {
+        let format = OutputFormat::RAW_24KHZ_16BIT_MONO_PCM;
+        let word = Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(500), duration: Duration::from_millis(200), text: "Hi".to_owned() };
+        let output = SynthesisOutput { audio: to_bytes(&sine(220.0, 1.0)), boundaries: vec![word], ..Default::default() };
+
+        let faster = stretch_pcm(output.clone(), &format, 2.0, 0.0).unwrap();
+        assert_eq!(faster.duration, Some(Duration::from_millis(500)));
+        assert_eq!((faster.boundaries[0].offset, faster.boundaries[0].duration), (Duration::from_millis(250), Duration::from_millis(100)));
+        let samples = to_samples(&faster.audio);
+        assert!(level(&samples[1200..10800], 220.0) > 8000.0, "{}", level(&samples[1200..10800], 220.0));
+
+        let higher = stretch_pcm(output.clone(), &format, 1.0, 12.0).unwrap();
+        let samples = to_samples(&higher.audio);
+        assert_eq!(samples.len(), 24000);
+        assert!(level(&samples[2400..21600], 440.0) > 8000.0, "{}", level(&samples[2400..21600], 440.0));
+        assert!(level(&samples[2400..21600], 220.0) < 1000.0);
+
+        assert_eq!(stretch_pcm(output.clone(), &format, 1.0, 0.0).unwrap(), output);
+        assert!(stretch_pcm(output, &format, 0.0, 0.0).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+use xml::escape::escape_str_pcdata;
+
+use crate::{Boundary, BoundaryKind};
+
+/// One subtitle cue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    /// Lines joined with '\n', without formatting tags.
+    pub text: String,
+}
+
+/// A subtitle format of [`SubtitleFormat::write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    /// Timed Text Markup Language, eg: for broadcast workflows.
+    Ttml,
+    /// Lyrics for music players, with a timestamp for each word.
+    Lrc,
+}
+
+impl SubtitleFormat {
+    /// Usual file extension, eg: "srt".
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::WebVtt => "vtt",
+            Self::Ttml => "ttml",
+            Self::Lrc => "lrc",
+        }
+    }
+
+    /// Subtitles of `boundaries` in this format, see [`cues_from_boundaries`] for `max_chars`.
+    pub fn write(&self, boundaries: &[Boundary], max_chars: usize) -> String {
+        self.write_timed(boundaries, max_chars, &SubtitleTiming::default())
+    }
+
+    /// [`Self::write`] with the times moved by `timing`, eg: for audio edited after synthesis.
+    pub fn write_timed(&self, boundaries: &[Boundary], max_chars: usize, timing: &SubtitleTiming) -> String {
+        let retimed = timing.retime_boundaries(boundaries);
+        let boundaries = &retimed;
+        match self {
+            Self::Srt => to_srt(&cues_from_boundaries(boundaries, max_chars)),
+            Self::WebVtt => to_webvtt(&cues_from_boundaries(boundaries, max_chars)),
+            Self::Ttml => to_ttml(&cues_from_boundaries(boundaries, max_chars)),
+            Self::Lrc => to_lrc(boundaries, max_chars),
+        }
+    }
+}
+
+/// A change of subtitle times, to keep them in sync with audio that was edited after synthesis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubtitleTiming {
+    /// Added to each time after scaling, in milliseconds, eg: 2000 for an intro of 2 s, -500 for trimmed
+    /// leading silence. Times don't go below zero.
+    pub offset_ms: i64,
+    /// Factor of each time, eg: 0.8 for audio played 1.25 times as fast.
+    pub scale: f64,
+}
+
+impl Default for SubtitleTiming {
+    fn default() -> Self {
+        Self { offset_ms: 0, scale: 1.0 }
+    }
+}
+
+impl SubtitleTiming {
+    /// `at` scaled, then offset.
+    pub fn apply(&self, at: Duration) -> Duration {
+        let millis = at.as_secs_f64() * 1000.0 * self.scale + self.offset_ms as f64;
+        Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+    }
+
+    /// `cues` with their times changed, eg: for [`to_srt`] or [`to_webvtt`].
+    pub fn retime(&self, cues: &[Cue]) -> Vec<Cue> {
+        cues.iter().map(|cue| Cue { start: self.apply(cue.start), end: self.apply(cue.end), text: cue.text.clone() }).collect()
+    }
+
+    /// `boundaries` with their times changed, durations only scaled.
+    pub fn retime_boundaries(&self, boundaries: &[Boundary]) -> Vec<Boundary> {
+        boundaries
+            .iter()
+            .map(|b| Boundary { offset: self.apply(b.offset), duration: b.duration.mul_f64(self.scale.max(0.0)), ..b.clone() })
+            .collect()
+    }
+}
+
+/// Parse SubRip subtitles. Formatting tags like `<i>` and `{\an8}` are removed from the text.
+pub fn parse_srt(srt: &str) -> Result<Vec<Cue>> {
+    let srt = srt.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in srt.split("\n\n").map(str::trim).filter(|b| !b.is_empty()) {
+        let mut lines = block.lines();
+        let mut timing = lines.next().unwrap_or_default();
+        if !timing.contains("-->") {
+            // Cue number.
+            timing = lines.next().ok_or_else(|| anyhow!("srt cue without timing: {}", block))?;
+        }
+        let (start, end) = timing.split_once("-->").ok_or_else(|| anyhow!("bad srt timing: {}", timing))?;
+        // Some files append positions after the end time.
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let (start, end) = match (parse_srt_time(start.trim()), parse_srt_time(end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err(anyhow!("bad srt timing: {}", timing)),
+        };
+        let text = lines.map(strip_tags).collect::<Vec<_>>().join("\n");
+        cues.push(Cue { start, end, text });
+    }
+    Ok(cues)
+}
+
+/// Cues from sentence boundaries when there are any, otherwise from word boundaries grouped up to `max_chars`
+/// characters per cue, eg: 42. A cue of words also ends after a word ending a sentence.
+pub fn cues_from_boundaries(boundaries: &[Boundary], max_chars: usize) -> Vec<Cue> {
+    let cue = |b: &Boundary| Cue { start: b.offset, end: b.offset + b.duration, text: b.text.clone() };
+    if boundaries.iter().any(|b| b.kind == BoundaryKind::Sentence) {
+        return boundaries.iter().filter(|b| b.kind == BoundaryKind::Sentence).map(cue).collect();
+    }
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut open = false;
+    for word in boundaries.iter().filter(|b| b.kind == BoundaryKind::Word) {
+        match cues.last_mut() {
+            Some(last) if open && last.text.chars().count() + 1 + word.text.chars().count() <= max_chars => {
+                last.text.push(' ');
+                last.text.push_str(&word.text);
+                last.end = word.offset + word.duration;
+            }
+            _ => cues.push(cue(word)),
+        }
+        open = !word.text.ends_with(['.', '!', '?', '。', '！', '？']);
+    }
+    cues
+}
+
+/// SubRip text of `cues`, numbered from 1.
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut srt = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let _ = write!(srt, "{}\n{} --> {}\n{}\n\n", i + 1, timestamp(cue.start, ','), timestamp(cue.end, ','), cue.text);
+    }
+    srt
+}
+
+/// WebVTT text of `cues`.
+pub fn to_webvtt(cues: &[Cue]) -> String {
+    let mut vtt = "WEBVTT\n\n".to_owned();
+    for cue in cues {
+        let _ = write!(vtt, "{} --> {}\n{}\n\n", timestamp(cue.start, '.'), timestamp(cue.end, '.'), cue.text);
+    }
+    vtt
+}
+
+/// TTML document of `cues`, lines of a cue split by `<br/>`.
+pub fn to_ttml(cues: &[Cue]) -> String {
+    let mut ttml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\">\n<body>\n<div>\n".to_owned();
+    for cue in cues {
+        let text = cue.text.split('\n').map(|line| escape_str_pcdata(line).into_owned()).collect::<Vec<_>>().join("<br/>");
+        let _ = writeln!(ttml, "<p begin=\"{}\" end=\"{}\">{}</p>", timestamp(cue.start, '.'), timestamp(cue.end, '.'), text);
+    }
+    ttml.push_str("</div>\n</body>\n</tt>\n");
+    ttml
+}
+
+/// Enhanced LRC lyrics of `boundaries`, one line per cue of [`cues_from_boundaries`] with the start of each of its
+/// words and the end of the last one, eg: "[00:01.20]<00:01.20>Hello <00:01.60>there.<00:02.10>". Lines of cues
+/// without word boundaries have only their start.
+pub fn to_lrc(boundaries: &[Boundary], max_chars: usize) -> String {
+    let mut lrc = String::new();
+    let words: Vec<&Boundary> = boundaries.iter().filter(|b| b.kind == BoundaryKind::Word).collect();
+    for cue in cues_from_boundaries(boundaries, max_chars) {
+        let _ = write!(lrc, "[{}]", lrc_timestamp(cue.start));
+        let cue_words: Vec<&&Boundary> = words.iter().filter(|w| w.offset >= cue.start && w.offset < cue.end.max(cue.start + Duration::from_millis(1))).collect();
+        match cue_words.last() {
+            None => lrc.push_str(&cue.text.replace('\n', " ")),
+            Some(last) => {
+                let line: Vec<String> = cue_words.iter().map(|w| format!("<{}>{}", lrc_timestamp(w.offset), w.text)).collect();
+                let _ = write!(lrc, "{}<{}>", line.join(" "), lrc_timestamp(last.offset + last.duration));
+            }
+        }
+        lrc.push('\n');
+    }
+    lrc
+}
+
+/// eg: "62:03.45", minutes going past 59.
+fn lrc_timestamp(at: Duration) -> String {
+    let centis = at.as_millis() / 10;
+    format!("{:02}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+/// eg: "01:02:03,456"
+fn timestamp(at: Duration, separator: char) -> String {
+    let millis = at.as_millis();
+    format!("{:02}:{:02}:{:02}{}{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, separator, millis % 1000)
+}
+
+/// eg: "01:02:03,456"
+fn parse_srt_time(s: &str) -> Option<Duration> {
+    let (hms, millis) = s.split_once([',', '.']).unwrap_or((s, "0"));
+    let mut parts = hms.split(':').map(|p| p.trim().parse::<u64>());
+    let (h, m, sec) = (parts.next()?.ok()?, parts.next()?.ok()?, parts.next()?.ok()?);
+    let millis: u64 = format!("{:0<3}", millis).get(..3)?.parse().ok()?;
+    Some(Duration::from_millis(((h * 60 + m) * 60 + sec) * 1000 + millis))
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut text = String::new();
+    let mut closing = None;
+    for c in line.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => text.push(c),
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    text.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there\r\nfriend\r\n\r\n2\r\n00:01:00.25 --> 00:01:03,000 X1:0\r\n{\\an8}Bye\r\n";
+        assert_eq!(parse_srt(srt).unwrap(), vec![
+            Cue { start: Duration::from_millis(1000), end: Duration::from_millis(2500), text: "Hello there\nfriend".to_owned() },
+            Cue { start: Duration::from_millis(60_250), end: Duration::from_secs(63), text: "Bye".to_owned() },
+        ]);
+        assert!(parse_srt("1\n00:00:01 -> 00:00:02\nHi").is_err());
+    }
+
+    #[test]
+    fn writes_cues_from_words() {
+        let word = |ms: u64, text: &str| Boundary {
+            kind: BoundaryKind::Word,
+            offset: Duration::from_millis(ms),
+            duration: Duration::from_millis(300),
+            text: text.to_owned(),
+        };
+        let cues = cues_from_boundaries(&[word(0, "Hello"), word(400, "there."), word(3_723_456, "Bye")], 42);
+        assert_eq!(to_srt(&cues), "1\n00:00:00,000 --> 00:00:00,700\nHello there.\n\n2\n01:02:03,456 --> 01:02:03,756\nBye\n\n");
+        assert!(to_webvtt(&cues).starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:00.700\n"));
+        assert_eq!(parse_srt(&to_srt(&cues)).unwrap(), cues);
+    }
+
+    #[test]
+    fn writes_ttml_and_lrc() {
+        let boundary = |kind: BoundaryKind, ms: u64, duration: u64, text: &str| Boundary {
+            kind,
+            offset: Duration::from_millis(ms),
+            duration: Duration::from_millis(duration),
+            text: text.to_owned(),
+        };
+        let words = [boundary(BoundaryKind::Word, 1200, 300, "Tom"), boundary(BoundaryKind::Word, 1600, 500, "& Jerry."), boundary(BoundaryKind::Word, 3_723_450, 100, "Bye")];
+        assert_eq!(SubtitleFormat::Lrc.write(&words, 42), "[00:01.20]<00:01.20>Tom <00:01.60>& Jerry.<00:02.10>\n[62:03.45]<62:03.45>Bye<62:03.55>\n");
+        let ttml = SubtitleFormat::Ttml.write(&words, 42);
+        assert!(ttml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\">"));
+        assert!(ttml.contains("<p begin=\"00:00:01.200\" end=\"00:00:02.100\">Tom &amp; Jerry.</p>\n<p begin=\"01:02:03.450\" end=\"01:02:03.550\">Bye</p>\n</div>"));
+        assert_eq!(to_ttml(&[Cue { start: Duration::ZERO, end: Duration::from_secs(1), text: "a\nb".to_owned() }]).lines().nth(4), Some("<p begin=\"00:00:00.000\" end=\"00:00:01.000\">a<br/>b</p>"));
+
+        // Sentences make the lines, with the words within them.
+        let sentences = [boundary(BoundaryKind::Sentence, 1200, 900, "Tom & Jerry."), words[0].clone(), words[1].clone(), boundary(BoundaryKind::Sentence, 5000, 500, "Bye")];
+        assert_eq!(to_lrc(&sentences, 42), "[00:01.20]<00:01.20>Tom <00:01.60>& Jerry.<00:02.10>\n[00:05.00]Bye\n");
+        assert_eq!(SubtitleFormat::WebVtt.extension(), "vtt");
+    }
+
+    #[test]
+    fn shifts_and_scales_times() {
+        let words = [Boundary { kind: BoundaryKind::Word, offset: Duration::from_millis(1000), duration: Duration::from_millis(500), text: "Hi".to_owned() }];
+        let faster = SubtitleTiming { offset_ms: 2000, scale: 0.8 };
+        assert_eq!(SubtitleFormat::Srt.write_timed(&words, 42, &faster), "1\n00:00:02,800 --> 00:00:03,200\nHi\n\n");
+        let cues = cues_from_boundaries(&words, 42);
+        let earlier = SubtitleTiming { offset_ms: -1200, ..Default::default() };
+        assert_eq!(to_webvtt(&earlier.retime(&cues)), "WEBVTT\n\n00:00:00.000 --> 00:00:00.300\nHi\n\n");
+        assert_eq!(SubtitleFormat::Srt.write_timed(&words, 42, &SubtitleTiming::default()), SubtitleFormat::Srt.write(&words, 42));
+    }
+}
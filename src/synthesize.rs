@@ -1,35 +1,58 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use rand::RngCore;
+use serde_json::json;
 use sha2::{Sha256, Digest};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::borrow::Cow;
+use std::io::ErrorKind;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tungstenite::error::CapacityError;
+use tungstenite::protocol::WebSocketConfig;
 use tungstenite::{Message, WebSocket};
 use tungstenite::client::IntoClientRequest;
-use tungstenite::http::HeaderValue;
+use tungstenite::http::{HeaderName, HeaderValue};
 use uuid::Uuid;
 use xml::escape::{escape_str_attribute, escape_str_pcdata};
 
+use crate::cache::{DiskCache, SynthKey};
+use crate::request::SynthesisRequest;
+use crate::format::OutputFormat;
+use crate::rate_limit::RateLimiter;
+use crate::limit::WordLimit;
+use crate::normalize::TextNormalizer;
+use crate::quota::{text_chars, CharacterQuota};
+use crate::presets::VoicePresets;
+use crate::lexicon::Lexicon;
+use crate::save::audio_duration;
+use crate::silence::{adjust_silence, is_pcm16, SilenceOptions};
+use crate::error::Error;
+use crate::stream::{connect_stream, websocket_handshake, Connector, Stream};
+use crate::frame::{parse_binary_frame, parse_text_frame, FrameError, Headers};
+use crate::browser::{check_version, edge_version, user_agent};
+use crate::backend::{is_rejection, TtsBackend};
+use crate::clock;
+use crate::dump::{ConnectionDump, ProtocolDump};
+use crate::metadata::{parse_metadata, Boundary, MetadataOptions};
+use crate::metrics::{ErrorCategory, MetricsObserver, TurnRecorder};
+use crate::observer::EventObserver;
+use crate::turn_info::TurnInfo;
+use crate::trace::trace_event;
 
-const SYNTH_URL: &str = "wss://speech.platform.bing.com/consumer/speech/synthesize/readaloud/edge/v1?TrustedClientToken=6A5AA1D4EAFF4E9FB37E23D68491D6F4";
 
-fn random_request_id() -> String {
+const SYNTH_URL: &str = "wss://speech.platform.bing.com/consumer/speech/synthesize/readaloud/edge/v1";
+const TRUSTED_CLIENT_TOKEN: &str = "6A5AA1D4EAFF4E9FB37E23D68491D6F4";
+
+pub(crate) fn speech_config_message(speech_config: &str) -> Message {
+    Message::Text(format!("Content-Type:application/json; charset=utf-8\r\nPath:speech.config\r\n\r\n{}", speech_config))
+}
+
+pub(crate) fn random_request_id() -> String {
     let mut buf = [0u8; 16];
     rand::thread_rng().fill_bytes(&mut buf);
     hex::encode(&buf[..])
 }
 
-fn parse_headers(s: impl AsRef<str>) -> Vec<(String, String)> {
-    s.as_ref().split("\r\n").filter_map(|s| {
-        if s.len() > 0 {
-            let mut iter = s.splitn(2, ":");
-            let k = iter.next().unwrap_or("").to_owned();
-            let v = iter.next().unwrap_or("").to_owned();
-            Some((k, v))
-        } else {
-            None
-        }
-    }).collect()
-}
-
 /// `voice_short_name`: eg: "zh-CN-XiaoxiaoNeural"
 ///
 /// `pitch`
@@ -60,7 +83,8 @@ pub fn build_ssml(text: &str, voice_short_name: &str, pitch: &str, rate: &str, v
     format!("<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xmlns:mstts=\"https://www.w3.org/2001/mstts\" xml:lang=\"en-US\"><voice name=\"{}\"><prosody pitch=\"{}\" rate=\"{}\" volume=\"{}\">{}</prosody></voice></speak>", escape_str_attribute(voice_short_name), escape_str_attribute(pitch), escape_str_attribute(rate), escape_str_attribute(volume), escape_str_pcdata(text))
 }
 
-pub fn configure_request(mut request: tungstenite::http::Request<()>) -> Result<tungstenite::http::Request<()>> {
+/// Headers of the read aloud extension of Edge `version`.
+pub fn configure_request(mut request: tungstenite::http::Request<()>, version: &str) -> Result<tungstenite::http::Request<()>> {
     let headers = request.headers_mut();
     headers.insert(
         "Accept-Encoding",
@@ -72,7 +96,7 @@ pub fn configure_request(mut request: tungstenite::http::Request<()>) -> Result<
     );
     headers.insert(
         "User-Agent",
-        HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/143.0.0.0 Safari/537.36 Edg/143.0.0.0"),
+        HeaderValue::from_str(&user_agent(version))?,
     );
     headers.insert(
         "Origin",
@@ -81,39 +105,648 @@ pub fn configure_request(mut request: tungstenite::http::Request<()>) -> Result<
     Ok(request)
 }
 /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3". See https://learn.microsoft.com/en-us/azure/ai-services/speech-service/rest-text-to-speech?tabs=streaming#audio-outputs
+///
+/// Synthesizes with [`Client::from_env`], so eg: [`crate::ENV_CACHE_DIR`] looks the audio up in a [`DiskCache`] before
+/// connecting.
 pub fn request_audio(ssml: &str, output_format: &str) -> anyhow::Result<Vec<u8>> {
-    let synth_url = format!("{}&Sec-MS-GEC={}&Sec-MS-GEC-Version=1-143.0.3650.139&ConnectionId={}", SYNTH_URL, generate_sec_ms_gec_sync("6A5AA1D4EAFF4E9FB37E23D68491D6F4"), Uuid::new_v4());
-    let request = synth_url.into_client_request()?;
-    let request = configure_request(request)?;
-    let (mut socket, _) = tungstenite::connect(request)?;
-    process_socket_data(&ssml, &output_format, &mut socket)
+    Ok(Client::from_env()?.synthesize(ssml, output_format)?.audio)
 }
 
 /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3". See https://learn.microsoft.com/en-us/azure/ai-services/speech-service/rest-text-to-speech?tabs=streaming#audio-outputs
 /// `proxy_addr`: socks5 proxy addr，like "127.0.0.1:1080"
+///
+/// Like [`request_audio`], with `proxy_addr` instead of [`crate::ENV_PROXY`].
 pub fn request_audio_via_socks5_proxy(ssml: &str, output_format: &str, proxy_addr: &str) -> anyhow::Result<Vec<u8>> {
-    let synth_url = format!("{}&Sec-MS-GEC={}&Sec-MS-GEC-Version=1-143.0.3650.139&ConnectionId={}", SYNTH_URL, generate_sec_ms_gec_sync("6A5AA1D4EAFF4E9FB37E23D68491D6F4"), Uuid::new_v4());
-    let url = url::Url::parse(&synth_url)?;
-    let host = url.host_str().unwrap();
-    let port = url.port_or_known_default().unwrap();
+    Ok(Client::from_env()?.with_socks5_proxy(proxy_addr).synthesize(ssml, output_format)?.audio)
+}
+
+/// Audio and metadata events of one synthesis.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SynthesisOutput {
+    pub audio: Vec<u8>,
+    /// Word/sentence boundaries, visemes and bookmarks, as enabled by [`MetadataOptions`].
+    pub boundaries: Vec<Boundary>,
+    /// Playing time of `audio`: from its length for constant bitrate and PCM formats, from the Ogg granule
+    /// positions, or else up to the end of the last boundary. `None` when none of these is known.
+    pub duration: Option<Duration>,
+    /// From the start of the turn, connecting included, to its first audio, eg: to watch the time to first byte.
+    /// `None` when cached.
+    pub first_audio: Option<Duration>,
+    /// Of the turn that produced it, the last one if it was resumed, eg: to quote to support. `None` when cached or
+    /// from another backend.
+    pub ids: Option<TurnIds>,
+    /// What the service told of the turn that produced it. `None` like `ids`.
+    pub turn_info: Option<TurnInfo>,
+}
+
+/// Identifiers of a turn, as sent to the service. Errors of a turn have them as context:
+/// `error.downcast_ref::<TurnIds>()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnIds {
+    /// X-RequestId of the SSML message.
+    pub request_id: String,
+    /// ConnectionId of the WebSocket URL.
+    pub connection_id: String,
+}
+
+impl std::fmt::Display for TurnIds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request {} on connection {}", self.request_id, self.connection_id)
+    }
+}
+
+impl SynthesisOutput {
+    /// Fill in [`SynthesisOutput::duration`] for `audio` in `format`.
+    pub(crate) fn with_duration(mut self, format: &OutputFormat) -> Self {
+        self.duration = audio_duration(format, &self.audio).or_else(|| self.boundaries.iter().map(|b| b.offset + b.duration).max());
+        self
+    }
+}
+
+/// Synthesis client with connection and speech.config options.
+///
+/// Each turn runs on its own connection, closed with a Close handshake when the turn ends or fails, so the client
+/// holds none of its own; a [`crate::Pool`] keeps some open.
+///
+/// ```no_run
+/// use edge_tts::{build_ssml, Client, MetadataOptions};
+///
+/// let output = Client::new()
+///     .with_metadata_options(MetadataOptions { sentence_boundary_enabled: true, word_boundary_enabled: false, ..Default::default() })
+///     .synthesize(&build_ssml("Hello.", "en-US-AriaNeural", "default", "default", "default"), "audio-24khz-48kbitrate-mono-mp3")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    socks5_proxy: Option<String>,
+    metadata_options: MetadataOptions,
+    speech_config: Option<String>,
+    pub(crate) keep_alive: Option<Duration>,
+    pub(crate) disk_cache: Option<DiskCache>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) max_resumes: u32,
+    word_limit: Option<WordLimit>,
+    normalizer: Option<TextNormalizer>,
+    silence: Option<SilenceOptions>,
+    voice_presets: VoicePresets,
+    lexicon: Lexicon,
+    pause_markers: bool,
+    #[cfg(feature = "loudness")]
+    loudness_target: Option<f64>,
+    metrics: Option<Arc<dyn MetricsObserver>>,
+    pub(crate) event_observer: Option<Arc<dyn EventObserver>>,
+    pub(crate) protocol_dump: Option<ProtocolDump>,
+    connector: Option<Arc<dyn Connector>>,
+    #[cfg(any(test, feature = "testing"))]
+    recording: Option<crate::testing::Recording>,
+    edge_version: Option<String>,
+    /// Handshake headers replacing the defaults, `None` to remove one.
+    headers: Vec<(HeaderName, Option<HeaderValue>)>,
+    endpoint: Option<url::Url>,
+    trusted_client_token: Option<String>,
+    pub(crate) backend: Option<Arc<dyn TtsBackend>>,
+    max_message_size: Option<usize>,
+    pub(crate) max_audio_size: Option<u64>,
+    pub(crate) strict_format: bool,
+    close_timeout: Option<Duration>,
+    fallback: Option<Arc<dyn TtsBackend>>,
+    quota: Option<(Arc<CharacterQuota>, String)>,
+}
+
+impl Client {
+    /// A client with the built-in defaults. The `EDGE_TTS_*` variables are opt-in: they configure [`Client::from_env`]
+    /// and the top-level helpers, eg: [`request_audio`], not this.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `proxy_addr`: socks5 proxy addr，like "127.0.0.1:1080"
+    pub fn with_socks5_proxy(mut self, proxy_addr: impl Into<String>) -> Self {
+        self.socks5_proxy = Some(proxy_addr.into());
+        self
+    }
+
+    pub fn with_metadata_options(mut self, metadata_options: MetadataOptions) -> Self {
+        self.metadata_options = metadata_options;
+        self
+    }
+
+    /// Send `config` as the speech.config body instead of the one built from the output format and [`MetadataOptions`].
+    ///
+    /// Escape hatch for options this crate doesn't model. `config` must contain `context.synthesis.audio.outputFormat` itself.
+    pub fn with_speech_config(mut self, config: serde_json::Value) -> Self {
+        self.speech_config = Some(config.to_string());
+        self
+    }
+
+    /// Send a Ping whenever the service has been silent for `interval`, to keep long syntheses from idling out.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Look up results in `cache` before connecting, and store new ones in it.
+    pub fn with_disk_cache(mut self, cache: DiskCache) -> Self {
+        self.disk_cache = Some(cache);
+        self
+    }
+
+    /// Wait on `limiter` before each connection attempt. Share one limiter between clients to limit them together.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Let [`Client::synthesize_request`] reconnect up to `max_resumes` times when a turn is cut off, asking only for
+    /// the text after the last complete word boundary and joining the audio. Needs word boundaries and an MP3 or raw
+    /// PCM output format; other formats fail as before.
+    pub fn with_resume(mut self, max_resumes: u32) -> Self {
+        self.max_resumes = max_resumes;
+        self
+    }
+
+    /// Refuse, or reduce, input longer than `limit`. For [`Client::synthesize`] the words of the SSML text content
+    /// are counted and the reducer isn't used.
+    pub fn with_word_limit(mut self, limit: WordLimit) -> Self {
+        self.word_limit = Some(limit);
+        self
+    }
+
+    /// Normalize the text of requests before the word limit, eg: [`Client::synthesize_request`]. SSML given to
+    /// [`Client::synthesize`] is sent as is.
+    pub fn with_normalizer(mut self, normalizer: TextNormalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Replace the [`VoicePresets`] applied by [`Client::synthesize_request`]; [`VoicePresets::empty`] turns them off.
+    pub fn with_voice_presets(mut self, presets: VoicePresets) -> Self {
+        self.voice_presets = presets;
+        self
+    }
+
+    /// Say the words of `lexicon` its way in the text of requests, eg: [`Client::synthesize_request`]. SSML given to
+    /// [`Client::synthesize`] is sent as is, see [`crate::Ssml::text_with_lexicon`].
+    pub fn with_lexicon(mut self, lexicon: Lexicon) -> Self {
+        self.lexicon = lexicon;
+        self
+    }
+
+    /// Turn markers like `[pause:1s]` in the text of requests into breaks, see [`crate::Ssml::text_with_pauses`].
+    pub fn with_pause_markers(mut self) -> Self {
+        self.pause_markers = true;
+        self
+    }
+
+    /// Trim and pad the silence of 16-bit PCM output, see [`crate::adjust_silence`]. Other formats are returned as
+    /// received. The disk cache keeps the audio as received.
+    pub fn with_silence(mut self, options: SilenceOptions) -> Self {
+        self.silence = Some(options);
+        self
+    }
+
+    /// Normalize 16-bit PCM output to `target_lufs` integrated loudness, after [`Client::with_silence`], see
+    /// [`crate::normalize_loudness`]. Other formats are returned as received.
+    #[cfg(feature = "loudness")]
+    pub fn with_loudness_target(mut self, target_lufs: f64) -> Self {
+        self.loudness_target = Some(target_lufs);
+        self
+    }
+
+    /// Fail with [`Error::MessageTooLarge`] on a WebSocket message or frame over `bytes`, instead of at tungstenite's
+    /// 64 MiB, eg: 1 MiB, as audio messages of the service are a few KiB.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Fail a turn with [`Error::AudioTooLarge`] once its audio is over `bytes`, eg: to bound the memory of a
+    /// long-running service whatever the service sends.
+    pub fn with_max_audio_size(mut self, bytes: u64) -> Self {
+        self.max_audio_size = Some(bytes);
+        self
+    }
+
+    /// Fail a turn with [`Error::FormatMismatch`] if its first audio doesn't start like the output format asked
+    /// for, eg: when the service fell back to MP3 on a misspelled format name.
+    pub fn with_strict_format(mut self) -> Self {
+        self.strict_format = true;
+        self
+    }
+
+    /// Wait at most `timeout` for the service to acknowledge the Close of a connection, 5 s by default, however
+    /// much it keeps sending meanwhile.
+    pub fn with_close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn close_timeout(&self) -> Duration {
+        self.close_timeout.unwrap_or(CLOSE_TIMEOUT)
+    }
+
+    /// Pass the connections, turn messages and errors of every turn to `observer` as they happen.
+    pub fn with_event_observer(mut self, observer: Arc<dyn EventObserver>) -> Self {
+        self.event_observer = Some(observer);
+        self
+    }
+
+    /// Report the connection time, time to first audio, size and failures of every turn to `observer`.
+    pub fn with_metrics(mut self, observer: Arc<dyn MetricsObserver>) -> Self {
+        self.metrics = Some(observer);
+        self
+    }
+
+    /// Write the handshake and every protocol message of every connection to `dump`, eg: to see how the service
+    /// responds after a protocol change.
+    pub fn with_protocol_dump(mut self, dump: ProtocolDump) -> Self {
+        self.protocol_dump = Some(dump);
+        self
+    }
+
+    /// Open connections with `connector` instead of TCP and TLS, eg: to test against a fake server. The socks5
+    /// proxy isn't used then.
+    pub fn with_connector(mut self, connector: Arc<dyn Connector>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// Keep every message received in `recording`, to replay it with [`crate::testing::MockServer::replay`].
+    #[cfg(any(test, feature = "testing"))]
+    pub fn with_recording(mut self, recording: crate::testing::Recording) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Look like Edge `version`, eg: "143.0.3650.139", instead of [`crate::edge_version`]. Fails unless it looks like
+    /// a version.
+    pub fn with_edge_version(mut self, version: &str) -> Result<Self> {
+        check_version(version)?;
+        self.edge_version = Some(version.to_owned());
+        Ok(self)
+    }
+
+    /// Send `value` as handshake header `name`, replacing the default if any, eg: ("Accept-Language", "de-DE").
+    /// Fails if either isn't valid in a header.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        self.headers.retain(|(n, _)| *n != name);
+        self.headers.push((name, Some(HeaderValue::from_str(value)?)));
+        Ok(self)
+    }
+
+    /// Don't send the default handshake header `name`, eg: "Origin".
+    pub fn without_header(mut self, name: &str) -> Result<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        self.headers.retain(|(n, _)| *n != name);
+        self.headers.push((name, None));
+        Ok(self)
+    }
+
+    /// Connect to `url` instead of the service, eg: a regional mirror, or "ws://127.0.0.1:8080/v1" behind a debugging
+    /// proxy. A TrustedClientToken parameter is added unless `url` has one; Sec-MS-GEC and ConnectionId always are.
+    pub fn with_endpoint(mut self, url: &str) -> Result<Self> {
+        let url = url::Url::parse(url)?;
+        if !matches!(url.scheme(), "ws" | "wss") {
+            return Err(anyhow::anyhow!("endpoint must be a ws:// or wss:// url: {}", url));
+        }
+        self.endpoint = Some(url);
+        Ok(self)
+    }
+
+    /// Send `token` as TrustedClientToken, and derive Sec-MS-GEC from it, instead of Edge's.
+    pub fn with_trusted_client_token(mut self, token: impl Into<String>) -> Self {
+        self.trusted_client_token = Some(token.into());
+        self
+    }
+
+    /// Synthesize through `backend` instead of the Edge endpoint. [`Client::synthesize_reader`], the protocol dump,
+    /// metrics and recordings keep using the Edge endpoint.
+    pub fn with_backend(mut self, backend: Arc<dyn TtsBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Synthesize through `backend` when the Edge endpoint rejects a synthesis, eg: `AzureBackend` (feature
+    /// `azure`), which has no boundaries. Used by [`Client::synthesize`] and [`Client::synthesize_request`].
+    pub fn with_fallback(mut self, backend: Arc<dyn TtsBackend>) -> Self {
+        self.fallback = Some(backend);
+        self
+    }
+
+    /// Count the characters this client submits as `tenant`'s in `quota`, and keep them within its limit. Cached
+    /// results aren't counted, resumed turns are.
+    pub fn with_quota(mut self, quota: Arc<CharacterQuota>, tenant: impl Into<String>) -> Self {
+        self.quota = Some((quota, tenant.into()));
+        self
+    }
+
+    /// Like [`Client::with_speech_config`], with raw JSON text sent as is. Fails if `config` isn't valid JSON.
+    pub fn with_speech_config_json(mut self, config: &str) -> Result<Self> {
+        serde_json::from_str::<serde_json::Value>(config)?;
+        self.speech_config = Some(config.to_owned());
+        Ok(self)
+    }
+
+    pub(crate) fn speech_config(&self, output_format: &str) -> String {
+        match &self.speech_config {
+            Some(config) => config.clone(),
+            None => json!({
+                "context": {
+                    "synthesis": {
+                        "audio": {
+                            "metadataoptions": {
+                                "sentenceBoundaryEnabled": self.metadata_options.sentence_boundary_enabled,
+                                "wordBoundaryEnabled": self.metadata_options.word_boundary_enabled,
+                                "visemeEnabled": self.metadata_options.viseme_enabled,
+                                "bookmarkEnabled": self.metadata_options.bookmark_enabled,
+                            },
+                            "outputFormat": output_format,
+                        }
+                    }
+                }
+            }).to_string(),
+        }
+    }
+
+    /// `output_format`: eg: "audio-24khz-48kbitrate-mono-mp3". See https://learn.microsoft.com/en-us/azure/ai-services/speech-service/rest-text-to-speech?tabs=streaming#audio-outputs
+    pub fn synthesize(&self, ssml: &str, output_format: &str) -> Result<SynthesisOutput> {
+        if let Some(limit) = &self.word_limit {
+            limit.check(&ssml_text(ssml))?;
+        }
+        let key = self.synth_key(ssml, output_format);
+        if let Some(output) = self.disk_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return self.post_process(output, output_format);
+        }
+        let mut output = SynthesisOutput::default();
+        if let Err(e) = self.connect_and_synthesize(ssml, output_format, &mut output) {
+            output = self.fall_back(e, ssml, output_format)?;
+        }
+        if let Some(cache) = &self.disk_cache {
+            // A broken cache shouldn't fail a successful synthesis.
+            let _ = cache.put(&key, &output);
+        }
+        self.post_process(output, output_format)
+    }
+
+    pub(crate) fn post_process(&self, output: SynthesisOutput, output_format: &str) -> Result<SynthesisOutput> {
+        let format = OutputFormat::new(output_format);
+        if !is_pcm16(&format) {
+            return Ok(output.with_duration(&format));
+        }
+        let output = match &self.silence {
+            Some(options) => adjust_silence(output, &format, options)?,
+            None => output,
+        };
+        #[cfg(feature = "loudness")]
+        if let Some(target) = self.loudness_target {
+            return crate::loudness::normalize_loudness(output, &format, target);
+        }
+        Ok(output.with_duration(&format))
+    }
+
+    /// Like [`Client::synthesize`]. With [`Client::with_resume`], a turn cut off mid-stream continues from the last
+    /// complete word. A request without pitch, rate and volume gets the [`VoicePresets`] of its voice.
+    pub fn synthesize_request(&self, request: &SynthesisRequest) -> Result<SynthesisOutput> {
+        let request = &*self.prepare(request)?;
+        if self.max_resumes == 0 {
+            return self.synthesize(&self.request_ssml(request), request.output_format.as_str());
+        }
+        let key = self.synth_key(&self.request_ssml(request), request.output_format.as_str());
+        if let Some(output) = self.disk_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return self.post_process(output, request.output_format.as_str());
+        }
+        let output = match self.synthesize_resuming(request) {
+            Ok(output) => output,
+            Err(e) => self.fall_back(e, &self.request_ssml(request), request.output_format.as_str())?,
+        };
+        if let Some(cache) = &self.disk_cache {
+            let _ = cache.put(&key, &output);
+        }
+        self.post_process(output, request.output_format.as_str())
+    }
+
+    /// Synthesize with the fallback backend if `error` is a rejection, else return it.
+    fn fall_back(&self, error: anyhow::Error, ssml: &str, output_format: &str) -> Result<SynthesisOutput> {
+        let Some(backend) = self.fallback.as_ref().filter(|_| is_rejection(&error)) else { return Err(error) };
+        trace_event!(warn, error = %error, "Edge rejected the synthesis, using the fallback backend");
+        let mut output = SynthesisOutput::default();
+        backend.synthesize(ssml, output_format, &mut |event| {
+            match event {
+                SynthesisEvent::Audio(audio) => output.audio.extend_from_slice(audio),
+                SynthesisEvent::Boundaries(boundaries) => output.boundaries.extend(boundaries),
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok(output)
+    }
+
+    /// `request` with its voice preset, normalizer and word limit applied.
+    pub(crate) fn prepare<'a>(&self, request: &'a SynthesisRequest) -> Result<Cow<'a, SynthesisRequest>> {
+        let mut request = match self.voice_presets.apply(request) {
+            Some(preset) => Cow::Owned(preset),
+            None => Cow::Borrowed(request),
+        };
+        if let Some(normalizer) = &self.normalizer {
+            request.to_mut().text = normalizer.normalize(&request.text);
+        }
+        if let Some(text) = self.word_limit.as_ref().map(|limit| limit.apply(&request.text)).transpose()?.flatten() {
+            request.to_mut().text = text;
+        }
+        Ok(request)
+    }
+
+    /// SSML of a prepared `request`, with the lexicon and pause markers applied.
+    pub(crate) fn request_ssml(&self, request: &SynthesisRequest) -> String {
+        if self.pause_markers {
+            return request.ssml().text_with_pauses(&request.text, &self.lexicon).to_string();
+        }
+        request.to_ssml_with_lexicon(&self.lexicon)
+    }
+
+    /// Connect and run one turn into `output`, which keeps the audio and boundaries received before an error.
+    pub(crate) fn connect_and_synthesize(&self, ssml: &str, output_format: &str, output: &mut SynthesisOutput) -> Result<()> {
+        let started = Instant::now();
+        let (_, turn) = self.connect_and_stream(ssml, output_format, &mut |event| {
+            match event {
+                SynthesisEvent::Audio(audio) => {
+                    output.first_audio.get_or_insert_with(|| started.elapsed());
+                    output.audio.extend_from_slice(audio)
+                }
+                SynthesisEvent::Boundaries(boundaries) => output.boundaries.extend(boundaries),
+            }
+            ControlFlow::Continue(())
+        })?;
+        (output.ids, output.turn_info) = turn.unzip();
+        Ok(())
+    }
 
-    let proxy_stream = socks::Socks5Stream::connect(proxy_addr, (host, port))?;
-    let tls_connector = native_tls::TlsConnector::new()?;
-    let tls_stream = tls_connector.connect(host, proxy_stream)?;
-    let request = url.into_client_request()?;
-    let request = configure_request(request)?;
-    let (mut socket, _) = tungstenite::client::client(request, tls_stream)?;
-    process_socket_data(&ssml, &output_format, &mut socket)
+    /// Connect and run one turn, passing its data to `on_event` as it arrives. No ids nor info with a backend.
+    pub(crate) fn connect_and_stream(&self, ssml: &str, output_format: &str, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<(TurnEnd, Option<(TurnIds, TurnInfo)>)> {
+        if let Some(backend) = &self.backend {
+            self.charge_quota(ssml)?;
+            let mut stopped = false;
+            backend.synthesize(ssml, output_format, &mut |event| {
+                let flow = on_event(event);
+                stopped |= flow.is_break();
+                flow
+            })?;
+            return Ok((if stopped { TurnEnd::Stopped } else { TurnEnd::Completed }, None));
+        }
+        let (end, ids, info) = process_turn(self.start_turn(ssml, output_format)?, on_event)?;
+        Ok((end, Some((ids, info))))
+    }
+
+    /// Connect and send the turn's request, leaving its events to be read.
+    pub(crate) fn start_turn(&self, ssml: &str, output_format: &str) -> Result<Turn<Box<dyn Stream>>> {
+        self.charge_quota(ssml)?;
+        let mut recorder = self.metrics.clone().map(|observer| TurnRecorder::start(observer, ssml, output_format));
+        let dump = self.protocol_dump.as_ref().map(ProtocolDump::connection);
+        let turn = self.connect(dump.as_ref()).and_then(|(socket, connection_id)| {
+            if let Some(recorder) = &mut recorder {
+                recorder.connected();
+            }
+            if let Some(interval) = self.keep_alive {
+                socket.get_ref().set_read_timeout(Some(interval))?;
+            }
+            Turn::start(ssml, Some(&self.speech_config(output_format)), socket, connection_id, dump).inspect_err(|e| {
+                if let Some(observer) = &self.event_observer {
+                    observer.error(e);
+                }
+            })
+        });
+        match turn {
+            Ok(mut turn) => {
+                turn.recorder = recorder;
+                turn.observer = self.event_observer.clone();
+                turn.max_audio = self.max_audio_size;
+                turn.close_timeout = self.close_timeout();
+                turn.expected_format = self.strict_format.then(|| OutputFormat::new(output_format));
+                #[cfg(any(test, feature = "testing"))]
+                {
+                    turn.recording = self.recording.as_ref().map(|recording| (recording.clone(), recording.start_turn(ssml)));
+                }
+                Ok(turn)
+            }
+            Err(e) => {
+                if let Some(recorder) = &mut recorder {
+                    recorder.failed(ErrorCategory::Connect);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub(crate) fn charge_quota(&self, ssml: &str) -> Result<()> {
+        match &self.quota {
+            Some((quota, tenant)) => quota.charge(tenant, text_chars(ssml)),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn synth_key(&self, ssml: &str, output_format: &str) -> SynthKey {
+        SynthKey {
+            ssml: ssml.to_owned(),
+            output_format: output_format.to_owned(),
+            speech_config: self.speech_config(output_format),
+        }
+    }
+
+    /// The socket and its ConnectionId.
+    pub(crate) fn connect(&self, dump: Option<&ConnectionDump>) -> Result<(WebSocket<Box<dyn Stream>>, String)> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("connect", proxy = self.socks5_proxy.as_deref()).entered();
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire();
+            trace_event!(debug, waited_ms = started.elapsed().as_millis() as u64, "rate limiter passed");
+        }
+        let connection_id = Uuid::new_v4().to_string();
+        let socket = match self.handshake(&connection_id, dump) {
+            // A clock off by minutes makes the token expired; the rejection tells the service's time.
+            Err(e) if clock::correct_skew(&e) => {
+                trace_event!(warn, error = %e, "Sec-MS-GEC rejected, retrying with the service's clock");
+                if let Some(dump) = dump {
+                    dump.note("retrying with the clock of the service");
+                }
+                self.handshake(&connection_id, dump)
+            }
+            socket => socket,
+        };
+        trace_event!(debug, ok = socket.is_ok(), connection_id, elapsed_ms = started.elapsed().as_millis() as u64, "websocket handshake");
+        if let Some(observer) = &self.event_observer {
+            match &socket {
+                Ok(_) => observer.connected(&connection_id),
+                Err(e) => observer.error(e),
+            }
+        }
+        Ok((socket?, connection_id))
+    }
+
+    fn handshake(&self, connection_id: &str, dump: Option<&ConnectionDump>) -> Result<WebSocket<Box<dyn Stream>>> {
+        let version = self.edge_version.clone().unwrap_or_else(edge_version);
+        let mut url = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => url::Url::parse(SYNTH_URL)?,
+        };
+        let token = self.trusted_client_token.as_deref().unwrap_or(TRUSTED_CLIENT_TOKEN);
+        if !url.query_pairs().any(|(name, _)| name == "TrustedClientToken") {
+            url.query_pairs_mut().append_pair("TrustedClientToken", token);
+        }
+        url.query_pairs_mut()
+            .append_pair("Sec-MS-GEC", &generate_sec_ms_gec_sync(token))
+            .append_pair("Sec-MS-GEC-Version", &format!("1-{}", version))
+            .append_pair("ConnectionId", connection_id);
+        let stream = match &self.connector {
+            Some(connector) => connector.connect(&url)?,
+            None => connect_stream(&url, self.socks5_proxy.as_deref())?,
+        };
+        let request = url.into_client_request()?;
+        let mut request = configure_request(request, &version)?;
+        for (name, value) in &self.headers {
+            match value {
+                Some(value) => request.headers_mut().insert(name.clone(), value.clone()),
+                None => request.headers_mut().remove(name),
+            };
+        }
+        if let Some(dump) = dump {
+            dump.note(&format!("handshake GET {}", request.uri()));
+        }
+        let config = self.max_message_size.map(|size| WebSocketConfig { max_message_size: Some(size), max_frame_size: Some(size), ..Default::default() });
+        let socket = websocket_handshake(request, stream, config);
+        if let (Some(dump), Err(e)) = (dump, &socket) {
+            dump.note(&format!("handshake failed: {:#}", e));
+        }
+        socket
+    }
+}
+
+/// Text content of `ssml`, with tags replaced by spaces.
+fn ssml_text(ssml: &str) -> String {
+    let mut text = String::with_capacity(ssml.len());
+    let mut in_tag = false;
+    for c in ssml.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
 }
 
 fn generate_sec_ms_gec_sync(trusted_client_token: &str) -> String {
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let ticks = now + 11644473600;
+    let ticks = clock::now() + 11644473600;
     let rounded = ticks - (ticks % 300);
     let windows_ticks = rounded * 10000000;
+    trace_event!(trace, windows_ticks, "generating Sec-MS-GEC");
 
     let data = format!("{}{}", windows_ticks, trusted_client_token);
 
@@ -126,55 +759,398 @@ fn generate_sec_ms_gec_sync(trusted_client_token: &str) -> String {
         .map(|byte| format!("{:02X}", byte))
         .collect::<String>()
 }
-fn process_socket_data<S: std::io::Read + std::io::Write>(
-    ssml: &str,
-    output_format: &str,
-    socket: &mut WebSocket<S>,
-) -> Result<Vec<u8>> {
-    socket.send(Message::Text(format!("Content-Type:application/json; charset=utf-8\r\nPath:speech.config\r\n\r\n{{\"context\":{{\"synthesis\":{{\"audio\":{{\"metadataoptions\":{{\"sentenceBoundaryEnabled\":false,\"wordBoundaryEnabled\":true}},\"outputFormat\":\"{}\"}}}}}}}}", output_format)))?;
-    let request_id = random_request_id();
-    socket.send(Message::Text(format!("X-RequestId:{}\r\nContent-Type:application/ssml+xml\r\nPath:ssml\r\n\r\n{}", request_id, ssml)))?;
-    let mut buf = Vec::new();
-    loop {
-        match socket.read() {
-            Ok(msg) => {
-                match msg {
-                    Message::Text(s) => {
-                        if let Some(header_str) = s.splitn(2, "\r\n\r\n").next() {
-                            let headers = parse_headers(header_str);
-                            if headers.iter().any(|(k, v)| k == "Path" && v == "turn.end") {
-                                if headers.iter().any(|(k, v)| k == "X-RequestId" && v.as_str() == request_id) {
-                                    return Ok(buf);
-                                } else {
-                                    return Err(anyhow!("Path:turn.end no X-RequestId header"));
+/// Data of a synthesis, as it arrives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SynthesisEvent<'a> {
+    Audio(&'a [u8]),
+    Boundaries(Vec<Boundary>),
+}
+
+/// How a turn ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TurnEnd {
+    /// `turn.end` received.
+    Completed,
+    /// `on_event` returned [`ControlFlow::Break`]; the connection was closed.
+    Stopped,
+}
+
+/// One turn on a socket, read event by event.
+pub(crate) struct Turn<S: Stream> {
+    socket: WebSocket<S>,
+    request_id: String,
+    connection_id: String,
+    /// Last binary message, which audio events borrow from.
+    message: Vec<u8>,
+    finished: bool,
+    recorder: Option<TurnRecorder>,
+    pub(crate) observer: Option<Arc<dyn EventObserver>>,
+    /// Audio received so far and the most allowed.
+    audio_bytes: u64,
+    pub(crate) max_audio: Option<u64>,
+    pub(crate) close_timeout: Duration,
+    /// Format the first audio is checked against.
+    pub(crate) expected_format: Option<OutputFormat>,
+    info: TurnInfo,
+    dump: Option<ConnectionDump>,
+    /// Recording and index of this turn in it.
+    #[cfg(any(test, feature = "testing"))]
+    recording: Option<(crate::testing::Recording, usize)>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// What [`Turn::read`] received.
+enum Received {
+    /// Audio of this length, in `Turn::message`.
+    Audio(usize),
+    Boundaries(Vec<Boundary>),
+}
+
+impl<S: Stream> Turn<S> {
+    /// Send speech.config, unless the connection already has it, and the SSML.
+    pub(crate) fn start(ssml: &str, speech_config: Option<&str>, socket: WebSocket<S>, connection_id: String, dump: Option<ConnectionDump>) -> Result<Self> {
+        let request_id = random_request_id();
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("turn", request_id = %request_id);
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        let mut turn = Self {
+            socket,
+            request_id,
+            connection_id,
+            message: Vec::new(),
+            finished: false,
+            recorder: None,
+            observer: None,
+            audio_bytes: 0,
+            max_audio: None,
+            close_timeout: CLOSE_TIMEOUT,
+            expected_format: None,
+            info: TurnInfo::default(),
+            dump,
+            #[cfg(any(test, feature = "testing"))]
+            recording: None,
+            #[cfg(feature = "tracing")]
+            span: span.clone(),
+        };
+        if let Some(speech_config) = speech_config {
+            turn.send(speech_config_message(speech_config)).with_context(|| turn.ids())?;
+            trace_event!(debug, bytes = speech_config.len(), "sent speech.config");
+        }
+        let message = Message::Text(format!("X-RequestId:{}\r\nContent-Type:application/ssml+xml\r\nPath:ssml\r\n\r\n{}", turn.request_id, ssml));
+        turn.send(message).with_context(|| turn.ids())?;
+        trace_event!(debug, bytes = ssml.len(), "sent ssml");
+        Ok(turn)
+    }
+
+    pub(crate) fn ids(&self) -> TurnIds {
+        TurnIds { request_id: self.request_id.clone(), connection_id: self.connection_id.clone() }
+    }
+
+    fn send(&mut self, message: Message) -> Result<()> {
+        if let Some(dump) = &self.dump {
+            dump.sent(&message);
+        }
+        Ok(self.socket.send(message)?)
+    }
+
+    /// Next audio or boundary event, `None` after `turn.end`.
+    pub(crate) fn next_event(&mut self) -> Result<Option<SynthesisEvent<'_>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.clone().entered();
+        let received = self.read();
+        if let Some(recorder) = &mut self.recorder {
+            match &received {
+                Ok(Some(Received::Audio(len))) => recorder.audio(*len),
+                Ok(Some(Received::Boundaries(_))) => {}
+                Ok(None) => recorder.completed(false),
+                Err(e) => recorder.failed(ErrorCategory::of(e)),
+            }
+        }
+        let received = received.with_context(|| self.ids());
+        let Some(observer) = &self.observer else {
+            return self.event(received?);
+        };
+        match &received {
+            Ok(Some(Received::Audio(_))) => {}
+            Ok(Some(Received::Boundaries(boundaries))) => observer.metadata(&self.request_id, boundaries),
+            Ok(None) => observer.turn_ended(&self.request_id),
+            Err(e) => observer.error(e),
+        }
+        let observer = observer.clone();
+        let event = self.event(received?)?;
+        if let Some(SynthesisEvent::Audio(audio)) = &event {
+            observer.audio(&self.request_id, audio);
+        }
+        Ok(event)
+    }
+
+    /// The audio of the last audio event, sharing the buffer of its message.
+    #[cfg(feature = "bytes")]
+    pub(crate) fn take_audio(&mut self) -> Result<bytes::Bytes> {
+        let body = parse_binary_frame(&self.message)?.body.len();
+        let message = bytes::Bytes::from(std::mem::take(&mut self.message));
+        Ok(message.slice(message.len() - body..))
+    }
+
+    fn event(&self, received: Option<Received>) -> Result<Option<SynthesisEvent<'_>>> {
+        match received {
+            Some(Received::Audio(_)) => {
+                // Parsed again so the audio can borrow from `self`; `read` just checked it.
+                let frame = parse_binary_frame(&self.message)?;
+                Ok(Some(SynthesisEvent::Audio(frame.body)))
+            }
+            Some(Received::Boundaries(boundaries)) => Ok(Some(SynthesisEvent::Boundaries(boundaries))),
+            None => Ok(None),
+        }
+    }
+
+    fn read(&mut self) -> Result<Option<Received>> {
+        loop {
+            match self.socket.read() {
+                Ok(msg) => {
+                    if let Some(dump) = &self.dump {
+                        dump.received(&msg);
+                    }
+                    #[cfg(any(test, feature = "testing"))]
+                    if let Some((recording, turn)) = &self.recording {
+                        recording.record(*turn, &msg);
+                    }
+                    match msg {
+                        Message::Text(s) => {
+                            let frame = parse_text_frame(&s);
+                            trace_event!(debug, path = frame.path(), request_id = frame.request_id(), bytes = frame.body.len(), "received text message");
+                            match frame.path() {
+                                Some("turn.end") => {
+                                    if frame.request_id() == Some(self.request_id.as_str()) {
+                                        self.finished = true;
+                                        return Ok(None);
+                                    } else {
+                                        return Err(FrameError::RequestIdMismatch { path: "turn.end" }.into());
+                                    }
+                                }
+                                Some("audio.metadata") => {
+                                    if frame.request_id() == Some(self.request_id.as_str()) {
+                                        return Ok(Some(Received::Boundaries(parse_metadata(frame.body)?)));
+                                    } else {
+                                        return Err(FrameError::RequestIdMismatch { path: "audio.metadata" }.into());
+                                    }
                                 }
+                                Some("turn.start") if frame.request_id() == Some(self.request_id.as_str()) => {
+                                    self.info.read_text("turn.start", frame.body);
+                                    if let Some(observer) = &self.observer {
+                                        observer.turn_started(&self.request_id);
+                                    }
+                                }
+                                Some("response") if frame.request_id() == Some(self.request_id.as_str()) => self.info.read_text("response", frame.body),
+                                _ => {}
                             }
-                        } else {
-                            return Err(anyhow!("bad text response. message not complete"));
                         }
-                    }
-                    Message::Binary(s) => {
-                        let header_len = s[0] as usize * 256 + s[1] as usize;
-                        if s.len() >= header_len + 2 {
-                            let headers = parse_headers(String::from_utf8_lossy(&s[2..header_len]));
-                            let body = &s[(header_len + 2)..];
-                            if headers.iter().any(|(k, v)| k == "Path" && v == "audio") {
-                                if headers.iter().any(|(k, v)| k == "X-RequestId" && v.as_str() == request_id) {
-                                    buf.extend(body);
+                        Message::Binary(s) => {
+                            let frame = parse_binary_frame(&s)?;
+                            trace_event!(trace, path = frame.path(), request_id = frame.request_id(), bytes = frame.body.len(), "received binary message");
+                            if frame.path() == Some("audio") {
+                                if frame.request_id() == Some(self.request_id.as_str()) {
+                                    let len = frame.body.len();
+                                    if let Some(format) = self.expected_format.as_ref().filter(|_| self.audio_bytes == 0 && len > 0) {
+                                        format.check_start(frame.body)?;
+                                    }
+                                    self.audio_bytes += len as u64;
+                                    if let Some(limit) = self.max_audio.filter(|&limit| self.audio_bytes > limit) {
+                                        return Err(Error::AudioTooLarge { limit }.into());
+                                    }
+                                    self.info.read_audio(&frame);
+                                    self.message = s;
+                                    return Ok(Some(Received::Audio(len)));
                                 } else {
-                                    return Err(anyhow!("Path:audio no X-RequestId header"));
+                                    return Err(FrameError::RequestIdMismatch { path: "audio" }.into());
                                 }
                             }
-                        } else {
-                            return Err(anyhow!("bad binary response. response len: {} header len: {}", s.len(), header_len));
                         }
+                        Message::Ping(_) => {
+                            trace_event!(debug, "received ping");
+                            // tungstenite queues the Pong, flush sends it.
+                            self.socket.flush()?;
+                        }
+                        Message::Close(frame) => {
+                            trace_event!(debug, frame = ?frame, "closed by server");
+                            return Err(Error::ConnectionClosedByServer {
+                                code: frame.as_ref().map(|f| u16::from(f.code)),
+                                reason: frame.map(|f| f.reason.into_owned()).unwrap_or_default(),
+                            }.into());
+                        }
+                        _ => {}
+                    };
+                }
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    // Read timeout set for keep-alive.
+                    trace_event!(debug, "sending keep-alive ping");
+                    self.send(Message::Ping(Vec::new()))?;
+                }
+                Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                    if let Some(dump) = &self.dump {
+                        dump.note("connection closed");
                     }
-                    _ => {}
-                };
+                    return Err(Error::ConnectionClosedByServer { code: None, reason: String::new() }.into());
+                }
+                Err(tungstenite::Error::Capacity(CapacityError::MessageTooLong { size, max_size })) => {
+                    return Err(Error::MessageTooLarge { size, limit: max_size }.into());
+                }
+                Err(e) => {
+                    if let Some(dump) = &self.dump {
+                        dump.note(&format!("read error: {}", e));
+                    }
+                    return Err(anyhow::Error::from(e).context("socket read error"));
+                }
+            };
+        }
+    }
+
+    /// End the turn early, closing the connection.
+    pub(crate) fn stop(&mut self) {
+        if !self.finished {
+            #[cfg(feature = "tracing")]
+            let _entered = self.span.enter();
+            trace_event!(debug, "closing turn early");
+            self.finished = true;
+            if let Some(recorder) = &mut self.recorder {
+                recorder.completed(true);
             }
-            Err(e) => {
-                return Err(anyhow!("socket read error: {:?}", e));
+            if let Some(dump) = &self.dump {
+                dump.note("closing early");
             }
-        };
+            close(&mut self.socket, self.close_timeout);
+        }
+    }
+}
+
+impl<S: Stream> Drop for Turn<S> {
+    /// Close the connection properly, after `turn.end` or an error, unless it already is.
+    fn drop(&mut self) {
+        if self.socket.can_write() {
+            close(&mut self.socket, self.close_timeout);
+        }
+    }
+}
+
+/// Pass the data of `turn` to `on_event` until `turn.end` or until `on_event` breaks.
+pub(crate) fn process_turn<S: Stream>(mut turn: Turn<S>, on_event: &mut dyn FnMut(SynthesisEvent<'_>) -> ControlFlow<()>) -> Result<(TurnEnd, TurnIds, TurnInfo)> {
+    while let Some(event) = turn.next_event()? {
+        if on_event(event).is_break() {
+            turn.stop();
+            return Ok((TurnEnd::Stopped, turn.ids(), turn.info.clone()));
+        }
+    }
+    Ok((TurnEnd::Completed, turn.ids(), turn.info.clone()))
+}
+
+/// How long [`close`] waits for the acknowledgement by default.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Send Close and read until the service acknowledges it, or until `timeout` has passed, even if the service keeps
+/// sending meanwhile.
+pub(crate) fn close<S: Stream>(socket: &mut WebSocket<S>, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    if socket.close(None).is_err() {
+        return;
+    }
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || socket.get_ref().set_read_timeout(Some(remaining)).is_err() || socket.read().is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    const FORMAT: &str = "audio-24khz-48kbitrate-mono-mp3";
+
+    #[test]
+    fn pings_while_the_service_is_silent() {
+        let script = vec![MockReply::Raw(Message::Ping(b"hi".to_vec())), MockReply::Wait(Duration::from_millis(400)), MockReply::Audio(b"ab".to_vec()), MockReply::TurnEnd];
+        let ssml = build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default");
+        let server = MockServer::start(vec![script.clone()]).unwrap();
+        assert_eq!(server.client().with_keep_alive(Duration::from_millis(100)).synthesize(&ssml, FORMAT).unwrap().audio, b"ab");
+        // Keep-alive pings during the wait, and the Pong answering the service's Ping.
+        assert!(server.pings() >= 2, "{}", server.pings());
+        assert_eq!(server.pongs(), 1);
+
+        // Without a keep-alive, silence is just waited out.
+        let server = MockServer::start(vec![script]).unwrap();
+        assert_eq!(server.client().synthesize(&ssml, FORMAT).unwrap().audio, b"ab");
+        assert_eq!((server.pings(), server.pongs()), (0, 1));
+    }
+
+    #[test]
+    fn closes_connections_with_a_handshake() {
+        let ssml = build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default");
+        let server = MockServer::start(vec![
+            MockReply::turn(b"ab"),
+            vec![MockReply::Raw(Message::Binary(vec![0]))],
+            vec![MockReply::Close { code: 1011, reason: "busy".to_owned() }],
+            vec![MockReply::Audio(vec![0; 3000]), MockReply::Audio(vec![0; 3000]), MockReply::Audio(vec![0; 3000]), MockReply::TurnEnd],
+            MockReply::turn(b"ab"),
+        ])
+        .unwrap();
+        let client = server.client();
+        client.synthesize(&ssml, FORMAT).unwrap();
+        assert_eq!(server.closes(), 1);
+        // After an error of the turn, but not once the service closed it.
+        assert!(client.synthesize(&ssml, FORMAT).is_err());
+        assert!(client.synthesize(&ssml, FORMAT).is_err());
+        assert_eq!(server.closes(), 2);
+        // Stopped early, at 0.1 s of 48000 bytes/s.
+        let request = SynthesisRequest::new("Hi", "en-US-AriaNeural");
+        assert_eq!(client.synthesize_preview(&request.clone().with_output_format("raw-24khz-16bit-mono-pcm"), 0.1).unwrap().audio.len(), 4800);
+        assert_eq!(server.closes(), 3);
+        client.synthesize_pipelined(&[request.clone(), request]).unwrap();
+        assert_eq!(server.closes(), 4);
+    }
+
+    #[test]
+    fn closes_within_the_timeout_after_errors() {
+        let ssml = build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default");
+        // Acknowledged as soon as the script is sent.
+        let server = MockServer::start(vec![vec![MockReply::Audio(vec![1; 8]), MockReply::TurnEnd]]).unwrap();
+        let error = server.client().with_max_audio_size(4).synthesize(&ssml, FORMAT).unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::AudioTooLarge { limit: 4 }));
+        assert_eq!(server.closes(), 1);
+
+        // A service that keeps sending for 3 s doesn't hold the client for as long.
+        let mut flood = vec![MockReply::Audio(vec![1; 8])];
+        for _ in 0..100 {
+            flood.extend([MockReply::Audio(vec![1; 10]), MockReply::Wait(Duration::from_millis(30))]);
+        }
+        let server = MockServer::start(vec![flood]).unwrap();
+        let client = server.client().with_max_audio_size(4).with_close_timeout(Duration::from_millis(300));
+        let started = Instant::now();
+        assert!(client.synthesize(&ssml, FORMAT).is_err());
+        assert!(started.elapsed() < Duration::from_millis(1500), "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn sends_speech_config_overrides_as_is() {
+        let ssml = build_ssml("Hi", "en-US-AriaNeural", "default", "default", "default");
+        let config = json!({ "context": { "synthesis": { "audio": { "outputFormat": FORMAT, "metadataoptions": { "visemeEnabled": "true" } } } } });
+        let text = "{ \"context\": { \"synthesis\": { \"audio\": { \"outputFormat\": \"riff-24khz-16bit-mono-pcm\" } } } }";
+        let server = MockServer::start(vec![MockReply::turn(b"a"), MockReply::turn(b"b")]).unwrap();
+        server.client().with_speech_config(config.clone()).synthesize(&ssml, FORMAT).unwrap();
+        server.client().with_speech_config_json(text).unwrap().synthesize(&ssml, FORMAT).unwrap();
+        let sent: Vec<String> = server.requests().into_iter().map(|r| r.speech_config).collect();
+        assert_eq!(sent, [config.to_string(), text.to_owned()]);
+
+        let server = MockServer::start(vec![MockReply::turn(b"a")]).unwrap();
+        let error = server.client().with_speech_config_json("{\"context\": ").unwrap_err();
+        assert!(error.is::<serde_json::Error>(), "{}", error);
+        assert!(server.client().with_speech_config_json("").is_err());
+        assert!(server.requests().is_empty());
     }
 }
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::Announcer;
+
+/// Which followed lines to announce, eg: for `edge-tts tail`: the ones matching `include` and not `exclude`, at most
+/// one per `interval`. Lines held back by the interval or a full queue are counted and mentioned with the next
+/// announcement.
+#[derive(Debug, Clone)]
+pub struct TailFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+    interval: Duration,
+    last_announcement: Option<Instant>,
+    suppressed: usize,
+}
+
+impl TailFilter {
+    pub fn new(include: Option<Regex>, exclude: Option<Regex>, interval: Duration) -> Self {
+        Self { include, exclude, interval, last_announcement: None, suppressed: 0 }
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        self.include.as_ref().is_none_or(|re| re.is_match(line)) && !self.exclude.as_ref().is_some_and(|re| re.is_match(line))
+    }
+
+    /// Announce `line` on `announcer` if it matches and the interval has passed. Returns whether it was queued.
+    pub fn offer(&mut self, announcer: &Announcer, line: String) -> bool {
+        self.offer_at(announcer, line, Instant::now())
+    }
+
+    fn offer_at(&mut self, announcer: &Announcer, line: String, now: Instant) -> bool {
+        if !self.matches(&line) {
+            return false;
+        }
+        if self.last_announcement.is_some_and(|t| now.saturating_duration_since(t) < self.interval) {
+            self.suppressed += 1;
+            return false;
+        }
+        let text = match self.suppressed {
+            0 => line,
+            n => format!("{}. {} more matching lines were skipped.", line, n),
+        };
+        if announcer.announce(text) {
+            self.last_announcement = Some(now);
+            self.suppressed = 0;
+            true
+        } else {
+            self.suppressed += 1;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::Player;
+
+    /// A player appending each audio and a newline to a file.
+    fn recorder() -> (Player, PathBuf) {
+        let path = std::env::temp_dir().join(format!("edge-tts-tail-{}", uuid::Uuid::new_v4().simple()));
+        (Player::new("sh", ["-c".to_owned(), format!("cat >> {}; echo >> {}", path.display(), path.display())]), path)
+    }
+
+    #[test]
+    fn filters_and_spaces_out_lines() {
+        let server = MockServer::start(vec![MockReply::turn(b"error: disk"), MockReply::turn(b"error: net. 2 more")]).unwrap();
+        let (player, path) = recorder();
+        let announcer = Announcer::spawn(server.client(), "audio-24khz-48kbitrate-mono-mp3", player, 4, str::to_owned, |e| panic!("{:#}", e));
+        let mut filter = TailFilter::new(Some(Regex::new("^error").unwrap()), Some(Regex::new("ignored").unwrap()), Duration::from_secs(5));
+        assert!(!filter.matches("info: started") && !filter.matches("error: ignored") && filter.matches("error: disk"));
+
+        let start = Instant::now();
+        assert!(filter.offer_at(&announcer, "error: disk".to_owned(), start));
+        assert!(!filter.offer_at(&announcer, "info: started".to_owned(), start));
+        assert!(!filter.offer_at(&announcer, "error: cpu".to_owned(), start + Duration::from_secs(1)));
+        assert!(!filter.offer_at(&announcer, "error: ram".to_owned(), start + Duration::from_secs(4)));
+        assert!(filter.offer_at(&announcer, "error: net".to_owned(), start + Duration::from_secs(5)));
+        announcer.finish();
+
+        let ssml: Vec<String> = server.requests().into_iter().map(|r| r.ssml).collect();
+        assert_eq!(ssml, ["error: disk", "error: net. 2 more matching lines were skipped."]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "error: disk\nerror: net. 2 more\n");
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,489 @@
+//! A scripted stand-in for the speech service, to test code using [`Client`] offline.
+//!
+//! ```
+//! use edge_tts::testing::{MockReply, MockServer};
+//! use edge_tts::SynthesisRequest;
+//!
+//! let server = MockServer::start(vec![MockReply::turn(b"mp3 data")]).unwrap();
+//! let output = server.client().synthesize_request(&SynthesisRequest::new("Hello.", "en-US-AriaNeural")).unwrap();
+//! assert_eq!(output.audio, b"mp3 data");
+//! ```
+//!
+//! A [`Recording`] of real turns, saved as a [`Fixture`] file, can be replayed by [`MockServer::replay`].
+
+use std::fs;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::{Message, WebSocket};
+
+use crate::frame::{parse_binary_frame, parse_text_frame, Headers};
+use crate::stream::{Connector, Stream};
+use crate::Client;
+
+/// What the mock server sends after it received a turn's SSML, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockReply {
+    /// A `Path:audio` message.
+    Audio(Vec<u8>),
+    /// A `Path:audio.metadata` message with one word boundary.
+    Word { text: String, offset: Duration, duration: Duration },
+    /// A text message of the turn, eg: path "turn.start", body "{}".
+    Text { path: String, body: String },
+    TurnEnd,
+    /// Sent as is, eg: a malformed binary message.
+    Raw(Message),
+    /// Close the connection with this code, eg: 1011.
+    Close { code: u16, reason: String },
+    /// Drop the connection without a Close.
+    Disconnect,
+    Wait(Duration),
+}
+
+impl MockReply {
+    /// A complete turn: turn.start, `audio` and turn.end.
+    pub fn turn(audio: &[u8]) -> Vec<MockReply> {
+        vec![
+            MockReply::Text { path: "turn.start".to_owned(), body: "{}".to_owned() },
+            MockReply::Audio(audio.to_vec()),
+            MockReply::TurnEnd,
+        ]
+    }
+}
+
+/// What the mock server received for one turn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MockRequest {
+    /// Path and query of the handshake, eg: "/consumer/speech/synthesize/readaloud/edge/v1?TrustedClientToken=..."
+    pub uri: String,
+    /// Headers of the handshake, names in lowercase.
+    pub headers: Vec<(String, String)>,
+    /// Body of the last speech.config of the connection.
+    pub speech_config: String,
+    /// X-RequestId of the SSML message.
+    pub request_id: String,
+    pub ssml: String,
+}
+
+/// The received messages of turns, eg: of the real service, see [`Recording`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fixture {
+    pub turns: Vec<RecordedTurn>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedTurn {
+    pub ssml: String,
+    /// Text, binary and close messages, in order.
+    pub messages: Vec<Message>,
+}
+
+impl Fixture {
+    /// eg: `{"turns": [{"ssml": "<speak ...", "messages": [{"text": "X-RequestId:..."}, {"binary": "<hex>"}]}]}`
+    pub fn to_json(&self) -> Value {
+        let turns: Vec<Value> = self
+            .turns
+            .iter()
+            .map(|turn| {
+                let messages: Vec<Value> = turn
+                    .messages
+                    .iter()
+                    .filter_map(|message| match message {
+                        Message::Text(text) => Some(json!({ "text": text })),
+                        Message::Binary(data) => Some(json!({ "binary": hex::encode(data) })),
+                        Message::Close(frame) => Some(json!({
+                            "close": frame.as_ref().map(|f| u16::from(f.code)),
+                            "reason": frame.as_ref().map(|f| f.reason.to_string()).unwrap_or_default(),
+                        })),
+                        _ => None,
+                    })
+                    .collect();
+                json!({ "ssml": turn.ssml, "messages": messages })
+            })
+            .collect();
+        json!({ "turns": turns })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let turns = value.get("turns").and_then(Value::as_array).ok_or_else(|| anyhow!("fixture without turns"))?;
+        let turns = turns
+            .iter()
+            .map(|turn| {
+                let ssml = turn.get("ssml").and_then(Value::as_str).ok_or_else(|| anyhow!("fixture turn without ssml"))?.to_owned();
+                let messages = turn.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+                let messages = messages.iter().map(fixture_message).collect::<Result<_>>()?;
+                Ok(RecordedTurn { ssml, messages })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { turns })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| path.display().to_string())?;
+        Self::from_json(&serde_json::from_str(&text)?).with_context(|| path.display().to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(&self.to_json())?).with_context(|| path.display().to_string())
+    }
+}
+
+fn fixture_message(value: &Value) -> Result<Message> {
+    if let Some(text) = value.get("text").and_then(Value::as_str) {
+        return Ok(Message::Text(text.to_owned()));
+    }
+    if let Some(data) = value.get("binary").and_then(Value::as_str) {
+        return Ok(Message::Binary(hex::decode(data)?));
+    }
+    if let Some(close) = value.get("close") {
+        let reason = value.get("reason").and_then(Value::as_str).unwrap_or_default().to_owned();
+        let frame = close.as_u64().map(|code| CloseFrame { code: CloseCode::from(code as u16), reason: reason.into() });
+        return Ok(Message::Close(frame));
+    }
+    Err(anyhow!("unknown fixture message: {}", value))
+}
+
+/// Collects the messages a [`Client`] receives, see [`Client::with_recording`]. eg: record against the service once,
+/// [`Recording::save`] the fixture, then test against [`MockServer::replay`].
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    fixture: Arc<Mutex<Fixture>>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.fixture().save(path)
+    }
+
+    /// Index of the new turn.
+    pub(crate) fn start_turn(&self, ssml: &str) -> usize {
+        let mut fixture = self.fixture.lock().unwrap_or_else(|e| e.into_inner());
+        fixture.turns.push(RecordedTurn { ssml: ssml.to_owned(), messages: Vec::new() });
+        fixture.turns.len() - 1
+    }
+
+    pub(crate) fn record(&self, turn: usize, message: &Message) {
+        if matches!(message, Message::Text(_) | Message::Binary(_) | Message::Close(_)) {
+            if let Some(turn) = self.fixture.lock().unwrap_or_else(|e| e.into_inner()).turns.get_mut(turn) {
+                turn.messages.push(message.clone());
+            }
+        }
+    }
+}
+
+/// `message` with its X-RequestId header set to `request_id`.
+fn with_request_id(message: &Message, request_id: &str) -> Message {
+    let replace = |headers: &str| {
+        headers
+            .split("\r\n")
+            .map(|line| match line.starts_with("X-RequestId:") {
+                true => format!("X-RequestId:{}", request_id),
+                false => line.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    };
+    match message {
+        Message::Text(text) => match text.split_once("\r\n\r\n") {
+            Some((headers, body)) => Message::Text(format!("{}\r\n\r\n{}", replace(headers), body)),
+            None => message.clone(),
+        },
+        Message::Binary(data) => match parse_binary_frame(data) {
+            Ok(frame) => {
+                let header_len = data.len() - frame.body.len() - 2;
+                let headers = replace(&String::from_utf8_lossy(data.get(2..2 + header_len).unwrap_or_default()));
+                let mut replaced = (headers.len() as u16).to_be_bytes().to_vec();
+                replaced.extend_from_slice(headers.as_bytes());
+                replaced.extend_from_slice(frame.body);
+                Message::Binary(replaced)
+            }
+            Err(_) => message.clone(),
+        },
+        _ => message.clone(),
+    }
+}
+
+/// Replies to a turn: connection number, SSML and request id.
+type Responder = dyn Fn(usize, &str, &str) -> Vec<MockReply> + Send + Sync;
+
+/// WebSocket server on a local port answering each turn with a script, see [`MockServer::start`], or with recorded
+/// messages, see [`MockServer::replay`]. Stops when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    counts: Arc<Counts>,
+    stopped: Arc<AtomicBool>,
+}
+
+/// Control messages received from clients.
+#[derive(Debug, Default)]
+struct Counts {
+    closes: AtomicUsize,
+    pings: AtomicUsize,
+    pongs: AtomicUsize,
+}
+
+impl MockServer {
+    /// Answer the turns of connection `i` with `scripts[i]`, or with the last script once they run out.
+    pub fn start(scripts: Vec<Vec<MockReply>>) -> Result<Self> {
+        Self::spawn(Arc::new(move |connection, _, _| scripts.get(connection).or(scripts.last()).cloned().unwrap_or_default()))
+    }
+
+    /// Answer each turn with the recorded messages of the turn with the same SSML, with the request id replaced, or
+    /// close the connection with code 1011 if there is none.
+    pub fn replay(fixture: Fixture) -> Result<Self> {
+        Self::spawn(Arc::new(move |_, ssml, request_id| match fixture.turns.iter().find(|turn| turn.ssml == ssml) {
+            Some(turn) => turn.messages.iter().map(|message| MockReply::Raw(with_request_id(message, request_id))).collect(),
+            None => vec![MockReply::Close { code: 1011, reason: "no recorded turn for this SSML".to_owned() }],
+        }))
+    }
+
+    fn spawn(responder: Arc<Responder>) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let server = Self { addr: listener.local_addr()?, requests: Arc::default(), counts: Arc::default(), stopped: Arc::default() };
+        let (requests, counts, stopped) = (server.requests.clone(), server.counts.clone(), server.stopped.clone());
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let (requests, counts, responder) = (requests.clone(), counts.clone(), responder.clone());
+                std::thread::spawn(move || serve(stream, i, &*responder, &requests, &counts));
+            }
+        });
+        Ok(server)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Connects to this server, whatever the URL.
+    pub fn connector(&self) -> Arc<dyn Connector> {
+        Arc::new(MockConnector { addr: self.addr })
+    }
+
+    /// eg: `server.client().with_resume(1)`
+    pub fn client(&self) -> Client {
+        Client::new().with_connector(self.connector())
+    }
+
+    /// Turns received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Connections the client closed with a Close message.
+    pub fn closes(&self) -> usize {
+        self.counts.closes.load(Ordering::SeqCst)
+    }
+
+    /// Pings received, eg: of [`Client::with_keep_alive`]. Read once a turn's script has been sent.
+    pub fn pings(&self) -> usize {
+        self.counts.pings.load(Ordering::SeqCst)
+    }
+
+    /// Pongs received, answering `MockReply::Raw(Message::Ping(..))`.
+    pub fn pongs(&self) -> usize {
+        self.counts.pongs.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+#[derive(Debug)]
+struct MockConnector {
+    addr: SocketAddr,
+}
+
+impl Connector for MockConnector {
+    fn connect(&self, _: &url::Url) -> Result<Box<dyn Stream>> {
+        Ok(Box::new(TcpStream::connect(self.addr)?))
+    }
+}
+
+fn serve(stream: TcpStream, connection: usize, responder: &Responder, requests: &Mutex<Vec<MockRequest>>, counts: &Counts) -> Result<()> {
+    let (mut uri, mut headers) = (String::new(), Vec::new());
+    // The error type is tungstenite's.
+    #[allow(clippy::result_large_err)]
+    let mut socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        uri = request.uri().to_string();
+        headers = request.headers().iter().map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())).collect();
+        Ok(response)
+    })
+    .map_err(|e| anyhow!("mock handshake failed: {}", e))?;
+    let mut speech_config = String::new();
+    loop {
+        let text = match socket.read()? {
+            Message::Text(text) => text,
+            Message::Ping(_) => {
+                counts.pings.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            Message::Pong(_) => {
+                counts.pongs.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            Message::Close(_) => {
+                counts.closes.fetch_add(1, Ordering::SeqCst);
+                // Sends the queued acknowledgement.
+                let _ = socket.flush();
+                return Ok(());
+            }
+            _ => continue,
+        };
+        let frame = parse_text_frame(&text);
+        match frame.path() {
+            Some("speech.config") => speech_config = frame.body.to_owned(),
+            Some("ssml") => {
+                let request_id = frame.request_id().unwrap_or_default().to_owned();
+                let request = MockRequest { uri: uri.clone(), headers: headers.clone(), speech_config: speech_config.clone(), request_id: request_id.clone(), ssml: frame.body.to_owned() };
+                requests.lock().unwrap_or_else(|e| e.into_inner()).push(request);
+                if !play(&mut socket, &responder(connection, frame.body, &request_id), &request_id)? {
+                    return Ok(());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Send `script`. `false` if it ended the connection.
+fn play(socket: &mut WebSocket<TcpStream>, script: &[MockReply], request_id: &str) -> Result<bool> {
+    let text = |path: &str, body: &str| Message::Text(format!("X-RequestId:{}\r\nContent-Type:application/json; charset=utf-8\r\nPath:{}\r\n\r\n{}", request_id, path, body));
+    for reply in script {
+        let message = match reply {
+            MockReply::Audio(audio) => {
+                let headers = format!("X-RequestId:{}\r\nContent-Type:audio/mpeg\r\nPath:audio\r\n", request_id);
+                let mut data = (headers.len() as u16).to_be_bytes().to_vec();
+                data.extend_from_slice(headers.as_bytes());
+                data.extend_from_slice(audio);
+                Message::Binary(data)
+            }
+            MockReply::Word { text: word, offset, duration } => {
+                let ticks = |d: &Duration| d.as_nanos() as u64 / 100;
+                let body = json!({
+                    "Metadata": [{
+                        "Type": "WordBoundary",
+                        "Data": { "Offset": ticks(offset), "Duration": ticks(duration), "text": { "Text": word, "Length": word.len(), "BoundaryType": "WordBoundary" } },
+                    }]
+                });
+                text("audio.metadata", &body.to_string())
+            }
+            MockReply::Text { path, body } => text(path, body),
+            MockReply::TurnEnd => text("turn.end", "{}"),
+            MockReply::Raw(message) => message.clone(),
+            MockReply::Close { code, reason } => {
+                socket.close(Some(CloseFrame { code: CloseCode::from(*code), reason: reason.clone().into() }))?;
+                // Until the client acknowledges it.
+                while socket.read().is_ok() {}
+                return Ok(false);
+            }
+            MockReply::Disconnect => return Ok(false),
+            MockReply::Wait(duration) => {
+                std::thread::sleep(*duration);
+                continue;
+            }
+        };
+        socket.send(message)?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_ssml, Error, MetadataOptions, TurnIds};
+
+    #[test]
+    fn serves_scripted_turns() {
+        let word = MockReply::Word { text: "Hello".to_owned(), offset: Duration::from_millis(100), duration: Duration::from_millis(400) };
+        let server = MockServer::start(vec![
+            vec![MockReply::Close { code: 1011, reason: "overloaded".to_owned() }],
+            vec![word, MockReply::Audio(b"ab".to_vec()), MockReply::Audio(b"cd".to_vec()), MockReply::TurnEnd],
+        ])
+        .unwrap();
+        let client = server
+            .client()
+            .with_metadata_options(MetadataOptions { sentence_boundary_enabled: false, word_boundary_enabled: true, ..Default::default() })
+            .with_header("Accept-Language", "de-DE")
+            .and_then(|client| client.without_header("Origin"))
+            .unwrap();
+        let ssml = build_ssml("Hello", "en-US-AriaNeural", "default", "default", "default");
+        let error = client.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::ConnectionClosedByServer { code: Some(1011), reason: "overloaded".to_owned() }));
+        let output = client.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap();
+        assert_eq!(output.audio, b"abcd");
+        assert_eq!((output.boundaries[0].text.as_str(), output.boundaries[0].offset), ("Hello", Duration::from_millis(100)));
+        // Only the connection the service didn't close itself.
+        assert_eq!(server.closes(), 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let failed = error.downcast_ref::<TurnIds>().unwrap();
+        assert_eq!(failed.request_id, requests[0].request_id);
+        let ids = output.ids.unwrap();
+        assert_eq!(ids.request_id, requests[1].request_id);
+        assert!(requests[1].uri.contains(&format!("ConnectionId={}", ids.connection_id)), "{}", requests[1].uri);
+        assert_eq!(requests[1].ssml, ssml);
+        assert!(requests[1].speech_config.contains("\"wordBoundaryEnabled\":true"), "{}", requests[1].speech_config);
+        assert!(requests[1].uri.contains("Sec-MS-GEC="), "{}", requests[1].uri);
+        let header = |name: &str| requests[1].headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+        assert_eq!((header("accept-language"), header("origin")), (Some("de-DE"), None));
+        assert!(header("user-agent").is_some_and(|agent| agent.contains("Edg/")));
+    }
+
+    #[test]
+    fn connects_to_custom_endpoint() {
+        let server = MockServer::start(vec![MockReply::turn(b"mirrored")]).unwrap();
+        let client = Client::new().with_endpoint(&format!("ws://{}/mirror/v1?Region=eu", server.addr())).unwrap();
+        let ssml = build_ssml("Hello", "en-US-AriaNeural", "default", "default", "default");
+        assert_eq!(client.synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"mirrored");
+        let uri = &server.requests()[0].uri;
+        assert!(uri.starts_with("/mirror/v1?Region=eu&TrustedClientToken=6A5AA1D4EAFF4E9FB37E23D68491D6F4&Sec-MS-GEC="), "{}", uri);
+        assert!(Client::new().with_endpoint("https://example.com").is_err());
+    }
+
+    #[test]
+    fn replays_recorded_turns() {
+        let server = MockServer::start(vec![MockReply::turn(b"recorded")]).unwrap();
+        let recording = Recording::new();
+        let ssml = build_ssml("Hello", "en-US-AriaNeural", "default", "default", "default");
+        server.client().with_recording(recording.clone()).synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap();
+        let path = std::env::temp_dir().join(format!("edge-tts-fixture-{}.json", uuid::Uuid::new_v4().simple()));
+        recording.save(&path).unwrap();
+        let fixture = Fixture::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(fixture, recording.fixture());
+        assert_eq!(fixture.turns[0].messages.len(), 3);
+        let replay = MockServer::replay(fixture).unwrap();
+        assert_eq!(replay.client().synthesize(&ssml, "audio-24khz-48kbitrate-mono-mp3").unwrap().audio, b"recorded");
+        let other = build_ssml("Bye", "en-US-AriaNeural", "default", "default", "default");
+        assert!(replay.client().synthesize(&other, "audio-24khz-48kbitrate-mono-mp3").is_err());
+    }
+}
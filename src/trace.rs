@@ -0,0 +1,10 @@
+/// A `tracing` event, eg: `trace_event!(debug, bytes = len, "sent ssml")`. Compiled out without the `tracing`
+/// feature, so fields must not be the only use of a value.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+pub(crate) use trace_event;
@@ -0,0 +1,97 @@
+use serde_json::Value;
+
+use crate::frame::{BinaryFrame, Headers};
+use crate::{Container, OutputFormat};
+
+/// What the service tells of a turn besides its audio and boundaries, from its `turn.start` and `response`
+/// messages and the headers of its first audio message, eg: to quote to support or to check the output format.
+/// Fields the service didn't send are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnInfo {
+    /// `context.serviceTag` of `turn.start`, which names the service instance.
+    pub service_tag: Option<String>,
+    /// `audio.type` of `response`, eg: "inline".
+    pub audio_type: Option<String>,
+    /// `audio.streamId` of `response`, else the X-StreamId of the audio.
+    pub stream_id: Option<String>,
+    /// Content-Type of the audio, eg: "audio/mpeg".
+    pub content_type: Option<String>,
+}
+
+impl TurnInfo {
+    /// Whether the audio is in the container of `format`, from its Content-Type. `None` without one, or for raw
+    /// formats, which have no media type to tell them apart.
+    pub fn honors(&self, format: &OutputFormat) -> Option<bool> {
+        let content_type = self.content_type.as_deref()?;
+        let media_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let container = match media_type.as_str() {
+            "audio/mpeg" | "audio/mp3" => Container::Mp3,
+            "audio/ogg" => return Some(matches!(format.container(), Container::Ogg | Container::Opus)),
+            "audio/webm" => Container::Webm,
+            "audio/wav" | "audio/x-wav" | "audio/wave" => Container::Riff,
+            _ => return None,
+        };
+        match format.container() {
+            Container::Raw | Container::Other => None,
+            requested => Some(requested == container),
+        }
+    }
+
+    /// Read the body of a `turn.start` or `response` message; others are ignored.
+    pub(crate) fn read_text(&mut self, path: &str, body: &str) {
+        let Ok(body) = serde_json::from_str::<Value>(body) else { return };
+        let string = |pointer: &str| body.pointer(pointer).and_then(Value::as_str).map(str::to_owned);
+        match path {
+            "turn.start" => self.service_tag = string("/context/serviceTag").or(self.service_tag.take()),
+            "response" => {
+                self.service_tag = self.service_tag.take().or_else(|| string("/context/serviceTag"));
+                self.audio_type = string("/audio/type").or(self.audio_type.take());
+                self.stream_id = string("/audio/streamId").or(self.stream_id.take());
+            }
+            _ => {}
+        }
+    }
+
+    /// Read the headers of an audio message, unless an earlier one had them.
+    pub(crate) fn read_audio(&mut self, frame: &BinaryFrame<'_>) {
+        if self.content_type.is_none() {
+            self.content_type = frame.header("Content-Type").map(str::to_owned);
+        }
+        if self.stream_id.is_none() {
+            self.stream_id = frame.header("X-StreamId").map(str::to_owned);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+    use crate::SynthesisRequest;
+
+    #[test]
+    fn reads_turn_messages() {
+        let text = |path: &str, body: &str| MockReply::Text { path: path.to_owned(), body: body.to_owned() };
+        let turn = vec![
+            text("turn.start", r#"{"context": {"serviceTag": "tag1"}}"#),
+            text("response", r#"{"context": {"serviceTag": "tag1"}, "audio": {"type": "inline", "streamId": "stream1"}}"#),
+            MockReply::Audio(b"abc".to_vec()),
+            MockReply::TurnEnd,
+        ];
+        let server = MockServer::start(vec![turn]).unwrap();
+        let output = server.client().synthesize_request(&SynthesisRequest::new("Hi", "en-US-AriaNeural")).unwrap();
+        let info = output.turn_info.unwrap();
+        let expected = |content_type: &str| TurnInfo {
+            service_tag: Some("tag1".to_owned()),
+            audio_type: Some("inline".to_owned()),
+            stream_id: Some("stream1".to_owned()),
+            content_type: Some(content_type.to_owned()),
+        };
+        assert_eq!(info, expected("audio/mpeg"));
+        assert_eq!(info.honors(&OutputFormat::AUDIO_24KHZ_48KBITRATE_MONO_MP3), Some(true));
+        assert_eq!(info.honors(&OutputFormat::WEBM_24KHZ_16BIT_MONO_OPUS), Some(false));
+        assert_eq!(info.honors(&OutputFormat::RAW_16KHZ_16BIT_MONO_PCM), None);
+        assert_eq!(expected("audio/webm; codecs=opus").honors(&OutputFormat::WEBM_24KHZ_16BIT_MONO_OPUS), Some(true));
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{Client, OutputFormat, SynthesisRequest};
+
+/// Narration text starting at `start` in the video.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NarrationSegment {
+    pub start: Duration,
+    pub text: String,
+}
+
+/// Parse a narration script: one segment per line, `TIMESTAMP TEXT`, eg: "1:05.5 Now open the settings."
+///
+/// Timestamps are `[[h:]m:]s[.fraction]`. Blank lines and lines starting with `#` are skipped.
+pub fn parse_narration_script(script: &str) -> Result<Vec<NarrationSegment>> {
+    let mut segments = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (timestamp, text) = line.split_once(char::is_whitespace).ok_or_else(|| anyhow!("line {}: missing text", i + 1))?;
+        let start = parse_timestamp(timestamp).ok_or_else(|| anyhow!("line {}: bad timestamp {}", i + 1, timestamp))?;
+        segments.push(NarrationSegment {
+            start,
+            text: text.trim().to_owned(),
+        });
+    }
+    Ok(segments)
+}
+
+fn parse_timestamp(s: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for part in s.split(':') {
+        let value: f64 = part.parse().ok()?;
+        if value.is_sign_negative() {
+            return None;
+        }
+        secs = secs * 60.0 + value;
+    }
+    Some(Duration::from_secs_f64(secs))
+}
+
+/// Options of [`narrate_video`].
+#[derive(Debug, Clone)]
+pub struct NarrationOptions {
+    /// ffmpeg executable, eg: "ffmpeg" or "/usr/local/bin/ffmpeg"
+    pub ffmpeg: PathBuf,
+    /// Volume of the video's own audio under the narration, 0 drops it. The video must have an audio track unless 0.
+    pub original_volume: f32,
+    /// Segments synthesized at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for NarrationOptions {
+    fn default() -> Self {
+        Self {
+            ffmpeg: PathBuf::from("ffmpeg"),
+            original_volume: 0.3,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Synthesize `segments` with the voice and prosody of `template` and mux them into `video`'s audio track at their
+/// start times, writing `output`. The video stream is copied as is.
+pub fn narrate_video(client: &Client, video: &Path, segments: &[NarrationSegment], template: &SynthesisRequest, output: &Path, options: &NarrationOptions) -> Result<()> {
+    if segments.is_empty() {
+        bail!("no narration segments");
+    }
+    let requests = segments
+        .iter()
+        .map(|segment| SynthesisRequest {
+            text: segment.text.clone(),
+            output_format: OutputFormat::AUDIO_24KHZ_96KBITRATE_MONO_MP3,
+            ..template.clone()
+        })
+        .collect();
+    let dir = std::env::temp_dir().join(format!("edge-tts-narration-{}", uuid::Uuid::new_v4().simple()));
+    fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let mut files = Vec::new();
+        for (i, result) in client.synthesize_batch(requests, options.concurrency).into_iter().enumerate() {
+            let path = dir.join(format!("{}.mp3", i));
+            fs::write(&path, result.map_err(|e| e.context(format!("narration segment {}", i + 1)))?.audio)?;
+            files.push(path);
+        }
+        run_ffmpeg(video, segments, &files, output, options)
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn run_ffmpeg(video: &Path, segments: &[NarrationSegment], files: &[PathBuf], output: &Path, options: &NarrationOptions) -> Result<()> {
+    let mut command = Command::new(&options.ffmpeg);
+    command.args(["-y", "-loglevel", "error", "-i"]).arg(video);
+    for file in files {
+        command.arg("-i").arg(file);
+    }
+    let mut filter = String::new();
+    let mut mix_inputs = String::new();
+    if options.original_volume > 0.0 {
+        filter.push_str(&format!("[0:a]volume={}[orig];", options.original_volume));
+        mix_inputs.push_str("[orig]");
+    }
+    for (i, segment) in segments.iter().enumerate() {
+        filter.push_str(&format!("[{}:a]adelay={}:all=1[n{}];", i + 1, segment.start.as_millis(), i));
+        mix_inputs.push_str(&format!("[n{}]", i));
+    }
+    let inputs = segments.len() + (options.original_volume > 0.0) as usize;
+    // With the original audio, the mix ends with it; otherwise with the last narration.
+    let duration = if options.original_volume > 0.0 { "first" } else { "longest" };
+    filter.push_str(&format!("{}amix=inputs={}:duration={}:normalize=0[aout]", mix_inputs, inputs, duration));
+    command.arg("-filter_complex").arg(filter);
+    command.args(["-map", "0:v", "-map", "[aout]", "-c:v", "copy", "-c:a", "aac"]).arg(output);
+    let result = command.output().map_err(|e| anyhow!("failed to run {}: {}", options.ffmpeg.display(), e))?;
+    if !result.status.success() {
+        bail!("ffmpeg exited with {}: {}", result.status, String::from_utf8_lossy(&result.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_script() {
+        let script = "# intro\n0 Welcome.\n\n1:05.5   Now open the settings.\n1:00:00 The end.";
+        assert_eq!(parse_narration_script(script).unwrap(), vec![
+            NarrationSegment { start: Duration::ZERO, text: "Welcome.".to_owned() },
+            NarrationSegment { start: Duration::from_millis(65_500), text: "Now open the settings.".to_owned() },
+            NarrationSegment { start: Duration::from_secs(3600), text: "The end.".to_owned() },
+        ]);
+        assert!(parse_narration_script("soon Hello").is_err());
+        assert!(parse_narration_script("1:00").is_err());
+    }
+}
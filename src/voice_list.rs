@@ -1,6 +1,12 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
 use serde::{Deserialize, Serialize};
 use ureq::get;
 
+use crate::storage::{FsStorage, Storage};
+
 const VOICES_URL: &str = "https://speech.platform.bing.com/consumer/speech/synthesize/readaloud/voices/list?trustedclienttoken=6A5AA1D4EAFF4E9FB37E23D68491D6F4";
 
 // region voice list
@@ -45,3 +51,222 @@ pub struct VoiceTag {
 pub fn get_voice_list() -> anyhow::Result<Vec<Voice>> {
     Ok(get(VOICES_URL).call()?.into_json()?)
 }
+
+// endregion
+
+// region cached voice list
+
+/// Voices known when this crate was released, from `data/voices.json`, which
+/// `cargo run --example voices_snapshot --features voice_list` regenerates. A subset of the full list.
+pub fn bundled_voices() -> Vec<Voice> {
+    serde_json::from_str(include_str!("../data/voices.json")).unwrap_or_default()
+}
+
+/// The voice list stored as `voices.json` in a [`Storage`], fetched again once older than the max age (default 7
+/// days). Offline, a stale list, else [`bundled_voices`], is used.
+#[derive(Debug, Clone)]
+pub struct VoiceListCache {
+    storage: Arc<dyn Storage>,
+    namespace: String,
+    max_age: Duration,
+    fetch: fn() -> anyhow::Result<Vec<Voice>>,
+}
+
+impl VoiceListCache {
+    const KEY: &'static str = "voices.json";
+
+    /// `dir/voices.json`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_storage(Arc::new(FsStorage::new(dir)), "")
+    }
+
+    /// eg: `namespace`: "cache"
+    pub fn with_storage(storage: Arc<dyn Storage>, namespace: impl Into<String>) -> Self {
+        Self { storage, namespace: namespace.into(), max_age: Duration::from_secs(7 * 24 * 3600), fetch: get_voice_list }
+    }
+
+    /// eg: `Duration::ZERO` to fetch every time, falling back to the stored list offline.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The stored list if fresh, else a fetched one, else the stored one however old, else [`bundled_voices`].
+    pub fn voices(&self) -> Vec<Voice> {
+        let stored = self.storage.stat(&self.namespace, Self::KEY).ok().flatten();
+        let fresh = stored.is_some_and(|entry| SystemTime::now().duration_since(entry.modified).unwrap_or_default() <= self.max_age);
+        if fresh {
+            if let Some(voices) = self.stored() {
+                return voices;
+            }
+        }
+        self.refresh().ok().or_else(|| self.stored()).unwrap_or_else(bundled_voices)
+    }
+
+    /// Fetch and store the list regardless of its age.
+    pub fn refresh(&self) -> anyhow::Result<Vec<Voice>> {
+        let voices = (self.fetch)()?;
+        self.storage.put(&self.namespace, Self::KEY, &serde_json::to_vec(&voices)?)?;
+        Ok(voices)
+    }
+
+    fn stored(&self) -> Option<Vec<Voice>> {
+        let json = self.storage.get(&self.namespace, Self::KEY).ok()??;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+// endregion
+
+// region find voices
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Gender {
+    Female,
+    Male,
+}
+
+/// Regions preferred when only a language is given, eg: "zh" matches "zh-CN" before "zh-TW".
+const DEFAULT_REGIONS: &[(&str, &str)] = &[
+    ("ar", "SA"), ("bn", "IN"), ("en", "US"), ("es", "ES"), ("fa", "IR"), ("hi", "IN"), ("ja", "JP"), ("ko", "KR"),
+    ("ms", "MY"), ("pt", "BR"), ("sv", "SE"), ("sw", "KE"), ("ta", "IN"), ("uk", "UA"), ("ur", "PK"), ("vi", "VN"),
+    ("zh", "CN"),
+];
+
+/// Voices of `voices` matching all given filters, best first.
+///
+/// `locale`: eg: "zh-CN", or a language like "zh", preferring its main region. `query` matches the voice name
+/// loosely, eg: "xiaoxiao", "aria", "jenny multilingual", with typos.
+pub fn find_voices<'a>(voices: &'a [Voice], locale: Option<&str>, gender: Option<Gender>, query: Option<&str>) -> Vec<&'a Voice> {
+    let query = query.map(normalize).filter(|q| !q.is_empty());
+    let mut matches: Vec<(u32, u32, &Voice)> = voices
+        .iter()
+        .filter(|voice| gender.is_none_or(|gender| voice.gender.eq_ignore_ascii_case(&format!("{:?}", gender))))
+        .filter_map(|voice| {
+            let locale_rank = match locale {
+                Some(locale) => locale_rank(&voice.locale, locale)?,
+                None => 0,
+            };
+            let query_rank = match &query {
+                Some(query) => name_rank(voice, query)?,
+                None => 0,
+            };
+            Some((query_rank, locale_rank, voice))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then_with(|| a.2.short_name.cmp(&b.2.short_name)));
+    matches.into_iter().map(|(_, _, voice)| voice).collect()
+}
+
+/// Lowercase letters and digits only.
+fn normalize(s: &str) -> String {
+    s.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// `None` if `voice_locale` isn't `locale`, nor of its language.
+fn locale_rank(voice_locale: &str, locale: &str) -> Option<u32> {
+    if voice_locale.eq_ignore_ascii_case(locale) {
+        return Some(2);
+    }
+    let (language, region) = voice_locale.split_once('-')?;
+    if !language.eq_ignore_ascii_case(locale) {
+        return None;
+    }
+    let default_region = DEFAULT_REGIONS.iter().find(|(l, _)| l.eq_ignore_ascii_case(language)).map(|(_, r)| r.to_string()).unwrap_or_else(|| language.to_uppercase());
+    Some(if region.eq_ignore_ascii_case(&default_region) { 1 } else { 0 })
+}
+
+/// `None` if `voice` doesn't look like `query`, which is normalized.
+fn name_rank(voice: &Voice, query: &str) -> Option<u32> {
+    let short_name = normalize(&voice.short_name);
+    // eg: "Xiaoxiao" of "zh-CN-XiaoxiaoNeural", "JennyMultilingual" of "en-US-JennyMultilingualNeural".
+    let name = normalize(voice.short_name.rsplit('-').next().unwrap_or_default().trim_end_matches("Neural"));
+    if short_name == query {
+        return Some(100);
+    }
+    if name == query {
+        return Some(90);
+    }
+    if name.starts_with(query) {
+        return Some(80);
+    }
+    if name.contains(query) || normalize(&voice.friendly_name).contains(query) {
+        return Some(60);
+    }
+    let distance = edit_distance(&name, query);
+    let allowed = if query.len() < 5 { 1 } else { 2 };
+    (distance <= allowed).then(|| 40 - distance as u32 * 10)
+}
+
+/// Edit distance, a swap of adjacent characters counting as one edit, eg: "aira" is 1 from "aria".
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(short_name: &str, gender: &str) -> Voice {
+        Voice {
+            short_name: short_name.to_owned(),
+            gender: gender.to_owned(),
+            locale: short_name.rsplit_once('-').unwrap().0.to_owned(),
+            ..Voice::default()
+        }
+    }
+
+    #[test]
+    fn finds_voices_loosely() {
+        let voices = [
+            voice("zh-TW-HsiaoChenNeural", "Female"),
+            voice("zh-CN-XiaoxiaoNeural", "Female"),
+            voice("zh-CN-YunxiNeural", "Male"),
+            voice("en-US-AriaNeural", "Female"),
+            voice("en-GB-SoniaNeural", "Female"),
+            voice("en-US-JennyMultilingualNeural", "Female"),
+        ];
+        let names = |found: Vec<&Voice>| found.iter().map(|v| v.short_name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(find_voices(&voices, Some("zh"), None, None)), ["zh-CN-XiaoxiaoNeural", "zh-CN-YunxiNeural", "zh-TW-HsiaoChenNeural"]);
+        assert_eq!(names(find_voices(&voices, Some("zh"), Some(Gender::Male), None)), ["zh-CN-YunxiNeural"]);
+        assert_eq!(names(find_voices(&voices, None, None, Some("xiaoxiao"))), ["zh-CN-XiaoxiaoNeural"]);
+        assert_eq!(names(find_voices(&voices, None, None, Some("Aira"))), ["en-US-AriaNeural"]);
+        assert_eq!(names(find_voices(&voices, Some("en"), None, Some("jenny multi"))), ["en-US-JennyMultilingualNeural"]);
+        assert!(find_voices(&voices, Some("fr"), None, None).is_empty());
+    }
+
+    #[test]
+    fn caches_voice_list() {
+        let dir = std::env::temp_dir().join(format!("edge-tts-voices-{}", uuid::Uuid::new_v4()));
+        let offline = VoiceListCache { fetch: || Err(anyhow::anyhow!("offline")), ..VoiceListCache::new(&dir) };
+        let bundled = offline.voices();
+        assert!(bundled.iter().any(|v| v.short_name == "zh-CN-XiaoxiaoNeural"), "{:?}", bundled);
+        assert!(offline.refresh().is_err());
+        let online = VoiceListCache { fetch: || Ok(vec![voice("en-US-AriaNeural", "Female")]), ..VoiceListCache::new(&dir) };
+        assert_eq!(online.refresh().unwrap().len(), 1);
+        assert_eq!(offline.voices().len(), 1);
+        assert_eq!(offline.clone().with_max_age(Duration::ZERO).voices().len(), 1);
+        let stale = VoiceListCache { fetch: || Ok(Vec::new()), ..VoiceListCache::new(&dir) };
+        assert_eq!(stale.clone().voices().len(), 1);
+        assert!(stale.with_max_age(Duration::ZERO).voices().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::{Codec, Container, OutputFormat};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_ALAW: u16 = 6;
+const FORMAT_MULAW: u16 = 7;
+
+/// 44-byte RIFF/WAVE header for `data_len` bytes of little-endian PCM.
+pub(crate) fn wav_header(sample_rate: u32, bits_per_sample: u16, channels: u16, data_len: u32) -> Vec<u8> {
+    wav_header_with_tag(FORMAT_PCM, sample_rate, bits_per_sample, channels, data_len)
+}
+
+fn wav_header_with_tag(format_tag: u16, sample_rate: u32, bits_per_sample: u16, channels: u16, data_len: u32) -> Vec<u8> {
+    let block_align = channels * bits_per_sample / 8;
+    let mut header = Vec::with_capacity(44);
+    header.extend(b"RIFF");
+    header.extend(data_len.saturating_add(36).to_le_bytes());
+    header.extend(b"WAVEfmt ");
+    header.extend(16u32.to_le_bytes());
+    header.extend(format_tag.to_le_bytes());
+    header.extend(channels.to_le_bytes());
+    header.extend(sample_rate.to_le_bytes());
+    header.extend((sample_rate * block_align as u32).to_le_bytes());
+    header.extend(block_align.to_le_bytes());
+    header.extend(bits_per_sample.to_le_bytes());
+    header.extend(b"data");
+    header.extend(data_len.to_le_bytes());
+    header
+}
+
+/// The `data` chunk of a WAVE file.
+pub(crate) fn wav_data(riff: &[u8]) -> Option<&[u8]> {
+    if riff.get(..4)? != b"RIFF" || riff.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut chunks = riff.get(12..)?;
+    loop {
+        let id = chunks.get(..4)?;
+        let len = u32::from_le_bytes(chunks.get(4..8)?.try_into().ok()?) as usize;
+        let body = chunks.get(8..)?;
+        if id == b"data" {
+            // Streamed files may announce more than they hold.
+            return Some(body.get(..len).unwrap_or(body));
+        }
+        // Chunks are padded to an even length.
+        chunks = body.get(len + (len & 1)..)?;
+    }
+}
+
+/// Wrap headerless `audio` of a `raw-*` `format` (PCM, A-law or μ-law) into a WAVE file. `riff-*` audio already has
+/// its header and is returned as is.
+///
+/// ```
+/// use edge_tts::{pcm_to_wav, OutputFormat};
+///
+/// let wav = pcm_to_wav(&[0; 4800], &OutputFormat::RAW_24KHZ_16BIT_MONO_PCM).unwrap();
+/// assert_eq!(&wav[..4], b"RIFF");
+/// ```
+pub fn pcm_to_wav(audio: &[u8], format: &OutputFormat) -> Result<Vec<u8>> {
+    match format.container() {
+        Container::Riff => return Ok(audio.to_vec()),
+        Container::Raw => {}
+        _ => bail!("{} is not raw PCM", format),
+    }
+    let format_tag = match format.codec() {
+        Codec::Pcm => FORMAT_PCM,
+        Codec::Alaw => FORMAT_ALAW,
+        Codec::Mulaw => FORMAT_MULAW,
+        _ => bail!("{} is not raw PCM", format),
+    };
+    let sample_rate = format.sample_rate().ok_or_else(|| anyhow!("{} has no sample rate", format))?;
+    let bits = format.bits_per_sample().unwrap_or(8);
+    let data_len = u32::try_from(audio.len()).map_err(|_| anyhow!("audio too long for a WAVE file"))?;
+    let mut wav = wav_header_with_tag(format_tag, sample_rate, bits, format.channels(), data_len);
+    wav.extend_from_slice(audio);
+    Ok(wav)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_header() {
+        let wav = pcm_to_wav(&[1, 2, 3, 4], &OutputFormat::new("raw-8khz-8bit-mono-mulaw")).unwrap();
+        assert_eq!(wav.len(), 48);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), FORMAT_MULAW);
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 8000);
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 4);
+        assert_eq!(&wav[44..], &[1, 2, 3, 4]);
+        assert!(pcm_to_wav(&[], &OutputFormat::default()).is_err());
+        assert_eq!(wav_data(&wav), Some(&[1, 2, 3, 4][..]));
+    }
+}
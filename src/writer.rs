@@ -0,0 +1,98 @@
+use std::io::Write;
+use std::ops::ControlFlow;
+
+use anyhow::{Context, Result};
+
+use crate::metadata::Boundary;
+use crate::synthesize::SynthesisEvent;
+use crate::{Client, SynthesisRequest};
+
+/// What [`Client::synthesize_to_writer`] wrote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WrittenAudio {
+    pub bytes: u64,
+    pub boundaries: Vec<Boundary>,
+}
+
+impl Client {
+    /// Write the audio of `request` into `writer` as it arrives, without holding it in memory. The disk cache and
+    /// post-processing aren't used.
+    ///
+    /// A failing `writer` closes the connection and fails the synthesis.
+    pub fn synthesize_to_writer<W: Write + ?Sized>(&self, request: &SynthesisRequest, writer: &mut W) -> Result<WrittenAudio> {
+        let request = self.prepare(request)?;
+        let mut written = WrittenAudio::default();
+        let mut write_error = None;
+        self.connect_and_stream(&self.request_ssml(&request), request.output_format.as_str(), &mut |event| match event {
+            SynthesisEvent::Audio(audio) => match writer.write_all(audio) {
+                Ok(()) => {
+                    written.bytes += audio.len() as u64;
+                    ControlFlow::Continue(())
+                }
+                Err(e) => {
+                    write_error = Some(e);
+                    ControlFlow::Break(())
+                }
+            },
+            SynthesisEvent::Boundaries(boundaries) => {
+                written.boundaries.extend(boundaries);
+                ControlFlow::Continue(())
+            }
+        })?;
+        if let Some(e) = write_error {
+            return Err(e).context("writing audio");
+        }
+        writer.flush().context("writing audio")?;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, ErrorKind};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{MockReply, MockServer};
+
+    /// Fails every write after `ok` bytes.
+    struct FullDisk {
+        ok: usize,
+        written: Vec<u8>,
+    }
+
+    impl Write for FullDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.ok - self.written.len());
+            if n == 0 {
+                return Err(io::Error::new(ErrorKind::StorageFull, "disk full"));
+            }
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_audio_as_it_arrives() {
+        let request = SynthesisRequest::new("Hello world", "en-US-AriaNeural");
+        let word = |text: &str, ms| MockReply::Word { text: text.to_owned(), offset: Duration::from_millis(ms), duration: Duration::from_millis(300) };
+        let server = MockServer::start(vec![vec![word("Hello", 100), MockReply::Audio(b"abc".to_vec()), word("world", 500), MockReply::Audio(b"de".to_vec()), MockReply::TurnEnd]]).unwrap();
+        let mut audio = Vec::new();
+        let written = server.client().synthesize_to_writer(&request, &mut audio).unwrap();
+        assert_eq!((audio.as_slice(), written.bytes), (&b"abcde"[..], 5));
+        assert_eq!(written.boundaries.iter().map(|b| (b.text.as_str(), b.offset)).collect::<Vec<_>>(), [("Hello", Duration::from_millis(100)), ("world", Duration::from_millis(500))]);
+        assert_eq!(server.closes(), 1);
+
+        let server = MockServer::start(vec![vec![MockReply::Audio(b"abc".to_vec()), MockReply::Audio(b"de".to_vec()), MockReply::Wait(Duration::from_millis(300)), MockReply::TurnEnd]]).unwrap();
+        let mut disk = FullDisk { ok: 4, written: Vec::new() };
+        let error = server.client().synthesize_to_writer(&request, &mut disk).unwrap_err();
+        assert_eq!(format!("{:#}", error), "writing audio: disk full");
+        assert_eq!(error.downcast_ref::<io::Error>().map(io::Error::kind), Some(ErrorKind::StorageFull));
+        assert_eq!(disk.written, b"abcd");
+        assert_eq!(server.closes(), 1);
+    }
+}